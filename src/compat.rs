@@ -0,0 +1,109 @@
+//! Drop-in paths mirroring `std::collections`.
+//!
+//! This module exposes the `Slab`-based collections and their companion types
+//! under the names used by [`std::collections`],
+//! so that migrating code from the standard B-Trees only requires changing imports:
+//!
+//! ```
+//! // use std::collections::{btree_map::Entry, BTreeMap};
+//! use btree_slab::compat::{btree_map::Entry, BTreeMap};
+//!
+//! let mut map = BTreeMap::new();
+//! match map.entry("a") {
+//!     Entry::Vacant(entry) => { entry.insert(1); }
+//!     Entry::Occupied(mut entry) => { *entry.get_mut() += 1; }
+//! }
+//!
+//! assert_eq!(map[&"a"], 1);
+//! ```
+//!
+//! Requires the `std-slab` feature, since the aliases use the `Slab` type of the `slab` crate.
+use slab::Slab;
+
+pub use btree_map::BTreeMap;
+pub use btree_set::BTreeSet;
+
+/// Mirror of [`std::collections::btree_map`].
+pub mod btree_map {
+	use super::Slab;
+	use crate::generic::{map, Node};
+
+	/// Slab-based map, see [`map::BTreeMap`].
+	pub type BTreeMap<K, V> = crate::BTreeMap<K, V>;
+
+	/// See [`map::Entry`].
+	pub type Entry<'a, K, V> = map::Entry<'a, K, V, Slab<Node<K, V>>>;
+
+	/// See [`map::OccupiedEntry`].
+	pub type OccupiedEntry<'a, K, V> = map::OccupiedEntry<'a, K, V, Slab<Node<K, V>>>;
+
+	/// See [`map::VacantEntry`].
+	pub type VacantEntry<'a, K, V> = map::VacantEntry<'a, K, V, Slab<Node<K, V>>>;
+
+	/// See [`map::Iter`].
+	pub type Iter<'a, K, V> = map::Iter<'a, K, V, Slab<Node<K, V>>>;
+
+	/// See [`map::IterMut`].
+	pub type IterMut<'a, K, V> = map::IterMut<'a, K, V, Slab<Node<K, V>>>;
+
+	/// See [`map::IntoIter`].
+	pub type IntoIter<K, V> = map::IntoIter<K, V, Slab<Node<K, V>>>;
+
+	/// See [`map::Keys`].
+	pub type Keys<'a, K, V> = map::Keys<'a, K, V, Slab<Node<K, V>>>;
+
+	/// See [`map::Values`].
+	pub type Values<'a, K, V> = map::Values<'a, K, V, Slab<Node<K, V>>>;
+
+	/// See [`map::ValuesMut`].
+	pub type ValuesMut<'a, K, V> = map::ValuesMut<'a, K, V, Slab<Node<K, V>>>;
+
+	/// See [`map::IntoKeys`].
+	pub type IntoKeys<K, V> = map::IntoKeys<K, V, Slab<Node<K, V>>>;
+
+	/// See [`map::IntoValues`].
+	pub type IntoValues<K, V> = map::IntoValues<K, V, Slab<Node<K, V>>>;
+
+	/// See [`map::Range`].
+	pub type Range<'a, K, V> = map::Range<'a, K, V, Slab<Node<K, V>>>;
+
+	/// See [`map::RangeMut`].
+	pub type RangeMut<'a, K, V> = map::RangeMut<'a, K, V, Slab<Node<K, V>>>;
+
+	/// See [`map::DrainFilter`].
+	pub type DrainFilter<'a, K, V, F> = map::DrainFilter<'a, K, V, Slab<Node<K, V>>, F>;
+}
+
+/// Mirror of [`std::collections::btree_set`].
+pub mod btree_set {
+	use super::Slab;
+	use crate::generic::{set, Node};
+
+	/// Slab-based set, see [`set::BTreeSet`].
+	pub type BTreeSet<T> = crate::BTreeSet<T>;
+
+	/// See [`set::Iter`].
+	pub type Iter<'a, T> = set::Iter<'a, T, Slab<Node<T, ()>>>;
+
+	/// See [`set::IntoIter`].
+	pub type IntoIter<T> = set::IntoIter<T, Slab<Node<T, ()>>>;
+
+	/// See [`set::Range`].
+	pub type Range<'a, T> = set::Range<'a, T, Slab<Node<T, ()>>>;
+
+	/// See [`set::Union`].
+	pub type Union<'a, T> = set::Union<'a, T, Slab<Node<T, ()>>, Slab<Node<T, ()>>>;
+
+	/// See [`set::Intersection`].
+	pub type Intersection<'a, T> = set::Intersection<'a, T, Slab<Node<T, ()>>, Slab<Node<T, ()>>>;
+
+	/// See [`set::Difference`].
+	pub type Difference<'a, T> = set::Difference<'a, T, Slab<Node<T, ()>>, Slab<Node<T, ()>>>;
+
+	/// See [`set::SymmetricDifference`].
+	pub type SymmetricDifference<'a, T> =
+		set::SymmetricDifference<'a, T, Slab<Node<T, ()>>, Slab<Node<T, ()>>>;
+
+	/// See [`set::DrainFilter`].
+	pub type DrainFilter<'a, T, F> = set::DrainFilter<'a, T, Slab<Node<T, ()>>, F>;
+}
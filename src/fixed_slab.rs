@@ -0,0 +1,185 @@
+//! Fixed-capacity slab, storing its entries inline.
+//!
+//! It can be used as node container to store an entire B-Tree
+//! without any heap allocation, for instance on the stack.
+use cc_traits::{
+	Capacity, Clear, Collection, CollectionMut, CollectionRef, Get, GetMut, Insert, Len, Remove,
+	SimpleCollectionMut, SimpleCollectionRef,
+};
+
+#[derive(Clone)]
+enum Entry<T> {
+	/// Free slot, holding the index of the next free slot.
+	Vacant(usize),
+
+	/// Occupied slot.
+	Occupied(T),
+}
+
+/// Slab holding at most `N` entries, stored inline.
+///
+/// Free slots are chained together so that insertion and removal take constant time.
+/// Inserting into a full slab panics:
+/// use [`BTreeMap::try_insert`](crate::generic::BTreeMap::try_insert)
+/// to insert into a map without exceeding the capacity of its container.
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::{fixed_slab::FixedSlab, generic::{BTreeMap, Node}};
+///
+/// let mut map: BTreeMap<u32, u32, FixedSlab<Node<u32, u32>, 16>> = BTreeMap::new();
+///
+/// for i in 0..32 {
+///     map.try_insert(i, i * 2).unwrap();
+/// }
+///
+/// assert_eq!(map.len(), 32);
+/// assert_eq!(map.get(&16), Some(&32));
+/// ```
+#[derive(Clone)]
+pub struct FixedSlab<T, const N: usize> {
+	entries: [Entry<T>; N],
+
+	/// Number of occupied slots.
+	len: usize,
+
+	/// Index of the first free slot, or `N` if the slab is full.
+	next: usize,
+}
+
+impl<T, const N: usize> FixedSlab<T, N> {
+	/// Creates a new empty slab.
+	#[inline]
+	pub fn new() -> Self {
+		FixedSlab {
+			entries: std::array::from_fn(|i| Entry::Vacant(i + 1)),
+			len: 0,
+			next: 0,
+		}
+	}
+
+	/// Returns `true` if every slot of the slab is occupied.
+	#[inline]
+	pub fn is_full(&self) -> bool {
+		self.len == N
+	}
+}
+
+impl<T, const N: usize> Default for FixedSlab<T, N> {
+	#[inline]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<T, const N: usize> Collection for FixedSlab<T, N> {
+	type Item = T;
+}
+
+impl<T, const N: usize> CollectionRef for FixedSlab<T, N> {
+	type ItemRef<'a>
+		= &'a T
+	where
+		Self: 'a;
+
+	cc_traits::covariant_item_ref!();
+}
+
+impl<T, const N: usize> CollectionMut for FixedSlab<T, N> {
+	type ItemMut<'a>
+		= &'a mut T
+	where
+		Self: 'a;
+
+	cc_traits::covariant_item_mut!();
+}
+
+impl<T, const N: usize> SimpleCollectionRef for FixedSlab<T, N> {
+	cc_traits::simple_collection_ref!();
+}
+
+impl<T, const N: usize> SimpleCollectionMut for FixedSlab<T, N> {
+	cc_traits::simple_collection_mut!();
+}
+
+impl<T, const N: usize> Len for FixedSlab<T, N> {
+	#[inline]
+	fn len(&self) -> usize {
+		self.len
+	}
+}
+
+impl<T, const N: usize> Capacity for FixedSlab<T, N> {
+	#[inline]
+	fn capacity(&self) -> usize {
+		N
+	}
+}
+
+impl<T, const N: usize> Get<usize> for FixedSlab<T, N> {
+	#[inline]
+	fn get(&self, key: usize) -> Option<&T> {
+		match self.entries.get(key) {
+			Some(Entry::Occupied(value)) => Some(value),
+			_ => None,
+		}
+	}
+}
+
+impl<T, const N: usize> GetMut<usize> for FixedSlab<T, N> {
+	#[inline]
+	fn get_mut(&mut self, key: usize) -> Option<&mut T> {
+		match self.entries.get_mut(key) {
+			Some(Entry::Occupied(value)) => Some(value),
+			_ => None,
+		}
+	}
+}
+
+impl<T, const N: usize> Insert for FixedSlab<T, N> {
+	type Output = usize;
+
+	/// Inserts a value in the first free slot and returns its index.
+	///
+	/// # Panics
+	///
+	/// Panics if the slab is full.
+	#[inline]
+	fn insert(&mut self, value: T) -> usize {
+		let key = self.next;
+		let entry = self.entries.get_mut(key).expect("fixed slab is full");
+		match std::mem::replace(entry, Entry::Occupied(value)) {
+			Entry::Vacant(next) => self.next = next,
+			Entry::Occupied(_) => unreachable!("free slot is occupied"),
+		}
+
+		self.len += 1;
+		key
+	}
+}
+
+impl<T, const N: usize> Remove<usize> for FixedSlab<T, N> {
+	#[inline]
+	fn remove(&mut self, key: usize) -> Option<T> {
+		match self.entries.get(key) {
+			Some(Entry::Occupied(_)) => {
+				let entry = std::mem::replace(&mut self.entries[key], Entry::Vacant(self.next));
+				self.next = key;
+				self.len -= 1;
+				match entry {
+					Entry::Occupied(value) => Some(value),
+					Entry::Vacant(_) => unreachable!(),
+				}
+			}
+			_ => None,
+		}
+	}
+}
+
+impl<T, const N: usize> Clear for FixedSlab<T, N> {
+	#[inline]
+	fn clear(&mut self) {
+		*self = Self::new()
+	}
+}
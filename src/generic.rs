@@ -10,3 +10,28 @@ pub use map::BTreeMap;
 
 pub mod set;
 pub use set::BTreeSet;
+
+pub mod multiset;
+pub use multiset::BTreeMultiSet;
+
+pub mod multimap;
+pub use multimap::BTreeMultiMap;
+
+pub mod partial_ord_map;
+pub use partial_ord_map::PartialOrdMap;
+
+pub mod compare_map;
+pub use compare_map::CompareMap;
+
+pub mod indirect_map;
+pub use indirect_map::IndirectBTreeMap;
+
+#[cfg(feature = "dyn-map")]
+pub mod dyn_map;
+#[cfg(feature = "dyn-map")]
+pub use dyn_map::DynBTreeMap;
+
+#[cfg(feature = "bplus")]
+pub mod bplus_map;
+#[cfg(feature = "bplus")]
+pub use bplus_map::BPlusTreeMap;
@@ -0,0 +1,1356 @@
+//! B+Tree variant of the map.
+//!
+//! Requires the `bplus` feature.
+use crate::generic::{
+	map::{is_valid_range, Invariant, InvariantError, M},
+	node::{Address, Item},
+};
+use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
+use smallvec::SmallVec;
+use std::{
+	borrow::Borrow,
+	fmt,
+	iter::{FromIterator, FusedIterator},
+	marker::PhantomData,
+	mem,
+	ops::{Bound, Index, RangeBounds},
+};
+
+/// Maximum number of entries in a leaf.
+const LEAF_MAX: usize = M;
+
+/// Minimum number of entries in a leaf other than the root.
+const LEAF_MIN: usize = M / 2;
+
+/// Maximum number of children of an internal node.
+const INTERNAL_MAX: usize = M;
+
+/// Minimum number of children of an internal node other than the root.
+const INTERNAL_MIN: usize = M / 2;
+
+/// Node of a [`BPlusTreeMap`].
+///
+/// Leaves store the entries of the map and are linked to their neighbours.
+/// Internal nodes only store separator keys.
+#[derive(Clone)]
+pub struct BPlusNode<K, V> {
+	parent: Option<usize>,
+	kind: Kind<K, V>,
+}
+
+#[derive(Clone)]
+enum Kind<K, V> {
+	Leaf(Leaf<K, V>),
+	Internal(Internal<K>),
+}
+
+#[derive(Clone)]
+struct Leaf<K, V> {
+	/// Previous leaf, in order.
+	prev: Option<usize>,
+
+	/// Next leaf, in order.
+	next: Option<usize>,
+
+	/// Entries, sorted by key, plus one while the leaf overflows.
+	items: SmallVec<[Item<K, V>; LEAF_MAX + 1]>,
+}
+
+#[derive(Clone)]
+struct Internal<K> {
+	/// Separators, plus one while the node overflows.
+	///
+	/// Keys of the child `i` are lower than `keys[i]`,
+	/// and keys of the child `i + 1` are greater or equal.
+	keys: SmallVec<[K; INTERNAL_MAX]>,
+
+	/// Children, one more than the separators.
+	children: SmallVec<[usize; INTERNAL_MAX + 1]>,
+}
+
+impl<K, V> BPlusNode<K, V> {
+	/// Checks if this node is a leaf.
+	#[inline]
+	pub fn is_leaf(&self) -> bool {
+		matches!(self.kind, Kind::Leaf(_))
+	}
+
+	/// Returns the identifier of the parent node, if any.
+	#[inline]
+	pub fn parent(&self) -> Option<usize> {
+		self.parent
+	}
+
+	#[inline]
+	fn leaf(&self) -> &Leaf<K, V> {
+		match &self.kind {
+			Kind::Leaf(leaf) => leaf,
+			Kind::Internal(_) => panic!("not a leaf"),
+		}
+	}
+
+	#[inline]
+	fn leaf_mut(&mut self) -> &mut Leaf<K, V> {
+		match &mut self.kind {
+			Kind::Leaf(leaf) => leaf,
+			Kind::Internal(_) => panic!("not a leaf"),
+		}
+	}
+
+	#[inline]
+	fn internal(&self) -> &Internal<K> {
+		match &self.kind {
+			Kind::Internal(node) => node,
+			Kind::Leaf(_) => panic!("not an internal node"),
+		}
+	}
+
+	#[inline]
+	fn internal_mut(&mut self) -> &mut Internal<K> {
+		match &mut self.kind {
+			Kind::Internal(node) => node,
+			Kind::Leaf(_) => panic!("not an internal node"),
+		}
+	}
+}
+
+impl<K> Internal<K> {
+	/// Returns the index of the given child.
+	#[inline]
+	fn child_index(&self, id: usize) -> usize {
+		self.children.iter().position(|c| *c == id).unwrap()
+	}
+
+	/// Returns the index of the child in which the given key belongs.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	fn child_for<Q: ?Sized>(&self, key: &Q) -> usize
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.keys.partition_point(|k| k.borrow() <= key)
+	}
+}
+
+/// An ordered map based on a B+Tree.
+///
+/// Unlike [`BTreeMap`](crate::generic::BTreeMap), internal nodes only store copies of keys,
+/// used as separators, and every entry lives in a leaf.
+/// Leaves are linked left-to-right, so range scans walk from leaf to leaf
+/// without going back up the tree, and internal nodes stay small.
+/// The price is that keys must be [`Clone`] to be inserted or removed.
+///
+/// Entries are addressed like in `BTreeMap`, by an [`Address`] whose `id` is a leaf.
+///
+/// Requires the `bplus` feature.
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::BPlusTreeMap;
+///
+/// let mut map: BPlusTreeMap<u32, String> = BPlusTreeMap::new();
+/// for i in 0..100 {
+///     map.insert(i, i.to_string());
+/// }
+///
+/// assert_eq!(map.get(&42).map(String::as_str), Some("42"));
+/// assert!(map.range(10..20).map(|(k, _)| *k).eq(10..20));
+///
+/// map.remove(&42);
+/// assert_eq!(map.len(), 99);
+/// ```
+pub struct BPlusTreeMap<K, V, C> {
+	/// Allocated and free nodes.
+	nodes: C,
+
+	/// Root node id.
+	root: Option<usize>,
+
+	/// First leaf, in order.
+	first_leaf: Option<usize>,
+
+	/// Last leaf, in order.
+	last_leaf: Option<usize>,
+
+	/// Number of entries in the map.
+	len: usize,
+
+	k: PhantomData<K>,
+	v: PhantomData<V>,
+}
+
+impl<K, V, C> BPlusTreeMap<K, V, C> {
+	/// Creates a new empty map.
+	#[inline]
+	pub fn new() -> Self
+	where
+		C: Default,
+	{
+		Self::with_container(C::default())
+	}
+
+	/// Creates a new empty map using the given node container.
+	#[inline]
+	pub fn with_container(nodes: C) -> Self {
+		BPlusTreeMap {
+			nodes,
+			root: None,
+			first_leaf: None,
+			last_leaf: None,
+			len: 0,
+			k: PhantomData,
+			v: PhantomData,
+		}
+	}
+
+	/// Returns the number of elements in the map.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns `true` if the map contains no elements.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+}
+
+impl<K, V, C: Default> Default for BPlusTreeMap<K, V, C> {
+	#[inline]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<K, V, C: Slab<BPlusNode<K, V>>> BPlusTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn node(&self, id: usize) -> &BPlusNode<K, V> {
+		C::into_ref(self.nodes.get(id).unwrap())
+	}
+
+	#[inline]
+	fn leaf(&self, id: usize) -> &Leaf<K, V> {
+		self.node(id).leaf()
+	}
+
+	#[inline]
+	fn internal(&self, id: usize) -> &Internal<K> {
+		self.node(id).internal()
+	}
+
+	/// Returns the identifier of the leaf in which the given key belongs.
+	#[allow(clippy::multiple_bound_locations)]
+	fn find_leaf<Q: ?Sized>(&self, key: &Q) -> Option<usize>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let mut id = self.root?;
+		loop {
+			match &self.node(id).kind {
+				Kind::Leaf(_) => return Some(id),
+				Kind::Internal(node) => id = node.children[node.child_for(key)],
+			}
+		}
+	}
+
+	/// Returns the address of the entry with the given key.
+	///
+	/// If there is no such entry, returns the address where it would be inserted,
+	/// as the error.
+	/// This is `Address::nowhere()` in an empty map.
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn address_of<Q: ?Sized>(&self, key: &Q) -> Result<Address, Address>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		match self.find_leaf(key) {
+			Some(id) => match self
+				.leaf(id)
+				.items
+				.binary_search_by(|item| item.key().borrow().cmp(key))
+			{
+				Ok(offset) => Ok(Address::new(id, offset.into())),
+				Err(offset) => Err(Address::new(id, offset.into())),
+			},
+			None => Err(Address::nowhere()),
+		}
+	}
+
+	/// Returns the entry at the given address, if any.
+	#[inline]
+	pub fn item(&self, addr: Address) -> Option<&Item<K, V>> {
+		match self.nodes.get(addr.id).map(C::into_ref) {
+			Some(BPlusNode {
+				kind: Kind::Leaf(leaf),
+				..
+			}) => leaf.items.get(addr.offset.value()?),
+			_ => None,
+		}
+	}
+
+	/// Returns a reference to the value corresponding to the key.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.get_key_value(key).map(|(_, v)| v)
+	}
+
+	/// Returns the key-value pair corresponding to the supplied key.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn get_key_value<Q: ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let addr = self.address_of(key).ok()?;
+		self.item(addr).map(Item::as_pair)
+	}
+
+	/// Returns `true` if the map contains a value for the specified key.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.address_of(key).is_ok()
+	}
+
+	/// Returns the first key-value pair in the map.
+	#[inline]
+	pub fn first_key_value(&self) -> Option<(&K, &V)> {
+		self.first_leaf.map(|id| self.leaf(id).items[0].as_pair())
+	}
+
+	/// Returns the last key-value pair in the map.
+	#[inline]
+	pub fn last_key_value(&self) -> Option<(&K, &V)> {
+		self.last_leaf
+			.map(|id| self.leaf(id).items.last().unwrap().as_pair())
+	}
+
+	/// Gets an iterator over the entries of the map, sorted by key.
+	#[inline]
+	pub fn iter(&self) -> Iter<'_, K, V, C> {
+		Iter {
+			inner: Range {
+				map: self,
+				front: self.first_address(),
+				back: self.end_address(),
+			},
+			len: self.len,
+		}
+	}
+
+	/// Gets an iterator over the keys of the map, in sorted order.
+	#[inline]
+	pub fn keys(&self) -> Keys<'_, K, V, C> {
+		Keys { inner: self.iter() }
+	}
+
+	/// Gets an iterator over the values of the map, in order by key.
+	#[inline]
+	pub fn values(&self) -> Values<'_, K, V, C> {
+		Values { inner: self.iter() }
+	}
+
+	/// Constructs a double-ended iterator over a sub-range of entries in the map.
+	///
+	/// The first entry is found by going down the tree once,
+	/// then the iterator follows the links between leaves.
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BPlusTreeMap;
+	///
+	/// let map: BPlusTreeMap<_, _> = (0..1000).map(|i| (i, i * 2)).collect();
+	/// assert!(map.range(100..=200).map(|(_, v)| *v).eq((100..=200).map(|i| i * 2)));
+	/// assert!(map.range(..10).rev().map(|(k, _)| *k).eq((0..10).rev()));
+	/// ```
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn range<T: ?Sized, R>(&self, range: R) -> Range<'_, K, V, C>
+	where
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
+	{
+		if !is_valid_range(&range) {
+			panic!("Invalid range")
+		}
+
+		let front = match range.start_bound() {
+			Bound::Included(key) => self.lower_bound(key, false),
+			Bound::Excluded(key) => self.lower_bound(key, true),
+			Bound::Unbounded => self.first_address(),
+		};
+
+		let back = match range.end_bound() {
+			Bound::Included(key) => self.lower_bound(key, true),
+			Bound::Excluded(key) => self.lower_bound(key, false),
+			Bound::Unbounded => self.end_address(),
+		};
+
+		Range {
+			map: self,
+			front,
+			back,
+		}
+	}
+
+	/// Address of the first entry.
+	#[inline]
+	fn first_address(&self) -> Address {
+		match self.first_leaf {
+			Some(id) => Address::new(id, 0.into()),
+			None => Address::nowhere(),
+		}
+	}
+
+	/// Address following the last entry.
+	#[inline]
+	fn end_address(&self) -> Address {
+		match self.last_leaf {
+			Some(id) => Address::new(id, self.leaf(id).items.len().into()),
+			None => Address::nowhere(),
+		}
+	}
+
+	/// Address of the first entry whose key is greater (or equal, if not `strict`)
+	/// than the given key, or the end address.
+	#[allow(clippy::multiple_bound_locations)]
+	fn lower_bound<Q: ?Sized>(&self, key: &Q, strict: bool) -> Address
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		match self.find_leaf(key) {
+			Some(id) => {
+				let offset = self.leaf(id).items.partition_point(|item| {
+					if strict {
+						item.key().borrow() <= key
+					} else {
+						item.key().borrow() < key
+					}
+				});
+
+				self.normalize(Address::new(id, offset.into()))
+			}
+			None => Address::nowhere(),
+		}
+	}
+
+	/// Moves an address following the last entry of a leaf to the start of the next leaf.
+	///
+	/// Only the address following the last entry of the map is not an entry address.
+	#[inline]
+	fn normalize(&self, addr: Address) -> Address {
+		let leaf = self.leaf(addr.id);
+		match leaf.next {
+			Some(next) if addr.offset >= leaf.items.len() => Address::new(next, 0.into()),
+			_ => addr,
+		}
+	}
+
+	/// Address of the entry preceding the given normalized address.
+	#[inline]
+	fn previous_address(&self, addr: Address) -> Address {
+		let offset = addr.offset.unwrap();
+		if offset > 0 {
+			Address::new(addr.id, (offset - 1).into())
+		} else {
+			let prev = self.leaf(addr.id).prev.unwrap();
+			Address::new(prev, (self.leaf(prev).items.len() - 1).into())
+		}
+	}
+
+	/// Checks the structure of the tree.
+	///
+	/// On top of the `BTreeMap` invariants, this checks that separators are ordered
+	/// and that every leaf is linked to its neighbours.
+	pub fn check_invariants(&self) -> Result<(), InvariantError>
+	where
+		K: Ord,
+	{
+		let mut leaves = Vec::new();
+		let len = match self.root {
+			Some(id) => self.check_node(id, None, None, None, &mut leaves)?.1,
+			None => 0,
+		};
+
+		if self.first_leaf != leaves.first().copied() || self.last_leaf != leaves.last().copied() {
+			return Err(InvariantError::new(None, Invariant::LeafBounds));
+		}
+
+		for (i, id) in leaves.iter().enumerate() {
+			let leaf = self.leaf(*id);
+			let prev = i.checked_sub(1).map(|i| leaves[i]);
+			let next = leaves.get(i + 1).copied();
+			if leaf.prev != prev || leaf.next != next {
+				return Err(InvariantError::new(Some(*id), Invariant::LeafLinks));
+			}
+		}
+
+		if len != self.len {
+			return Err(InvariantError::new(None, Invariant::Len));
+		}
+
+		Ok(())
+	}
+
+	/// Checks the subtree rooted in the given node, and returns its depth and length.
+	fn check_node(
+		&self,
+		id: usize,
+		parent: Option<usize>,
+		min: Option<&K>,
+		max: Option<&K>,
+		leaves: &mut Vec<usize>,
+	) -> Result<(usize, usize), InvariantError>
+	where
+		K: Ord,
+	{
+		let err = |invariant| Err(InvariantError::new(Some(id), invariant));
+		let in_bounds =
+			|key: &K| min.is_none_or(|min| min <= key) && max.is_none_or(|max| key < max);
+
+		let node = match self.nodes.get(id) {
+			Some(node) => C::into_ref(node),
+			None => return err(Invariant::Allocated),
+		};
+
+		if node.parent != parent {
+			return err(Invariant::Parent);
+		}
+
+		match &node.kind {
+			Kind::Leaf(leaf) => {
+				if leaf.items.spilled() {
+					return err(Invariant::InlineStorage);
+				}
+
+				if leaf.items.len() > LEAF_MAX {
+					return err(Invariant::Overflow);
+				}
+
+				if parent.is_some() && leaf.items.len() < LEAF_MIN {
+					return err(Invariant::Underflow);
+				}
+
+				if leaf.items.is_empty() {
+					return err(Invariant::EmptyRoot);
+				}
+
+				if leaf.items.windows(2).any(|w| w[0].key() >= w[1].key()) {
+					return err(Invariant::Sorted);
+				}
+
+				if !leaf.items.iter().all(|item| in_bounds(item.key())) {
+					return err(Invariant::Separators);
+				}
+
+				leaves.push(id);
+				Ok((1, leaf.items.len()))
+			}
+			Kind::Internal(node) => {
+				if node.keys.spilled() || node.children.spilled() {
+					return err(Invariant::InlineStorage);
+				}
+
+				if node.children.len() > INTERNAL_MAX {
+					return err(Invariant::Overflow);
+				}
+
+				if parent.is_some() && node.children.len() < INTERNAL_MIN {
+					return err(Invariant::Underflow);
+				}
+
+				if node.children.len() < 2 {
+					return err(Invariant::EmptyRoot);
+				}
+
+				if node.keys.len() + 1 != node.children.len() || !node.keys.iter().all(in_bounds) {
+					return err(Invariant::Separators);
+				}
+
+				if node.keys.windows(2).any(|w| w[0] >= w[1]) {
+					return err(Invariant::Sorted);
+				}
+
+				let mut depth = None;
+				let mut len = 0;
+				for (i, child) in node.children.iter().enumerate() {
+					let child_min = if i > 0 { Some(&node.keys[i - 1]) } else { min };
+					let child_max = node.keys.get(i).or(max);
+					let (child_depth, child_len) =
+						self.check_node(*child, Some(id), child_min, child_max, leaves)?;
+
+					if *depth.get_or_insert(child_depth) != child_depth {
+						return err(Invariant::Balanced);
+					}
+
+					len += child_len
+				}
+
+				Ok((depth.unwrap() + 1, len))
+			}
+		}
+	}
+}
+
+impl<K, V, C: SlabMut<BPlusNode<K, V>>> BPlusTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn node_mut(&mut self, id: usize) -> &mut BPlusNode<K, V> {
+		C::into_mut(self.nodes.get_mut(id).unwrap())
+	}
+
+	#[inline]
+	fn leaf_mut(&mut self, id: usize) -> &mut Leaf<K, V> {
+		self.node_mut(id).leaf_mut()
+	}
+
+	#[inline]
+	fn internal_mut(&mut self, id: usize) -> &mut Internal<K> {
+		self.node_mut(id).internal_mut()
+	}
+
+	/// Returns a mutable reference to the value corresponding to the key.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let addr = self.address_of(key).ok()?;
+		Some(self.leaf_mut(addr.id).items[addr.offset.unwrap()].value_mut())
+	}
+
+	/// Inserts a key-value pair into the map.
+	///
+	/// If the map did not have this key present, `None` is returned.
+	/// Otherwise the value is updated and the old value is returned.
+	/// The key is not updated.
+	pub fn insert(&mut self, key: K, value: V) -> Option<V>
+	where
+		K: Ord + Clone,
+	{
+		match self.address_of(&key) {
+			Ok(addr) => Some(self.leaf_mut(addr.id).items[addr.offset.unwrap()].set_value(value)),
+			Err(addr) if addr.is_nowhere() => {
+				let mut items = SmallVec::new();
+				items.push(Item::new(key, value));
+				let id = self.nodes.insert(BPlusNode {
+					parent: None,
+					kind: Kind::Leaf(Leaf {
+						prev: None,
+						next: None,
+						items,
+					}),
+				});
+
+				self.root = Some(id);
+				self.first_leaf = Some(id);
+				self.last_leaf = Some(id);
+				self.len = 1;
+				None
+			}
+			Err(addr) => {
+				let leaf = self.leaf_mut(addr.id);
+				leaf.items
+					.insert(addr.offset.unwrap(), Item::new(key, value));
+				if leaf.items.len() > LEAF_MAX {
+					self.split_leaf(addr.id)
+				}
+
+				self.len += 1;
+				None
+			}
+		}
+	}
+
+	/// Removes a key from the map, returning the value at the key
+	/// if the key was previously in the map.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
+	where
+		K: Borrow<Q> + Clone,
+		Q: Ord,
+	{
+		self.remove_entry(key).map(|(_, v)| v)
+	}
+
+	/// Removes a key from the map, returning the stored key and value
+	/// if the key was previously in the map.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn remove_entry<Q: ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+	where
+		K: Borrow<Q> + Clone,
+		Q: Ord,
+	{
+		let addr = self.address_of(key).ok()?;
+		Some(self.remove_at(addr))
+	}
+
+	/// Removes and returns the first entry of the map.
+	#[inline]
+	pub fn pop_first(&mut self) -> Option<(K, V)>
+	where
+		K: Clone,
+	{
+		let addr = self.first_address();
+		if addr.is_nowhere() {
+			None
+		} else {
+			Some(self.remove_at(addr))
+		}
+	}
+
+	/// Removes and returns the last entry of the map.
+	#[inline]
+	pub fn pop_last(&mut self) -> Option<(K, V)>
+	where
+		K: Clone,
+	{
+		let addr = self.end_address();
+		if addr.is_nowhere() {
+			None
+		} else {
+			Some(self.remove_at(self.previous_address(addr)))
+		}
+	}
+
+	/// Clears the map, removing all elements.
+	#[inline]
+	pub fn clear(&mut self)
+	where
+		C: cc_traits::Clear,
+	{
+		self.nodes.clear();
+		self.root = None;
+		self.first_leaf = None;
+		self.last_leaf = None;
+		self.len = 0;
+	}
+
+	/// Removes the entry at the given address and rebalances the tree.
+	fn remove_at(&mut self, addr: Address) -> (K, V)
+	where
+		K: Clone,
+	{
+		let item = self.leaf_mut(addr.id).items.remove(addr.offset.unwrap());
+		self.len -= 1;
+		self.rebalance_leaf(addr.id);
+		item.into_pair()
+	}
+
+	/// Splits an overflowing leaf, moving its upper half to a new leaf.
+	fn split_leaf(&mut self, id: usize)
+	where
+		K: Clone,
+	{
+		let node = self.node_mut(id);
+		let parent = node.parent;
+		let leaf = node.leaf_mut();
+		let items: SmallVec<[Item<K, V>; LEAF_MAX + 1]> =
+			leaf.items.drain((leaf.items.len() / 2)..).collect();
+		let next = leaf.next;
+		let separator = items[0].key().clone();
+
+		let new_id = self.nodes.insert(BPlusNode {
+			parent,
+			kind: Kind::Leaf(Leaf {
+				prev: Some(id),
+				next,
+				items,
+			}),
+		});
+
+		self.leaf_mut(id).next = Some(new_id);
+		match next {
+			Some(next) => self.leaf_mut(next).prev = Some(new_id),
+			None => self.last_leaf = Some(new_id),
+		}
+
+		self.insert_in_parent(id, separator, new_id)
+	}
+
+	/// Splits an overflowing internal node, moving its upper half to a new node.
+	fn split_internal(&mut self, id: usize) {
+		let node = self.node_mut(id);
+		let parent = node.parent;
+		let internal = node.internal_mut();
+		let mid = internal.keys.len() / 2;
+		let keys: SmallVec<[K; INTERNAL_MAX]> = internal.keys.drain((mid + 1)..).collect();
+		let separator = internal.keys.pop().unwrap();
+		let children: SmallVec<[usize; INTERNAL_MAX + 1]> =
+			internal.children.drain((mid + 1)..).collect();
+
+		let new_id = self.nodes.insert(BPlusNode {
+			parent,
+			kind: Kind::Internal(Internal {
+				keys,
+				children: children.clone(),
+			}),
+		});
+
+		for child in children {
+			self.node_mut(child).parent = Some(new_id)
+		}
+
+		self.insert_in_parent(id, separator, new_id)
+	}
+
+	/// Inserts the new `right` sibling of `left` in their parent,
+	/// creating a new root if `left` is the root.
+	fn insert_in_parent(&mut self, left: usize, separator: K, right: usize) {
+		match self.node(left).parent {
+			Some(parent) => {
+				let node = self.internal_mut(parent);
+				let i = node.child_index(left);
+				node.keys.insert(i, separator);
+				node.children.insert(i + 1, right);
+				if node.children.len() > INTERNAL_MAX {
+					self.split_internal(parent)
+				}
+			}
+			None => {
+				let mut keys = SmallVec::new();
+				keys.push(separator);
+				let mut children = SmallVec::new();
+				children.push(left);
+				children.push(right);
+
+				let root = self.nodes.insert(BPlusNode {
+					parent: None,
+					kind: Kind::Internal(Internal { keys, children }),
+				});
+
+				self.node_mut(left).parent = Some(root);
+				self.node_mut(right).parent = Some(root);
+				self.root = Some(root)
+			}
+		}
+	}
+
+	/// Fixes a leaf that may underflow after a removal,
+	/// by taking an entry from a sibling or by merging it with a sibling.
+	fn rebalance_leaf(&mut self, id: usize)
+	where
+		K: Clone,
+	{
+		let node = self.node(id);
+		let len = node.leaf().items.len();
+		match node.parent {
+			None => {
+				if len == 0 {
+					self.nodes.remove(id);
+					self.root = None;
+					self.first_leaf = None;
+					self.last_leaf = None;
+				}
+			}
+			Some(parent) if len < LEAF_MIN => {
+				let node = self.internal(parent);
+				let i = node.child_index(id);
+				let left = i.checked_sub(1).map(|i| node.children[i]);
+				let right = node.children.get(i + 1).copied();
+
+				if let Some(left) = left {
+					if self.leaf(left).items.len() > LEAF_MIN {
+						let item = self.leaf_mut(left).items.pop().unwrap();
+						let separator = item.key().clone();
+						self.leaf_mut(id).items.insert(0, item);
+						self.internal_mut(parent).keys[i - 1] = separator;
+						return;
+					}
+				}
+
+				if let Some(right) = right {
+					if self.leaf(right).items.len() > LEAF_MIN {
+						let item = self.leaf_mut(right).items.remove(0);
+						self.leaf_mut(id).items.push(item);
+						let separator = self.leaf(right).items[0].key().clone();
+						self.internal_mut(parent).keys[i] = separator;
+						return;
+					}
+				}
+
+				match left {
+					Some(_) => self.merge_leaves(parent, i - 1),
+					None => self.merge_leaves(parent, i),
+				}
+			}
+			Some(_) => (),
+		}
+	}
+
+	/// Merges the children `i` and `i + 1` of the given node, both leaves.
+	fn merge_leaves(&mut self, parent: usize, i: usize) {
+		let node = self.internal_mut(parent);
+		node.keys.remove(i);
+		let left = node.children[i];
+		let right = node.children.remove(i + 1);
+
+		let right_leaf = match self.nodes.remove(right).unwrap().kind {
+			Kind::Leaf(leaf) => leaf,
+			Kind::Internal(_) => unreachable!(),
+		};
+
+		let left_leaf = self.leaf_mut(left);
+		left_leaf.items.extend(right_leaf.items);
+		left_leaf.next = right_leaf.next;
+		match right_leaf.next {
+			Some(next) => self.leaf_mut(next).prev = Some(left),
+			None => self.last_leaf = Some(left),
+		}
+
+		self.rebalance_internal(parent)
+	}
+
+	/// Fixes an internal node that may underflow after a merge of its children,
+	/// by rotating a child from a sibling or by merging it with a sibling.
+	fn rebalance_internal(&mut self, id: usize) {
+		let node = self.node(id);
+		let len = node.internal().children.len();
+		match node.parent {
+			None => {
+				if len == 1 {
+					let child = match self.nodes.remove(id).unwrap().kind {
+						Kind::Internal(node) => node.children[0],
+						Kind::Leaf(_) => unreachable!(),
+					};
+
+					self.node_mut(child).parent = None;
+					self.root = Some(child)
+				}
+			}
+			Some(parent) if len < INTERNAL_MIN => {
+				let node = self.internal(parent);
+				let i = node.child_index(id);
+				let left = i.checked_sub(1).map(|i| node.children[i]);
+				let right = node.children.get(i + 1).copied();
+
+				if let Some(left) = left {
+					if self.internal(left).children.len() > INTERNAL_MIN {
+						let left_node = self.internal_mut(left);
+						let key = left_node.keys.pop().unwrap();
+						let child = left_node.children.pop().unwrap();
+						let separator =
+							mem::replace(&mut self.internal_mut(parent).keys[i - 1], key);
+						let node = self.internal_mut(id);
+						node.keys.insert(0, separator);
+						node.children.insert(0, child);
+						self.node_mut(child).parent = Some(id);
+						return;
+					}
+				}
+
+				if let Some(right) = right {
+					if self.internal(right).children.len() > INTERNAL_MIN {
+						let right_node = self.internal_mut(right);
+						let key = right_node.keys.remove(0);
+						let child = right_node.children.remove(0);
+						let separator = mem::replace(&mut self.internal_mut(parent).keys[i], key);
+						let node = self.internal_mut(id);
+						node.keys.push(separator);
+						node.children.push(child);
+						self.node_mut(child).parent = Some(id);
+						return;
+					}
+				}
+
+				match left {
+					Some(_) => self.merge_internals(parent, i - 1),
+					None => self.merge_internals(parent, i),
+				}
+			}
+			Some(_) => (),
+		}
+	}
+
+	/// Merges the children `i` and `i + 1` of the given node, both internal nodes.
+	fn merge_internals(&mut self, parent: usize, i: usize) {
+		let node = self.internal_mut(parent);
+		let separator = node.keys.remove(i);
+		let left = node.children[i];
+		let right = node.children.remove(i + 1);
+
+		let right_node = match self.nodes.remove(right).unwrap().kind {
+			Kind::Internal(node) => node,
+			Kind::Leaf(_) => unreachable!(),
+		};
+
+		for child in &right_node.children {
+			self.node_mut(*child).parent = Some(left)
+		}
+
+		let left_node = self.internal_mut(left);
+		left_node.keys.push(separator);
+		left_node.keys.extend(right_node.keys);
+		left_node.children.extend(right_node.children);
+
+		self.rebalance_internal(parent)
+	}
+}
+
+impl<K: Clone, V: Clone, C: Clone> Clone for BPlusTreeMap<K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		BPlusTreeMap {
+			nodes: self.nodes.clone(),
+			root: self.root,
+			first_leaf: self.first_leaf,
+			last_leaf: self.last_leaf,
+			len: self.len,
+			k: PhantomData,
+			v: PhantomData,
+		}
+	}
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, C: Slab<BPlusNode<K, V>>> fmt::Debug for BPlusTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+
+impl<K: PartialEq, V: PartialEq, C: Slab<BPlusNode<K, V>>> PartialEq for BPlusTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn eq(&self, other: &Self) -> bool {
+		self.len() == other.len() && self.iter().eq(other.iter())
+	}
+}
+
+impl<K: Eq, V: Eq, C: Slab<BPlusNode<K, V>>> Eq for BPlusTreeMap<K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
+impl<K: Ord + Clone, V, C: SlabMut<BPlusNode<K, V>> + Default> FromIterator<(K, V)>
+	for BPlusTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn from_iter<T>(iter: T) -> Self
+	where
+		T: IntoIterator<Item = (K, V)>,
+	{
+		let mut map = BPlusTreeMap::new();
+		map.extend(iter);
+		map
+	}
+}
+
+impl<K: Ord + Clone, V, C: SlabMut<BPlusNode<K, V>>> Extend<(K, V)> for BPlusTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn extend<T>(&mut self, iter: T)
+	where
+		T: IntoIterator<Item = (K, V)>,
+	{
+		for (key, value) in iter {
+			self.insert(key, value);
+		}
+	}
+}
+
+impl<K, Q: ?Sized, V, C: Slab<BPlusNode<K, V>>> Index<&Q> for BPlusTreeMap<K, V, C>
+where
+	K: Borrow<Q>,
+	Q: Ord,
+	C: SimpleCollectionRef,
+{
+	type Output = V;
+
+	/// Returns a reference to the value corresponding to the supplied key.
+	///
+	/// # Panics
+	///
+	/// Panics if the key is not present in the map.
+	#[inline]
+	fn index(&self, key: &Q) -> &V {
+		self.get(key).expect("no entry found for key")
+	}
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> IntoIterator for &'a BPlusTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	type IntoIter = Iter<'a, K, V, C>;
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn into_iter(self) -> Iter<'a, K, V, C> {
+		self.iter()
+	}
+}
+
+/// Iterator over a sub-range of entries in a [`BPlusTreeMap`].
+///
+/// This `struct` is created by the [`BPlusTreeMap::range`] method.
+pub struct Range<'a, K, V, C> {
+	map: &'a BPlusTreeMap<K, V, C>,
+
+	/// Address of the next entry.
+	front: Address,
+
+	/// Address following the next back entry.
+	back: Address,
+}
+
+impl<'a, K, V, C> Clone for Range<'a, K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Range {
+			map: self.map,
+			front: self.front,
+			back: self.back,
+		}
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<BPlusNode<K, V>>> fmt::Debug for Range<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> Iterator for Range<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn next(&mut self) -> Option<(&'a K, &'a V)> {
+		if self.front != self.back {
+			let item = self.map.item(self.front).unwrap();
+			self.front = self.map.normalize(self.front.next_in_node());
+			Some(item.as_pair())
+		} else {
+			None
+		}
+	}
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> DoubleEndedIterator for Range<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+		if self.front != self.back {
+			self.back = self.map.previous_address(self.back);
+			Some(self.map.item(self.back).unwrap().as_pair())
+		} else {
+			None
+		}
+	}
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> FusedIterator for Range<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
+/// Iterator over the entries of a [`BPlusTreeMap`].
+///
+/// This `struct` is created by the [`BPlusTreeMap::iter`] method.
+pub struct Iter<'a, K, V, C> {
+	inner: Range<'a, K, V, C>,
+
+	/// Number of remaining entries.
+	len: usize,
+}
+
+impl<'a, K, V, C> Clone for Iter<'a, K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Iter {
+			inner: self.inner.clone(),
+			len: self.len,
+		}
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<BPlusNode<K, V>>> fmt::Debug for Iter<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.inner.fmt(f)
+	}
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> Iterator for Iter<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.len, Some(self.len))
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<(&'a K, &'a V)> {
+		let entry = self.inner.next()?;
+		self.len -= 1;
+		Some(entry)
+	}
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> DoubleEndedIterator for Iter<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+		let entry = self.inner.next_back()?;
+		self.len -= 1;
+		Some(entry)
+	}
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> FusedIterator for Iter<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> ExactSizeIterator for Iter<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
+/// Iterator over the keys of a [`BPlusTreeMap`].
+///
+/// This `struct` is created by the [`BPlusTreeMap::keys`] method.
+pub struct Keys<'a, K, V, C> {
+	inner: Iter<'a, K, V, C>,
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> Iterator for Keys<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = &'a K;
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<&'a K> {
+		self.inner.next().map(|(k, _)| k)
+	}
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> DoubleEndedIterator for Keys<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<&'a K> {
+		self.inner.next_back().map(|(k, _)| k)
+	}
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> FusedIterator for Keys<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> ExactSizeIterator for Keys<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
+/// Iterator over the values of a [`BPlusTreeMap`].
+///
+/// This `struct` is created by the [`BPlusTreeMap::values`] method.
+pub struct Values<'a, K, V, C> {
+	inner: Iter<'a, K, V, C>,
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> Iterator for Values<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = &'a V;
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<&'a V> {
+		self.inner.next().map(|(_, v)| v)
+	}
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> DoubleEndedIterator for Values<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<&'a V> {
+		self.inner.next_back().map(|(_, v)| v)
+	}
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> FusedIterator for Values<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
+impl<'a, K, V, C: Slab<BPlusNode<K, V>>> ExactSizeIterator for Values<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
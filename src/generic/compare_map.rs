@@ -0,0 +1,467 @@
+use crate::generic::{
+	map::{self, BTreeMap},
+	node::Node,
+};
+use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
+use std::{
+	cmp::Ordering,
+	fmt,
+	iter::{DoubleEndedIterator, ExactSizeIterator, FusedIterator},
+	marker::PhantomData,
+	ops::{Bound, RangeBounds},
+};
+
+/// Key ordering used by a [`CompareMap`].
+///
+/// The ordering is a type rather than a value,
+/// so that it takes no space in the map and its nodes.
+///
+/// # Correctness
+///
+/// The ordering must be a total order, as required by [`Ord`].
+pub trait Compare<K: ?Sized> {
+	/// Compares two keys.
+	fn compare(a: &K, b: &K) -> Ordering;
+}
+
+/// Natural ordering of keys, given by [`Ord`].
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct NaturalOrder;
+
+impl<K: ?Sized + Ord> Compare<K> for NaturalOrder {
+	#[inline]
+	fn compare(a: &K, b: &K) -> Ordering {
+		a.cmp(b)
+	}
+}
+
+/// Reverse of the natural ordering of keys.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct ReverseOrder;
+
+impl<K: ?Sized + Ord> Compare<K> for ReverseOrder {
+	#[inline]
+	fn compare(a: &K, b: &K) -> Ordering {
+		b.cmp(a)
+	}
+}
+
+/// Key wrapper ordering keys with the given [`Compare`] implementation.
+#[repr(transparent)]
+pub struct Ordered<K, O>(K, PhantomData<fn() -> O>);
+
+impl<K, O> Ordered<K, O> {
+	/// Wraps the given key.
+	#[inline]
+	fn new(key: K) -> Self {
+		Ordered(key, PhantomData)
+	}
+
+	/// Wraps the given key reference.
+	#[inline]
+	fn from_ref(key: &K) -> &Self {
+		// SAFETY: `Ordered` is a transparent wrapper around `K`.
+		unsafe { &*(key as *const K as *const Ordered<K, O>) }
+	}
+
+	/// Returns the wrapped key.
+	#[inline]
+	pub fn get(&self) -> &K {
+		&self.0
+	}
+
+	/// Unwraps the key.
+	#[inline]
+	pub fn into_inner(self) -> K {
+		self.0
+	}
+}
+
+impl<K: Clone, O> Clone for Ordered<K, O> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Ordered::new(self.0.clone())
+	}
+}
+
+impl<K: fmt::Debug, O> fmt::Debug for Ordered<K, O> {
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.0.fmt(f)
+	}
+}
+
+impl<K, O: Compare<K>> PartialEq for Ordered<K, O> {
+	#[inline]
+	fn eq(&self, other: &Self) -> bool {
+		O::compare(&self.0, &other.0) == Ordering::Equal
+	}
+}
+
+impl<K, O: Compare<K>> Eq for Ordered<K, O> {}
+
+impl<K, O: Compare<K>> PartialOrd for Ordered<K, O> {
+	#[inline]
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl<K, O: Compare<K>> Ord for Ordered<K, O> {
+	#[inline]
+	fn cmp(&self, other: &Self) -> Ordering {
+		O::compare(&self.0, &other.0)
+	}
+}
+
+/// An ordered map whose keys are sorted by a custom ordering `O`, based on a B-Tree.
+///
+/// The ordering is given by a type implementing [`Compare`],
+/// so that keys do not need to be wrapped in a new type implementing [`Ord`].
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::CompareMap;
+/// use btree_slab::generic::compare_map::Compare;
+/// use std::cmp::Ordering;
+///
+/// struct CaseInsensitive;
+///
+/// impl Compare<String> for CaseInsensitive {
+///     fn compare(a: &String, b: &String) -> Ordering {
+///         a.to_lowercase().cmp(&b.to_lowercase())
+///     }
+/// }
+///
+/// let mut map: CompareMap<String, u32, CaseInsensitive> = CompareMap::new();
+/// map.insert("b".to_string(), 1);
+/// map.insert("A".to_string(), 2);
+/// map.insert("B".to_string(), 3);
+///
+/// assert_eq!(map.get(&"b".to_string()), Some(&3));
+/// assert!(map.iter().eq([(&"A".to_string(), &2), (&"b".to_string(), &3)]));
+/// ```
+pub struct CompareMap<K, V, O, C> {
+	map: BTreeMap<Ordered<K, O>, V, C>,
+}
+
+impl<K, V, O, C> CompareMap<K, V, O, C> {
+	/// Makes a new, empty `CompareMap`.
+	#[inline]
+	pub fn new() -> Self
+	where
+		C: Default,
+	{
+		CompareMap {
+			map: BTreeMap::new(),
+		}
+	}
+
+	/// Returns the number of elements in the map.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.map.len()
+	}
+
+	/// Returns `true` if the map contains no elements.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.map.is_empty()
+	}
+
+	/// Returns the underlying map.
+	#[inline]
+	pub fn as_map(&self) -> &BTreeMap<Ordered<K, O>, V, C> {
+		&self.map
+	}
+}
+
+impl<K, V, O, C: Default> Default for CompareMap<K, V, O, C> {
+	#[inline]
+	fn default() -> Self {
+		CompareMap::new()
+	}
+}
+
+impl<K: Clone, V: Clone, O, C: Clone> Clone for CompareMap<K, V, O, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		CompareMap {
+			map: self.map.clone(),
+		}
+	}
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, O, C: Slab<Node<Ordered<K, O>, V>>> fmt::Debug
+	for CompareMap<K, V, O, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_map()
+			.entries(self.map.iter().map(|(k, v)| (k.get(), v)))
+			.finish()
+	}
+}
+
+impl<K, V, O: Compare<K>, C: Slab<Node<Ordered<K, O>, V>>> CompareMap<K, V, O, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Returns a reference to the value corresponding to the key.
+	#[inline]
+	pub fn get(&self, key: &K) -> Option<&V> {
+		self.map.get(Ordered::from_ref(key))
+	}
+
+	/// Returns `true` if the map contains a value for the specified key.
+	#[inline]
+	pub fn contains_key(&self, key: &K) -> bool {
+		self.map.contains_key(Ordered::from_ref(key))
+	}
+
+	/// Returns the first key-value pair in the map.
+	#[inline]
+	pub fn first_key_value(&self) -> Option<(&K, &V)> {
+		self.map.first_key_value().map(|(k, v)| (&k.0, v))
+	}
+
+	/// Returns the last key-value pair in the map.
+	#[inline]
+	pub fn last_key_value(&self) -> Option<(&K, &V)> {
+		self.map.last_key_value().map(|(k, v)| (&k.0, v))
+	}
+
+	/// Gets an iterator over the entries of the map, sorted by key.
+	#[inline]
+	pub fn iter(&self) -> Iter<'_, K, V, O, C> {
+		Iter {
+			inner: self.map.iter(),
+		}
+	}
+
+	/// Constructs a double-ended iterator over a sub-range of elements in the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::CompareMap;
+	/// use btree_slab::generic::compare_map::ReverseOrder;
+	///
+	/// let map: CompareMap<u32, u32, ReverseOrder> = (0..10).map(|i| (i, i)).collect();
+	/// assert!(map.range(7..=3).map(|(k, _)| *k).eq([7, 6, 5, 4, 3]));
+	/// ```
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end` for the ordering of the map.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	#[inline]
+	pub fn range<R>(&self, range: R) -> Range<'_, K, V, O, C>
+	where
+		R: RangeBounds<K>,
+	{
+		let start = ordered_bound(range.start_bound());
+		let end = ordered_bound(range.end_bound());
+		Range {
+			inner: self.map.range((start, end)),
+		}
+	}
+}
+
+impl<K, V, O: Compare<K>, C: SlabMut<Node<Ordered<K, O>, V>>> CompareMap<K, V, O, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Returns a mutable reference to the value corresponding to the key.
+	#[inline]
+	pub fn get_mut(&mut self, key: &K) -> Option<&mut V> {
+		self.map.get_mut(Ordered::from_ref(key))
+	}
+
+	/// Inserts a key-value pair into the map.
+	///
+	/// If the map already had an equal key present, the value is updated and the old
+	/// value is returned.
+	/// The key itself is not updated.
+	#[inline]
+	pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+		self.map.insert(Ordered::new(key), value)
+	}
+
+	/// Removes a key from the map, returning the value at the key if the key
+	/// was previously in the map.
+	#[inline]
+	pub fn remove(&mut self, key: &K) -> Option<V> {
+		self.map.remove(Ordered::from_ref(key))
+	}
+
+	/// Clears the map, removing all elements.
+	#[inline]
+	pub fn clear(&mut self)
+	where
+		C: cc_traits::Clear,
+	{
+		self.map.clear()
+	}
+}
+
+#[inline]
+fn ordered_bound<K, O>(bound: Bound<&K>) -> Bound<&Ordered<K, O>> {
+	match bound {
+		Bound::Included(key) => Bound::Included(Ordered::from_ref(key)),
+		Bound::Excluded(key) => Bound::Excluded(Ordered::from_ref(key)),
+		Bound::Unbounded => Bound::Unbounded,
+	}
+}
+
+impl<K, V, O: Compare<K>, C: SlabMut<Node<Ordered<K, O>, V>> + Default> FromIterator<(K, V)>
+	for CompareMap<K, V, O, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn from_iter<T>(iter: T) -> Self
+	where
+		T: IntoIterator<Item = (K, V)>,
+	{
+		let mut map = CompareMap::new();
+		for (key, value) in iter {
+			map.insert(key, value);
+		}
+
+		map
+	}
+}
+
+impl<'a, K, V, O: Compare<K>, C: Slab<Node<Ordered<K, O>, V>>> IntoIterator
+	for &'a CompareMap<K, V, O, C>
+where
+	C: SimpleCollectionRef,
+{
+	type IntoIter = Iter<'a, K, V, O, C>;
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn into_iter(self) -> Iter<'a, K, V, O, C> {
+		self.iter()
+	}
+}
+
+/// Iterator over the entries of a [`CompareMap`].
+pub struct Iter<'a, K, V, O, C> {
+	inner: map::Iter<'a, Ordered<K, O>, V, C>,
+}
+
+impl<'a, K, V, O, C> Clone for Iter<'a, K, V, O, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Iter {
+			inner: self.inner.clone(),
+		}
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, O, C: Slab<Node<Ordered<K, O>, V>>> fmt::Debug
+	for Iter<'a, K, V, O, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
+impl<'a, K, V, O, C: Slab<Node<Ordered<K, O>, V>>> Iterator for Iter<'a, K, V, O, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<(&'a K, &'a V)> {
+		self.inner.next().map(|(k, v)| (&k.0, v))
+	}
+}
+
+impl<'a, K, V, O, C: Slab<Node<Ordered<K, O>, V>>> DoubleEndedIterator for Iter<'a, K, V, O, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+		self.inner.next_back().map(|(k, v)| (&k.0, v))
+	}
+}
+
+impl<'a, K, V, O, C: Slab<Node<Ordered<K, O>, V>>> FusedIterator for Iter<'a, K, V, O, C> where
+	C: SimpleCollectionRef
+{
+}
+
+impl<'a, K, V, O, C: Slab<Node<Ordered<K, O>, V>>> ExactSizeIterator for Iter<'a, K, V, O, C> where
+	C: SimpleCollectionRef
+{
+}
+
+/// Iterator over a sub-range of entries of a [`CompareMap`].
+pub struct Range<'a, K, V, O, C> {
+	inner: map::Range<'a, Ordered<K, O>, V, C>,
+}
+
+impl<'a, K, V, O, C> Clone for Range<'a, K, V, O, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Range {
+			inner: self.inner.clone(),
+		}
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, O, C: Slab<Node<Ordered<K, O>, V>>> fmt::Debug
+	for Range<'a, K, V, O, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
+impl<'a, K, V, O, C: Slab<Node<Ordered<K, O>, V>>> Iterator for Range<'a, K, V, O, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn next(&mut self) -> Option<(&'a K, &'a V)> {
+		self.inner.next().map(|(k, v)| (&k.0, v))
+	}
+}
+
+impl<'a, K, V, O, C: Slab<Node<Ordered<K, O>, V>>> DoubleEndedIterator for Range<'a, K, V, O, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+		self.inner.next_back().map(|(k, v)| (&k.0, v))
+	}
+}
+
+impl<'a, K, V, O, C: Slab<Node<Ordered<K, O>, V>>> FusedIterator for Range<'a, K, V, O, C> where
+	C: SimpleCollectionRef
+{
+}
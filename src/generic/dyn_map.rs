@@ -0,0 +1,256 @@
+use crate::generic::{node::Node, BTreeMap};
+use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
+use std::{any::Any, borrow::Borrow};
+
+/// Type-erased value stored in a [`DynBTreeMap`].
+pub type DynValue = Box<dyn Any + Send>;
+
+/// An ordered map with type-erased values, based on a B-Tree.
+///
+/// Each value may have a different type.
+/// Typed accessors check the type of the stored value before returning it.
+/// The underlying [`BTreeMap`] is available through [`DynBTreeMap::as_map`]
+/// for ordered queries such as ranges.
+///
+/// Requires the `dyn-map` feature.
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::DynBTreeMap;
+///
+/// let mut map = DynBTreeMap::new();
+/// map.insert_typed("plugin.a.enabled", true);
+/// map.insert_typed("plugin.a.name", String::from("a"));
+///
+/// assert_eq!(map.get_as::<bool, _>("plugin.a.enabled"), Some(&true));
+/// assert_eq!(map.get_as::<u32, _>("plugin.a.enabled"), None);
+/// assert_eq!(map.as_map().range("plugin.a."..="plugin.a.~").count(), 2);
+/// ```
+pub struct DynBTreeMap<K, C> {
+	map: BTreeMap<K, DynValue, C>,
+}
+
+impl<K, C> DynBTreeMap<K, C> {
+	/// Makes a new, empty `DynBTreeMap`.
+	#[inline]
+	pub fn new() -> Self
+	where
+		C: Default,
+	{
+		DynBTreeMap {
+			map: BTreeMap::new(),
+		}
+	}
+
+	/// Returns the number of elements in the map.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.map.len()
+	}
+
+	/// Returns `true` if the map contains no elements.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.map.is_empty()
+	}
+
+	/// Returns the underlying map.
+	#[inline]
+	pub fn as_map(&self) -> &BTreeMap<K, DynValue, C> {
+		&self.map
+	}
+
+	/// Returns the underlying map, mutably.
+	#[inline]
+	pub fn as_map_mut(&mut self) -> &mut BTreeMap<K, DynValue, C> {
+		&mut self.map
+	}
+
+	/// Converts this map into the underlying map.
+	#[inline]
+	pub fn into_map(self) -> BTreeMap<K, DynValue, C> {
+		self.map
+	}
+}
+
+impl<K, C: Default> Default for DynBTreeMap<K, C> {
+	#[inline]
+	fn default() -> Self {
+		DynBTreeMap::new()
+	}
+}
+
+impl<K, C> From<BTreeMap<K, DynValue, C>> for DynBTreeMap<K, C> {
+	#[inline]
+	fn from(map: BTreeMap<K, DynValue, C>) -> Self {
+		DynBTreeMap { map }
+	}
+}
+
+impl<K: Ord, C: Slab<Node<K, DynValue>>> DynBTreeMap<K, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Returns `true` if the map contains a value for the specified key,
+	/// whatever its type.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map.contains_key(key)
+	}
+
+	/// Returns `true` if the map contains a value of type `T` for the specified key.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::DynBTreeMap;
+	///
+	/// let mut map = DynBTreeMap::new();
+	/// map.insert_typed(1, 'a');
+	/// assert!(map.is::<char, _>(&1));
+	/// assert!(!map.is::<u8, _>(&1));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn is<T: Any, Q: ?Sized>(&self, key: &Q) -> bool
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map
+			.get(key)
+			.map(|value| value.is::<T>())
+			.unwrap_or(false)
+	}
+
+	/// Returns a reference to the value corresponding to the key,
+	/// if it exists and is of type `T`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::DynBTreeMap;
+	///
+	/// let mut map = DynBTreeMap::new();
+	/// map.insert_typed(1, 'a');
+	/// assert_eq!(map.get_as::<char, _>(&1), Some(&'a'));
+	/// assert_eq!(map.get_as::<u8, _>(&1), None);
+	/// assert_eq!(map.get_as::<char, _>(&2), None);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn get_as<T: Any, Q: ?Sized>(&self, key: &Q) -> Option<&T>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map.get(key).and_then(|value| value.downcast_ref())
+	}
+}
+
+impl<K: Ord, C: SlabMut<Node<K, DynValue>>> DynBTreeMap<K, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Returns a mutable reference to the value corresponding to the key,
+	/// if it exists and is of type `T`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::DynBTreeMap;
+	///
+	/// let mut map = DynBTreeMap::new();
+	/// map.insert_typed(1, 1u32);
+	/// *map.get_mut_as::<u32, _>(&1).unwrap() += 1;
+	/// assert_eq!(map.get_as::<u32, _>(&1), Some(&2));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn get_mut_as<T: Any, Q: ?Sized>(&mut self, key: &Q) -> Option<&mut T>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map.get_mut(key).and_then(|value| value.downcast_mut())
+	}
+
+	/// Inserts a key-value pair into the map.
+	///
+	/// If the map already had this key present, the value is updated and the old
+	/// type-erased value is returned, whatever its type.
+	#[inline]
+	pub fn insert(&mut self, key: K, value: DynValue) -> Option<DynValue> {
+		self.map.insert(key, value)
+	}
+
+	/// Inserts a typed value into the map.
+	///
+	/// If the map already had this key present, the value is updated and the old
+	/// type-erased value is returned, whatever its type.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::DynBTreeMap;
+	///
+	/// let mut map = DynBTreeMap::new();
+	/// assert!(map.insert_typed(1, 'a').is_none());
+	/// let old = map.insert_typed(1, "b").unwrap();
+	/// assert_eq!(old.downcast_ref::<char>(), Some(&'a'));
+	/// ```
+	#[inline]
+	pub fn insert_typed<T: Any + Send>(&mut self, key: K, value: T) -> Option<DynValue> {
+		self.map.insert(key, Box::new(value))
+	}
+
+	/// Removes a key from the map, returning the value at the key
+	/// if it exists and is of type `T`.
+	///
+	/// If the value is not of type `T`, it is left in the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::DynBTreeMap;
+	///
+	/// let mut map = DynBTreeMap::new();
+	/// map.insert_typed(1, 'a');
+	/// assert_eq!(map.remove_as::<u8, _>(&1), None);
+	/// assert_eq!(map.remove_as::<char, _>(&1), Some('a'));
+	/// assert!(map.is_empty());
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn remove_as<T: Any, Q: ?Sized>(&mut self, key: &Q) -> Option<T>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		if self.is::<T, Q>(key) {
+			self.map
+				.remove(key)
+				.map(|value| *value.downcast::<T>().unwrap())
+		} else {
+			None
+		}
+	}
+
+	/// Removes a key from the map, returning the type-erased value at the key.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<DynValue>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map.remove(key)
+	}
+}
@@ -0,0 +1,542 @@
+use crate::generic::{
+	map::{self, BTreeMap},
+	node::Node,
+};
+use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
+use std::{
+	borrow::Borrow,
+	fmt,
+	iter::{DoubleEndedIterator, ExactSizeIterator, FromIterator, FusedIterator},
+	marker::PhantomData,
+	ops::{Deref, RangeBounds},
+};
+
+/// An ordered map based on a B-Tree, storing its values in a separate slab.
+///
+/// The nodes of the tree only hold the keys and the index of their value
+/// in the value slab `S`.
+/// Values are never moved when the tree is rebalanced,
+/// which makes node splits and merges cheaper for large values,
+/// at the cost of an additional indirection when accessing a value.
+///
+/// The value slab can be provided by the user with [`IndirectBTreeMap::with_containers`],
+/// for instance a pre-allocated slab.
+/// Dynamically sized values, such as byte buffers, are stored boxed in the slab
+/// and can be accessed without the box through [`IndirectBTreeMap::get_deref`].
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::IndirectBTreeMap;
+///
+/// let mut map = IndirectBTreeMap::new();
+/// for i in 0..100 {
+///     map.insert(i, [i; 64]);
+/// }
+///
+/// assert_eq!(map.get(&42), Some(&[42; 64]));
+/// assert_eq!(map.remove(&42), Some([42; 64]));
+/// assert!(map.iter().map(|(k, _)| *k).eq((0..100).filter(|k| *k != 42)));
+/// ```
+pub struct IndirectBTreeMap<K, V, C, S> {
+	/// Map from keys to value indexes.
+	map: BTreeMap<K, usize, C>,
+
+	/// Values storage.
+	values: S,
+
+	v: PhantomData<V>,
+}
+
+impl<K, V, C, S> IndirectBTreeMap<K, V, C, S> {
+	/// Makes a new, empty `IndirectBTreeMap`.
+	#[inline]
+	pub fn new() -> Self
+	where
+		C: Default,
+		S: Default,
+	{
+		IndirectBTreeMap {
+			map: BTreeMap::new(),
+			values: S::default(),
+			v: PhantomData,
+		}
+	}
+
+	/// Makes a new, empty `IndirectBTreeMap`
+	/// using the given containers to allocate the nodes and store the values.
+	///
+	/// Both containers are expected to be empty.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use slab::Slab;
+	/// use btree_slab::IndirectBTreeMap;
+	///
+	/// let mut map = IndirectBTreeMap::with_containers(Slab::new(), Slab::with_capacity(100));
+	/// map.insert(1, vec![0u8; 4096].into_boxed_slice());
+	/// assert!(map.value_slab().capacity() >= 100);
+	/// ```
+	#[inline]
+	pub fn with_containers(nodes: C, values: S) -> Self {
+		IndirectBTreeMap {
+			map: BTreeMap::with_container(nodes),
+			values,
+			v: PhantomData,
+		}
+	}
+
+	/// Returns the underlying map, from keys to value indexes, and the value slab.
+	#[inline]
+	pub fn into_parts(self) -> (BTreeMap<K, usize, C>, S) {
+		(self.map, self.values)
+	}
+
+	/// Returns the number of elements in the map.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.map.len()
+	}
+
+	/// Returns `true` if the map contains no elements.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.map.is_empty()
+	}
+
+	/// Returns the underlying map, from keys to value indexes.
+	#[inline]
+	pub fn as_map(&self) -> &BTreeMap<K, usize, C> {
+		&self.map
+	}
+
+	/// Returns the value slab.
+	#[inline]
+	pub fn value_slab(&self) -> &S {
+		&self.values
+	}
+}
+
+impl<K, V, C: Default, S: Default> Default for IndirectBTreeMap<K, V, C, S> {
+	#[inline]
+	fn default() -> Self {
+		IndirectBTreeMap::new()
+	}
+}
+
+impl<K: Clone, V, C: Clone, S: Clone> Clone for IndirectBTreeMap<K, V, C, S> {
+	#[inline]
+	fn clone(&self) -> Self {
+		IndirectBTreeMap {
+			map: self.map.clone(),
+			values: self.values.clone(),
+			v: PhantomData,
+		}
+	}
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, usize>>, S: Slab<V>> fmt::Debug
+	for IndirectBTreeMap<K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	S: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+
+impl<K, V, C: Slab<Node<K, usize>>, S: Slab<V>> IndirectBTreeMap<K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	S: SimpleCollectionRef,
+{
+	/// Returns the value stored at the given index of the value slab.
+	#[inline]
+	fn value(&self, index: usize) -> &V {
+		S::into_ref(self.values.get(index).expect("missing value"))
+	}
+
+	/// Returns a reference to the value corresponding to the key.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map.get(key).map(|index| self.value(*index))
+	}
+
+	/// Returns a reference to the dereferenced value corresponding to the key.
+	///
+	/// This gives access to the dynamically sized content of boxed values
+	/// without going through the box.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::IndirectBTreeMap;
+	///
+	/// let mut map: IndirectBTreeMap<u32, Box<[u8]>> = IndirectBTreeMap::new();
+	/// map.insert(1, vec![1, 2, 3].into_boxed_slice());
+	///
+	/// let bytes: Option<&[u8]> = map.get_deref(&1);
+	/// assert_eq!(bytes, Some(&[1, 2, 3][..]));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn get_deref<Q: ?Sized>(&self, key: &Q) -> Option<&V::Target>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+		V: Deref,
+	{
+		self.get(key).map(Deref::deref)
+	}
+
+	/// Returns `true` if the map contains a value for the specified key.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map.contains_key(key)
+	}
+
+	/// Returns the first key-value pair in the map.
+	#[inline]
+	pub fn first_key_value(&self) -> Option<(&K, &V)> {
+		self.map
+			.first_key_value()
+			.map(|(k, index)| (k, self.value(*index)))
+	}
+
+	/// Returns the last key-value pair in the map.
+	#[inline]
+	pub fn last_key_value(&self) -> Option<(&K, &V)> {
+		self.map
+			.last_key_value()
+			.map(|(k, index)| (k, self.value(*index)))
+	}
+
+	/// Gets an iterator over the entries of the map, sorted by key.
+	#[inline]
+	pub fn iter(&self) -> Iter<'_, K, V, C, S> {
+		Iter {
+			inner: self.map.iter(),
+			values: &self.values,
+			v: PhantomData,
+		}
+	}
+
+	/// Gets an iterator over the keys of the map, in sorted order.
+	#[inline]
+	pub fn keys(&self) -> map::Keys<'_, K, usize, C> {
+		self.map.keys()
+	}
+
+	/// Constructs a double-ended iterator over a sub-range of elements in the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::IndirectBTreeMap;
+	///
+	/// let map: IndirectBTreeMap<_, _> = (0..10).map(|i| (i, i * 2)).collect();
+	/// assert!(map.range(2..4).map(|(_, v)| *v).eq([4, 6]));
+	/// ```
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn range<T: ?Sized, R>(&self, range: R) -> Range<'_, K, V, C, S>
+	where
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
+	{
+		Range {
+			inner: self.map.range(range),
+			values: &self.values,
+			v: PhantomData,
+		}
+	}
+}
+
+impl<K, V, C: SlabMut<Node<K, usize>>, S: SlabMut<V>> IndirectBTreeMap<K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+	S: SimpleCollectionRef,
+	S: SimpleCollectionMut,
+{
+	/// Returns a mutable reference to the value corresponding to the key.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let index = *self.map.get(key)?;
+		Some(S::into_mut(
+			self.values.get_mut(index).expect("missing value"),
+		))
+	}
+
+	/// Inserts a key-value pair into the map.
+	///
+	/// If the map already had this key present, the value is updated and the old
+	/// value is returned.
+	/// The value keeps its slot in the value slab in that case.
+	#[inline]
+	pub fn insert(&mut self, key: K, value: V) -> Option<V>
+	where
+		K: Ord,
+	{
+		match self.map.get(&key) {
+			Some(index) => {
+				let slot = S::into_mut(self.values.get_mut(*index).expect("missing value"));
+				Some(std::mem::replace(slot, value))
+			}
+			None => {
+				let index = self.values.insert(value);
+				self.map.insert(key, index);
+				None
+			}
+		}
+	}
+
+	/// Removes a key from the map, returning the value at the key if the key
+	/// was previously in the map.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let index = self.map.remove(key)?;
+		self.values.remove(index)
+	}
+
+	/// Clears the map, removing all elements.
+	#[inline]
+	pub fn clear(&mut self)
+	where
+		C: cc_traits::Clear,
+		S: cc_traits::Clear,
+	{
+		self.map.clear();
+		self.values.clear()
+	}
+}
+
+impl<K: Ord, V, C: SlabMut<Node<K, usize>> + Default, S: SlabMut<V> + Default> FromIterator<(K, V)>
+	for IndirectBTreeMap<K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+	S: SimpleCollectionRef,
+	S: SimpleCollectionMut,
+{
+	#[inline]
+	fn from_iter<T>(iter: T) -> Self
+	where
+		T: IntoIterator<Item = (K, V)>,
+	{
+		let mut map = IndirectBTreeMap::new();
+		map.extend(iter);
+		map
+	}
+}
+
+impl<K: Ord, V, C: SlabMut<Node<K, usize>>, S: SlabMut<V>> Extend<(K, V)>
+	for IndirectBTreeMap<K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+	S: SimpleCollectionRef,
+	S: SimpleCollectionMut,
+{
+	#[inline]
+	fn extend<T>(&mut self, iter: T)
+	where
+		T: IntoIterator<Item = (K, V)>,
+	{
+		for (key, value) in iter {
+			self.insert(key, value);
+		}
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<K, usize>>, S: Slab<V>> IntoIterator
+	for &'a IndirectBTreeMap<K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	S: SimpleCollectionRef,
+{
+	type IntoIter = Iter<'a, K, V, C, S>;
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn into_iter(self) -> Iter<'a, K, V, C, S> {
+		self.iter()
+	}
+}
+
+pub struct Iter<'a, K, V, C, S> {
+	inner: map::Iter<'a, K, usize, C>,
+	values: &'a S,
+	v: PhantomData<&'a V>,
+}
+
+impl<'a, K, V, C, S> Clone for Iter<'a, K, V, C, S> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Iter {
+			inner: self.inner.clone(),
+			values: self.values,
+			v: PhantomData,
+		}
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, usize>>, S: Slab<V>> fmt::Debug
+	for Iter<'a, K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	S: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<K, usize>>, S: Slab<V>> Iterator for Iter<'a, K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	S: SimpleCollectionRef,
+{
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<(&'a K, &'a V)> {
+		let (k, index) = self.inner.next()?;
+		Some((
+			k,
+			S::into_ref(self.values.get(*index).expect("missing value")),
+		))
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<K, usize>>, S: Slab<V>> DoubleEndedIterator for Iter<'a, K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	S: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+		let (k, index) = self.inner.next_back()?;
+		Some((
+			k,
+			S::into_ref(self.values.get(*index).expect("missing value")),
+		))
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<K, usize>>, S: Slab<V>> FusedIterator for Iter<'a, K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	S: SimpleCollectionRef,
+{
+}
+
+impl<'a, K, V, C: Slab<Node<K, usize>>, S: Slab<V>> ExactSizeIterator for Iter<'a, K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	S: SimpleCollectionRef,
+{
+}
+
+pub struct Range<'a, K, V, C, S> {
+	inner: map::Range<'a, K, usize, C>,
+	values: &'a S,
+	v: PhantomData<&'a V>,
+}
+
+impl<'a, K, V, C, S> Clone for Range<'a, K, V, C, S> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Range {
+			inner: self.inner.clone(),
+			values: self.values,
+			v: PhantomData,
+		}
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, usize>>, S: Slab<V>> fmt::Debug
+	for Range<'a, K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	S: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<K, usize>>, S: Slab<V>> Iterator for Range<'a, K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	S: SimpleCollectionRef,
+{
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn next(&mut self) -> Option<(&'a K, &'a V)> {
+		let (k, index) = self.inner.next()?;
+		Some((
+			k,
+			S::into_ref(self.values.get(*index).expect("missing value")),
+		))
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<K, usize>>, S: Slab<V>> DoubleEndedIterator for Range<'a, K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	S: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+		let (k, index) = self.inner.next_back()?;
+		Some((
+			k,
+			S::into_ref(self.values.get(*index).expect("missing value")),
+		))
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<K, usize>>, S: Slab<V>> FusedIterator for Range<'a, K, V, C, S>
+where
+	C: SimpleCollectionRef,
+	S: SimpleCollectionRef,
+{
+}
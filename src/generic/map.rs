@@ -1,19 +1,73 @@
-use crate::generic::node::{Address, Balance, Item, Node, WouldUnderflow};
+use crate::generic::{
+	node::{
+		internal::Branch, Address, Balance, InternalNode, Item, LeafNode, Node, WouldUnderflow,
+	},
+	BTreeSet,
+};
 use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
 use std::{
 	borrow::Borrow,
 	cmp::Ordering,
+	convert::Infallible,
+	fmt,
 	hash::{Hash, Hasher},
 	iter::{DoubleEndedIterator, ExactSizeIterator, FromIterator, FusedIterator},
 	marker::PhantomData,
-	ops::{Bound, Index, RangeBounds},
+	ops::{Bound, ControlFlow, Index, IndexMut, RangeBounds},
 };
 
+mod arena;
+#[cfg(feature = "binary")]
+mod binary;
+#[cfg(feature = "bloom")]
+mod bloom;
+mod capacity;
+#[cfg(feature = "checksum")]
+mod checksum;
+mod cursor;
 mod entry;
 mod ext;
-
+mod invariant;
+mod limits;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod nodes;
+#[cfg(feature = "ord-check")]
+mod ord_check;
+#[cfg(feature = "rayon")]
+mod par;
+mod raw;
+mod snapshot;
+mod split_policy;
+mod stats;
+#[cfg(feature = "structural-hash")]
+mod structural_hash;
+#[cfg(feature = "watermark")]
+mod watermark;
+
+pub use arena::{ArenaRef, TreeRoot};
+#[cfg(feature = "binary")]
+pub use binary::BinaryCodec;
+pub use capacity::CapacityError;
+#[cfg(feature = "checksum")]
+pub use checksum::IntegrityError;
+pub use cursor::{Cursor, CursorMut, UnorderedKeyError};
 pub use entry::*;
 pub use ext::*;
+pub use invariant::{Invariant, InvariantError};
+pub use limits::{max_depth, Limit, LimitError, Limits};
+#[cfg(feature = "metrics")]
+pub use metrics::*;
+pub use nodes::{NodeRef, Nodes};
+#[cfg(feature = "rayon")]
+pub use par::{IntoParIter, ParIter, ParIterMut};
+pub use snapshot::BTreeSnapshot;
+pub use split_policy::SplitPolicy;
+pub use stats::Stats;
+#[cfg(feature = "watermark")]
+pub use watermark::{DirtyWatermarks, WatermarkId, WatermarkLimitReached, MAX_WATERMARKS};
+
+use raw::RawItemsMut;
 
 /// Knuth order of the B-Trees.
 ///
@@ -146,6 +200,18 @@ pub const M: usize = 8;
 /// These functions are not intended to be directly called by the users,
 /// but can be used to extend the data structure with new functionalities.
 ///
+/// ## Thread safety
+///
+/// The map owns its keys, values and node container, and is [`Send`] (resp. [`Sync`])
+/// whenever `K`, `V` and `C` are.
+/// Borrowing iterators behave like a shared reference to the map,
+/// and mutable iterators and cursors ([`IterMut`], [`RangeMut`], [`ValuesMut`],
+/// [`EntriesMut`], [`CursorMut`], ...) like a mutable reference:
+/// they are [`Send`] and [`Sync`] under the same conditions as `&mut BTreeMap<K, V, C>`.
+/// The unsafe lifetime extensions performed by the mutable iterators
+/// never give out two references to the same item,
+/// so these automatically derived guarantees hold.
+///
 /// # Correctness
 ///
 /// It is a logic error for a key to be modified in such a way that the key's ordering relative
@@ -163,10 +229,54 @@ pub struct BTreeMap<K, V, C> {
 	/// Number of items in the tree.
 	len: usize,
 
+	/// Identifiers of the first and last leaves, if the tree is not empty.
+	///
+	/// They give access to the first and last items without descending the tree.
+	leaf_bounds: Option<(usize, usize)>,
+
+	/// Counter of the modifications moving items or changing their keys,
+	/// used to detect outdated addresses.
+	generation: usize,
+
+	/// Size limits and split policy, if any was set.
+	settings: Option<Box<Settings>>,
+
+	/// Node checksums, if the map is sealed.
+	#[cfg(feature = "checksum")]
+	checksums: Option<checksum::Checksums<K, V>>,
+
+	/// Operations metrics receiver.
+	#[cfg(feature = "metrics")]
+	metrics: Option<std::sync::Arc<dyn MetricsSink>>,
+
+	/// Watermark ranges and their dirty flags.
+	#[cfg(feature = "watermark")]
+	watermarks: watermark::Watermarks<K>,
+
+	/// Key filter, if enabled.
+	#[cfg(feature = "bloom")]
+	key_filter: Option<bloom::KeyFilter<K>>,
+
+	/// Rolling hash of the keys, if enabled.
+	#[cfg(feature = "structural-hash")]
+	structural_hash: Option<structural_hash::StructuralHash<K>>,
+
 	k: PhantomData<K>,
 	v: PhantomData<V>,
 }
 
+/// Settings of a map that are rarely changed.
+///
+/// They are boxed so that maps using the default settings stay small.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+struct Settings {
+	/// Size limits enforced by `checked_insert`.
+	limits: Limits,
+
+	/// Where overflowing nodes are split.
+	split_policy: SplitPolicy,
+}
+
 impl<K, V, C> BTreeMap<K, V, C> {
 	/// Create a new empty B-tree.
 	#[inline]
@@ -178,6 +288,63 @@ impl<K, V, C> BTreeMap<K, V, C> {
 			nodes: Default::default(),
 			root: None,
 			len: 0,
+			leaf_bounds: None,
+			generation: 0,
+			settings: None,
+			#[cfg(feature = "checksum")]
+			checksums: None,
+			#[cfg(feature = "metrics")]
+			metrics: None,
+			#[cfg(feature = "watermark")]
+			watermarks: Default::default(),
+			#[cfg(feature = "bloom")]
+			key_filter: None,
+			#[cfg(feature = "structural-hash")]
+			structural_hash: None,
+			k: PhantomData,
+			v: PhantomData,
+		}
+	}
+
+	/// Create a new empty B-tree using the given container to allocate nodes.
+	///
+	/// This can be used to provide a pre-allocated container
+	/// instead of the default one.
+	/// The container is expected to be empty.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use slab::Slab;
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, &str> = BTreeMap::with_container(Slab::with_capacity(16));
+	/// map.insert(1, "a");
+	/// assert_eq!(map.get(&1), Some(&"a"));
+	/// ```
+	///
+	/// This is a `const fn`, so that a map can be created in a constant or `static` item
+	/// from a container with a `const` constructor
+	/// (see also [`BTreeMap::EMPTY`]).
+	#[inline]
+	pub const fn with_container(nodes: C) -> BTreeMap<K, V, C> {
+		BTreeMap {
+			nodes,
+			root: None,
+			len: 0,
+			leaf_bounds: None,
+			generation: 0,
+			settings: None,
+			#[cfg(feature = "checksum")]
+			checksums: None,
+			#[cfg(feature = "metrics")]
+			metrics: None,
+			#[cfg(feature = "watermark")]
+			watermarks: watermark::Watermarks::new(),
+			#[cfg(feature = "bloom")]
+			key_filter: None,
+			#[cfg(feature = "structural-hash")]
+			structural_hash: None,
 			k: PhantomData,
 			v: PhantomData,
 		}
@@ -216,12 +383,98 @@ impl<K, V, C> BTreeMap<K, V, C> {
 	pub fn len(&self) -> usize {
 		self.len
 	}
+
+	/// Attaches a metrics sink to the map, replacing the previous one.
+	///
+	/// The sink is notified of the single-key lookups, insertions and removals
+	/// performed on the map, listed by [`Operation`].
+	/// Other operations, such as iterations, cursors, range or bulk operations
+	/// and the methods working on addresses, are not reported.
+	/// It is shared by the clones of the map.
+	///
+	/// Requires the `metrics` feature.
+	#[cfg(feature = "metrics")]
+	#[inline]
+	pub fn set_metrics_sink(&mut self, sink: std::sync::Arc<dyn MetricsSink>) {
+		self.metrics = Some(sink)
+	}
+
+	/// Detaches the metrics sink of the map, if any.
+	///
+	/// Requires the `metrics` feature.
+	#[cfg(feature = "metrics")]
+	#[inline]
+	pub fn take_metrics_sink(&mut self) -> Option<std::sync::Arc<dyn MetricsSink>> {
+		self.metrics.take()
+	}
+
+	/// Returns the metrics sink attached to the map, if any.
+	///
+	/// Requires the `metrics` feature.
+	#[cfg(feature = "metrics")]
+	#[inline]
+	pub fn metrics_sink(&self) -> Option<&std::sync::Arc<dyn MetricsSink>> {
+		self.metrics.as_ref()
+	}
 }
 
 impl<K, V, C: Slab<Node<K, V>>> BTreeMap<K, V, C>
 where
 	C: SimpleCollectionRef,
 {
+	/// Reports the given operation, ending its descent at the given address,
+	/// to the metrics sink.
+	#[cfg(feature = "metrics")]
+	#[inline]
+	fn record(&self, op: Operation, addr: Address) {
+		if let Some(sink) = &self.metrics {
+			let mut depth = 0;
+			if !addr.is_nowhere() {
+				let mut id = Some(addr.id);
+				while let Some(i) = id {
+					depth += 1;
+					id = self.node(i).parent()
+				}
+			}
+
+			sink.record(op, depth)
+		}
+	}
+
+	/// Returns a reference to the value corresponding to the key,
+	/// panicking with the given message and the key if it is not present.
+	///
+	/// The key may be any borrowed form of the map's key type, but the ordering
+	/// on the borrowed form *must* match the ordering on the key type.
+	///
+	/// # Panics
+	///
+	/// Panics if the key is not present in the map, with a message of the form
+	/// `"{msg}: {key:?}"`.
+	///
+	/// # Example
+	///
+	/// ```should_panic
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert(1, "a");
+	/// assert_eq!(map.get_expect(&1, "unknown order"), &"a");
+	/// map.get_expect(&2, "unknown order"); // panics with "unknown order: 2"
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn get_expect<Q: ?Sized>(&self, key: &Q, msg: &str) -> &V
+	where
+		K: Borrow<Q>,
+		Q: Ord + fmt::Debug,
+	{
+		match self.get(key) {
+			Some(value) => value,
+			None => panic!("{}: {:?}", msg, key),
+		}
+	}
+
 	/// Returns the key-value pair corresponding to the supplied key.
 	///
 	/// The supplied key may be any borrowed form of the map's key type, but the ordering
@@ -238,11 +491,19 @@ where
 	/// assert_eq!(map.get_key_value(&2), None);
 	/// ```
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
 	where
 		K: Borrow<Q>,
 		Q: Ord,
 	{
+		#[cfg(feature = "metrics")]
+		if self.metrics.is_some() {
+			let addr = self.address_of(key);
+			self.record(Operation::Get, addr.unwrap_or_else(|addr| addr));
+			return addr.ok().map(|addr| self.item(addr).unwrap().value());
+		}
+
 		match self.root {
 			Some(id) => self.get_in(key, id),
 			None => None,
@@ -265,12 +526,18 @@ where
 	/// assert_eq!(map.get_key_value(&2), None);
 	/// ```
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn get_key_value<Q: ?Sized>(&self, k: &Q) -> Option<(&K, &V)>
 	where
 		K: Borrow<Q>,
 		Q: Ord,
 	{
-		match self.address_of(k) {
+		let addr = self.address_of(k);
+
+		#[cfg(feature = "metrics")]
+		self.record(Operation::Get, addr.unwrap_or_else(|addr| addr));
+
+		match addr {
 			Ok(addr) => {
 				let item = self.item(addr).unwrap();
 				Some((item.key(), item.value()))
@@ -282,6 +549,9 @@ where
 	/// Returns the first key-value pair in the map.
 	/// The key in this pair is the minimum key in the map.
 	///
+	/// The first and last leaves of the tree are tracked by the map,
+	/// so this does not descend the tree.
+	///
 	/// # Example
 	///
 	/// ```
@@ -307,6 +577,9 @@ where
 	/// Returns the last key-value pair in the map.
 	/// The key in this pair is the maximum key in the map.
 	///
+	/// The first and last leaves of the tree are tracked by the map,
+	/// so this does not descend the tree.
+	///
 	/// # Examples
 	///
 	/// Basic usage:
@@ -330,6 +603,142 @@ where
 		}
 	}
 
+	/// Returns the first key in the map.
+	/// This is the minimum key in the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// assert_eq!(map.first_key(), None);
+	/// map.insert(1, "b");
+	/// map.insert(2, "a");
+	/// assert_eq!(map.first_key(), Some(&1));
+	/// ```
+	#[inline]
+	pub fn first_key(&self) -> Option<&K> {
+		self.first_item_address()
+			.map(|addr| self.item(addr).unwrap().key())
+	}
+
+	/// Returns the last key in the map.
+	/// This is the maximum key in the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// assert_eq!(map.last_key(), None);
+	/// map.insert(1, "b");
+	/// map.insert(2, "a");
+	/// assert_eq!(map.last_key(), Some(&2));
+	/// ```
+	#[inline]
+	pub fn last_key(&self) -> Option<&K> {
+		self.last_item_address()
+			.map(|addr| self.item(addr).unwrap().key())
+	}
+
+	/// Returns the key-value pair at the given position in the map,
+	/// the first pair being at position `0`.
+	///
+	/// Each internal node keeps track of the number of items in its subtree,
+	/// so this takes `O(log n)` time.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let map: BTreeMap<_, _> = (0..100).map(|i| (i * 2, i)).collect();
+	/// assert_eq!(map.get_nth(0), Some((&0, &0)));
+	/// assert_eq!(map.get_nth(42), Some((&84, &42)));
+	/// assert_eq!(map.get_nth(100), None);
+	/// ```
+	#[inline]
+	pub fn get_nth(&self, index: usize) -> Option<(&K, &V)> {
+		self.nth_address(index)
+			.map(|addr| self.item(addr).unwrap().as_pair())
+	}
+
+	/// Returns the address of the item at the given position in the map,
+	/// the first item being at position `0`.
+	///
+	/// This takes `O(log n)` time.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::{BTreeMap, generic::map::BTreeExt};
+	///
+	/// let map: BTreeMap<_, _> = (0..100).map(|i| (i, i)).collect();
+	/// let addr = map.nth_address(10).unwrap();
+	/// assert_eq!(map.item(addr).unwrap().key(), &10);
+	/// assert_eq!(map.nth_address(100), None);
+	/// ```
+	#[inline]
+	pub fn nth_address(&self, mut index: usize) -> Option<Address> {
+		if index >= self.len {
+			return None;
+		}
+
+		let mut id = self.root?;
+		'descend: loop {
+			let node = self.node(id);
+			if node.child_count() == 0 {
+				return Some(Address::new(id, index.into()));
+			}
+
+			for (offset, child_id) in node.children().enumerate() {
+				let child_len = self.node(child_id).subtree_len();
+				match index.cmp(&child_len) {
+					Ordering::Less => {
+						id = child_id;
+						continue 'descend;
+					}
+					Ordering::Equal => return Some(Address::new(id, offset.into())),
+					Ordering::Greater => index -= child_len + 1,
+				}
+			}
+
+			unreachable!("inconsistent subtree length")
+		}
+	}
+
+	/// Returns the position of the given key in the map.
+	///
+	/// If the key is in the map, `Ok` is returned with its position.
+	/// Otherwise, `Err` is returned with the position at which the key would be inserted,
+	/// just like [`slice::binary_search`].
+	/// This takes `O(log n)` time.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let map: BTreeMap<_, _> = (0..100).map(|i| (i * 2, i)).collect();
+	/// assert_eq!(map.rank_of(&84), Ok(42));
+	/// assert_eq!(map.rank_of(&85), Err(43));
+	/// assert_eq!(map.rank_of(&1000), Err(100));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn rank_of<Q: ?Sized>(&self, key: &Q) -> Result<usize, usize>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		match self.address_of(key) {
+			Ok(addr) => Ok(self.rank_of_address(addr)),
+			Err(addr) => Err(self.rank_of_address(addr)),
+		}
+	}
+
 	/// Gets an iterator over the entries of the map, sorted by key.
 	///
 	/// # Example
@@ -350,7 +759,7 @@ where
 	/// assert_eq!((*first_key, *first_value), (1, "a"));
 	/// ```
 	#[inline]
-	pub fn iter(&self) -> Iter<K, V, C> {
+	pub fn iter(&self) -> Iter<'_, K, V, C> {
 		Iter::new(self)
 	}
 
@@ -369,7 +778,7 @@ where
 	/// assert_eq!(keys, [1, 2]);
 	/// ```
 	#[inline]
-	pub fn keys(&self) -> Keys<K, V, C> {
+	pub fn keys(&self) -> Keys<'_, K, V, C> {
 		Keys { inner: self.iter() }
 	}
 
@@ -388,10 +797,35 @@ where
 	/// assert_eq!(values, ["hello", "goodbye"]);
 	/// ```
 	#[inline]
-	pub fn values(&self) -> Values<K, V, C> {
+	pub fn values(&self) -> Values<'_, K, V, C> {
 		Values { inner: self.iter() }
 	}
 
+	/// Gets an iterator over the entries of the map, sorted by key,
+	/// yielding owned clones of the keys and borrowed values.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert(String::from("b"), 2);
+	/// map.insert(String::from("a"), 1);
+	///
+	/// let mut iter = map.iter_cloned_keys();
+	/// assert_eq!(iter.len(), 2);
+	/// assert_eq!(iter.next(), Some((String::from("a"), &1)));
+	/// assert_eq!(iter.next_back(), Some((String::from("b"), &2)));
+	/// ```
+	#[inline]
+	pub fn iter_cloned_keys(&self) -> ClonedKeys<Iter<'_, K, V, C>>
+	where
+		K: Clone,
+	{
+		ClonedKeys { inner: self.iter() }
+	}
+
 	/// Constructs a double-ended iterator over a sub-range of elements in the map.
 	/// The simplest way is to use the range syntax `min..max`, thus `range(min..max)` will
 	/// yield elements from min (inclusive) to max (exclusive).
@@ -399,10 +833,14 @@ where
 	/// `range((Excluded(4), Included(10)))` will yield a left-exclusive, right-inclusive
 	/// range from 4 to 10.
 	///
+	/// Bounds do not need to be keys of the map:
+	/// a range with no entries gives an empty iterator.
+	///
 	/// # Panics
 	///
 	/// Panics if range `start > end`.
 	/// Panics if range `start == end` and both bounds are `Excluded`.
+	/// Use [`BTreeMap::try_range`] to check the range instead.
 	///
 	/// # Example
 	///
@@ -420,303 +858,311 @@ where
 	/// assert_eq!(Some((&5, &"b")), map.range(4..).next());
 	/// ```
 	#[inline]
-	pub fn range<T: ?Sized, R>(&self, range: R) -> Range<K, V, C>
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn range<T: ?Sized, R>(&self, range: R) -> Range<'_, K, V, C>
 	where
 		T: Ord,
 		K: Borrow<T>,
 		R: RangeBounds<T>,
 	{
-		Range::new(self, range)
+		match Range::new(self, range) {
+			Ok(range) => range,
+			Err(InvalidRange) => panic!("Invalid range"),
+		}
 	}
 
-	/// Returns `true` if the map contains a value for the specified key.
+	/// Constructs a double-ended iterator over a sub-range of elements in the map,
+	/// or returns an error if the range bounds are not ordered.
 	///
-	/// The key may be any borrowed form of the map's key type, but the ordering
-	/// on the borrowed form *must* match the ordering on the key type.
+	/// This is the non-panicking version of [`BTreeMap::range`].
 	///
 	/// # Example
-	/// ```
-	/// use btree_slab::BTreeMap;
 	///
-	/// let mut map: BTreeMap<i32, &str> = BTreeMap::new();
-	/// map.insert(1, "a");
-	/// assert_eq!(map.contains_key(&1), true);
-	/// assert_eq!(map.contains_key(&2), false);
+	/// ```
+	/// use btree_slab::{BTreeMap, generic::map::InvalidRange};
+	/// use std::ops::Bound::Excluded;
+	///
+	/// let map: BTreeMap<i32, i32> = (0..10).map(|i| (i * 2, i)).collect();
+	/// assert!(map.try_range((Excluded(3), Excluded(9))).unwrap().eq([(&4, &2), (&6, &3), (&8, &4)]));
+	/// assert_eq!(map.try_range(100..).unwrap().next(), None);
+	/// assert_eq!(map.try_range(5..3).err(), Some(InvalidRange));
+	/// assert_eq!(map.try_range((Excluded(5), Excluded(5))).err(), Some(InvalidRange));
 	/// ```
 	#[inline]
-	pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn try_range<T: ?Sized, R>(&self, range: R) -> Result<Range<'_, K, V, C>, InvalidRange>
 	where
-		K: Borrow<Q>,
-		Q: Ord,
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
 	{
-		self.get(key).is_some()
+		Range::new(self, range)
 	}
 
-	/// Write the tree in the DOT graph descrption language.
+	/// Constructs a double-ended iterator over the entries between two addresses.
 	///
-	/// Requires the `dot` feature.
-	#[cfg(feature = "dot")]
+	/// The iterator starts with the item at `start` and stops before the item at `end`.
+	/// An address pointing after the last item of a node designates the next item of the map,
+	/// or the end of the map if there is none.
+	/// Addresses returned by [`BTreeExt`] methods such as [`BTreeExt::address_of`]
+	/// or by [`Cursor::address`] can be used.
+	///
+	/// Returns an error if one of the addresses is not a valid address of the map,
+	/// or if `start` is after `end`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use btree_slab::generic::map::BTreeExt;
+	///
+	/// let map: BTreeMap<_, _> = (0..100).map(|i| (i, i * 2)).collect();
+	/// let start = map.address_of(&10).unwrap();
+	/// let end = map.address_of(&13).unwrap();
+	///
+	/// let range = map.range_between(start, end).unwrap();
+	/// assert!(range.eq([(&10, &20), (&11, &22), (&12, &24)]));
+	/// assert!(map.range_between(end, start).is_err());
+	/// ```
 	#[inline]
-	pub fn dot_write<W: std::io::Write>(&self, f: &mut W) -> std::io::Result<()>
+	pub fn range_between(
+		&self,
+		start: Address,
+		end: Address,
+	) -> Result<Range<'_, K, V, C>, InvalidAddressRange>
 	where
-		K: std::fmt::Display,
-		V: std::fmt::Display,
+		K: Ord,
 	{
-		write!(f, "digraph tree {{\n\tnode [shape=record];\n")?;
-		if let Some(id) = self.root {
-			self.dot_write_node(f, id)?
-		}
-		write!(f, "}}")
+		let (addr, end) = self.address_range(start, end)?;
+		Ok(Range {
+			btree: self,
+			addr,
+			end,
+		})
 	}
 
-	/// Write the given node in the DOT graph descrption language.
-	///
-	/// Requires the `dot` feature.
-	#[cfg(feature = "dot")]
+	/// Normalizes the given range addresses,
+	/// checking that they are valid and ordered.
 	#[inline]
-	fn dot_write_node<W: std::io::Write>(&self, f: &mut W, id: usize) -> std::io::Result<()>
+	fn address_range(
+		&self,
+		start: Address,
+		end: Address,
+	) -> Result<(Address, Address), InvalidAddressRange>
 	where
-		K: std::fmt::Display,
-		V: std::fmt::Display,
+		K: Ord,
 	{
-		let name = format!("n{}", id);
-		let node = self.node(id);
+		if !self.is_valid_address(start) {
+			return Err(InvalidAddressRange::InvalidStart(start));
+		}
 
-		write!(f, "\t{} [label=\"", name)?;
-		if let Some(parent) = node.parent() {
-			write!(f, "({})|", parent)?;
+		if !self.is_valid_address(end) {
+			return Err(InvalidAddressRange::InvalidEnd(end));
 		}
 
-		node.dot_write_label(f)?;
-		writeln!(f, "({})\"];", id)?;
+		let start = self.normalize_or_last(start);
+		let end = self.normalize_or_last(end);
+		let start_key = self.item(start).map(Item::key);
+		let end_key = self.item(end).map(Item::key);
 
-		for child_id in node.children() {
-			self.dot_write_node(f, child_id)?;
-			let child_name = format!("n{}", child_id);
-			writeln!(f, "\t{} -> {}", name, child_name)?;
+		match (start_key, end_key) {
+			(None, Some(_)) => Err(InvalidAddressRange::Reversed),
+			(Some(a), Some(b)) if a > b => Err(InvalidAddressRange::Reversed),
+			_ => Ok((start, end)),
 		}
+	}
 
-		Ok(())
+	/// Checks that the given address is an item address or a back address of the map.
+	#[inline]
+	fn is_valid_address(&self, addr: Address) -> bool {
+		if addr.is_nowhere() {
+			self.is_empty()
+		} else {
+			self.is_node_reachable(addr.id)
+				&& addr
+					.offset
+					.value()
+					.is_none_or(|offset| offset <= self.node(addr.id).item_count())
+		}
 	}
-}
 
-impl<K, V, C: SlabMut<Node<K, V>>> BTreeMap<K, V, C>
-where
-	C: SimpleCollectionRef,
-	C: SimpleCollectionMut,
-{
-	/// Clears the map, removing all elements.
-	///
-	/// # Example
-	///
-	/// ```
-	/// use btree_slab::BTreeMap;
+	/// Panics with a clear message if the given address does not point into a live node,
+	/// or if its offset is out of the node bounds.
 	///
-	/// let mut a = BTreeMap::new();
-	/// a.insert(1, "a");
-	/// a.clear();
-	/// assert!(a.is_empty());
-	/// ```
+	/// Back addresses, right after the last item of a node, are accepted if `allow_back` is `true`.
+	/// Called by the extended API operations when the `validate-addresses` feature is enabled.
+	#[cfg(feature = "validate-addresses")]
 	#[inline]
-	pub fn clear(&mut self)
-	where
-		C: cc_traits::Clear,
-	{
-		self.root = None;
-		self.len = 0;
-		self.nodes.clear()
+	fn assert_live_address(&self, addr: Address, operation: &str, allow_back: bool) {
+		if addr.is_nowhere() {
+			panic!("`{}` called with the nowhere address", operation)
+		}
+
+		let item_count = match self.nodes.get(addr.id) {
+			Some(node) => C::into_ref(node).item_count(),
+			None => panic!(
+				"`{}` called with address {} to node {}, which is not allocated",
+				operation, addr, addr.id
+			),
+		};
+
+		if let Some(offset) = addr.offset.value() {
+			let in_bounds = offset < item_count || (allow_back && offset == item_count);
+			if !in_bounds {
+				panic!(
+					"`{}` called with address {}, out of the bounds of node {} holding {} items",
+					operation, addr, addr.id, item_count
+				)
+			}
+		}
 	}
 
-	/// Returns a mutable reference to the value corresponding to the key.
+	/// Constructs a double-ended iterator over a sub-range of entries in the map,
+	/// yielding owned clones of the keys and borrowed values.
 	///
-	/// The key may be any borrowed form of the map's key type, but the ordering
-	/// on the borrowed form *must* match the ordering on the key type.
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
 	///
 	/// # Example
 	///
 	/// ```
 	/// use btree_slab::BTreeMap;
 	///
-	/// let mut map = BTreeMap::new();
-	/// map.insert(1, "a");
-	/// if let Some(x) = map.get_mut(&1) {
-	///     *x = "b";
-	/// }
-	/// assert_eq!(map[&1], "b");
+	/// let map: BTreeMap<i32, char> = (0..5).zip('a'..).collect();
+	/// let entries: Vec<(i32, &char)> = map.range_cloned_keys(1..3).collect();
+	/// assert_eq!(entries, [(1, &'b'), (2, &'c')]);
 	/// ```
 	#[inline]
-	pub fn get_mut(&mut self, key: &K) -> Option<&mut V>
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn range_cloned_keys<T: ?Sized, R>(&self, range: R) -> ClonedKeys<Range<'_, K, V, C>>
 	where
-		K: Ord,
+		K: Clone,
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
 	{
-		match self.root {
-			Some(id) => self.get_mut_in(key, id),
-			None => None,
+		ClonedKeys {
+			inner: self.range(range),
 		}
 	}
 
-	/// Gets the given key's corresponding entry in the map for in-place manipulation.
+	/// Clones the entries with a key in the given range and appends them to `out`,
+	/// sorted by key.
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
 	///
 	/// # Example
 	///
 	/// ```
 	/// use btree_slab::BTreeMap;
 	///
-	/// let mut letters = BTreeMap::new();
-	///
-	/// for ch in "a short treatise on fungi".chars() {
-	///     let counter = letters.entry(ch).or_insert(0);
-	///     *counter += 1;
-	/// }
-	///
-	/// assert_eq!(letters[&'s'], 2);
-	/// assert_eq!(letters[&'t'], 3);
-	/// assert_eq!(letters[&'u'], 1);
-	/// assert_eq!(letters.get(&'y'), None);
+	/// let map: BTreeMap<i32, char> = (0..5).zip('a'..).collect();
+	/// let mut out = vec![(-1, 'z')];
+	/// map.collect_range_into(3.., &mut out);
+	/// assert_eq!(out, [(-1, 'z'), (3, 'd'), (4, 'e')]);
 	/// ```
 	#[inline]
-	pub fn entry(&mut self, key: K) -> Entry<K, V, C>
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn collect_range_into<T: ?Sized, R>(&self, range: R, out: &mut Vec<(K, V)>)
 	where
-		K: Ord,
+		K: Clone,
+		V: Clone,
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
 	{
-		match self.address_of(&key) {
-			Ok(addr) => Entry::Occupied(OccupiedEntry { map: self, addr }),
-			Err(addr) => Entry::Vacant(VacantEntry {
-				map: self,
-				key,
-				addr,
-			}),
-		}
+		out.extend(
+			self.range(range)
+				.map(|(key, value)| (key.clone(), value.clone())),
+		)
 	}
 
-	/// Returns the first entry in the map for in-place manipulation.
-	/// The key of this entry is the minimum key in the map.
+	/// Returns the minimum key of the map in the given range.
 	///
-	/// # Example
+	/// Only the path leading to the start of the range is visited.
 	///
-	/// ```
-	/// use btree_slab::BTreeMap;
+	/// # Panics
 	///
-	/// let mut map = BTreeMap::new();
-	/// map.insert(1, "a");
-	/// map.insert(2, "b");
-	/// if let Some(mut entry) = map.first_entry() {
-	///     if *entry.key() > 0 {
-	///         entry.insert("first");
-	///     }
-	/// }
-	/// assert_eq!(*map.get(&1).unwrap(), "first");
-	/// assert_eq!(*map.get(&2).unwrap(), "b");
-	/// ```
-	#[inline]
-	pub fn first_entry(&mut self) -> Option<OccupiedEntry<K, V, C>> {
-		self.first_item_address()
-			.map(move |addr| OccupiedEntry { map: self, addr })
-	}
-
-	/// Returns the last entry in the map for in-place manipulation.
-	/// The key of this entry is the maximum key in the map.
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
 	///
 	/// # Example
 	///
 	/// ```
 	/// use btree_slab::BTreeMap;
 	///
-	/// let mut map = BTreeMap::new();
-	/// map.insert(1, "a");
-	/// map.insert(2, "b");
-	/// if let Some(mut entry) = map.last_entry() {
-	///     if *entry.key() > 0 {
-	///         entry.insert("last");
-	///     }
-	/// }
-	/// assert_eq!(*map.get(&1).unwrap(), "a");
-	/// assert_eq!(*map.get(&2).unwrap(), "last");
+	/// let map: BTreeMap<i32, ()> = (0..10).map(|i| (i * 2, ())).collect();
+	/// assert_eq!(map.min_key_in(3..), Some(&4));
+	/// assert_eq!(map.min_key_in(5..6), None);
 	/// ```
 	#[inline]
-	pub fn last_entry(&mut self) -> Option<OccupiedEntry<K, V, C>> {
-		self.last_item_address()
-			.map(move |addr| OccupiedEntry { map: self, addr })
-	}
-
-	/// Insert a key-value pair in the tree.
-	#[inline]
-	pub fn insert(&mut self, key: K, value: V) -> Option<V>
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn min_key_in<T: ?Sized, R>(&self, range: R) -> Option<&K>
 	where
-		K: Ord,
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
 	{
-		match self.address_of(&key) {
-			Ok(addr) => Some(self.replace_value_at(addr, value)),
-			Err(addr) => {
-				self.insert_exactly_at(addr, Item::new(key, value), None);
-				None
-			}
+		if !is_valid_range(&range) {
+			panic!("Invalid range")
 		}
-	}
 
-	/// Replace a key-value pair in the tree.
-	#[inline]
-	pub fn replace(&mut self, key: K, value: V) -> Option<(K, V)>
-	where
-		K: Ord,
-	{
-		match self.address_of(&key) {
-			Ok(addr) => Some(self.replace_at(addr, key, value)),
-			Err(addr) => {
-				self.insert_exactly_at(addr, Item::new(key, value), None);
-				None
-			}
+		if self.is_empty() {
+			return None;
 		}
+
+		let addr = self.range_start_address(range.start_bound());
+		self.item(addr)
+			.map(Item::key)
+			.filter(|key| is_before_end(range.end_bound(), (*key).borrow()))
 	}
 
-	/// Removes and returns the first element in the map.
-	/// The key of this element is the minimum key that was in the map.
-	///
-	/// # Example
+	/// Returns the maximum key of the map in the given range.
 	///
-	/// Draining elements in ascending order, while keeping a usable map each iteration.
+	/// Only the path leading to the end of the range is visited.
 	///
-	/// ```
-	/// use btree_slab::BTreeMap;
+	/// # Panics
 	///
-	/// let mut map = BTreeMap::new();
-	/// map.insert(1, "a");
-	/// map.insert(2, "b");
-	/// while let Some((key, _val)) = map.pop_first() {
-	///     assert!(map.iter().all(|(k, _v)| *k > key));
-	/// }
-	/// assert!(map.is_empty());
-	/// ```
-	#[inline]
-	pub fn pop_first(&mut self) -> Option<(K, V)> {
-		self.first_entry().map(|entry| entry.remove_entry())
-	}
-
-	/// Removes and returns the last element in the map.
-	/// The key of this element is the maximum key that was in the map.
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
 	///
 	/// # Example
 	///
-	/// Draining elements in descending order, while keeping a usable map each iteration.
-	///
 	/// ```
 	/// use btree_slab::BTreeMap;
 	///
-	/// let mut map = BTreeMap::new();
-	/// map.insert(1, "a");
-	/// map.insert(2, "b");
-	/// while let Some((key, _val)) = map.pop_last() {
-	///     assert!(map.iter().all(|(k, _v)| *k < key));
-	/// }
-	/// assert!(map.is_empty());
+	/// let map: BTreeMap<i32, ()> = (0..10).map(|i| (i * 2, ())).collect();
+	/// assert_eq!(map.max_key_in(..=7), Some(&6));
+	/// assert_eq!(map.max_key_in(5..6), None);
 	/// ```
 	#[inline]
-	pub fn pop_last(&mut self) -> Option<(K, V)> {
-		self.last_entry().map(|entry| entry.remove_entry())
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn max_key_in<T: ?Sized, R>(&self, range: R) -> Option<&K>
+	where
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
+	{
+		if !is_valid_range(&range) {
+			panic!("Invalid range")
+		}
+
+		if self.is_empty() {
+			return None;
+		}
+
+		let end = self.range_end_address(range.end_bound());
+		self.previous_item_address(end)
+			.map(|addr| self.item(addr).unwrap().key())
+			.filter(|key| is_after_start(range.start_bound(), (*key).borrow()))
 	}
 
-	/// Removes a key from the map, returning the value at the key if the key
-	/// was previously in the map.
+	/// Returns the key-value pair with the smallest key strictly greater than the given key.
 	///
 	/// The key may be any borrowed form of the map's key type, but the ordering
 	/// on the borrowed form *must* match the ordering on the key type.
@@ -726,389 +1172,3459 @@ where
 	/// ```
 	/// use btree_slab::BTreeMap;
 	///
-	/// let mut map = BTreeMap::new();
-	/// map.insert(1, "a");
-	/// assert_eq!(map.remove(&1), Some("a"));
-	/// assert_eq!(map.remove(&1), None);
+	/// let map: BTreeMap<i32, char> = [(1, 'a'), (3, 'b'), (5, 'c')].iter().cloned().collect();
+	/// assert_eq!(map.strictly_above(&1), Some((&3, &'b')));
+	/// assert_eq!(map.strictly_above(&2), Some((&3, &'b')));
+	/// assert_eq!(map.strictly_above(&5), None);
 	/// ```
 	#[inline]
-	pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn strictly_above<Q: ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
 	where
 		K: Borrow<Q>,
 		Q: Ord,
 	{
-		match self.address_of(key) {
-			Ok(addr) => {
-				let (item, _) = self.remove_at(addr).unwrap();
-				Some(item.into_value())
-			}
-			Err(_) => None,
-		}
+		let addr = self.range_start_address(Bound::Excluded(key));
+		self.item(addr).map(|item| (item.key(), item.value()))
 	}
 
-	/// Removes a key from the map, returning the stored key and value if the key
-	/// was previously in the map.
+	/// Returns the key-value pair with the greatest key strictly less than the given key.
 	///
 	/// The key may be any borrowed form of the map's key type, but the ordering
 	/// on the borrowed form *must* match the ordering on the key type.
 	///
 	/// # Example
 	///
-	/// Basic usage:
-	///
 	/// ```
 	/// use btree_slab::BTreeMap;
 	///
-	/// let mut map = BTreeMap::new();
-	/// map.insert(1, "a");
-	/// assert_eq!(map.remove_entry(&1), Some((1, "a")));
-	/// assert_eq!(map.remove_entry(&1), None);
+	/// let map: BTreeMap<i32, char> = [(1, 'a'), (3, 'b'), (5, 'c')].iter().cloned().collect();
+	/// assert_eq!(map.strictly_below(&5), Some((&3, &'b')));
+	/// assert_eq!(map.strictly_below(&4), Some((&3, &'b')));
+	/// assert_eq!(map.strictly_below(&1), None);
 	/// ```
 	#[inline]
-	pub fn remove_entry<Q: ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn strictly_below<Q: ?Sized>(&self, key: &Q) -> Option<(&K, &V)>
 	where
 		K: Borrow<Q>,
 		Q: Ord,
 	{
-		match self.address_of(key) {
-			Ok(addr) => {
-				let (item, _) = self.remove_at(addr).unwrap();
-				Some(item.into_pair())
-			}
-			Err(_) => None,
-		}
+		let end = self.range_end_address(Bound::Excluded(key));
+		self.previous_item_address(end).map(|addr| {
+			let item = self.item(addr).unwrap();
+			(item.key(), item.value())
+		})
 	}
 
-	/// Removes and returns the binding in the map, if any, of which key matches the given one.
-	#[inline]
-	pub fn take<Q: ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+	/// Returns the address of the first item included by the given start bound.
+	///
+	/// If there are no such item, returns the last valid address of the tree.
+	#[allow(clippy::multiple_bound_locations)]
+	fn range_start_address<T: ?Sized>(&self, start: Bound<&T>) -> Address
 	where
-		K: Borrow<Q>,
-		Q: Ord,
+		T: Ord,
+		K: Borrow<T>,
 	{
-		match self.address_of(key) {
-			Ok(addr) => {
-				let (item, _) = self.remove_at(addr).unwrap();
-				Some(item.into_pair())
-			}
-			Err(_) => None,
+		match start {
+			Bound::Included(start) => match self.address_of(start) {
+				Ok(addr) => addr,
+				Err(addr) => self.normalize_or_last(addr),
+			},
+			Bound::Excluded(start) => match self.address_of(start) {
+				Ok(addr) => self.next_item_or_last(addr),
+				Err(addr) => self.normalize_or_last(addr),
+			},
+			Bound::Unbounded => self.first_back_address(),
 		}
 	}
 
-	/// General-purpose update function.
-	///
-	/// This can be used to insert, compare, replace or remove the value associated to the given
-	/// `key` in the tree.
-	/// The action to perform is specified by the `action` function.
-	/// This function is called once with:
-	///  - `Some(value)` when `value` is aready associated to `key` or
-	///  - `None` when the `key` is not associated to any value.
+	/// Returns the address of the first item excluded by the given end bound.
 	///
-	/// The `action` function must return a pair (`new_value`, `result`) where
-	/// `new_value` is the new value to be associated to `key`
-	/// (if it is `None` any previous binding is removed) and
-	/// `result` is the value returned by the entire `update` function call.
-	#[inline]
-	pub fn update<T, F>(&mut self, key: K, action: F) -> T
+	/// If there are no such item, returns the last valid address of the tree.
+	#[allow(clippy::multiple_bound_locations)]
+	fn range_end_address<T: ?Sized>(&self, end: Bound<&T>) -> Address
 	where
-		K: Ord,
-		F: FnOnce(Option<V>) -> (Option<V>, T),
+		T: Ord,
+		K: Borrow<T>,
 	{
-		match self.root {
-			Some(id) => self.update_in(id, key, action),
-			None => {
-				let (to_insert, result) = action(None);
-
-				if let Some(value) = to_insert {
-					let new_root = Node::leaf(None, Item::new(key, value));
-					self.root = Some(self.allocate_node(new_root));
-					self.len += 1;
+		match end {
+			Bound::Included(end) => match self.address_of(end) {
+				Ok(addr) => self.next_item_or_last(addr),
+				Err(addr) => self.normalize_or_last(addr),
+			},
+			Bound::Excluded(end) => match self.address_of(end) {
+				Ok(addr) => addr,
+				Err(addr) => self.normalize_or_last(addr),
+			},
+			Bound::Unbounded => self.last_valid_address(),
+		}
+	}
+
+	/// Returns the address following the given item address,
+	/// or the last valid address of the tree if there is none.
+	#[inline]
+	fn next_item_or_last(&self, addr: Address) -> Address {
+		self.next_item_or_back_address(addr)
+			.unwrap_or_else(|| self.last_valid_address())
+	}
+
+	/// Normalizes the given insertion address,
+	/// or returns the last valid address of the tree if it is after the last item.
+	#[inline]
+	fn normalize_or_last(&self, addr: Address) -> Address {
+		self.normalize(addr)
+			.unwrap_or_else(|| self.last_valid_address())
+	}
+
+	/// Counts the items from the address `addr` (included) to `end` (excluded).
+	///
+	/// Both addresses must be normalized, as the bounds of a [`Range`].
+	#[inline]
+	fn count_between(&self, addr: Address, end: Address) -> usize {
+		self.rank_of_address(end)
+			.saturating_sub(self.rank_of_address(addr))
+	}
+
+	/// Returns the number of items located before the given address.
+	///
+	/// This walks up the parent links from the addressed node,
+	/// counting the items of the subtrees on the left of the path.
+	#[inline]
+	fn rank_of_address(&self, addr: Address) -> usize {
+		if addr.is_nowhere() {
+			return 0;
+		}
+
+		let mut rank = match addr.offset.value() {
+			Some(offset) => offset + self.children_len(addr.id, offset + 1),
+			None => 0,
+		};
+
+		let mut id = addr.id;
+		while let Some(parent_id) = self.node(id).parent() {
+			let index = self.node(parent_id).child_index(id).unwrap();
+			rank += index + self.children_len(parent_id, index);
+			id = parent_id
+		}
+
+		rank
+	}
+
+	/// Returns the number of items in the first `count` children of the node `id`.
+	#[inline]
+	fn children_len(&self, id: usize, count: usize) -> usize {
+		self.node(id)
+			.children()
+			.take(count)
+			.map(|child_id| self.node(child_id).subtree_len())
+			.sum()
+	}
+
+	/// Computes the number of items in the subtree rooted in `id`
+	/// from the subtree length of its children.
+	#[inline]
+	fn compute_subtree_len(&self, id: usize) -> usize {
+		let node = self.node(id);
+		node.item_count() + self.children_len(id, node.child_count())
+	}
+
+	/// Folds every key-value pair of the given range, in order by key,
+	/// into an accumulator.
+	///
+	/// This is equivalent to `self.range(range).fold(init, |b, (k, v)| f(b, k, v))`,
+	/// but directly walks the tree nodes instead of going through the [`Range`] iterator,
+	/// which is faster for simple aggregations.
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let map: BTreeMap<i32, i32> = (0..100).map(|i| (i, i * 10)).collect();
+	/// let sum = map.fold_in_range(10..20, 0, |sum, _, v| sum + v);
+	/// assert_eq!(sum, (10..20).map(|i| i * 10).sum::<i32>());
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn fold_in_range<T: ?Sized, R, B, F>(&self, range: R, init: B, mut f: F) -> B
+	where
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
+		F: FnMut(B, &K, &V) -> B,
+	{
+		if !is_valid_range(&range) {
+			panic!("Invalid range")
+		}
+
+		match self.root {
+			Some(id) => self.fold_node_in_range(id, &range, init, &mut f).0,
+			None => init,
+		}
+	}
+
+	/// Folds the items of the given range in the node `id` and its descendants.
+	///
+	/// Returns the accumulator and `false` if the end of the range has been reached.
+	#[allow(clippy::multiple_bound_locations)]
+	fn fold_node_in_range<T: ?Sized, R, B, F>(
+		&self,
+		id: usize,
+		range: &R,
+		mut acc: B,
+		f: &mut F,
+	) -> (B, bool)
+	where
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
+		F: FnMut(B, &K, &V) -> B,
+	{
+		match self.node(id) {
+			Node::Leaf(leaf) => {
+				for item in leaf.items() {
+					let key = item.key().borrow();
+					if is_after_start(range.start_bound(), key) {
+						if !is_before_end(range.end_bound(), key) {
+							return (acc, false);
+						}
+
+						acc = f(acc, item.key(), item.value())
+					}
+				}
+
+				(acc, true)
+			}
+			Node::Internal(node) => {
+				let branches = node.branches();
+
+				// The first child only contains keys lower than the first item.
+				if may_reach_start(range.start_bound(), branches.first()) {
+					let (new_acc, go_on) =
+						self.fold_node_in_range(node.first_child_id(), range, acc, f);
+					if !go_on {
+						return (new_acc, false);
+					}
+					acc = new_acc
+				}
+
+				for (i, branch) in branches.iter().enumerate() {
+					let key = branch.item.key().borrow();
+					if is_after_start(range.start_bound(), key) {
+						if !is_before_end(range.end_bound(), key) {
+							return (acc, false);
+						}
+
+						acc = f(acc, branch.item.key(), branch.item.value())
+					}
+
+					if may_reach_start(range.start_bound(), branches.get(i + 1)) {
+						let (new_acc, go_on) = self.fold_node_in_range(branch.child, range, acc, f);
+						if !go_on {
+							return (new_acc, false);
+						}
+						acc = new_acc
+					}
+				}
+
+				(acc, true)
+			}
+		}
+	}
+
+	/// Returns `true` if the map contains a value for the specified key.
+	///
+	/// The key may be any borrowed form of the map's key type, but the ordering
+	/// on the borrowed form *must* match the ordering on the key type.
+	///
+	/// # Example
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, &str> = BTreeMap::new();
+	/// map.insert(1, "a");
+	/// assert_eq!(map.contains_key(&1), true);
+	/// assert_eq!(map.contains_key(&2), false);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		self.get(key).is_some()
+	}
+
+	/// Returns an iterator over the identifiers of the nodes reachable from the root,
+	/// in depth-first order.
+	///
+	/// Together with [`BTreeMap::occupied_node_ids`] when using the default container,
+	/// this can be used to check that no node is leaked in the container.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let map: BTreeMap<_, _> = (0..100).map(|i| (i, i)).collect();
+	/// let mut reachable: Vec<_> = map.reachable_node_ids().collect();
+	/// let mut occupied: Vec<_> = map.occupied_node_ids().collect();
+	/// reachable.sort();
+	/// occupied.sort();
+	/// assert_eq!(reachable, occupied);
+	/// ```
+	#[inline]
+	pub fn reachable_node_ids(&self) -> impl Iterator<Item = usize> + '_ {
+		let mut stack: Vec<usize> = self.root.into_iter().collect();
+		std::iter::from_fn(move || {
+			let id = stack.pop()?;
+			stack.extend(self.node(id).children());
+			Some(id)
+		})
+	}
+
+	/// Returns the first and last leaves of the tree, found by descending from the root.
+	#[inline]
+	fn find_leaf_bounds(&self) -> Option<(usize, usize)> {
+		self.root.map(|root_id| {
+			let mut first = root_id;
+			while let Some(child_id) = self.node(first).child_id_opt(0) {
+				first = child_id
+			}
+
+			let mut last = root_id;
+			while let Some(child_id) = self.node(last).child_id_opt(self.node(last).item_count()) {
+				last = child_id
+			}
+
+			(first, last)
+		})
+	}
+
+	/// Checks if the given node identifier is reachable from the root.
+	///
+	/// This walks up the parent links from the node,
+	/// checking that each parent lists the node as one of its children,
+	/// until reaching the root.
+	/// Returns `false` if the identifier is not occupied in the container.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::{BTreeMap, generic::map::BTreeExt};
+	///
+	/// let map: BTreeMap<_, _> = (0..100).map(|i| (i, i)).collect();
+	/// let leaf_id = map.first_item_address().unwrap().id;
+	/// assert!(map.is_node_reachable(leaf_id));
+	/// assert!(!map.is_node_reachable(1000));
+	/// ```
+	#[inline]
+	pub fn is_node_reachable(&self, mut id: usize) -> bool {
+		// Bound the walk in case the parent links form a cycle.
+		for _ in 0..usize::BITS {
+			let node = match self.nodes.get(id) {
+				Some(node) => C::into_ref(node),
+				None => return false,
+			};
+
+			match node.parent() {
+				Some(parent_id) => {
+					let is_child = self
+						.nodes
+						.get(parent_id)
+						.is_some_and(|parent| C::into_ref(parent).child_index(id).is_some());
+
+					if !is_child {
+						return false;
+					}
+
+					id = parent_id
+				}
+				None => return self.root == Some(id),
+			}
+		}
+
+		false
+	}
+
+	/// Write the tree in the DOT graph descrption language.
+	///
+	/// Requires the `dot` feature.
+	#[cfg(feature = "dot")]
+	#[inline]
+	pub fn dot_write<W: std::io::Write>(&self, f: &mut W) -> std::io::Result<()>
+	where
+		K: std::fmt::Display,
+		V: std::fmt::Display,
+	{
+		write!(f, "digraph tree {{\n\tnode [shape=record];\n")?;
+		if let Some(id) = self.root {
+			self.dot_write_node(f, id)?
+		}
+		write!(f, "}}")
+	}
+
+	/// Write the given node in the DOT graph descrption language.
+	///
+	/// Requires the `dot` feature.
+	#[cfg(feature = "dot")]
+	#[inline]
+	fn dot_write_node<W: std::io::Write>(&self, f: &mut W, id: usize) -> std::io::Result<()>
+	where
+		K: std::fmt::Display,
+		V: std::fmt::Display,
+	{
+		let name = format!("n{}", id);
+		let node = self.node(id);
+
+		write!(f, "\t{} [label=\"", name)?;
+		if let Some(parent) = node.parent() {
+			write!(f, "({})|", parent)?;
+		}
+
+		node.dot_write_label(f)?;
+		writeln!(f, "({})\"];", id)?;
+
+		for child_id in node.children() {
+			self.dot_write_node(f, child_id)?;
+			let child_name = format!("n{}", child_id);
+			writeln!(f, "\t{} -> {}", name, child_name)?;
+		}
+
+		Ok(())
+	}
+
+	/// Write the tree as a Mermaid flowchart.
+	///
+	/// Requires the `mermaid` feature.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert(1, 'a');
+	///
+	/// let mut buffer = Vec::new();
+	/// map.mermaid_write(&mut buffer).unwrap();
+	/// assert_eq!(
+	///     String::from_utf8(buffer).unwrap(),
+	///     "flowchart TD\n\tn0[\"1: a\"]\n"
+	/// );
+	/// ```
+	#[cfg(feature = "mermaid")]
+	#[inline]
+	pub fn mermaid_write<W: std::io::Write>(&self, f: &mut W) -> std::io::Result<()>
+	where
+		K: std::fmt::Display,
+		V: std::fmt::Display,
+	{
+		writeln!(f, "flowchart TD")?;
+		if let Some(id) = self.root {
+			self.mermaid_write_node(f, id)?
+		}
+
+		Ok(())
+	}
+
+	/// Write the given node as part of a Mermaid flowchart.
+	///
+	/// Requires the `mermaid` feature.
+	#[cfg(feature = "mermaid")]
+	#[inline]
+	fn mermaid_write_node<W: std::io::Write>(&self, f: &mut W, id: usize) -> std::io::Result<()>
+	where
+		K: std::fmt::Display,
+		V: std::fmt::Display,
+	{
+		let node = self.node(id);
+
+		// Quotes would end the label early.
+		let mut label = Vec::new();
+		node.mermaid_write_label(&mut label)?;
+		let label = String::from_utf8_lossy(&label).replace('"', "#quot;");
+		writeln!(f, "\tn{}[\"{}\"]", id, label)?;
+
+		for child_id in node.children() {
+			self.mermaid_write_node(f, child_id)?;
+			writeln!(f, "\tn{} --> n{}", id, child_id)?;
+		}
+
+		Ok(())
+	}
+
+	/// Dump the structure of the tree as a JSON value.
+	///
+	/// Each node is represented by an object with its identifier (`id`),
+	/// its items as `[key, value]` pairs (`items`),
+	/// and its children, if any (`children`).
+	/// The root node is found under the `root` field, `null` if the tree is empty.
+	///
+	/// Requires the `serde` feature.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert(1, 'a');
+	///
+	/// assert_eq!(
+	///     map.tree_to_json().unwrap(),
+	///     serde_json::json!({
+	///         "len": 1,
+	///         "root": { "id": 0, "items": [[1, "a"]], "children": [] }
+	///     })
+	/// );
+	/// ```
+	#[cfg(feature = "serde")]
+	pub fn tree_to_json(&self) -> serde_json::Result<serde_json::Value>
+	where
+		K: serde::Serialize,
+		V: serde::Serialize,
+	{
+		let root = match self.root {
+			Some(id) => self.node_to_json(id)?,
+			None => serde_json::Value::Null,
+		};
+
+		Ok(serde_json::json!({
+			"len": self.len,
+			"root": root
+		}))
+	}
+
+	/// Dump the structure of the given node as a JSON value.
+	///
+	/// Requires the `serde` feature.
+	#[cfg(feature = "serde")]
+	fn node_to_json(&self, id: usize) -> serde_json::Result<serde_json::Value>
+	where
+		K: serde::Serialize,
+		V: serde::Serialize,
+	{
+		let node = self.node(id);
+
+		let mut items = Vec::with_capacity(node.item_count());
+		for offset in 0..node.item_count() {
+			let item = node.item(offset.into()).unwrap();
+			items.push(serde_json::to_value((item.key(), item.value()))?);
+		}
+
+		let mut children = Vec::new();
+		for child_id in node.children() {
+			children.push(self.node_to_json(child_id)?);
+		}
+
+		Ok(serde_json::json!({
+			"id": id,
+			"items": items,
+			"children": children
+		}))
+	}
+}
+
+#[cfg(feature = "std-slab")]
+impl<K, V> BTreeMap<K, V, slab::Slab<Node<K, V>>> {
+	/// Empty map.
+	///
+	/// Unlike [`BTreeMap::new`], this can be used to initialize `static` items.
+	///
+	/// Requires the `std-slab` feature.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use std::sync::Mutex;
+	/// use btree_slab::BTreeMap;
+	///
+	/// static REGISTRY: Mutex<BTreeMap<u32, &str>> = Mutex::new(BTreeMap::EMPTY);
+	///
+	/// REGISTRY.lock().unwrap().insert(1, "a");
+	/// assert_eq!(REGISTRY.lock().unwrap().get(&1), Some(&"a"));
+	/// ```
+	// With the `watermark` feature, the map holds atomic dirty flags,
+	// but each use of the constant creates a new map.
+	#[allow(clippy::declare_interior_mutable_const)]
+	pub const EMPTY: Self = BTreeMap::with_container(slab::Slab::new());
+
+	/// Returns an iterator over the identifiers of the nodes occupied in the container,
+	/// in increasing order.
+	///
+	/// Nodes that are occupied but not reachable from the root
+	/// (see [`BTreeMap::reachable_node_ids`]) are leaked.
+	///
+	/// Requires the `std-slab` feature.
+	#[inline]
+	pub fn occupied_node_ids(&self) -> impl Iterator<Item = usize> + '_ {
+		self.nodes.iter().map(|(id, _)| id)
+	}
+}
+
+impl<K, V, C: SlabMut<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Clears the map, removing all elements.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut a = BTreeMap::new();
+	/// a.insert(1, "a");
+	/// a.clear();
+	/// assert!(a.is_empty());
+	/// ```
+	#[inline]
+	pub fn clear(&mut self)
+	where
+		C: cc_traits::Clear,
+	{
+		#[cfg(feature = "watermark")]
+		if !self.is_empty() {
+			self.watermarks.touch_all()
+		}
+
+		#[cfg(feature = "bloom")]
+		if let Some(filter) = &mut self.key_filter {
+			filter.clear()
+		}
+
+		#[cfg(feature = "structural-hash")]
+		if let Some(h) = &mut self.structural_hash {
+			h.clear()
+		}
+
+		self.root = None;
+		self.len = 0;
+		self.leaf_bounds = None;
+		self.generation = self.generation.wrapping_add(1);
+		#[cfg(feature = "checksum")]
+		self.unseal();
+		self.nodes.clear()
+	}
+
+	/// Returns a mutable reference to the value corresponding to the key.
+	///
+	/// The key may be any borrowed form of the map's key type, but the ordering
+	/// on the borrowed form *must* match the ordering on the key type.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert(1, "a");
+	/// if let Some(x) = map.get_mut(&1) {
+	///     *x = "b";
+	/// }
+	/// assert_eq!(map[&1], "b");
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		#[cfg(feature = "metrics")]
+		if self.metrics.is_some() {
+			let addr = self.address_of(key);
+			self.record(Operation::Get, addr.unwrap_or_else(|addr| addr));
+			return match addr {
+				Ok(addr) => Some(self.item_mut(addr).unwrap().value_mut()),
+				Err(_) => None,
+			};
+		}
+
+		match self.root {
+			Some(id) => self.get_mut_in(key, id),
+			None => None,
+		}
+	}
+
+	/// Gets the given key's corresponding entry in the map for in-place manipulation.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut letters = BTreeMap::new();
+	///
+	/// for ch in "a short treatise on fungi".chars() {
+	///     let counter = letters.entry(ch).or_insert(0);
+	///     *counter += 1;
+	/// }
+	///
+	/// assert_eq!(letters[&'s'], 2);
+	/// assert_eq!(letters[&'t'], 3);
+	/// assert_eq!(letters[&'u'], 1);
+	/// assert_eq!(letters.get(&'y'), None);
+	/// ```
+	#[inline]
+	pub fn entry(&mut self, key: K) -> Entry<'_, K, V, C>
+	where
+		K: Ord,
+	{
+		let addr = self.address_of(&key);
+
+		#[cfg(feature = "metrics")]
+		self.record(Operation::Insert, addr.unwrap_or_else(|addr| addr));
+
+		match addr {
+			Ok(addr) => Entry::Occupied(OccupiedEntry { map: self, addr }),
+			Err(addr) => Entry::Vacant(VacantEntry {
+				map: self,
+				key,
+				addr,
+			}),
+		}
+	}
+
+	/// Inserts a key-value pair into the map if the key is not already in the map,
+	/// and returns a mutable reference to the inserted value.
+	///
+	/// If the map already has this key present, nothing is updated,
+	/// and an error containing the occupied entry and the value is returned.
+	/// The key is only looked up once.
+	///
+	/// This is the equivalent of the unstable `try_insert` method of the standard `BTreeMap`.
+	/// Here, [`BTreeMap::try_insert`] is the insertion bounded by the node container capacity.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// assert_eq!(map.try_insert_vacant(37, "a").unwrap(), &"a");
+	///
+	/// let err = map.try_insert_vacant(37, "b").unwrap_err();
+	/// assert_eq!(err.entry.key(), &37);
+	/// assert_eq!(err.entry.get(), &"a");
+	/// assert_eq!(err.value, "b");
+	/// ```
+	#[inline]
+	pub fn try_insert_vacant(
+		&mut self,
+		key: K,
+		value: V,
+	) -> Result<&mut V, OccupiedError<'_, K, V, C>>
+	where
+		K: Ord,
+	{
+		match self.entry(key) {
+			Entry::Occupied(entry) => Err(OccupiedError { entry, value }),
+			Entry::Vacant(entry) => Ok(entry.insert(value)),
+		}
+	}
+
+	/// Gets the entry of the given borrowed key in the map for in-place manipulation.
+	///
+	/// Contrarily to [`BTreeMap::entry`], the key is only converted into an owned key
+	/// (with [`ToOwned::to_owned`]) if the entry is vacant and a value is inserted.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut words: BTreeMap<String, usize> = BTreeMap::new();
+	///
+	/// for word in "the cat and the dog".split(' ') {
+	///     *words.entry_ref(word).or_insert(0) += 1;
+	/// }
+	///
+	/// assert_eq!(words["the"], 2);
+	/// assert_eq!(words["cat"], 1);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn entry_ref<'b, Q: ?Sized>(&mut self, key: &'b Q) -> EntryRef<'_, 'b, K, Q, V, C>
+	where
+		K: Borrow<Q>,
+		Q: Ord + ToOwned<Owned = K>,
+	{
+		let addr = self.address_of(key);
+
+		#[cfg(feature = "metrics")]
+		self.record(Operation::Insert, addr.unwrap_or_else(|addr| addr));
+
+		match addr {
+			Ok(addr) => EntryRef::Occupied(OccupiedEntry { map: self, addr }),
+			Err(addr) => EntryRef::Vacant(VacantEntryRef {
+				map: self,
+				key,
+				addr,
+			}),
+		}
+	}
+
+	/// Finds the vacant slot of the given key without borrowing the map.
+	///
+	/// Returns `None` if the key is already in the map.
+	/// The returned slot can later be filled with [`VacantSlot::insert`],
+	/// which allows computing the value without holding a mutable borrow of the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<&str, usize> = BTreeMap::new();
+	/// let slot = map.vacant_slot("poneyland").unwrap();
+	///
+	/// // The map can be used while the value is computed.
+	/// let value = map.len() + 12;
+	///
+	/// assert!(slot.insert(&mut map, value).is_ok());
+	/// assert_eq!(map["poneyland"], 12);
+	/// assert!(map.vacant_slot("poneyland").is_none());
+	/// ```
+	#[inline]
+	pub fn vacant_slot(&self, key: K) -> Option<VacantSlot<K>>
+	where
+		K: Ord,
+	{
+		match self.address_of(&key) {
+			Ok(_) => None,
+			Err(addr) => Some(VacantSlot {
+				key,
+				addr,
+				generation: self.generation,
+			}),
+		}
+	}
+
+	/// Creates a persistent handle to the entry of the given key.
+	///
+	/// Returns `None` if the key is not in the map.
+	/// Unlike addresses, the handle stays usable after the map is modified,
+	/// and is resolved into the current address of the entry with [`BTreeMap::resolve`].
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use btree_slab::generic::map::BTreeExt;
+	///
+	/// let mut map: BTreeMap<_, _> = (0..10).map(|i| (i, i)).collect();
+	/// let mut handle = map.handle(5).unwrap();
+	///
+	/// for i in 10..100 {
+	///     map.insert(i, i);
+	/// }
+	///
+	/// let addr = map.resolve(&mut handle).unwrap();
+	/// assert_eq!(map.item(addr).unwrap().key(), &5);
+	///
+	/// map.remove(&5);
+	/// assert_eq!(map.resolve(&mut handle), None);
+	/// ```
+	#[inline]
+	pub fn handle(&self, key: K) -> Option<EntryHandle<K>>
+	where
+		K: Ord,
+	{
+		match self.address_of(&key) {
+			Ok(addr) => Some(EntryHandle {
+				key,
+				addr,
+				generation: self.generation,
+			}),
+			Err(_) => None,
+		}
+	}
+
+	/// Resolves the given handle into the current address of its entry.
+	///
+	/// If the map has not been structurally modified since the handle was last resolved,
+	/// and the cached address still holds the key of the handle,
+	/// the cached address is returned without searching the tree.
+	/// Otherwise the key is searched again and the handle is updated.
+	/// A handle created from another map is hence resolved by searching its key.
+	///
+	/// Returns `None` if the entry has been removed from the map.
+	/// Since the handle identifies the entry by its key,
+	/// it resolves to the new entry if the key is inserted again.
+	#[inline]
+	pub fn resolve(&self, handle: &mut EntryHandle<K>) -> Option<Address>
+	where
+		K: Ord,
+	{
+		let cached = handle.generation == self.generation
+			&& self
+				.nodes
+				.get(handle.addr.id)
+				.and_then(|node| C::into_ref(node).item(handle.addr.offset))
+				.is_some_and(|item| item.key() == &handle.key);
+
+		if !cached {
+			handle.addr = self.address_of(&handle.key).ok()?;
+			handle.generation = self.generation;
+		}
+
+		Some(handle.addr)
+	}
+
+	/// Returns the first entry in the map for in-place manipulation.
+	/// The key of this entry is the minimum key in the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert(1, "a");
+	/// map.insert(2, "b");
+	/// if let Some(mut entry) = map.first_entry() {
+	///     if *entry.key() > 0 {
+	///         entry.insert("first");
+	///     }
+	/// }
+	/// assert_eq!(*map.get(&1).unwrap(), "first");
+	/// assert_eq!(*map.get(&2).unwrap(), "b");
+	/// ```
+	#[inline]
+	pub fn first_entry(&mut self) -> Option<OccupiedEntry<'_, K, V, C>> {
+		self.first_item_address()
+			.map(move |addr| OccupiedEntry { map: self, addr })
+	}
+
+	/// Returns the last entry in the map for in-place manipulation.
+	/// The key of this entry is the maximum key in the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert(1, "a");
+	/// map.insert(2, "b");
+	/// if let Some(mut entry) = map.last_entry() {
+	///     if *entry.key() > 0 {
+	///         entry.insert("last");
+	///     }
+	/// }
+	/// assert_eq!(*map.get(&1).unwrap(), "a");
+	/// assert_eq!(*map.get(&2).unwrap(), "last");
+	/// ```
+	#[inline]
+	pub fn last_entry(&mut self) -> Option<OccupiedEntry<'_, K, V, C>> {
+		self.last_item_address()
+			.map(move |addr| OccupiedEntry { map: self, addr })
+	}
+
+	/// Insert a key-value pair in the tree.
+	#[inline]
+	pub fn insert(&mut self, key: K, value: V) -> Option<V>
+	where
+		K: Ord,
+	{
+		let addr = self.address_of(&key);
+
+		#[cfg(feature = "metrics")]
+		self.record(Operation::Insert, addr.unwrap_or_else(|addr| addr));
+
+		match addr {
+			Ok(addr) => Some(self.replace_value_at(addr, value)),
+			Err(addr) => {
+				self.insert_exactly_at(addr, Item::new(key, value), None);
+				None
+			}
+		}
+	}
+
+	/// Inserts a key-value pair into the map and returns the address of the entry.
+	///
+	/// If the map already had this key present, the value is updated and the old
+	/// value is returned along with the address. The key is not updated.
+	///
+	/// The returned address is normalized: it points to the inserted item
+	/// and stays valid until the next structural modification of the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use btree_slab::generic::map::BTreeExt;
+	///
+	/// let mut map = BTreeMap::new();
+	/// let (old, addr) = map.insert_at_key(37, "a");
+	/// assert_eq!(old, None);
+	/// assert_eq!(map.item(addr).unwrap().value(), &"a");
+	///
+	/// let (old, addr) = map.insert_at_key(37, "b");
+	/// assert_eq!(old, Some("a"));
+	/// assert_eq!(map.item(addr).unwrap().value(), &"b");
+	/// ```
+	#[inline]
+	pub fn insert_at_key(&mut self, key: K, value: V) -> (Option<V>, Address)
+	where
+		K: Ord,
+	{
+		let addr = self.address_of(&key);
+
+		#[cfg(feature = "metrics")]
+		self.record(Operation::Insert, addr.unwrap_or_else(|addr| addr));
+
+		match addr {
+			Ok(addr) => (Some(self.replace_value_at(addr, value)), addr),
+			Err(addr) => (
+				None,
+				self.insert_exactly_at(addr, Item::new(key, value), None),
+			),
+		}
+	}
+
+	/// Replace a key-value pair in the tree.
+	#[inline]
+	pub fn replace(&mut self, key: K, value: V) -> Option<(K, V)>
+	where
+		K: Ord,
+	{
+		let addr = self.address_of(&key);
+
+		#[cfg(feature = "metrics")]
+		self.record(Operation::Insert, addr.unwrap_or_else(|addr| addr));
+
+		match addr {
+			Ok(addr) => Some(self.replace_at(addr, key, value)),
+			Err(addr) => {
+				self.insert_exactly_at(addr, Item::new(key, value), None);
+				None
+			}
+		}
+	}
+
+	/// Removes and returns the first element in the map.
+	/// The key of this element is the minimum key that was in the map.
+	///
+	/// # Example
+	///
+	/// Draining elements in ascending order, while keeping a usable map each iteration.
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert(1, "a");
+	/// map.insert(2, "b");
+	/// while let Some((key, _val)) = map.pop_first() {
+	///     assert!(map.iter().all(|(k, _v)| *k > key));
+	/// }
+	/// assert!(map.is_empty());
+	/// ```
+	#[inline]
+	pub fn pop_first(&mut self) -> Option<(K, V)> {
+		let entry = self.first_entry()?;
+
+		#[cfg(feature = "metrics")]
+		entry.map.record(Operation::Remove, entry.addr);
+
+		Some(entry.remove_entry())
+	}
+
+	/// Removes and returns the last element in the map.
+	/// The key of this element is the maximum key that was in the map.
+	///
+	/// # Example
+	///
+	/// Draining elements in descending order, while keeping a usable map each iteration.
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert(1, "a");
+	/// map.insert(2, "b");
+	/// while let Some((key, _val)) = map.pop_last() {
+	///     assert!(map.iter().all(|(k, _v)| *k < key));
+	/// }
+	/// assert!(map.is_empty());
+	/// ```
+	#[inline]
+	pub fn pop_last(&mut self) -> Option<(K, V)> {
+		let entry = self.last_entry()?;
+
+		#[cfg(feature = "metrics")]
+		entry.map.record(Operation::Remove, entry.addr);
+
+		Some(entry.remove_entry())
+	}
+
+	/// Removes a key from the map, returning the value at the key if the key
+	/// was previously in the map.
+	///
+	/// The key may be any borrowed form of the map's key type, but the ordering
+	/// on the borrowed form *must* match the ordering on the key type.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert(1, "a");
+	/// assert_eq!(map.remove(&1), Some("a"));
+	/// assert_eq!(map.remove(&1), None);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn remove<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let addr = self.address_of(key);
+
+		#[cfg(feature = "metrics")]
+		self.record(Operation::Remove, addr.unwrap_or_else(|addr| addr));
+
+		match addr {
+			Ok(addr) => {
+				let (item, _) = self.remove_at(addr).unwrap();
+				Some(item.into_value())
+			}
+			Err(_) => None,
+		}
+	}
+
+	/// Removes a key from the map, returning the value at the key
+	/// and the address of the next item in the map, if any.
+	///
+	/// The key may be any borrowed form of the map's key type, but the ordering
+	/// on the borrowed form *must* match the ordering on the key type.
+	///
+	/// The returned address is normalized: it points to the item directly following
+	/// the removed one, or is `None` if the removed item was the last one.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use btree_slab::generic::map::BTreeExt;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert(1, "a");
+	/// map.insert(2, "b");
+	///
+	/// let (value, next) = map.remove_with_next(&1).unwrap();
+	/// assert_eq!(value, "a");
+	/// assert_eq!(map.item(next.unwrap()).unwrap().key(), &2);
+	///
+	/// assert_eq!(map.remove_with_next(&2), Some(("b", None)));
+	/// assert_eq!(map.remove_with_next(&2), None);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn remove_with_next<Q: ?Sized>(&mut self, key: &Q) -> Option<(V, Option<Address>)>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let addr = self.address_of(key);
+
+		#[cfg(feature = "metrics")]
+		self.record(Operation::Remove, addr.unwrap_or_else(|addr| addr));
+
+		match addr {
+			Ok(addr) => {
+				let (item, addr) = self.remove_at(addr).unwrap();
+				Some((item.into_value(), self.normalize(addr)))
+			}
+			Err(_) => None,
+		}
+	}
+
+	/// Removes a key from the map, returning the stored key and value if the key
+	/// was previously in the map.
+	///
+	/// The key may be any borrowed form of the map's key type, but the ordering
+	/// on the borrowed form *must* match the ordering on the key type.
+	///
+	/// # Example
+	///
+	/// Basic usage:
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert(1, "a");
+	/// assert_eq!(map.remove_entry(&1), Some((1, "a")));
+	/// assert_eq!(map.remove_entry(&1), None);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn remove_entry<Q: ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let addr = self.address_of(key);
+
+		#[cfg(feature = "metrics")]
+		self.record(Operation::Remove, addr.unwrap_or_else(|addr| addr));
+
+		match addr {
+			Ok(addr) => {
+				let (item, _) = self.remove_at(addr).unwrap();
+				Some(item.into_pair())
+			}
+			Err(_) => None,
+		}
+	}
+
+	/// Removes and returns the binding in the map, if any, of which key matches the given one.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn take<Q: ?Sized>(&mut self, key: &Q) -> Option<(K, V)>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let addr = self.address_of(key);
+
+		#[cfg(feature = "metrics")]
+		self.record(Operation::Remove, addr.unwrap_or_else(|addr| addr));
+
+		match addr {
+			Ok(addr) => {
+				let (item, _) = self.remove_at(addr).unwrap();
+				Some(item.into_pair())
+			}
+			Err(_) => None,
+		}
+	}
+
+	/// General-purpose update function.
+	///
+	/// This can be used to insert, compare, replace or remove the value associated to the given
+	/// `key` in the tree.
+	/// The action to perform is specified by the `action` function.
+	/// This function is called once with:
+	///  - `Some(value)` when `value` is aready associated to `key` or
+	///  - `None` when the `key` is not associated to any value.
+	///
+	/// The `action` function must return a pair (`new_value`, `result`) where
+	/// `new_value` is the new value to be associated to `key`
+	/// (if it is `None` any previous binding is removed) and
+	/// `result` is the value returned by the entire `update` function call.
+	#[inline]
+	pub fn update<T, F>(&mut self, key: K, action: F) -> T
+	where
+		K: Ord,
+		F: FnOnce(Option<V>) -> (Option<V>, T),
+	{
+		match self.root {
+			Some(id) => self.update_in(id, key, action),
+			None => {
+				let (to_insert, result) = action(None);
+
+				if let Some(value) = to_insert {
+					self.insert_exactly_at(Address::nowhere(), Item::new(key, value), None);
+				}
+
+				result
+			}
+		}
+	}
+
+	/// Entry-style update function.
+	///
+	/// The `action` function is called once with an [`UpdateSlot`] giving access
+	/// to the value associated to `key`, if any.
+	/// The binding is left untouched unless the action calls [`UpdateSlot::set`]
+	/// or [`UpdateSlot::remove`].
+	/// The value returned by `action` is returned by `update_entry`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert("a", 1);
+	///
+	/// // Increment the counter, and remove it once it reaches 3.
+	/// let incr = |map: &mut BTreeMap<&str, i32>, key| {
+	///     map.update_entry(key, |mut slot| match slot.get_mut() {
+	///         Some(n) if *n >= 2 => slot.remove().unwrap() + 1,
+	///         Some(n) => {
+	///             *n += 1;
+	///             *n
+	///         }
+	///         None => {
+	///             slot.set(1);
+	///             1
+	///         }
+	///     })
+	/// };
+	///
+	/// assert_eq!(incr(&mut map, "a"), 2);
+	/// assert_eq!(incr(&mut map, "b"), 1);
+	/// assert_eq!(incr(&mut map, "a"), 3);
+	/// assert_eq!(map.get("a"), None);
+	/// assert_eq!(map.get("b"), Some(&1));
+	/// ```
+	#[inline]
+	pub fn update_entry<T, F>(&mut self, key: K, action: F) -> T
+	where
+		K: Ord,
+		F: FnOnce(UpdateSlot<V>) -> T,
+	{
+		self.update(key, |current| {
+			let mut value = current;
+			let result = action(UpdateSlot::new(&mut value));
+			(value, result)
+		})
+	}
+
+	/// Gets a mutable iterator over the entries of the map, sorted by key.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert("a", 1);
+	/// map.insert("b", 2);
+	/// map.insert("c", 3);
+	///
+	/// // add 10 to the value if the key isn't "a"
+	/// for (key, value) in map.iter_mut() {
+	///     if key != &"a" {
+	///         *value += 10;
+	///     }
+	/// }
+	/// ```
+	#[inline]
+	pub fn iter_mut(&mut self) -> IterMut<'_, K, V, C> {
+		IterMut::new(self)
+	}
+
+	/// Gets a mutable iterator over the entries of the map, sorted by key, that allows insertion and deletion of the iterated entries.
+	///
+	/// # Correctness
+	///
+	/// It is safe to insert any key-value pair while iterating,
+	/// however this might break the well-formedness
+	/// of the underlying tree, which relies on several invariants.
+	/// To preserve these invariants,
+	/// the inserted key must be *strictly greater* than the previous visited item's key,
+	/// and *strictly less* than the next visited item
+	/// (which you can retrive through [`EntriesMut::peek`] without moving the iterator).
+	/// If this rule is not respected, the data structure will become unusable
+	/// (invalidate the specification of every method of the API).
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// map.insert("a", 1);
+	/// map.insert("b", 2);
+	/// map.insert("d", 4);
+	///
+	/// let mut entries = map.entries_mut();
+	/// entries.next();
+	/// entries.next();
+	/// entries.insert("c", 3);
+	///
+	/// let entries: Vec<_> = map.into_iter().collect();
+	/// assert_eq!(entries, vec![("a", 1), ("b", 2), ("c", 3), ("d", 4)]);
+	/// ```
+	#[inline]
+	pub fn entries_mut(&mut self) -> EntriesMut<'_, K, V, C> {
+		EntriesMut::new(self)
+	}
+
+	/// Gets an iterator over the entries of the map in the given range, sorted by key,
+	/// where each entry can be modified or removed.
+	///
+	/// Since each visited entry borrows the iterator,
+	/// entries are visited with [`RangeEntriesMut::next_entry`]
+	/// instead of the [`Iterator`] trait.
+	/// Removing an entry costs the same as [`BTreeMap::remove`] without the search.
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	///
+	/// # Example
+	///
+	/// Expiring the entries of a map indexed by time:
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// // Entries are indexed by creation time, and store their time to live.
+	/// let mut map: BTreeMap<u32, u32> = (0..10).map(|t| (t, t % 3)).collect();
+	///
+	/// let now = 6;
+	/// let mut entries = map.range_entries_mut(..now);
+	/// while let Some(mut entry) = entries.next_entry() {
+	///     if entry.key() + entry.get() < now {
+	///         entry.remove();
+	///     } else {
+	///         entry.replace_value(now - entry.key());
+	///     }
+	/// }
+	///
+	/// assert!(map.into_iter().eq([(5, 1), (6, 0), (7, 1), (8, 2), (9, 0)]));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn range_entries_mut<T: ?Sized, R>(&mut self, range: R) -> RangeEntriesMut<'_, K, V, C>
+	where
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
+	{
+		if !is_valid_range(&range) {
+			panic!("Invalid range")
+		}
+
+		let addr = self.range_start_address(range.start_bound());
+		let end = self.range_end_address(range.end_bound());
+		let len = self.count_between(addr, end);
+		RangeEntriesMut {
+			btree: self,
+			addr,
+			len,
+			visited: false,
+		}
+	}
+
+	/// Constructs a mutable double-ended iterator over a sub-range of elements in the map.
+	/// The simplest way is to use the range syntax `min..max`, thus `range(min..max)` will
+	/// yield elements from min (inclusive) to max (exclusive).
+	/// The range may also be entered as `(Bound<T>, Bound<T>)`, so for example
+	/// `range((Excluded(4), Included(10)))` will yield a left-exclusive, right-inclusive
+	/// range from 4 to 10.
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	/// Use [`BTreeMap::try_range_mut`] to check the range instead.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<&str, i32> = ["Alice", "Bob", "Carol", "Cheryl"]
+	///     .iter()
+	///     .map(|&s| (s, 0))
+	///     .collect();
+	/// for (_, balance) in map.range_mut("B".."Cheryl") {
+	///     *balance += 100;
+	/// }
+	/// for (name, balance) in &map {
+	///     println!("{} => {}", name, balance);
+	/// }
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn range_mut<T: ?Sized, R>(&mut self, range: R) -> RangeMut<'_, K, V, C>
+	where
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
+	{
+		match RangeMut::new(self, range) {
+			Ok(range) => range,
+			Err(InvalidRange) => panic!("Invalid range"),
+		}
+	}
+
+	/// Constructs a mutable double-ended iterator over a sub-range of elements in the map,
+	/// or returns an error if the range bounds are not ordered.
+	///
+	/// This is the non-panicking version of [`BTreeMap::range_mut`].
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::{BTreeMap, generic::map::InvalidRange};
+	///
+	/// let mut map: BTreeMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+	/// for (_, v) in map.try_range_mut(8..).unwrap() {
+	///     *v = 0;
+	/// }
+	/// assert_eq!(map[&9], 0);
+	/// assert!(map.try_range_mut(8..2).is_err());
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn try_range_mut<T: ?Sized, R>(
+		&mut self,
+		range: R,
+	) -> Result<RangeMut<'_, K, V, C>, InvalidRange>
+	where
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
+	{
+		RangeMut::new(self, range)
+	}
+
+	/// Constructs a mutable double-ended iterator over the entries between two addresses.
+	///
+	/// See [`BTreeMap::range_between`] for the meaning of the addresses.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use btree_slab::generic::map::BTreeExt;
+	///
+	/// let mut map: BTreeMap<_, _> = (0..100).map(|i| (i, i)).collect();
+	/// let start = map.address_of(&10).unwrap();
+	/// let end = map.address_of(&20).unwrap();
+	///
+	/// for (_, value) in map.range_between_mut(start, end).unwrap() {
+	///     *value = 0;
+	/// }
+	/// assert_eq!(map.values().filter(|v| **v == 0).count(), 11);
+	/// ```
+	#[inline]
+	pub fn range_between_mut(
+		&mut self,
+		start: Address,
+		end: Address,
+	) -> Result<RangeMut<'_, K, V, C>, InvalidAddressRange>
+	where
+		K: Ord,
+	{
+		let (addr, end) = self.address_range(start, end)?;
+		Ok(RangeMut::between(self, addr, end))
+	}
+
+	/// Gets a mutable iterator over the values of the map, in order by key.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut a = BTreeMap::new();
+	/// a.insert(1, String::from("hello"));
+	/// a.insert(2, String::from("goodbye"));
+	///
+	/// for value in a.values_mut() {
+	///     value.push_str("!");
+	/// }
+	///
+	/// let values: Vec<String> = a.values().cloned().collect();
+	/// assert_eq!(values, [String::from("hello!"),
+	///                     String::from("goodbye!")]);
+	/// ```
+	#[inline]
+	pub fn values_mut(&mut self) -> ValuesMut<'_, K, V, C> {
+		ValuesMut {
+			inner: self.iter_mut(),
+		}
+	}
+
+	/// Overwrites the value of each entry, in key order, with the values of the given iterator.
+	///
+	/// This runs in linear time, without any key lookup.
+	/// If the iterator yields fewer values than the map has entries,
+	/// the first entries are still overwritten and the error gives the number of values.
+	/// If it yields more values, every entry is overwritten and the remaining values are ignored.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::{BTreeMap, generic::map::LengthMismatch};
+	///
+	/// let mut map: BTreeMap<_, _> = [(3, 'a'), (1, 'b'), (2, 'c')].into_iter().collect();
+	/// map.assign_values_in_order(['x', 'y', 'z']).unwrap();
+	/// assert!(map.into_iter().eq([(1, 'x'), (2, 'y'), (3, 'z')]));
+	///
+	/// let mut map: BTreeMap<_, _> = (0..3).map(|i| (i, i)).collect();
+	/// assert_eq!(map.assign_values_in_order([10, 11]), Err(LengthMismatch::TooFew(2)));
+	/// assert_eq!(map.assign_values_in_order(0..4), Err(LengthMismatch::TooMany));
+	/// ```
+	#[inline]
+	pub fn assign_values_in_order<I>(&mut self, values: I) -> Result<(), LengthMismatch>
+	where
+		I: IntoIterator<Item = V>,
+	{
+		let mut values = values.into_iter();
+		for (count, value) in self.values_mut().enumerate() {
+			match values.next() {
+				Some(new_value) => *value = new_value,
+				None => return Err(LengthMismatch::TooFew(count)),
+			}
+		}
+
+		match values.next() {
+			Some(_) => Err(LengthMismatch::TooMany),
+			None => Ok(()),
+		}
+	}
+
+	/// Creates an iterator which uses a closure to determine if an element should be removed.
+	///
+	/// If the closure returns true, the element is removed from the map and yielded.
+	/// If the closure returns false, or panics, the element remains in the map and will not be
+	/// yielded.
+	///
+	/// Note that `drain_filter` lets you mutate every value in the filter closure, regardless of
+	/// whether you choose to keep or remove it.
+	///
+	/// If the iterator is only partially consumed or not consumed at all, each of the remaining
+	/// elements will still be subjected to the closure and removed and dropped if it returns true,
+	/// unless [`DrainFilter::keep_rest`] is called.
+	///
+	/// If the closure panics, the panic is propagated and the closure is never called again:
+	/// the element and all the remaining elements stay in the map.
+	/// If a panic occurs while dropping a removed element, the remaining elements stay in the map.
+	/// If the `DrainFilter` value is leaked, the remaining elements stay in the map.
+	///
+	/// # Example
+	///
+	/// Splitting a map into even and odd keys, reusing the original map:
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, i32> = (0..8).map(|x| (x, x)).collect();
+	/// let evens: BTreeMap<_, _> = map.drain_filter(|k, _v| k % 2 == 0).collect();
+	/// let odds = map;
+	/// assert_eq!(evens.keys().copied().collect::<Vec<_>>(), vec![0, 2, 4, 6]);
+	/// assert_eq!(odds.keys().copied().collect::<Vec<_>>(), vec![1, 3, 5, 7]);
+	/// ```
+	#[inline]
+	pub fn drain_filter<F>(&mut self, pred: F) -> DrainFilter<'_, K, V, C, F>
+	where
+		F: FnMut(&K, &mut V) -> bool,
+	{
+		DrainFilter::new(DrainFilterInner::new(self), pred)
+	}
+
+	/// Creates an iterator which uses a closure to determine if an element
+	/// in the given range should be removed.
+	///
+	/// This behaves like [`BTreeMap::drain_filter`],
+	/// but only visits the entries in the range,
+	/// starting at the address of the range start
+	/// instead of walking the whole tree.
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, i32> = (0..100).map(|x| (x, x)).collect();
+	/// let evens: Vec<_> = map.drain_filter_range(10..20, |k, _| k % 2 == 0).collect();
+	///
+	/// assert!(evens.into_iter().eq((10..20).step_by(2).map(|x| (x, x))));
+	/// assert_eq!(map.len(), 95);
+	/// assert!(map.contains_key(&8));
+	/// assert!(!map.contains_key(&12));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn drain_filter_range<T: ?Sized, R, F>(
+		&mut self,
+		range: R,
+		pred: F,
+	) -> DrainFilter<'_, K, V, C, F>
+	where
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
+		F: FnMut(&K, &mut V) -> bool,
+	{
+		DrainFilter::new(self.drain_filter_range_inner(range), pred)
+	}
+
+	/// Creates the inner iterator visiting the entries in the given range.
+	///
+	/// # Panics
+	///
+	/// Panics if the range is invalid.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub(crate) fn drain_filter_range_inner<T: ?Sized, R>(
+		&mut self,
+		range: R,
+	) -> DrainFilterInner<'_, K, V, C>
+	where
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
+	{
+		if !is_valid_range(&range) {
+			panic!("Invalid range")
+		}
+
+		let addr = self.range_start_address(range.start_bound());
+		let end = self.range_end_address(range.end_bound());
+		let len = self.count_between(addr, end);
+		DrainFilterInner::new_at(self, addr, len)
+	}
+
+	/// Retains only the elements specified by the predicate.
+	///
+	/// In other words, remove all pairs `(k, v)` such that `f(&k, &mut v)` returns `false`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, i32> = (0..8).map(|x| (x, x*10)).collect();
+	/// // Keep only the elements with even-numbered keys.
+	/// map.retain(|&k, _| k % 2 == 0);
+	/// assert!(map.into_iter().eq(vec![(0, 0), (2, 20), (4, 40), (6, 60)]));
+	/// ```
+	#[inline]
+	pub fn retain<F>(&mut self, mut f: F)
+	where
+		F: FnMut(&K, &mut V) -> bool,
+	{
+		self.drain_filter(|k, v| !f(k, v));
+	}
+
+	/// Retains only the elements of the given range specified by the predicate.
+	///
+	/// In other words, remove all pairs `(k, v)` with `k` in `range`
+	/// such that `f(&k, &mut v)` returns `false`.
+	/// Elements outside of the range are not visited.
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, i32> = (0..8).map(|x| (x, x*10)).collect();
+	/// // Keep only the elements with even-numbered keys in `2..6`.
+	/// map.retain_in(2..6, |&k, _| k % 2 == 0);
+	/// assert!(map.keys().copied().eq([0, 1, 2, 4, 6, 7]));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn retain_in<T: ?Sized, R, F>(&mut self, range: R, mut f: F)
+	where
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
+		F: FnMut(&K, &mut V) -> bool,
+	{
+		self.drain_filter_range(range, |k, v| !f(k, v));
+	}
+
+	/// Retains only the elements specified by the predicate,
+	/// visiting the keys in ascending order while `scan` returns `true`.
+	///
+	/// In other words, remove all pairs `(k, v)` such that `f(&k, &mut v)` returns `false`,
+	/// until reaching the first key for which `scan` returns `false`.
+	/// This key and all the following entries are kept without calling `f`,
+	/// which avoids a full scan of the map when the keys are known not to match past some point.
+	///
+	/// Returns the number of removed elements.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, i32> = (0..100).map(|x| (x, x)).collect();
+	/// // Remove the odd keys lower than 10.
+	/// let removed = map.retain_while(|&k| k < 10, |&k, _| k % 2 == 0);
+	///
+	/// assert_eq!(removed, 5);
+	/// assert_eq!(map.len(), 95);
+	/// assert!(map.contains_key(&11));
+	/// ```
+	#[inline]
+	pub fn retain_while<S, F>(&mut self, mut scan: S, mut f: F) -> usize
+	where
+		S: FnMut(&K) -> bool,
+		F: FnMut(&K, &mut V) -> bool,
+	{
+		let mut inner = DrainFilterInner::new(self);
+		let mut removed = 0;
+		while inner
+			.next_until(&mut |k, v| {
+				if scan(k) {
+					ControlFlow::Continue(!f(k, v))
+				} else {
+					ControlFlow::Break(())
+				}
+			})
+			.is_some()
+		{
+			removed += 1
+		}
+
+		removed
+	}
+
+	/// Moves all elements from `other` into `Self`, leaving `other` empty.
+	///
+	/// If all the keys of `other` are greater (or all lower) than the keys of `self`,
+	/// the nodes of `other` are moved into the node storage of `self`
+	/// without comparing their keys, and the two trees are joined in `O(log n)`.
+	/// Otherwise the entries of `other` are inserted one by one.
+	/// The settings of `self`, such as its limits and split policy, are kept,
+	/// and the entries are not checked against its limits.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut a = BTreeMap::new();
+	/// a.insert(1, "a");
+	/// a.insert(2, "b");
+	/// a.insert(3, "c");
+	///
+	/// let mut b = BTreeMap::new();
+	/// b.insert(3, "d");
+	/// b.insert(4, "e");
+	/// b.insert(5, "f");
+	///
+	/// a.append(&mut b);
+	///
+	/// assert_eq!(a.len(), 5);
+	/// assert_eq!(b.len(), 0);
+	///
+	/// assert_eq!(a[&1], "a");
+	/// assert_eq!(a[&2], "b");
+	/// assert_eq!(a[&3], "d");
+	/// assert_eq!(a[&4], "e");
+	/// assert_eq!(a[&5], "f");
+	/// ```
+	#[inline]
+	pub fn append(&mut self, other: &mut Self)
+	where
+		K: Ord,
+		C: Default,
+	{
+		// Do we have to append anything at all?
+		if other.is_empty() {
+			return;
+		}
+
+		// We can just swap the trees of `self` and `other` if `self` is empty,
+		// keeping the settings of each map.
+		if self.is_empty() {
+			self.swap_contents(other);
+
+			#[cfg(feature = "bloom")]
+			{
+				std::mem::swap(&mut self.key_filter, &mut other.key_filter);
+				if let Some(filter) = &mut other.key_filter {
+					filter.clear()
+				}
+
+				if let Some(mut filter) = self.key_filter.take() {
+					for key in self.keys() {
+						filter.insert(key)
+					}
+
+					self.key_filter = Some(filter);
+					self.sync_key_filter()
+				}
+			}
+
+			#[cfg(feature = "structural-hash")]
+			{
+				std::mem::swap(&mut self.structural_hash, &mut other.structural_hash);
+				if let Some(h) = &mut other.structural_hash {
+					h.clear()
+				}
+
+				if let Some(mut h) = self.structural_hash.take() {
+					for key in self.keys() {
+						h.insert(key)
+					}
+
+					self.structural_hash = Some(h)
+				}
+			}
+
+			return;
+		}
+
+		let self_range = (
+			self.first_key_value().unwrap().0,
+			self.last_key_value().unwrap().0,
+		);
+		let other_range = (
+			other.first_key_value().unwrap().0,
+			other.last_key_value().unwrap().0,
+		);
+
+		if self_range.1 < other_range.0 {
+			self.join(other, true)
+		} else if other_range.1 < self_range.0 {
+			self.join(other, false)
+		} else {
+			let other = std::mem::take(other);
+			for (key, value) in other {
+				self.insert(key, value);
+			}
+		}
+	}
+
+	/// Joins the tree of `other` to the tree of this map, leaving `other` empty.
+	///
+	/// # Correctness
+	///
+	/// If `other_is_after` is `true`, every key of `other` must be greater than the keys of this map.
+	/// Otherwise, every key of `other` must be lower than the keys of this map.
+	#[inline]
+	fn join(&mut self, other: &mut Self, other_is_after: bool)
+	where
+		C: Default,
+	{
+		let mut other = std::mem::take(other);
+		let (key, value) = if other_is_after {
+			other.pop_first()
+		} else {
+			other.pop_last()
+		}
+		.unwrap();
+		let separator = Item::new(key, value);
+
+		let other_root = match other.root.take() {
+			Some(root_id) => root_id,
+			None => {
+				let addr = if other_is_after {
+					self.last_valid_address()
+				} else {
+					self.first_back_address()
+				};
+				self.insert_at(addr, separator);
+				return;
+			}
+		};
+
+		#[cfg(feature = "watermark")]
+		self.watermarks.touch_all();
+		#[cfg(feature = "bloom")]
+		if let Some(filter) = &mut self.key_filter {
+			filter.insert(separator.key());
+			for key in other.keys() {
+				filter.insert(key)
+			}
+		}
+		#[cfg(feature = "structural-hash")]
+		if let Some(h) = &mut self.structural_hash {
+			h.insert(separator.key());
+			for key in other.keys() {
+				h.insert(key)
+			}
+		}
+
+		self.len += std::mem::take(&mut other.len) + 1;
+		let other_root = other.move_node_into(self, other_root, &mut |_, value| value);
+		let root = self.root.unwrap();
+		if other_is_after {
+			self.join_trees(root, separator, other_root)
+		} else {
+			self.join_trees(other_root, separator, root)
+		}
+		self.reset_leaf_bounds();
+
+		#[cfg(feature = "bloom")]
+		self.sync_key_filter();
+	}
+
+	/// Splits the collection into two at the given key.
+	/// Returns everything after the given key, including the key.
+	///
+	/// The tree is cut along the path leading to the key,
+	/// and the nodes on the right of this path are moved to the returned map
+	/// without comparing or reinserting their entries.
+	/// Only the nodes along the cut are rebalanced.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut a = BTreeMap::new();
+	/// a.insert(1, "a");
+	/// a.insert(2, "b");
+	/// a.insert(3, "c");
+	/// a.insert(17, "d");
+	/// a.insert(41, "e");
+	///
+	/// let b = a.split_off(&3);
+	///
+	/// assert_eq!(a.len(), 2);
+	/// assert_eq!(b.len(), 3);
+	///
+	/// assert_eq!(a[&1], "a");
+	/// assert_eq!(a[&2], "b");
+	///
+	/// assert_eq!(b[&3], "c");
+	/// assert_eq!(b[&17], "d");
+	/// assert_eq!(b[&41], "e");
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn split_off<Q: ?Sized>(&mut self, key: &Q) -> Self
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+		C: Default,
+	{
+		let mut right = BTreeMap::new();
+		right.settings = self.settings.clone();
+
+		let (right_root, separator) = self.cut_tree(key, false);
+
+		// Move the right tree into the new map.
+		let mut moved = 0;
+		if let Some(right_root) = right_root {
+			let root_id = self.move_node_into(&mut right, right_root, &mut |_, value| {
+				moved += 1;
+				value
+			});
+			right.root = Some(root_id);
+			right.len = moved;
+		}
+
+		self.reset_leaf_bounds();
+		right.reset_leaf_bounds();
+
+		if let Some(item) = separator {
+			right.insert_at(right.first_back_address(), item);
+			moved += 1
+		}
+
+		self.len -= moved;
+
+		#[cfg(feature = "watermark")]
+		if moved > 0 {
+			self.watermarks.touch_all()
+		}
+
+		#[cfg(feature = "bloom")]
+		self.split_key_filter(&mut right, moved);
+
+		#[cfg(feature = "structural-hash")]
+		self.split_structural_hash(&mut right);
+
+		right
+	}
+
+	/// Removes the entries whose key is in the given range, and returns them in a new map.
+	///
+	/// The tree is cut along the paths leading to both ends of the range,
+	/// the nodes in between are moved to the returned map,
+	/// and the two remaining parts of the tree are joined back together.
+	/// The entries outside of the range are neither compared nor moved,
+	/// so that the operation runs in `O(log n + k)` time, where `k` is the number of removed entries.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<_, _> = (0..100).map(|i| (i, i)).collect();
+	/// let removed = map.remove_range(10..90);
+	///
+	/// assert!(removed.keys().copied().eq(10..90));
+	/// assert!(map.keys().copied().eq((0..10).chain(90..100)));
+	/// ```
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn remove_range<T: ?Sized, R>(&mut self, range: R) -> Self
+	where
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
+		C: Default,
+	{
+		if !is_valid_range(&range) {
+			panic!("Invalid range")
+		}
+
+		let mut removed = BTreeMap::new();
+		removed.settings = self.settings.clone();
+
+		// The key filter would be rebuilt from the partial trees.
+		#[cfg(feature = "bloom")]
+		let key_filter = self.key_filter.take();
+		// The structural hash is updated once the removed entries are known.
+		#[cfg(feature = "structural-hash")]
+		let structural_hash = self.structural_hash.take();
+
+		// Cut the tree at the start of the range.
+		let (middle, first) = match range.start_bound() {
+			Bound::Included(key) => self.cut_tree(key, false),
+			Bound::Excluded(key) => self.cut_tree(key, true),
+			Bound::Unbounded => (self.root.take(), None),
+		};
+		let left = self.root.take();
+
+		// Cut the middle tree at the end of the range.
+		self.set_root(middle);
+		if let Some(item) = first {
+			self.insert_at(self.first_back_address(), item);
+		}
+		let (right, separator) = match range.end_bound() {
+			Bound::Included(key) => self.cut_tree(key, true),
+			Bound::Excluded(key) => self.cut_tree(key, false),
+			Bound::Unbounded => (None, None),
+		};
+
+		// Move the middle tree into the new map.
+		if let Some(middle) = self.root.take() {
+			let root_id = self.move_node_into(&mut removed, middle, &mut |_, value| value);
+			removed.set_root(Some(root_id));
+		}
+
+		// Join the remaining trees.
+		self.set_root(right);
+		let separator = separator.or_else(|| {
+			left?;
+			let addr = self.first_item_address()?;
+			self.remove_at(addr).map(|(item, _)| item)
+		});
+		match (left, separator, self.root) {
+			(Some(left), Some(separator), Some(right)) => self.join_trees(left, separator, right),
+			(Some(left), Some(separator), None) => {
+				self.set_root(Some(left));
+				self.insert_at(self.last_valid_address(), separator);
+			}
+			(None, Some(separator), _) => {
+				self.insert_at(self.first_back_address(), separator);
+			}
+			(Some(left), None, _) => self.root = Some(left),
+			(None, None, _) => (),
+		}
+		self.set_root(self.root);
+
+		#[cfg(feature = "watermark")]
+		if !removed.is_empty() {
+			self.watermarks.touch_all()
+		}
+
+		#[cfg(feature = "bloom")]
+		{
+			let moved = removed.len;
+			self.key_filter = key_filter;
+			self.split_key_filter(&mut removed, moved);
+		}
+
+		#[cfg(feature = "structural-hash")]
+		{
+			self.structural_hash = structural_hash;
+			self.split_structural_hash(&mut removed);
+		}
+
+		removed
+	}
+
+	/// Sets the root of the tree,
+	/// and updates the length and leaf bounds of the map accordingly.
+	#[inline]
+	fn set_root(&mut self, root: Option<usize>) {
+		self.root = root;
+		self.len = root.map_or(0, |id| self.node(id).subtree_len());
+		self.reset_leaf_bounds()
+	}
+
+	/// Swaps the contents of two maps, including their node storage.
+	///
+	/// Unlike [`std::mem::swap`], the metrics sink attached to each map, if any,
+	/// stays in place.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut a: BTreeMap<_, _> = (0..3).map(|i| (i, i)).collect();
+	/// let mut b: BTreeMap<_, _> = (10..12).map(|i| (i, i)).collect();
+	/// a.swap_contents(&mut b);
+	///
+	/// assert!(a.keys().eq(&[10, 11]));
+	/// assert!(b.keys().eq(&[0, 1, 2]));
+	/// ```
+	#[inline]
+	pub fn swap_contents(&mut self, other: &mut Self) {
+		std::mem::swap(&mut self.nodes, &mut other.nodes);
+		std::mem::swap(&mut self.root, &mut other.root);
+		std::mem::swap(&mut self.len, &mut other.len);
+		std::mem::swap(&mut self.leaf_bounds, &mut other.leaf_bounds);
+		self.generation = self.generation.wrapping_add(1);
+		other.generation = other.generation.wrapping_add(1);
+		#[cfg(feature = "watermark")]
+		{
+			self.watermarks.touch_all();
+			other.watermarks.touch_all();
+		}
+		#[cfg(feature = "bloom")]
+		std::mem::swap(&mut self.key_filter, &mut other.key_filter);
+		#[cfg(feature = "structural-hash")]
+		std::mem::swap(&mut self.structural_hash, &mut other.structural_hash);
+		#[cfg(feature = "checksum")]
+		std::mem::swap(&mut self.checksums, &mut other.checksums);
+	}
+
+	/// Replaces the contents of the map with the contents of `other`,
+	/// leaving `other` empty.
+	///
+	/// The current entries of the map are dropped, but its node storage is kept:
+	/// the entries of `other` are moved into it.
+	/// This allows refreshing a long-lived map with a pre-allocated
+	/// container from a freshly built map without losing the allocated capacity.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use slab::Slab;
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, i32> = BTreeMap::with_container(Slab::with_capacity(64));
+	/// map.insert(1, 1);
+	///
+	/// let mut fresh: BTreeMap<_, _> = (0..100).map(|i| (i, i * 2)).collect();
+	/// map.replace_contents_from(&mut fresh);
+	///
+	/// assert!(fresh.is_empty());
+	/// assert_eq!(map.len(), 100);
+	/// assert_eq!(map[&50], 100);
+	/// ```
+	#[inline]
+	pub fn replace_contents_from(&mut self, other: &mut Self)
+	where
+		C: cc_traits::Clear + Default,
+	{
+		self.clear();
+		self.push_back_sorted(std::mem::take(other));
+	}
+
+	/// Appends the given items at the end of the tree, without any key comparison.
+	///
+	/// If the map is empty, the tree is built bottom-up with [`BTreeMap::build_sorted`].
+	/// Otherwise each item is inserted right after the previous one.
+	/// In both cases the whole operation runs in amortized linear time.
+	///
+	/// # Correctness
+	///
+	/// Items must be sorted and greater than any key already in the map.
+	#[inline]
+	pub(crate) fn push_back_sorted<I>(&mut self, items: I)
+	where
+		I: IntoIterator<Item = (K, V)>,
+	{
+		if self.is_empty() {
+			let items = items
+				.into_iter()
+				.map(|(key, value)| Item::new(key, value))
+				.collect();
+			return self.build_sorted(items);
+		}
+
+		let mut addr = self.last_valid_address();
+		for (key, value) in items {
+			let item_addr = self.insert_at(addr, Item::new(key, value));
+			addr = Address::new(item_addr.id, (item_addr.offset.unwrap() + 1).into());
+		}
+	}
+
+	/// Builds the tree of an empty map from the given sorted items, level by level.
+	///
+	/// The items are spread evenly over as few leaves as possible,
+	/// keeping one separator item between two consecutive leaves.
+	/// The leaves are then grouped under as few internal nodes as possible,
+	/// and so on until a single root node is left.
+	/// Every node is allocated once, and no node is ever split or rebalanced.
+	///
+	/// # Correctness
+	///
+	/// Items must be sorted and the map must be empty.
+	fn build_sorted(&mut self, items: Vec<Item<K, V>>) {
+		debug_assert!(self.is_empty());
+		let len = items.len();
+		if len == 0 {
+			return;
+		}
+
+		#[cfg(any(feature = "watermark", feature = "bloom", feature = "structural-hash"))]
+		for item in &items {
+			#[cfg(feature = "watermark")]
+			self.watermarks.touch(item.key());
+			#[cfg(feature = "bloom")]
+			if let Some(filter) = &mut self.key_filter {
+				filter.insert(item.key())
+			}
+			#[cfg(feature = "structural-hash")]
+			if let Some(h) = &mut self.structural_hash {
+				h.insert(item.key())
+			}
+		}
+
+		// `leaf_count` leaves hold every item but the `leaf_count - 1` separators.
+		let leaf_count = (len + 1).div_ceil(M + 1);
+		let mut items = items.into_iter();
+		let mut children = Vec::with_capacity(leaf_count);
+		let mut separators = Vec::with_capacity(leaf_count - 1);
+		for i in 0..leaf_count {
+			let mut leaf = LeafNode::empty();
+			for _ in 0..even_share(len + 1 - leaf_count, leaf_count, i) {
+				leaf.push_right(items.next().unwrap());
+			}
+
+			children.push(self.allocate_node(Node::Leaf(leaf)));
+			if i + 1 < leaf_count {
+				separators.push(items.next().unwrap())
+			}
+		}
+
+		self.leaf_bounds = Some((children[0], children[leaf_count - 1]));
+
+		while children.len() > 1 {
+			let child_count = children.len();
+			let node_count = child_count.div_ceil(M);
+			let mut level_children = children.into_iter();
+			let mut level_separators = separators.into_iter();
+			children = Vec::with_capacity(node_count);
+			separators = Vec::with_capacity(node_count - 1);
+			for i in 0..node_count {
+				let first_child = level_children.next().unwrap();
+				let branches = (1..even_share(child_count, node_count, i))
+					.map(|_| Branch {
+						item: level_separators.next().unwrap(),
+						child: level_children.next().unwrap(),
+					})
+					.collect();
+
+				let node = InternalNode::from_parts(None, first_child, branches);
+				children.push(self.allocate_node(Node::Internal(node)));
+				if i + 1 < node_count {
+					separators.push(level_separators.next().unwrap())
 				}
+			}
+		}
 
-				result
+		self.root = Some(children[0]);
+		self.len = len;
+
+		#[cfg(feature = "bloom")]
+		self.sync_key_filter();
+	}
+
+	/// Merges the map with the given sorted iterator into a new map.
+	///
+	/// Both sequences are traversed once and the resulting map is built bottom-up.
+	/// When a key appears in both the map and the iterator,
+	/// `policy` is called with the key, the value of the map and the value of the iterator,
+	/// and returns the merged value.
+	///
+	/// The resulting map keeps the configuration of this map:
+	/// size limits, split policy, metrics receiver, watermarks,
+	/// key filter and structural hash (filled with the merged keys).
+	///
+	/// # Panics
+	///
+	/// Panics if the keys of the iterator are not strictly increasing.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let map: BTreeMap<i32, i32> = (0..10).map(|i| (i * 2, 1)).collect();
+	/// let merged = map.merged_with((5..15).map(|i| (i, 10)), |_, a, b| a + b);
+	///
+	/// assert_eq!(merged.len(), 15);
+	/// assert_eq!(merged[&4], 1);
+	/// assert_eq!(merged[&5], 10);
+	/// assert_eq!(merged[&6], 11);
+	/// ```
+	#[inline]
+	pub fn merged_with<I, F>(mut self, sorted_iter: I, mut policy: F) -> Self
+	where
+		K: Ord,
+		C: Default,
+		I: IntoIterator<Item = (K, V)>,
+		F: FnMut(&K, V, V) -> V,
+	{
+		let mut result = BTreeMap::new();
+		result.settings = self.settings.take();
+		#[cfg(feature = "metrics")]
+		{
+			result.metrics = self.metrics.take();
+		}
+		#[cfg(feature = "watermark")]
+		{
+			result.watermarks = std::mem::take(&mut self.watermarks);
+		}
+		#[cfg(feature = "bloom")]
+		if let Some(mut filter) = self.key_filter.take() {
+			filter.clear();
+			result.key_filter = Some(filter)
+		}
+		#[cfg(feature = "structural-hash")]
+		if let Some(mut h) = self.structural_hash.take() {
+			h.clear();
+			result.structural_hash = Some(h)
+		}
+
+		let mut left = self.into_iter().peekable();
+		let mut right = SortedCheck::new(sorted_iter.into_iter()).peekable();
+
+		let merged = std::iter::from_fn(|| match (left.peek(), right.peek()) {
+			(Some((a, _)), Some((b, _))) => match a.cmp(b) {
+				Ordering::Less => left.next(),
+				Ordering::Greater => right.next(),
+				Ordering::Equal => {
+					let (key, a) = left.next().unwrap();
+					let (_, b) = right.next().unwrap();
+					let value = policy(&key, a, b);
+					Some((key, value))
+				}
+			},
+			(Some(_), None) => left.next(),
+			(None, _) => right.next(),
+		});
+
+		result.push_back_sorted(merged);
+		result
+	}
+
+	/// Merges the map with another map into a new map.
+	///
+	/// This is [`BTreeMap::merged_with`] applied to the entries of `other`:
+	/// both maps are traversed once, in `O(n + m)`,
+	/// and the resulting map is built bottom-up
+	/// instead of inserting the entries of one map into the other.
+	/// When a key appears in both maps,
+	/// `resolve` is called with the key, the value of this map and the value of `other`,
+	/// and returns the merged value.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let a: BTreeMap<i32, &str> = [(1, "a"), (3, "c")].into_iter().collect();
+	/// let b: BTreeMap<i32, &str> = [(2, "b"), (3, "d")].into_iter().collect();
+	/// let merged = a.merge_with(b, |_, _, newer| newer);
+	///
+	/// assert!(merged.into_iter().eq([(1, "a"), (2, "b"), (3, "d")]));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn merge_with<D: SlabMut<Node<K, V>>, F>(self, other: BTreeMap<K, V, D>, resolve: F) -> Self
+	where
+		K: Ord,
+		C: Default,
+		D: SimpleCollectionRef,
+		D: SimpleCollectionMut,
+		F: FnMut(&K, V, V) -> V,
+	{
+		self.merged_with(other, resolve)
+	}
+
+	/// Builds a map from an unsorted vector of key-value pairs.
+	///
+	/// The vector is sorted in place and the map is built bottom-up,
+	/// without any key comparison in the tree,
+	/// which is much faster than inserting the pairs one by one
+	/// (as [`FromIterator`] does) for large inputs.
+	/// When a key appears more than once, the last value is kept,
+	/// as if the pairs were inserted in order.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let map: BTreeMap<i32, char> = BTreeMap::from_unsorted_vec(vec![(3, 'c'), (1, 'a'), (3, 'd')]);
+	/// assert!(map.into_iter().eq([(1, 'a'), (3, 'd')]));
+	/// ```
+	#[inline]
+	pub fn from_unsorted_vec(items: Vec<(K, V)>) -> Self
+	where
+		K: Ord,
+		C: Default,
+	{
+		Self::from_unsorted_vec_with(items, |_, _, last| last)
+	}
+
+	/// Builds a map from an unsorted vector of key-value pairs,
+	/// merging the values of duplicate keys with the given policy.
+	///
+	/// When a key appears more than once,
+	/// `policy` is called with the key, the value merged so far and the next value,
+	/// in the order of the vector, and returns the merged value.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let words = vec![("b", 1), ("a", 1), ("b", 1)];
+	/// let counts: BTreeMap<&str, usize> = BTreeMap::from_unsorted_vec_with(words, |_, a, b| a + b);
+	/// assert!(counts.into_iter().eq([("a", 1), ("b", 2)]));
+	/// ```
+	#[inline]
+	pub fn from_unsorted_vec_with<F>(mut items: Vec<(K, V)>, policy: F) -> Self
+	where
+		K: Ord,
+		C: Default,
+		F: FnMut(&K, V, V) -> V,
+	{
+		// The sort is stable so that duplicate values are merged in order.
+		items.sort_by(|(a, _), (b, _)| a.cmp(b));
+		Self::from_sorted_vec_with(items, policy)
+	}
+
+	/// Builds a map from an unsorted vector of key-value pairs, sorting it in parallel,
+	/// and merging the values of duplicate keys with the given policy.
+	///
+	/// This is the parallel version of [`BTreeMap::from_unsorted_vec_with`].
+	///
+	/// Requires the `rayon` feature.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let items: Vec<_> = (0..10000).rev().map(|i| (i % 5000, 1)).collect();
+	/// let map: BTreeMap<i32, i32> = BTreeMap::par_from_unsorted_vec_with(items, |_, a, b| a + b);
+	/// assert_eq!(map.len(), 5000);
+	/// assert!(map.values().all(|v| *v == 2));
+	/// ```
+	#[cfg(feature = "rayon")]
+	#[inline]
+	pub fn par_from_unsorted_vec_with<F>(mut items: Vec<(K, V)>, policy: F) -> Self
+	where
+		K: Ord + Send,
+		V: Send,
+		C: Default,
+		F: FnMut(&K, V, V) -> V,
+	{
+		use rayon::slice::ParallelSliceMut;
+		items.par_sort_by(|(a, _), (b, _)| a.cmp(b));
+		Self::from_sorted_vec_with(items, policy)
+	}
+
+	/// Builds a map from a vector of key-value pairs sorted by key,
+	/// merging the values of consecutive duplicate keys with the given policy.
+	#[inline]
+	fn from_sorted_vec_with<F>(items: Vec<(K, V)>, mut policy: F) -> Self
+	where
+		K: Ord,
+		C: Default,
+		F: FnMut(&K, V, V) -> V,
+	{
+		let mut items = items.into_iter().peekable();
+		let deduplicated = std::iter::from_fn(|| {
+			let (key, mut value) = items.next()?;
+			while let Some((_, next)) = items.next_if(|(next_key, _)| *next_key == key) {
+				value = policy(&key, value, next)
+			}
+
+			Some((key, value))
+		});
+
+		let mut result = BTreeMap::new();
+		result.push_back_sorted(deduplicated);
+		result
+	}
+
+	/// Copies the entries of `src` with a key in the given range into the map.
+	///
+	/// Entries with a key already present in the map replace the current value.
+	/// The copied entries greater than every key of the map (all of them when the map is empty)
+	/// are appended at the end of the tree without any key comparison,
+	/// the other ones are inserted one by one.
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let src: BTreeMap<i32, i32> = (0..100).map(|i| (i, i * 10)).collect();
+	/// let mut shard: BTreeMap<i32, i32> = [(10, 0), (200, 0)].into_iter().collect();
+	///
+	/// shard.extend_from_range(&src, 10..20);
+	/// assert_eq!(shard.len(), 11);
+	/// assert_eq!(shard[&10], 100);
+	/// assert!(shard.keys().copied().eq((10..20).chain([200])));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn extend_from_range<T: ?Sized, R, D: Slab<Node<K, V>>>(
+		&mut self,
+		src: &BTreeMap<K, V, D>,
+		range: R,
+	) where
+		K: Ord + Clone,
+		V: Clone,
+		T: Ord,
+		K: Borrow<T>,
+		R: RangeBounds<T>,
+		D: SimpleCollectionRef,
+	{
+		let mut items = src.range(range).peekable();
+
+		// Entries that do not go after the last key.
+		while let Some((key, value)) =
+			items.next_if(|(key, _)| self.last_key().is_some_and(|last| *key <= last))
+		{
+			self.insert(key.clone(), value.clone());
+		}
+
+		self.push_back_sorted(items.map(|(key, value)| (key.clone(), value.clone())));
+	}
+
+	/// Extends the map with the given key-value pairs,
+	/// failing on the first key already present in the map.
+	///
+	/// The pairs inserted before the duplicate key are left in the map,
+	/// and the remaining pairs of the iterator are not consumed.
+	/// The rejected pair is returned in the error.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// assert!(map.extend_unique(vec![(1, "a"), (2, "b")]).is_ok());
+	///
+	/// let err = map.extend_unique(vec![(3, "c"), (1, "d"), (4, "e")]).unwrap_err();
+	/// assert_eq!((err.key, err.value), (1, "d"));
+	/// assert_eq!(map.len(), 3);
+	/// assert_eq!(map[&1], "a");
+	/// ```
+	#[inline]
+	pub fn extend_unique<I>(&mut self, iter: I) -> Result<(), DuplicateKeyError<K, V>>
+	where
+		K: Ord,
+		I: IntoIterator<Item = (K, V)>,
+	{
+		for (key, value) in iter {
+			match self.address_of(&key) {
+				Ok(_) => return Err(DuplicateKeyError { key, value }),
+				Err(addr) => {
+					self.insert_exactly_at(addr, Item::new(key, value), None);
+				}
 			}
 		}
+
+		Ok(())
+	}
+
+	/// Creates a consuming iterator visiting all the keys, in sorted order.
+	/// The map cannot be used after calling this.
+	/// The iterator element type is `K`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut a = BTreeMap::new();
+	/// a.insert(2, "b");
+	/// a.insert(1, "a");
+	///
+	/// let keys: Vec<i32> = a.into_keys().collect();
+	/// assert_eq!(keys, [1, 2]);
+	/// ```
+	#[inline]
+	pub fn into_keys(self) -> IntoKeys<K, V, C> {
+		IntoKeys {
+			inner: self.into_iter(),
+		}
 	}
 
-	/// Gets a mutable iterator over the entries of the map, sorted by key.
+	/// Creates a consuming iterator visiting all the values, in order by key.
+	/// The map cannot be used after calling this.
+	/// The iterator element type is `V`.
 	///
 	/// # Example
 	///
 	/// ```
 	/// use btree_slab::BTreeMap;
 	///
-	/// let mut map = BTreeMap::new();
-	/// map.insert("a", 1);
-	/// map.insert("b", 2);
-	/// map.insert("c", 3);
+	/// let mut a = BTreeMap::new();
+	/// a.insert(1, "hello");
+	/// a.insert(2, "goodbye");
 	///
-	/// // add 10 to the value if the key isn't "a"
-	/// for (key, value) in map.iter_mut() {
-	///     if key != &"a" {
-	///         *value += 10;
-	///     }
-	/// }
+	/// let values: Vec<&str> = a.into_values().collect();
+	/// assert_eq!(values, ["hello", "goodbye"]);
 	/// ```
 	#[inline]
-	pub fn iter_mut(&mut self) -> IterMut<K, V, C> {
-		IterMut::new(self)
+	pub fn into_values(self) -> IntoValues<K, V, C> {
+		IntoValues {
+			inner: self.into_iter(),
+		}
 	}
 
-	/// Gets a mutable iterator over the entries of the map, sorted by key, that allows insertion and deletion of the iterated entries.
+	/// Creates a consuming iterator visiting all the entries in order by key,
+	/// along with their address.
 	///
-	/// # Correctness
-	///
-	/// It is safe to insert any key-value pair while iterating,
-	/// however this might break the well-formedness
-	/// of the underlying tree, which relies on several invariants.
-	/// To preserve these invariants,
-	/// the inserted key must be *strictly greater* than the previous visited item's key,
-	/// and *strictly less* than the next visited item
-	/// (which you can retrive through [`EntriesMut::peek`] without moving the iterator).
-	/// If this rule is not respected, the data structure will become unusable
-	/// (invalidate the specification of every method of the API).
+	/// Entries are moved out of the nodes in place, without rebalancing the tree,
+	/// so each address is the one the entry had before calling this method.
+	/// This lets code that refers to entries by their [`Address`]
+	/// map its bookkeeping onto the drained entries.
 	///
 	/// # Example
 	///
 	/// ```
-	/// use btree_slab::BTreeMap;
+	/// use btree_slab::{BTreeMap, generic::map::BTreeExt};
 	///
-	/// let mut map = BTreeMap::new();
-	/// map.insert("a", 1);
-	/// map.insert("b", 2);
-	/// map.insert("d", 4);
+	/// let map: BTreeMap<_, _> = (0..100).map(|i| (i, i * 2)).collect();
+	/// let addresses: Vec<_> = map.addresses().collect();
 	///
-	/// let mut entries = map.entries_mut();
-	/// entries.next();
-	/// entries.next();
-	/// entries.insert("c", 3);
+	/// for (i, (addr, key, value)) in map.into_entries().enumerate() {
+	///     assert_eq!(addr, addresses[i]);
+	///     assert_eq!((key, value), (i, i * 2));
+	/// }
+	/// ```
+	#[inline]
+	pub fn into_entries(self) -> IntoEntries<K, V, C> {
+		IntoEntries {
+			inner: self.into_iter(),
+		}
+	}
+
+	/// Converts this map into the set of its keys, dropping the values.
+	///
+	/// The nodes are moved as they are into the storage of the set:
+	/// no key is compared and the shape of the tree is kept.
+	///
+	/// # Example
 	///
-	/// let entries: Vec<_> = map.into_iter().collect();
-	/// assert_eq!(entries, vec![("a", 1), ("b", 2), ("c", 3), ("d", 4)]);
+	/// ```
+	/// use btree_slab::{BTreeMap, BTreeSet};
+	///
+	/// let map: BTreeMap<_, _> = (0..100).map(|i| (i, i.to_string())).collect();
+	/// let set: BTreeSet<_> = map.into_key_set();
+	/// assert!(set.iter().copied().eq(0..100));
 	/// ```
 	#[inline]
-	pub fn entries_mut(&mut self) -> EntriesMut<K, V, C> {
-		EntriesMut::new(self)
+	pub fn into_key_set<D>(self) -> BTreeSet<K, D>
+	where
+		D: SlabMut<Node<K, ()>> + Default,
+		D: SimpleCollectionRef,
+		D: SimpleCollectionMut,
+	{
+		BTreeSet::from_map(self.map_values_into(|_, _| ()))
 	}
 
-	/// Constructs a mutable double-ended iterator over a sub-range of elements in the map.
-	/// The simplest way is to use the range syntax `min..max`, thus `range(min..max)` will
-	/// yield elements from min (inclusive) to max (exclusive).
-	/// The range may also be entered as `(Bound<T>, Bound<T>)`, so for example
-	/// `range((Excluded(4), Included(10)))` will yield a left-exclusive, right-inclusive
-	/// range from 4 to 10.
+	/// Rewrites the nodes of the map into a fresh container,
+	/// where they are stored contiguously.
 	///
-	/// # Panics
+	/// After many insertions and removals, the node container may be fragmented,
+	/// with a capacity far exceeding the number of nodes.
+	/// This moves every node into a new container and renumbers them,
+	/// releasing the memory of the previous container and improving locality.
+	/// The shape of the tree is kept and no key is compared.
 	///
-	/// Panics if range `start > end`.
-	/// Panics if range `start == end` and both bounds are `Excluded`.
+	/// Addresses obtained before the compaction are invalidated.
 	///
 	/// # Example
 	///
 	/// ```
 	/// use btree_slab::BTreeMap;
 	///
-	/// let mut map: BTreeMap<&str, i32> = ["Alice", "Bob", "Carol", "Cheryl"]
-	///     .iter()
-	///     .map(|&s| (s, 0))
-	///     .collect();
-	/// for (_, balance) in map.range_mut("B".."Cheryl") {
-	///     *balance += 100;
-	/// }
-	/// for (name, balance) in &map {
-	///     println!("{} => {}", name, balance);
-	/// }
+	/// let mut map: BTreeMap<_, _> = (0..10000).map(|i| (i, i)).collect();
+	/// map.retain(|k, _| k % 100 == 0);
+	/// let before = map.stats();
+	///
+	/// map.compact();
+	/// let after = map.stats();
+	///
+	/// assert!(map.keys().copied().eq((0..10000).step_by(100)));
+	/// assert_eq!(after.nodes, before.nodes);
+	/// assert!(after.slab_capacity < before.slab_capacity);
 	/// ```
 	#[inline]
-	pub fn range_mut<T: ?Sized, R>(&mut self, range: R) -> RangeMut<K, V, C>
+	pub fn compact(&mut self)
 	where
-		T: Ord,
-		K: Borrow<T>,
-		R: RangeBounds<T>,
+		C: Default,
 	{
-		RangeMut::new(self, range)
+		let generation = self.generation;
+		let map = std::mem::take(self);
+		*self = map.map_values_into(|_, value| value);
+		self.generation = generation.wrapping_add(1);
 	}
 
-	/// Gets a mutable iterator over the values of the map, in order by key.
+	/// Clones the map into a fresh container, where its nodes are stored contiguously.
+	///
+	/// Unlike [`Clone::clone`], which copies the node container as is,
+	/// this copies only the nodes of the tree and renumbers them,
+	/// so that the clone does not inherit the fragmentation of this map.
+	/// The shape of the tree is kept and no key is compared.
 	///
 	/// # Example
 	///
 	/// ```
 	/// use btree_slab::BTreeMap;
 	///
-	/// let mut a = BTreeMap::new();
-	/// a.insert(1, String::from("hello"));
-	/// a.insert(2, String::from("goodbye"));
-	///
-	/// for value in a.values_mut() {
-	///     value.push_str("!");
-	/// }
+	/// let mut map: BTreeMap<_, _> = (0..10000).map(|i| (i, i)).collect();
+	/// map.retain(|k, _| k % 100 == 0);
 	///
-	/// let values: Vec<String> = a.values().cloned().collect();
-	/// assert_eq!(values, [String::from("hello!"),
-	///                     String::from("goodbye!")]);
+	/// let clone = map.clone_compact();
+	/// assert!(clone == map);
+	/// assert!(clone.stats().slab_capacity < map.stats().slab_capacity);
 	/// ```
 	#[inline]
-	pub fn values_mut(&mut self) -> ValuesMut<K, V, C> {
-		ValuesMut {
-			inner: self.iter_mut(),
+	pub fn clone_compact(&self) -> Self
+	where
+		K: Clone,
+		V: Clone,
+		C: Default,
+	{
+		let mut target = BTreeMap::new();
+		target.settings = self.settings.clone();
+		#[cfg(feature = "metrics")]
+		{
+			target.metrics = self.metrics.clone();
+		}
+		#[cfg(feature = "watermark")]
+		{
+			target.watermarks = self.watermarks.clone();
+		}
+		#[cfg(feature = "bloom")]
+		{
+			target.key_filter = self.key_filter.clone();
+		}
+		#[cfg(feature = "structural-hash")]
+		{
+			target.structural_hash = self.structural_hash.clone();
+		}
+
+		if let Some(root_id) = self.root {
+			target.root = Some(self.clone_node_into(&mut target, root_id));
+			target.len = self.len;
+			target.reset_leaf_bounds();
 		}
+
+		target
 	}
 
-	/// Creates an iterator which uses a closure to determine if an element should be removed.
-	///
-	/// If the closure returns true, the element is removed from the map and yielded.
-	/// If the closure returns false, or panics, the element remains in the map and will not be
-	/// yielded.
-	///
-	/// Note that `drain_filter` lets you mutate every value in the filter closure, regardless of
-	/// whether you choose to keep or remove it.
+	/// Clones the subtree rooted in `id` into `target`.
 	///
-	/// If the iterator is only partially consumed or not consumed at all, each of the remaining
-	/// elements will still be subjected to the closure and removed and dropped if it returns true.
+	/// Returns the identifier of the subtree root in `target`.
+	/// Its parent is left unchanged: it is assigned when the parent node is allocated.
+	fn clone_node_into(&self, target: &mut Self, id: usize) -> usize
+	where
+		K: Clone,
+		V: Clone,
+	{
+		match self.node(id) {
+			Node::Leaf(leaf) => target.allocate_node(Node::Leaf(leaf.clone())),
+			Node::Internal(node) => {
+				let first_child = self.clone_node_into(target, node.first_child_id());
+				let branches = node
+					.branches()
+					.iter()
+					.map(|branch| Branch {
+						item: branch.item.clone(),
+						child: self.clone_node_into(target, branch.child),
+					})
+					.collect();
+
+				target.allocate_node(Node::Internal(InternalNode::from_parts(
+					node.parent(),
+					first_child,
+					branches,
+				)))
+			}
+		}
+	}
+
+	/// Converts the values of the map in place, in key order.
 	///
-	/// It is unspecified how many more elements will be subjected to the closure
-	/// if a panic occurs in the closure, or a panic occurs while dropping an element,
-	/// or if the `DrainFilter` value is leaked.
+	/// The nodes of the tree are kept in their slots, and no key is compared.
+	/// If `f` panics, the map is left empty.
 	///
 	/// # Example
 	///
-	/// Splitting a map into even and odd keys, reusing the original map:
-	///
 	/// ```
 	/// use btree_slab::BTreeMap;
 	///
-	/// let mut map: BTreeMap<i32, i32> = (0..8).map(|x| (x, x)).collect();
-	/// let evens: BTreeMap<_, _> = map.drain_filter(|k, _v| k % 2 == 0).collect();
-	/// let odds = map;
-	/// assert_eq!(evens.keys().copied().collect::<Vec<_>>(), vec![0, 2, 4, 6]);
-	/// assert_eq!(odds.keys().copied().collect::<Vec<_>>(), vec![1, 3, 5, 7]);
+	/// let mut map: BTreeMap<_, _> = (0..100).map(|i| (i, i.to_string())).collect();
+	/// map.map_values(|k, v| format!("{}:{}", v, k * 2));
+	/// assert_eq!(map[&21], "21:42");
 	/// ```
-	#[inline]
-	pub fn drain_filter<F>(&mut self, pred: F) -> DrainFilter<K, V, C, F>
+	pub fn map_values<F>(&mut self, mut f: F)
 	where
-		F: FnMut(&K, &mut V) -> bool,
+		F: FnMut(&K, V) -> V,
 	{
-		DrainFilter::new(self, pred)
+		/// Empties the map if a conversion panics,
+		/// since the node being converted has been taken out of the tree.
+		struct Guard<'a, K, V, C>(&'a mut BTreeMap<K, V, C>);
+
+		impl<K, V, C> Drop for Guard<'_, K, V, C> {
+			fn drop(&mut self) {
+				if std::thread::panicking() {
+					self.0.root = None;
+					self.0.len = 0;
+					self.0.leaf_bounds = None;
+					self.0.generation = self.0.generation.wrapping_add(1);
+					#[cfg(feature = "structural-hash")]
+					if let Some(h) = &mut self.0.structural_hash {
+						h.clear()
+					}
+				}
+			}
+		}
+
+		if let Some(root_id) = self.root {
+			#[cfg(feature = "watermark")]
+			self.watermarks.touch_all();
+			let guard = Guard(self);
+			guard.0.map_node_values(root_id, &mut f);
+		}
 	}
 
-	/// Retains only the elements specified by the predicate.
+	/// Converts the values of this map, in key order, into a map with another storage.
 	///
-	/// In other words, remove all pairs `(k, v)` such that `f(&k, &mut v)` returns `false`.
+	/// Nodes are moved one by one from the storage of this map to the new storage
+	/// and receive new identifiers, but the shape of the tree is kept and no key is compared.
+	/// This is much faster than collecting `map.into_iter().map(...)`.
 	///
 	/// # Example
 	///
 	/// ```
 	/// use btree_slab::BTreeMap;
 	///
-	/// let mut map: BTreeMap<i32, i32> = (0..8).map(|x| (x, x*10)).collect();
-	/// // Keep only the elements with even-numbered keys.
-	/// map.retain(|&k, _| k % 2 == 0);
-	/// assert!(map.into_iter().eq(vec![(0, 0), (2, 20), (4, 40), (6, 60)]));
+	/// let map: BTreeMap<_, _> = (0..100).map(|i| (i, i)).collect();
+	/// let strings: BTreeMap<i32, String> = map.map_values_into(|_, v| v.to_string());
+	/// assert_eq!(strings[&42], "42");
 	/// ```
-	#[inline]
-	pub fn retain<F>(&mut self, mut f: F)
+	pub fn map_values_into<W, D, F>(mut self, mut f: F) -> BTreeMap<K, W, D>
 	where
-		F: FnMut(&K, &mut V) -> bool,
+		D: SlabMut<Node<K, W>> + Default,
+		D: SimpleCollectionRef,
+		D: SimpleCollectionMut,
+		F: FnMut(&K, V) -> W,
 	{
-		self.drain_filter(|k, v| !f(k, v));
+		let mut target = BTreeMap::new();
+		target.settings = self.settings.clone();
+		#[cfg(feature = "metrics")]
+		{
+			target.metrics = self.metrics.take();
+		}
+		#[cfg(feature = "watermark")]
+		{
+			target.watermarks = std::mem::take(&mut self.watermarks);
+		}
+		#[cfg(feature = "bloom")]
+		{
+			target.key_filter = self.key_filter.take();
+		}
+		#[cfg(feature = "structural-hash")]
+		{
+			target.structural_hash = self.structural_hash.take();
+		}
+
+		if let Some(root_id) = self.root.take() {
+			target.root = Some(self.move_node_into(&mut target, root_id, &mut f));
+			target.len = std::mem::take(&mut self.len);
+			target.reset_leaf_bounds();
+		}
+
+		target
 	}
 
-	/// Moves all elements from `other` into `Self`, leaving `other` empty.
+	/// Converts the values of this map, in key order, into a map with another storage,
+	/// stopping at the first conversion error.
+	///
+	/// This is the fallible version of [`BTreeMap::map_values_into`].
+	/// On error, the map and the values converted so far are dropped.
 	///
 	/// # Example
 	///
 	/// ```
 	/// use btree_slab::BTreeMap;
 	///
-	/// let mut a = BTreeMap::new();
-	/// a.insert(1, "a");
-	/// a.insert(2, "b");
-	/// a.insert(3, "c");
+	/// let map: BTreeMap<_, _> = [(1, "1"), (2, "2")].into_iter().collect();
+	/// let numbers: BTreeMap<i32, u32> = map.try_map_values(|_, v| v.parse()).unwrap();
+	/// assert_eq!(numbers[&2], 2);
 	///
-	/// let mut b = BTreeMap::new();
-	/// b.insert(3, "d");
-	/// b.insert(4, "e");
-	/// b.insert(5, "f");
+	/// let map: BTreeMap<_, _> = [(1, "1"), (2, "two")].into_iter().collect();
+	/// assert!(map.try_map_values::<u32, slab::Slab<_>, _, _>(|_, v| v.parse()).is_err());
+	/// ```
+	pub fn try_map_values<W, D, E, F>(mut self, mut f: F) -> Result<BTreeMap<K, W, D>, E>
+	where
+		D: SlabMut<Node<K, W>> + Default,
+		D: SimpleCollectionRef,
+		D: SimpleCollectionMut,
+		F: FnMut(&K, V) -> Result<W, E>,
+	{
+		let mut target = BTreeMap::new();
+		target.settings = self.settings.clone();
+
+		if let Some(root_id) = self.root.take() {
+			// The nodes left in the storage of this map on error are dropped with it.
+			target.root = Some(self.try_move_node_into(&mut target, root_id, &mut f)?);
+			target.len = std::mem::take(&mut self.len);
+			target.reset_leaf_bounds();
+		}
+
+		#[cfg(feature = "metrics")]
+		{
+			target.metrics = self.metrics.take();
+		}
+		#[cfg(feature = "watermark")]
+		{
+			target.watermarks = std::mem::take(&mut self.watermarks);
+		}
+		#[cfg(feature = "bloom")]
+		{
+			target.key_filter = self.key_filter.take();
+		}
+		#[cfg(feature = "structural-hash")]
+		{
+			target.structural_hash = self.structural_hash.take();
+		}
+
+		Ok(target)
+	}
+
+	/// Moves the subtree rooted in `id` into `target`, converting its values in key order.
 	///
-	/// a.append(&mut b);
+	/// Returns the identifier of the subtree root in `target`.
+	/// Its parent is left unset: it is assigned when the parent node is allocated.
+	fn move_node_into<W, D, F>(
+		&mut self,
+		target: &mut BTreeMap<K, W, D>,
+		id: usize,
+		f: &mut F,
+	) -> usize
+	where
+		D: SlabMut<Node<K, W>>,
+		D: SimpleCollectionRef,
+		D: SimpleCollectionMut,
+		F: FnMut(&K, V) -> W,
+	{
+		match self.try_move_node_into(target, id, &mut |key, value| {
+			Ok::<_, Infallible>(f(key, value))
+		}) {
+			Ok(id) => id,
+			Err(e) => match e {},
+		}
+	}
+
+	/// Moves the subtree rooted in `id` into `target`, converting its values in key order,
+	/// and stops at the first conversion error.
 	///
-	/// assert_eq!(a.len(), 5);
-	/// assert_eq!(b.len(), 0);
+	/// On error, the nodes that have not been moved yet are left in the storage of this map,
+	/// unreachable from its root.
+	fn try_move_node_into<W, D, E, F>(
+		&mut self,
+		target: &mut BTreeMap<K, W, D>,
+		id: usize,
+		f: &mut F,
+	) -> Result<usize, E>
+	where
+		D: SlabMut<Node<K, W>>,
+		D: SimpleCollectionRef,
+		D: SimpleCollectionMut,
+		F: FnMut(&K, V) -> Result<W, E>,
+	{
+		match self.release_node(id) {
+			Node::Leaf(leaf) => Ok(target.allocate_node(Node::Leaf(leaf.try_map_values(f)?))),
+			Node::Internal(node) => {
+				let (first_child, branches) = node.into_parts();
+				let first_child = self.try_move_node_into(target, first_child, f)?;
+				let branches = branches
+					.into_iter()
+					.map(|branch| {
+						let (key, value) = branch.item.into_pair();
+						let value = f(&key, value)?;
+						Ok(Branch {
+							item: Item::new(key, value),
+							child: self.try_move_node_into(target, branch.child, f)?,
+						})
+					})
+					.collect::<Result<_, E>>()?;
+
+				Ok(
+					target.allocate_node(Node::Internal(InternalNode::from_parts(
+						None,
+						first_child,
+						branches,
+					))),
+				)
+			}
+		}
+	}
+
+	/// Converts the values of the subtree rooted in `id` in place, in key order.
 	///
-	/// assert_eq!(a[&1], "a");
-	/// assert_eq!(a[&2], "b");
-	/// assert_eq!(a[&3], "d");
-	/// assert_eq!(a[&4], "e");
-	/// assert_eq!(a[&5], "f");
-	/// ```
+	/// Each node is taken out of its slot while its values are converted,
+	/// leaving an empty leaf in its place.
+	fn map_node_values<F>(&mut self, id: usize, f: &mut F)
+	where
+		F: FnMut(&K, V) -> V,
+	{
+		let node = std::mem::replace(self.node_mut(id), Node::Leaf(LeafNode::empty()));
+		let node = match node {
+			Node::Leaf(leaf) => {
+				let parent = leaf.parent();
+				let mut leaf = leaf.map_values(f);
+				leaf.set_parent(parent);
+				Node::Leaf(leaf)
+			}
+			Node::Internal(node) => {
+				let parent = node.parent();
+				let len = node.subtree_len();
+				let (first_child, branches) = node.into_parts();
+				self.map_node_values(first_child, f);
+				let branches = branches
+					.into_iter()
+					.map(|branch| {
+						let (key, value) = branch.item.into_pair();
+						let value = f(&key, value);
+						self.map_node_values(branch.child, f);
+						Branch {
+							item: Item::new(key, value),
+							child: branch.child,
+						}
+					})
+					.collect();
+
+				let mut node = InternalNode::from_parts(parent, first_child, branches);
+				node.set_subtree_len(len);
+				Node::Internal(node)
+			}
+		};
+
+		*self.node_mut(id) = node
+	}
+
+	/// Finds the first and last leaves of the tree from the root.
+	///
+	/// This must be called after any structural modification
+	/// that does not keep track of these leaves itself.
 	#[inline]
-	pub fn append(&mut self, other: &mut Self)
+	pub(crate) fn reset_leaf_bounds(&mut self) {
+		self.leaf_bounds = self.find_leaf_bounds()
+	}
+
+	/// Recomputes the number of items in the subtree rooted in `id`
+	/// from the subtree length of its children.
+	#[inline]
+	fn update_subtree_len(&mut self, id: usize) {
+		let len = self.compute_subtree_len(id);
+		self.node_mut(id).set_subtree_len(len)
+	}
+
+	/// Adds `delta` to the subtree length of the node `id` and of all its ancestors.
+	#[inline]
+	fn add_subtree_len(&mut self, mut id: usize, delta: isize) {
+		loop {
+			let node = self.node_mut(id);
+			let len = node.subtree_len().wrapping_add_signed(delta);
+			node.set_subtree_len(len);
+			match node.parent() {
+				Some(parent_id) => id = parent_id,
+				None => break,
+			}
+		}
+	}
+
+	/// Returns the height of the subtree rooted in `id`.
+	#[inline]
+	fn node_height(&self, mut id: usize) -> usize {
+		let mut height = 0;
+		while let Some(child_id) = self.node(id).child_id_opt(0) {
+			id = child_id;
+			height += 1
+		}
+
+		height
+	}
+
+	/// Joins the trees rooted in `left` and `right` with the given separator,
+	/// and makes the result the tree of the map.
+	///
+	/// The lowest tree is inserted as a child of the node at the same height
+	/// on the inner border of the highest tree,
+	/// so that only this node and its ancestors are rebalanced.
+	///
+	/// # Correctness
+	///
+	/// The keys of `left` must be lower than the separator,
+	/// and the keys of `right` greater.
+	#[inline]
+	fn join_trees(&mut self, left: usize, separator: Item<K, V>, right: usize) {
+		self.generation = self.generation.wrapping_add(1);
+		let left_height = self.node_height(left);
+		let right_height = self.node_height(right);
+
+		match left_height.cmp(&right_height) {
+			Ordering::Equal => {
+				let root_id = self.allocate_node(Node::binary(None, left, separator, right));
+				if !self.fix_child(root_id, 0) {
+					self.fix_child(root_id, 1);
+				}
+
+				self.root = self.trim_root(Some(root_id))
+			}
+			Ordering::Greater => {
+				let mut id = left;
+				for _ in right_height + 1..left_height {
+					id = self.node(id).child_id(self.node(id).child_count() - 1)
+				}
+
+				self.node_mut(id).push_right(separator, Some(right));
+				self.node_mut(right).set_parent(Some(id));
+				self.add_subtree_len(id, 1 + self.node(right).subtree_len() as isize);
+				let index = self.node(id).child_count() - 1;
+				self.fix_child(id, index);
+				self.root = Some(left);
+				self.rebalance(id, Address::nowhere());
+			}
+			Ordering::Less => {
+				let mut id = right;
+				for _ in left_height + 1..right_height {
+					id = self.node(id).child_id(0)
+				}
+
+				self.node_mut(id).push_left(separator, Some(left));
+				self.node_mut(left).set_parent(Some(id));
+				self.add_subtree_len(id, 1 + self.node(left).subtree_len() as isize);
+				self.fix_child(id, 0);
+				self.root = Some(right);
+				self.rebalance(id, Address::nowhere());
+			}
+		}
+	}
+
+	/// Cuts the tree of the map along the path leading to the given key.
+	///
+	/// The tree of the map keeps the keys lower than `key`,
+	/// or lower than or equal to `key` if `strict` is `true`.
+	/// Returns the root of the tree holding the other keys,
+	/// allocated in the same node storage,
+	/// and the item that must be inserted in front of it, if any.
+	///
+	/// Only the nodes along the cut are rebalanced.
+	/// The length and leaf bounds of the map are not updated.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	fn cut_tree<Q: ?Sized>(&mut self, key: &Q, strict: bool) -> (Option<usize>, Option<Item<K, V>>)
 	where
-		K: Ord,
-		C: Default,
+		K: Borrow<Q>,
+		Q: Ord,
 	{
-		// Do we have to append anything at all?
-		if other.is_empty() {
-			return;
-		}
+		let mut path = Vec::new();
+		let mut id = match self.root {
+			Some(id) => id,
+			None => return (None, None),
+		};
+		self.generation = self.generation.wrapping_add(1);
 
-		// We can just swap `self` and `other` if `self` is empty.
-		if self.is_empty() {
-			std::mem::swap(self, other);
-			return;
+		// Find the path leading to the key.
+		let mut separator_offset = None;
+		loop {
+			match self.node(id).offset_of(key) {
+				Ok(offset) if strict => {
+					// The cut goes along the left border of the subtree following the key.
+					path.push((id, offset.unwrap() + 1));
+					let mut child_id = self.node(id).child_id_opt(offset.unwrap() + 1);
+					while let Some(id) = child_id {
+						path.push((id, 0));
+						child_id = self.node(id).child_id_opt(0)
+					}
+					break;
+				}
+				Ok(offset) => {
+					if self.node(id).child_count() > 0 {
+						separator_offset = Some(offset.unwrap())
+					} else {
+						path.push((id, offset.unwrap()))
+					}
+					break;
+				}
+				Err((index, child_id)) => {
+					path.push((id, index));
+					match child_id {
+						Some(child_id) => id = child_id,
+						None => break,
+					}
+				}
+			}
 		}
 
-		let other = std::mem::take(other);
-		for (key, value) in other {
-			self.insert(key, value);
+		// Cut the nodes along the path, from the bottom up.
+		let mut separator = None;
+		let mut right_id = separator_offset.map(|offset| {
+			let (item, right_node) = self.node_mut(id).split_at(offset);
+			separator = Some(item);
+			self.update_subtree_len(id);
+			self.allocate_node(right_node)
+		});
+		for (id, offset) in path.into_iter().rev() {
+			let right_node = self.node_mut(id).split_off(offset, right_id);
+			self.update_subtree_len(id);
+			right_id = Some(self.allocate_node(right_node));
 		}
+
+		let right_root = right_id.unwrap();
+		self.node_mut(right_root).set_parent(None);
+		let right_root = self.fix_left_border(Some(right_root));
+		self.root = self.fix_right_border(self.root);
+		(right_root, separator)
 	}
 
-	/// Creates a consuming iterator visiting all the keys, in sorted order.
-	/// The map cannot be used after calling this.
-	/// The iterator element type is `K`.
-	///
-	/// # Example
-	///
-	/// ```
-	/// use btree_slab::BTreeMap;
-	///
-	/// let mut a = BTreeMap::new();
-	/// a.insert(2, "b");
-	/// a.insert(1, "a");
+	/// Rebalances the child `index` of the node `id` until it does not underflow,
+	/// by rotation or by merging it with one of its siblings.
 	///
-	/// let keys: Vec<i32> = a.into_keys().collect();
-	/// assert_eq!(keys, [1, 2]);
-	/// ```
+	/// Returns `true` if the child has been merged.
 	#[inline]
-	pub fn into_keys(self) -> IntoKeys<K, V, C> {
-		IntoKeys {
-			inner: self.into_iter(),
+	fn fix_child(&mut self, id: usize, index: usize) -> bool {
+		let mut addr = Address::nowhere();
+		let child_id = self.node(id).child_id(index);
+
+		while self.node(child_id).is_underflowing() {
+			if !self.try_rotate_left(id, index, &mut addr)
+				&& !self.try_rotate_right(id, index, &mut addr)
+			{
+				self.merge(id, index, addr);
+				return true;
+			}
 		}
+
+		false
 	}
 
-	/// Creates a consuming iterator visiting all the values, in order by key.
-	/// The map cannot be used after calling this.
-	/// The iterator element type is `V`.
-	///
-	/// # Example
+	/// Removes the empty nodes at the top of the tree rooted in `root`.
 	///
-	/// ```
-	/// use btree_slab::BTreeMap;
+	/// Returns the new root of the tree, if it is not empty.
+	#[inline]
+	fn trim_root(&mut self, mut root: Option<usize>) -> Option<usize> {
+		while let Some(id) = root {
+			if self.node(id).item_count() > 0 {
+				break;
+			}
+
+			root = self.node(id).child_id_opt(0);
+			self.release_node(id);
+			if let Some(root_id) = root {
+				self.node_mut(root_id).set_parent(None)
+			}
+		}
+
+		root
+	}
+
+	/// Rebalances the right-most nodes of the tree rooted in `root`,
+	/// which may underflow after a split.
 	///
-	/// let mut a = BTreeMap::new();
-	/// a.insert(1, "hello");
-	/// a.insert(2, "goodbye");
+	/// The nodes are fixed from the top down so that each of them
+	/// has at least one spare item when its own last child is merged.
+	/// Returns the new root of the tree, if it is not empty.
+	#[inline]
+	fn fix_right_border(&mut self, root: Option<usize>) -> Option<usize> {
+		let mut root = self.trim_root(root);
+		let mut id = root?;
+		let mut addr = Address::nowhere();
+
+		while self.node(id).child_count() > 0 {
+			let index = self.node(id).child_count() - 1;
+			let mut child_id = self.node(id).child_id(index);
+
+			while self.node(child_id).item_count() < M / 2 {
+				if !self.try_rotate_right(id, index, &mut addr) {
+					self.merge(id, index, addr);
+					child_id = self.node(id).child_id(index - 1);
+					break;
+				}
+			}
+
+			if self.node(id).item_count() == 0 {
+				root = self.trim_root(root)
+			}
+
+			id = child_id
+		}
+
+		root
+	}
+
+	/// Rebalances the left-most nodes of the tree rooted in `root`,
+	/// which may underflow after a split.
 	///
-	/// let values: Vec<&str> = a.into_values().collect();
-	/// assert_eq!(values, ["hello", "goodbye"]);
-	/// ```
+	/// This is the mirror of [`Self::fix_right_border`].
 	#[inline]
-	pub fn into_values(self) -> IntoValues<K, V, C> {
-		IntoValues {
-			inner: self.into_iter(),
+	fn fix_left_border(&mut self, root: Option<usize>) -> Option<usize> {
+		let mut root = self.trim_root(root);
+		let mut id = root?;
+		let mut addr = Address::nowhere();
+
+		while self.node(id).child_count() > 0 {
+			let child_id = self.node(id).child_id(0);
+
+			while self.node(child_id).item_count() < M / 2 {
+				if !self.try_rotate_left(id, 0, &mut addr) {
+					self.merge(id, 0, addr);
+					break;
+				}
+			}
+
+			if self.node(id).item_count() == 0 {
+				root = self.trim_root(root)
+			}
+
+			id = child_id
 		}
+
+		root
 	}
 
 	/// Try to rotate left the node `id` to benefits the child number `deficient_child_index`.
@@ -1152,6 +4668,9 @@ where
 					self.node_mut(child_id).set_parent(Some(deficient_child_id))
 				}
 
+				self.update_subtree_len(deficient_child_id);
+				self.update_subtree_len(right_sibling_id);
+
 				// update address.
 				if addr.id == right_sibling_id {
 					// addressed item is in the right node.
@@ -1213,6 +4732,9 @@ where
 						self.node_mut(child_id).set_parent(Some(deficient_child_id))
 					}
 
+					self.update_subtree_len(deficient_child_id);
+					self.update_subtree_len(left_sibling_id);
+
 					// update address.
 					if addr.id == deficient_child_id {
 						// addressed item is in the right (deficient) node.
@@ -1262,12 +4784,19 @@ where
 
 		// update children's parent.
 		let right_node = self.release_node(right_id);
+		if let Some((_, last)) = &mut self.leaf_bounds {
+			if *last == right_id {
+				*last = left_id
+			}
+		}
+
 		for right_child_id in right_node.children() {
 			self.node_mut(right_child_id).set_parent(Some(left_id));
 		}
 
 		// actually merge.
 		let left_offset = self.node_mut(left_id).append(separator, right_node);
+		self.update_subtree_len(left_id);
 
 		// update addr.
 		if addr.id == id {
@@ -1307,6 +4836,27 @@ where
 	}
 }
 
+impl<K: Ord, Q: ?Sized, V, C: SlabMut<Node<K, V>>> IndexMut<&Q> for BTreeMap<K, V, C>
+where
+	K: Borrow<Q>,
+	Q: Ord,
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Returns a mutable reference to the value corresponding to the supplied key.
+	///
+	/// # Panics
+	///
+	/// Panics if the key is not present in the `BTreeMap`.
+	#[inline]
+	fn index_mut(&mut self, key: &Q) -> &mut V {
+		match self.address_of(key) {
+			Ok(addr) => self.item_mut(addr).unwrap().value_mut(),
+			Err(_) => panic!("no entry found for key"),
+		}
+	}
+}
+
 impl<K, L: PartialEq<K>, V, W: PartialEq<V>, C: Slab<Node<K, V>>, D: Slab<Node<L, W>>>
 	PartialEq<BTreeMap<L, W, D>> for BTreeMap<K, V, C>
 where
@@ -1344,6 +4894,28 @@ impl<K, V, C: Default> Default for BTreeMap<K, V, C> {
 	}
 }
 
+/// Builds the map bottom-up with [`BTreeMap::from_unsorted_vec`].
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::BTreeMap;
+///
+/// let map = BTreeMap::from([(2, 'b'), (1, 'a'), (2, 'c')]);
+/// assert!(map.into_iter().eq([(1, 'a'), (2, 'c')]));
+/// ```
+impl<K: Ord, V, C: SlabMut<Node<K, V>> + Default, const N: usize> From<[(K, V); N]>
+	for BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn from(items: [(K, V); N]) -> Self {
+		BTreeMap::from_unsorted_vec(Vec::from(items))
+	}
+}
+
 impl<K: Ord, V, C: SlabMut<Node<K, V>> + Default> FromIterator<(K, V)> for BTreeMap<K, V, C>
 where
 	C: SimpleCollectionRef,
@@ -1395,6 +4967,108 @@ where
 	}
 }
 
+/// Returns the size of the `i`-th of `parts` shares of `total`,
+/// spreading the remainder over the first shares.
+#[inline]
+fn even_share(total: usize, parts: usize, i: usize) -> usize {
+	total / parts + usize::from(i < total % parts)
+}
+
+/// Iterator adapter panicking if the keys of the inner iterator are not strictly increasing.
+struct SortedCheck<I: Iterator> {
+	inner: std::iter::Peekable<I>,
+}
+
+impl<I: Iterator> SortedCheck<I> {
+	fn new(inner: I) -> Self {
+		SortedCheck {
+			inner: inner.peekable(),
+		}
+	}
+}
+
+impl<K: Ord, V, I: Iterator<Item = (K, V)>> Iterator for SortedCheck<I> {
+	type Item = (K, V);
+
+	#[inline]
+	fn next(&mut self) -> Option<(K, V)> {
+		let item = self.inner.next()?;
+		if let Some((next_key, _)) = self.inner.peek() {
+			if item.0 >= *next_key {
+				panic!("keys are not strictly increasing")
+			}
+		}
+
+		Some(item)
+	}
+}
+
+/// Error returned by [`BTreeMap::extend_unique`] when a key is already in the map.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct DuplicateKeyError<K, V> {
+	/// The duplicate key.
+	pub key: K,
+
+	/// The value that was not inserted.
+	pub value: V,
+}
+
+impl<K: fmt::Debug, V> fmt::Display for DuplicateKeyError<K, V> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "duplicate key: {:?}", self.key)
+	}
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> std::error::Error for DuplicateKeyError<K, V> {}
+
+/// Error returned by [`BTreeMap::assign_values_in_order`]
+/// when the number of values differs from the length of the map.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LengthMismatch {
+	/// Fewer values than entries, with the number of values.
+	TooFew(usize),
+
+	/// More values than entries.
+	TooMany,
+}
+
+impl fmt::Display for LengthMismatch {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			LengthMismatch::TooFew(n) => write!(f, "not enough values: {}", n),
+			LengthMismatch::TooMany => write!(f, "too many values"),
+		}
+	}
+}
+
+impl std::error::Error for LengthMismatch {}
+
+/// Error returned by [`BTreeMap::range_between`] and [`BTreeMap::range_between_mut`]
+/// when the given addresses do not delimit a range of the map.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum InvalidAddressRange {
+	/// The start address is not a valid address of the map.
+	InvalidStart(Address),
+
+	/// The end address is not a valid address of the map.
+	InvalidEnd(Address),
+
+	/// The start address is after the end address.
+	Reversed,
+}
+
+impl fmt::Display for InvalidAddressRange {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			InvalidAddressRange::InvalidStart(addr) => write!(f, "invalid start address: {}", addr),
+			InvalidAddressRange::InvalidEnd(addr) => write!(f, "invalid end address: {}", addr),
+			InvalidAddressRange::Reversed => write!(f, "range start is after range end"),
+		}
+	}
+}
+
+impl std::error::Error for InvalidAddressRange {}
+
 impl<K: Eq, V: Eq, C: Slab<Node<K, V>>> Eq for BTreeMap<K, V, C> where C: SimpleCollectionRef {}
 
 impl<K, L: PartialOrd<K>, V, W: PartialOrd<V>, C: Slab<Node<K, V>>, D: Slab<Node<L, W>>>
@@ -1468,6 +5142,16 @@ where
 	}
 }
 
+impl<K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Debug for BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_map().entries(self.iter()).finish()
+	}
+}
+
 pub struct Iter<'a, K, V, C> {
 	/// The tree reference.
 	btree: &'a BTreeMap<K, V, C>,
@@ -1480,6 +5164,28 @@ pub struct Iter<'a, K, V, C> {
 	len: usize,
 }
 
+impl<'a, K, V, C> Clone for Iter<'a, K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Iter {
+			btree: self.btree,
+			addr: self.addr,
+			end: self.end,
+			len: self.len,
+		}
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Debug for Iter<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
 impl<'a, K, V, C: Slab<Node<K, V>>> Iter<'a, K, V, C>
 where
 	C: SimpleCollectionRef,
@@ -1495,6 +5201,19 @@ where
 			len,
 		}
 	}
+
+	/// Creates an iterator over the `len` items following the given address.
+	///
+	/// This is used to inspect the remaining items of other iterators.
+	#[inline]
+	fn remaining(btree: &'a BTreeMap<K, V, C>, addr: Option<Address>, len: usize) -> Self {
+		Iter {
+			btree,
+			addr: addr.and_then(|addr| btree.normalize(addr)),
+			end: None,
+			len,
+		}
+	}
 }
 
 impl<'a, K, V, C: Slab<Node<K, V>>> Iterator for Iter<'a, K, V, C>
@@ -1515,8 +5234,17 @@ where
 				if self.len > 0 {
 					self.len -= 1;
 
-					let item = self.btree.item(addr).unwrap();
-					self.addr = self.btree.next_item_address(addr);
+					let items = leaf_items_from(self.btree, addr);
+					let item = match items {
+						Some(items) => &items[0],
+						None => self.btree.item(addr).unwrap(),
+					};
+
+					self.addr = match items {
+						Some(items) if items.len() > 1 => Some(addr.next_in_node()),
+						_ => self.btree.next_item_address(addr),
+					};
+
 					Some((item.key(), item.value()))
 				} else {
 					None
@@ -1525,6 +5253,61 @@ where
 			None => None,
 		}
 	}
+
+	#[inline]
+	fn fold<B, F>(mut self, init: B, mut f: F) -> B
+	where
+		F: FnMut(B, (&'a K, &'a V)) -> B,
+	{
+		let mut acc = init;
+		while self.len > 0 {
+			let addr = self.addr.unwrap();
+			match leaf_items_from(self.btree, addr) {
+				Some(items) => {
+					// Visit the remaining items of the leaf at once.
+					let items = &items[..items.len().min(self.len)];
+					for item in items {
+						acc = f(acc, (item.key(), item.value()))
+					}
+
+					self.len -= items.len();
+					if self.len > 0 {
+						let last =
+							Address::new(addr.id, (addr.offset.unwrap() + items.len() - 1).into());
+						self.addr = self.btree.next_item_address(last)
+					}
+				}
+				None => {
+					let item = self.next().unwrap();
+					acc = f(acc, item)
+				}
+			}
+		}
+
+		acc
+	}
+}
+
+/// Returns the items of the leaf containing `addr`, starting at `addr`.
+///
+/// Returns `None` if `addr` is not the address of an item located in a leaf.
+#[inline]
+#[allow(clippy::multiple_bound_locations)]
+fn leaf_items_from<K, V, C: Slab<Node<K, V>>>(
+	btree: &BTreeMap<K, V, C>,
+	addr: Address,
+) -> Option<&[Item<K, V>]>
+where
+	C: SimpleCollectionRef,
+{
+	match btree.node(addr.id) {
+		Node::Leaf(leaf) => addr
+			.offset
+			.value()
+			.and_then(|offset| leaf.items().get(offset..))
+			.filter(|items| !items.is_empty()),
+		Node::Internal(_) => None,
+	}
 }
 
 impl<'a, K, V, C: Slab<Node<K, V>>> FusedIterator for Iter<'a, K, V, C> where C: SimpleCollectionRef {}
@@ -1569,16 +5352,32 @@ where
 	}
 }
 
-pub struct IterMut<'a, K, V, C> {
-	/// The tree reference.
-	btree: &'a mut BTreeMap<K, V, C>,
+impl<'a, K, V, C: SlabMut<Node<K, V>>> IntoIterator for &'a mut BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	type IntoIter = IterMut<'a, K, V, C>;
+	type Item = (&'a K, &'a mut V);
 
-	/// Address of the next item.
-	addr: Option<Address>,
+	#[inline]
+	fn into_iter(self) -> IterMut<'a, K, V, C> {
+		self.iter_mut()
+	}
+}
 
-	end: Option<Address>,
+pub struct IterMut<'a, K, V, C> {
+	/// The borrowed items.
+	items: RawItemsMut<'a, K, V, C>,
+}
 
-	len: usize,
+impl<'a, K: fmt::Debug, V: fmt::Debug, C> fmt::Debug for IterMut<'a, K, V, C> {
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list()
+			.entries(self.items.remaining().map(Item::as_pair))
+			.finish()
+	}
 }
 
 impl<'a, K, V, C: SlabMut<Node<K, V>>> IterMut<'a, K, V, C>
@@ -1588,50 +5387,70 @@ where
 {
 	#[inline]
 	fn new(btree: &'a mut BTreeMap<K, V, C>) -> Self {
-		let addr = btree.first_item_address();
+		let first = btree.first_item_address();
+		let last = btree.last_item_address();
 		let len = btree.len();
 		IterMut {
-			btree,
-			addr,
-			end: None,
-			len,
+			items: RawItemsMut::new(btree, first, last, len),
 		}
 	}
 
+	/// Returns the entry that `next` would return, without advancing the iterator.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, i32> = (0..4).map(|i| (i, i)).collect();
+	/// let mut iter = map.iter_mut();
+	/// assert_eq!(iter.peek(), Some((&0, &0)));
+	/// assert_eq!(iter.peek_back(), Some((&3, &3)));
+	///
+	/// // Add the next value to each value.
+	/// while let Some((_, v)) = iter.next() {
+	///     *v += iter.peek().map_or(0, |(_, next)| *next);
+	/// }
+	///
+	/// assert!(map.values().copied().eq([1, 3, 5, 3]));
+	/// ```
+	#[inline]
+	pub fn peek(&self) -> Option<(&K, &V)> {
+		// SAFETY: the item is not lent out yet, and is borrowed for no longer than `self`.
+		self.items
+			.peek()
+			.map(|item| unsafe { item.as_ref() }.as_pair())
+	}
+
+	/// Returns the entry that `next` would return, with a mutable reference to its value,
+	/// without advancing the iterator.
+	#[inline]
+	pub fn peek_mut(&mut self) -> Option<(&K, &mut V)> {
+		self.items.peek().map(|mut item| {
+			// SAFETY: the item is not lent out yet, and is borrowed for no longer than `self`.
+			let (key, value) = unsafe { item.as_mut() }.as_pair_mut();
+			(&*key, value)
+		})
+	}
+
+	/// Returns the entry that `next_back` would return, without advancing the iterator.
 	#[inline]
-	fn next_item(&mut self) -> Option<&'a mut Item<K, V>> {
-		match self.addr {
-			Some(addr) => {
-				if self.len > 0 {
-					self.len -= 1;
-
-					self.addr = self.btree.next_item_address(addr);
-					let item = self.btree.item_mut(addr).unwrap();
-					Some(unsafe { std::mem::transmute(item) }) // this is safe because only one mutable reference to the same item can be emitted.
-				} else {
-					None
-				}
-			}
-			None => None,
-		}
+	pub fn peek_back(&self) -> Option<(&K, &V)> {
+		// SAFETY: the item is not lent out yet, and is borrowed for no longer than `self`.
+		self.items
+			.peek_back()
+			.map(|item| unsafe { item.as_ref() }.as_pair())
 	}
 
+	/// Returns the entry that `next_back` would return, with a mutable reference to its value,
+	/// without advancing the iterator.
 	#[inline]
-	fn next_back_item(&mut self) -> Option<&'a mut Item<K, V>> {
-		if self.len > 0 {
-			let addr = match self.end {
-				Some(addr) => self.btree.previous_item_address(addr).unwrap(),
-				None => self.btree.last_item_address().unwrap(),
-			};
-
-			self.len -= 1;
-
-			let item = self.btree.item_mut(addr).unwrap();
-			self.end = Some(addr);
-			Some(unsafe { std::mem::transmute(item) }) // this is safe because only one mutable reference to the same item can be emitted.s
-		} else {
-			None
-		}
+	pub fn peek_back_mut(&mut self) -> Option<(&K, &mut V)> {
+		self.items.peek_back().map(|mut item| {
+			// SAFETY: the item is not lent out yet, and is borrowed for no longer than `self`.
+			let (key, value) = unsafe { item.as_mut() }.as_pair_mut();
+			(&*key, value)
+		})
 	}
 }
 
@@ -1644,12 +5463,13 @@ where
 
 	#[inline]
 	fn size_hint(&self) -> (usize, Option<usize>) {
-		(self.len, Some(self.len))
+		let len = self.items.len();
+		(len, Some(len))
 	}
 
 	#[inline]
 	fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
-		self.next_item().map(|item| {
+		self.items.next().map(|item| {
 			let (key, value) = item.as_pair_mut();
 			(key as &'a K, value)
 		})
@@ -1676,7 +5496,7 @@ where
 {
 	#[inline]
 	fn next_back(&mut self) -> Option<(&'a K, &'a mut V)> {
-		self.next_back_item().map(|item| {
+		self.items.next_back().map(|item| {
 			let (key, value) = item.as_pair_mut();
 			(key as &'a K, value)
 		})
@@ -1694,6 +5514,18 @@ pub struct EntriesMut<'a, K, V, C> {
 	len: usize,
 }
 
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Debug for EntriesMut<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list()
+			.entries(Iter::remaining(self.btree, Some(self.addr), self.len))
+			.finish()
+	}
+}
+
 impl<'a, K, V, C: SlabMut<Node<K, V>>> EntriesMut<'a, K, V, C>
 where
 	C: SimpleCollectionRef,
@@ -1790,6 +5622,180 @@ where
 	}
 }
 
+/// Iterator over the entries of a range of a [`BTreeMap`], that can modify or remove them.
+///
+/// This `struct` is created by [`BTreeMap::range_entries_mut`].
+pub struct RangeEntriesMut<'a, K, V, C> {
+	/// The tree reference.
+	btree: &'a mut BTreeMap<K, V, C>,
+
+	/// Address of the last visited item if it is still in the map,
+	/// or of the next item.
+	addr: Address,
+
+	/// Number of items left to visit.
+	len: usize,
+
+	/// Whether `addr` is the address of the last visited item.
+	visited: bool,
+}
+
+impl<'a, K, V, C: SlabMut<Node<K, V>>> RangeEntriesMut<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Returns the address of the next item to visit, if any.
+	#[inline]
+	fn next_address(&self) -> Option<Address> {
+		if self.len == 0 {
+			return None;
+		}
+
+		if self.visited {
+			self.btree.next_item_address(self.addr)
+		} else {
+			// The address left by a removal may be out of node bounds.
+			self.btree.normalize(self.addr)
+		}
+	}
+
+	/// Moves to the next entry of the range and returns it, if any.
+	#[inline]
+	pub fn next_entry(&mut self) -> Option<RangeEntry<'_, 'a, K, V, C>> {
+		let addr = self.next_address()?;
+		self.addr = addr;
+		self.len -= 1;
+		self.visited = true;
+		Some(RangeEntry { entries: self })
+	}
+
+	/// Returns the next entry of the range without moving the iterator, if any.
+	#[inline]
+	pub fn peek(&self) -> Option<(&K, &V)> {
+		self.next_address()
+			.map(|addr| self.btree.item(addr).unwrap().as_pair())
+	}
+
+	/// Returns the number of entries left to visit.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns `true` if every entry of the range has been visited.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: SlabMut<Node<K, V>>> fmt::Debug
+	for RangeEntriesMut<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list()
+			.entries(Iter::remaining(self.btree, self.next_address(), self.len))
+			.finish()
+	}
+}
+
+/// Entry visited by a [`RangeEntriesMut`] iterator.
+///
+/// Dropping the entry keeps it in the map.
+pub struct RangeEntry<'r, 'a, K, V, C> {
+	entries: &'r mut RangeEntriesMut<'a, K, V, C>,
+}
+
+impl<'r, 'a, K, V, C: SlabMut<Node<K, V>>> RangeEntry<'r, 'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Returns the address of the entry.
+	#[inline]
+	pub fn address(&self) -> Address {
+		self.entries.addr
+	}
+
+	/// Gets a reference to the key of the entry.
+	#[inline]
+	pub fn key(&self) -> &K {
+		self.entries.btree.item(self.entries.addr).unwrap().key()
+	}
+
+	/// Gets a reference to the value of the entry.
+	#[inline]
+	pub fn get(&self) -> &V {
+		self.entries.btree.item(self.entries.addr).unwrap().value()
+	}
+
+	/// Gets a mutable reference to the value of the entry.
+	#[inline]
+	pub fn get_mut(&mut self) -> &mut V {
+		self.entries
+			.btree
+			.item_mut(self.entries.addr)
+			.unwrap()
+			.value_mut()
+	}
+
+	/// Converts the entry into a mutable reference to its value,
+	/// borrowing the iterator.
+	#[inline]
+	pub fn into_mut(self) -> &'r mut V {
+		self.entries
+			.btree
+			.item_mut(self.entries.addr)
+			.unwrap()
+			.value_mut()
+	}
+
+	/// Sets the value of the entry, and returns the entry's old value.
+	#[inline]
+	pub fn replace_value(&mut self, value: V) -> V {
+		self.entries
+			.btree
+			.replace_value_at(self.entries.addr, value)
+	}
+
+	/// Removes the entry from the map and returns its value.
+	#[inline]
+	pub fn remove(self) -> V {
+		self.remove_entry().1
+	}
+
+	/// Removes the entry from the map and returns its key and value.
+	///
+	/// The iteration continues with the entry following the removed one.
+	#[inline]
+	pub fn remove_entry(self) -> (K, V) {
+		let (item, addr) = self.entries.btree.remove_at(self.entries.addr).unwrap();
+		self.entries.addr = addr;
+		self.entries.visited = false;
+		item.into_pair()
+	}
+}
+
+impl<'r, 'a, K: fmt::Debug, V: fmt::Debug, C: SlabMut<Node<K, V>>> fmt::Debug
+	for RangeEntry<'r, 'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("RangeEntry")
+			.field("key", self.key())
+			.field("value", self.get())
+			.finish()
+	}
+}
+
 /// An owning iterator over the entries of a `BTreeMap`.
 ///
 /// This `struct` is created by the [`into_iter`] method on [`BTreeMap`]
@@ -1810,6 +5816,18 @@ pub struct IntoIter<K, V, C> {
 	len: usize,
 }
 
+impl<K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Debug for IntoIter<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list()
+			.entries(Iter::remaining(&self.btree, self.addr, self.len))
+			.finish()
+	}
+}
+
 impl<K, V, C: SlabMut<Node<K, V>>> IntoIter<K, V, C>
 where
 	C: SimpleCollectionRef,
@@ -1854,6 +5872,21 @@ where
 
 	#[inline]
 	fn next(&mut self) -> Option<(K, V)> {
+		self.next_entry().map(|(_, item)| item.into_pair())
+	}
+}
+
+impl<K, V, C: SlabMut<Node<K, V>>> IntoIter<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Moves out the next item, along with its address.
+	///
+	/// Items are read in place and nodes are only released once all their items are moved out,
+	/// so the address is the one of the item when the iteration started.
+	#[inline]
+	fn next_entry(&mut self) -> Option<(Address, Item<K, V>)> {
 		match self.addr {
 			Some(addr) => {
 				if self.len > 0 {
@@ -1902,7 +5935,7 @@ where
 						}
 					}
 
-					Some(item.into_pair())
+					Some((addr, item))
 				} else {
 					None
 				}
@@ -1910,14 +5943,12 @@ where
 			None => None,
 		}
 	}
-}
 
-impl<K, V, C: SlabMut<Node<K, V>>> DoubleEndedIterator for IntoIter<K, V, C>
-where
-	C: SimpleCollectionRef,
-	C: SimpleCollectionMut,
-{
-	fn next_back(&mut self) -> Option<(K, V)> {
+	/// Moves out the last item, along with its address.
+	///
+	/// See [`IntoIter::next_entry`].
+	#[inline]
+	fn next_back_entry(&mut self) -> Option<(Address, Item<K, V>)> {
 		if self.len > 0 {
 			let addr = match self.end {
 				Some(mut addr) => {
@@ -1967,13 +5998,89 @@ where
 				}
 			}
 
-			Some(item.into_pair())
+			Some((addr, item))
 		} else {
 			None
 		}
 	}
 }
 
+impl<K, V, C: SlabMut<Node<K, V>>> DoubleEndedIterator for IntoIter<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<(K, V)> {
+		self.next_back_entry().map(|(_, item)| item.into_pair())
+	}
+}
+
+/// A consuming iterator over the entries of a `BTreeMap`, along with their address.
+///
+/// This `struct` is created by the [`BTreeMap::into_entries`] method.
+pub struct IntoEntries<K, V, C> {
+	inner: IntoIter<K, V, C>,
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Debug for IntoEntries<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.inner.fmt(f)
+	}
+}
+
+impl<K, V, C: SlabMut<Node<K, V>>> FusedIterator for IntoEntries<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+}
+impl<K, V, C: SlabMut<Node<K, V>>> ExactSizeIterator for IntoEntries<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+}
+
+impl<K, V, C: SlabMut<Node<K, V>>> Iterator for IntoEntries<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	type Item = (Address, K, V);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<(Address, K, V)> {
+		self.inner.next_entry().map(|(addr, item)| {
+			let (key, value) = item.into_pair();
+			(addr, key, value)
+		})
+	}
+}
+
+impl<K, V, C: SlabMut<Node<K, V>>> DoubleEndedIterator for IntoEntries<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<(Address, K, V)> {
+		self.inner.next_back_entry().map(|(addr, item)| {
+			let (key, value) = item.into_pair();
+			(addr, key, value)
+		})
+	}
+}
+
 impl<K, V, C: SlabMut<Node<K, V>>> IntoIterator for BTreeMap<K, V, C>
 where
 	C: SimpleCollectionRef,
@@ -1995,7 +6102,14 @@ pub(crate) struct DrainFilterInner<'a, K, V, C> {
 	/// Address of the next item, or last valid address.
 	addr: Address,
 
+	/// Number of items left to visit.
 	len: usize,
+
+	/// Whether the remaining items are kept when the iterator is dropped.
+	///
+	/// Set while the predicate runs, so that a panicking predicate
+	/// is not called again by `drop_rest`.
+	keep_rest: bool,
 }
 
 impl<'a, K: 'a, V: 'a, C: SlabMut<Node<K, V>>> DrainFilterInner<'a, K, V, C>
@@ -2007,7 +6121,18 @@ where
 	pub fn new(btree: &'a mut BTreeMap<K, V, C>) -> Self {
 		let addr = btree.first_back_address();
 		let len = btree.len();
-		DrainFilterInner { btree, addr, len }
+		Self::new_at(btree, addr, len)
+	}
+
+	/// Creates an inner iterator visiting the `len` items starting at `addr`.
+	#[inline]
+	pub fn new_at(btree: &'a mut BTreeMap<K, V, C>, addr: Address, len: usize) -> Self {
+		DrainFilterInner {
+			btree,
+			addr,
+			len,
+			keep_rest: false,
+		}
 	}
 
 	#[inline]
@@ -2019,17 +6144,38 @@ where
 	fn next_item<F>(&mut self, pred: &mut F) -> Option<Item<K, V>>
 	where
 		F: FnMut(&K, &mut V) -> bool,
+	{
+		self.next_item_until(&mut |key, value| ControlFlow::Continue((*pred)(key, value)))
+	}
+
+	/// Removes and returns the next item matching the predicate.
+	///
+	/// If the predicate breaks, the item it was called on and every following item
+	/// are left untouched and the iteration ends.
+	#[inline]
+	fn next_item_until<F>(&mut self, pred: &mut F) -> Option<Item<K, V>>
+	where
+		F: FnMut(&K, &mut V) -> ControlFlow<(), bool>,
 	{
 		if self.addr.id == usize::MAX {
 			return None;
 		}
 
-		loop {
+		while self.len > 0 {
 			match self.btree.item_mut(self.addr) {
 				Some(item) => {
 					let (key, value) = item.as_pair_mut();
 					self.len -= 1;
-					if (*pred)(key, value) {
+					self.keep_rest = true;
+					let remove = match (*pred)(key, value) {
+						ControlFlow::Continue(remove) => remove,
+						ControlFlow::Break(()) => {
+							self.len = 0;
+							return None;
+						}
+					};
+					self.keep_rest = false;
+					if remove {
 						let (item, next_addr) = self.btree.remove_at(self.addr).unwrap();
 						self.addr = next_addr;
 						return Some(item);
@@ -2037,9 +6183,15 @@ where
 						self.addr = self.btree.next_item_or_back_address(self.addr).unwrap();
 					}
 				}
-				None => return None,
+				// The address returned by `remove_at` may be out of node bounds.
+				None => match self.btree.normalize(self.addr) {
+					Some(addr) => self.addr = addr,
+					None => return None,
+				},
 			}
 		}
+
+		None
 	}
 
 	#[inline]
@@ -2049,31 +6201,139 @@ where
 	{
 		self.next_item(pred).map(Item::into_pair)
 	}
+
+	/// Removes the next item matching the predicate, unless the predicate breaks.
+	///
+	/// See [`DrainFilterInner::next_item_until`].
+	#[inline]
+	pub fn next_until<F>(&mut self, pred: &mut F) -> Option<(K, V)>
+	where
+		F: FnMut(&K, &mut V) -> ControlFlow<(), bool>,
+	{
+		self.next_item_until(pred).map(Item::into_pair)
+	}
+
+	/// Drains the remaining items matching the predicate,
+	/// unless the predicate previously panicked or `keep_rest` was called.
+	#[inline]
+	pub fn drop_rest<F>(&mut self, pred: &mut F)
+	where
+		F: FnMut(&K, &mut V) -> bool,
+	{
+		if !self.keep_rest {
+			while self.next_item(pred).is_some() {}
+		}
+	}
+
+	/// Keeps the remaining items when the iterator is dropped.
+	#[inline]
+	pub fn keep_rest(&mut self) {
+		self.keep_rest = true
+	}
+
+	/// Returns the next item to be visited, if any.
+	#[inline]
+	pub fn peek(&self) -> Option<(&K, &V)> {
+		if self.len == 0 {
+			return None;
+		}
+
+		self.btree
+			.normalize(self.addr)
+			.map(|addr| self.btree.item(addr).unwrap().as_pair())
+	}
+}
+
+/// An iterator produced by calling [`BTreeMap::drain_filter`].
+///
+/// Dropping the iterator drains the remaining matching entries,
+/// unless the predicate panicked or [`DrainFilter::keep_rest`] was called.
+///
+/// The iterator is [`Send`] (resp. [`Sync`]) whenever the keys, values,
+/// node container and predicate are.
+pub struct DrainFilter<'a, K, V, C: SlabMut<Node<K, V>>, F>
+where
+	F: FnMut(&K, &mut V) -> bool,
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	pred: F,
+
+	inner: DrainFilterInner<'a, K, V, C>,
 }
 
-pub struct DrainFilter<'a, K, V, C: SlabMut<Node<K, V>>, F>
+impl<'a, K: 'a, V: 'a, C: SlabMut<Node<K, V>>, F> DrainFilter<'a, K, V, C, F>
 where
 	F: FnMut(&K, &mut V) -> bool,
 	C: SimpleCollectionRef,
 	C: SimpleCollectionMut,
 {
-	pred: F,
+	#[inline]
+	fn new(inner: DrainFilterInner<'a, K, V, C>, pred: F) -> Self {
+		DrainFilter { pred, inner }
+	}
 
-	inner: DrainFilterInner<'a, K, V, C>,
+	/// Consumes the iterator, keeping the entries that were not yet visited in the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, i32> = (0..8).map(|x| (x, x)).collect();
+	/// let mut drain = map.drain_filter(|k, _| k % 2 == 0);
+	/// assert_eq!(drain.next(), Some((0, 0)));
+	/// assert_eq!(drain.next(), Some((2, 2)));
+	/// drain.keep_rest();
+	///
+	/// assert!(map.keys().copied().eq([1, 3, 4, 5, 6, 7]));
+	/// ```
+	#[inline]
+	pub fn keep_rest(mut self) {
+		self.inner.keep_rest()
+	}
+
+	/// Returns the next entry to be passed to the predicate, without visiting it.
+	///
+	/// Together with [`DrainFilter::keep_rest`],
+	/// this allows stopping the drain before reaching a given entry.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, i32> = (0..8).map(|x| (x, x)).collect();
+	/// let mut drain = map.drain_filter(|k, _| k % 2 == 0);
+	/// assert_eq!(drain.peek(), Some((&0, &0)));
+	/// assert_eq!(drain.next(), Some((0, 0)));
+	/// assert_eq!(drain.peek(), Some((&1, &1)));
+	///
+	/// while drain.peek().map_or(false, |(k, _)| *k < 5) {
+	///     drain.next();
+	/// }
+	/// drain.keep_rest();
+	///
+	/// assert!(map.keys().copied().eq([1, 3, 5, 6, 7]));
+	/// ```
+	#[inline]
+	pub fn peek(&self) -> Option<(&K, &V)> {
+		self.inner.peek()
+	}
 }
 
-impl<'a, K: 'a, V: 'a, C: SlabMut<Node<K, V>>, F> DrainFilter<'a, K, V, C, F>
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: SlabMut<Node<K, V>>, F> fmt::Debug
+	for DrainFilter<'a, K, V, C, F>
 where
 	F: FnMut(&K, &mut V) -> bool,
 	C: SimpleCollectionRef,
 	C: SimpleCollectionMut,
 {
 	#[inline]
-	fn new(btree: &'a mut BTreeMap<K, V, C>, pred: F) -> Self {
-		DrainFilter {
-			pred,
-			inner: DrainFilterInner::new(btree),
-		}
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("DrainFilter")
+			.field(&self.inner.peek())
+			.finish()
 	}
 }
 
@@ -2112,11 +6372,7 @@ where
 {
 	#[inline]
 	fn drop(&mut self) {
-		loop {
-			if self.next().is_none() {
-				break;
-			}
-		}
+		self.inner.drop_rest(&mut self.pred)
 	}
 }
 
@@ -2124,6 +6380,25 @@ pub struct Keys<'a, K, V, C> {
 	inner: Iter<'a, K, V, C>,
 }
 
+impl<'a, K, V, C> Clone for Keys<'a, K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Keys {
+			inner: self.inner.clone(),
+		}
+	}
+}
+
+impl<'a, K: fmt::Debug, V, C: Slab<Node<K, V>>> fmt::Debug for Keys<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
 impl<'a, K, V, C: Slab<Node<K, V>>> FusedIterator for Keys<'a, K, V, C> where C: SimpleCollectionRef {}
 impl<'a, K, V, C: Slab<Node<K, V>>> ExactSizeIterator for Keys<'a, K, V, C> where
 	C: SimpleCollectionRef
@@ -2145,6 +6420,14 @@ where
 	fn next(&mut self) -> Option<&'a K> {
 		self.inner.next().map(|(k, _)| k)
 	}
+
+	#[inline]
+	fn fold<B, F>(self, init: B, mut f: F) -> B
+	where
+		F: FnMut(B, &'a K) -> B,
+	{
+		self.inner.fold(init, |acc, (k, _)| f(acc, k))
+	}
 }
 
 impl<'a, K, V, C: Slab<Node<K, V>>> DoubleEndedIterator for Keys<'a, K, V, C>
@@ -2157,6 +6440,51 @@ where
 	}
 }
 
+/// Iterator adapter yielding owned clones of the keys and borrowed values.
+///
+/// Created by [`BTreeMap::iter_cloned_keys`] and [`BTreeMap::range_cloned_keys`].
+#[derive(Clone, Debug)]
+pub struct ClonedKeys<I> {
+	inner: I,
+}
+
+impl<'a, K: 'a + Clone, V: 'a, I> Iterator for ClonedKeys<I>
+where
+	I: Iterator<Item = (&'a K, &'a V)>,
+{
+	type Item = (K, &'a V);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<(K, &'a V)> {
+		self.inner.next().map(|(k, v)| (k.clone(), v))
+	}
+}
+
+impl<'a, K: 'a + Clone, V: 'a, I> DoubleEndedIterator for ClonedKeys<I>
+where
+	I: DoubleEndedIterator<Item = (&'a K, &'a V)>,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<(K, &'a V)> {
+		self.inner.next_back().map(|(k, v)| (k.clone(), v))
+	}
+}
+
+impl<'a, K: 'a + Clone, V: 'a, I> FusedIterator for ClonedKeys<I> where
+	I: FusedIterator<Item = (&'a K, &'a V)>
+{
+}
+
+impl<'a, K: 'a + Clone, V: 'a, I> ExactSizeIterator for ClonedKeys<I> where
+	I: ExactSizeIterator<Item = (&'a K, &'a V)>
+{
+}
+
 impl<K, V, C: SlabMut<Node<K, V>>> FusedIterator for IntoKeys<K, V, C>
 where
 	C: SimpleCollectionRef,
@@ -2174,6 +6502,19 @@ pub struct IntoKeys<K, V, C> {
 	inner: IntoIter<K, V, C>,
 }
 
+impl<K: fmt::Debug, V, C: Slab<Node<K, V>>> fmt::Debug for IntoKeys<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let inner = &self.inner;
+		f.debug_list()
+			.entries(Iter::remaining(&inner.btree, inner.addr, inner.len).map(|(k, _)| k))
+			.finish()
+	}
+}
+
 impl<K, V, C: SlabMut<Node<K, V>>> Iterator for IntoKeys<K, V, C>
 where
 	C: SimpleCollectionRef,
@@ -2216,6 +6557,25 @@ pub struct Values<'a, K, V, C> {
 	inner: Iter<'a, K, V, C>,
 }
 
+impl<'a, K, V, C> Clone for Values<'a, K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Values {
+			inner: self.inner.clone(),
+		}
+	}
+}
+
+impl<'a, K, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Debug for Values<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
 impl<'a, K, V, C: Slab<Node<K, V>>> Iterator for Values<'a, K, V, C>
 where
 	C: SimpleCollectionRef,
@@ -2231,6 +6591,14 @@ where
 	fn next(&mut self) -> Option<&'a V> {
 		self.inner.next().map(|(_, v)| v)
 	}
+
+	#[inline]
+	fn fold<B, F>(self, init: B, mut f: F) -> B
+	where
+		F: FnMut(B, &'a V) -> B,
+	{
+		self.inner.fold(init, |acc, (_, v)| f(acc, v))
+	}
 }
 
 impl<'a, K, V, C: Slab<Node<K, V>>> DoubleEndedIterator for Values<'a, K, V, C>
@@ -2247,6 +6615,16 @@ pub struct ValuesMut<'a, K, V, C> {
 	inner: IterMut<'a, K, V, C>,
 }
 
+impl<'a, K, V: fmt::Debug, C> fmt::Debug for ValuesMut<'a, K, V, C> {
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let inner = &self.inner;
+		f.debug_list()
+			.entries(inner.items.remaining().map(Item::value))
+			.finish()
+	}
+}
+
 impl<'a, K, V, C: SlabMut<Node<K, V>>> FusedIterator for ValuesMut<'a, K, V, C>
 where
 	C: SimpleCollectionRef,
@@ -2282,6 +6660,19 @@ pub struct IntoValues<K, V, C> {
 	inner: IntoIter<K, V, C>,
 }
 
+impl<K, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Debug for IntoValues<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let inner = &self.inner;
+		f.debug_list()
+			.entries(Iter::remaining(&inner.btree, inner.addr, inner.len).map(|(_, v)| v))
+			.finish()
+	}
+}
+
 impl<K, V, C: SlabMut<Node<K, V>>> FusedIterator for IntoValues<K, V, C>
 where
 	C: SimpleCollectionRef,
@@ -2324,7 +6715,21 @@ where
 	}
 }
 
-fn is_valid_range<T, R>(range: &R) -> bool
+/// Error returned by [`BTreeMap::try_range`] and [`BTreeMap::try_range_mut`]
+/// when the start bound of the range is after its end bound,
+/// or when both bounds are excluded and equal.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct InvalidRange;
+
+impl fmt::Display for InvalidRange {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "invalid range")
+	}
+}
+
+impl std::error::Error for InvalidRange {}
+
+pub(crate) fn is_valid_range<T, R>(range: &R) -> bool
 where
 	T: Ord + ?Sized,
 	R: RangeBounds<T>,
@@ -2340,6 +6745,45 @@ where
 	}
 }
 
+/// Checks that the given key is not before the start bound of a range.
+fn is_after_start<T>(start: Bound<&T>, key: &T) -> bool
+where
+	T: Ord + ?Sized,
+{
+	match start {
+		Bound::Included(start) => key >= start,
+		Bound::Excluded(start) => key > start,
+		Bound::Unbounded => true,
+	}
+}
+
+/// Checks that the given key is not after the end bound of a range.
+fn is_before_end<T>(end: Bound<&T>, key: &T) -> bool
+where
+	T: Ord + ?Sized,
+{
+	match end {
+		Bound::Included(end) => key <= end,
+		Bound::Excluded(end) => key < end,
+		Bound::Unbounded => true,
+	}
+}
+
+/// Checks if a child node whose right separator is `right_separator`
+/// may contain keys after the start bound of a range.
+fn may_reach_start<K, V, T>(start: Bound<&T>, right_separator: Option<&Branch<K, V>>) -> bool
+where
+	T: Ord + ?Sized,
+	K: Borrow<T>,
+{
+	match (start, right_separator) {
+		(Bound::Included(start), Some(b)) | (Bound::Excluded(start), Some(b)) => {
+			b.item.key().borrow() > start
+		}
+		_ => true,
+	}
+}
+
 pub struct Range<'a, K, V, C> {
 	/// The tree reference.
 	btree: &'a BTreeMap<K, V, C>,
@@ -2350,45 +6794,49 @@ pub struct Range<'a, K, V, C> {
 	end: Address,
 }
 
+impl<'a, K, V, C> Clone for Range<'a, K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Range {
+			btree: self.btree,
+			addr: self.addr,
+			end: self.end,
+		}
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Debug for Range<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
 impl<'a, K, V, C: Slab<Node<K, V>>> Range<'a, K, V, C>
 where
 	C: SimpleCollectionRef,
 {
-	fn new<T, R>(btree: &'a BTreeMap<K, V, C>, range: R) -> Self
+	/// Creates an iterator over the given range.
+	///
+	/// Any bounds give a valid iterator, possibly empty, as long as they are ordered.
+	fn new<T, R>(btree: &'a BTreeMap<K, V, C>, range: R) -> Result<Self, InvalidRange>
 	where
 		T: Ord + ?Sized,
 		R: RangeBounds<T>,
 		K: Borrow<T>,
 	{
 		if !is_valid_range(&range) {
-			panic!("Invalid range")
+			return Err(InvalidRange);
 		}
 
-		let addr = match range.start_bound() {
-			Bound::Included(start) => match btree.address_of(start) {
-				Ok(addr) => addr,
-				Err(addr) => addr,
-			},
-			Bound::Excluded(start) => match btree.address_of(start) {
-				Ok(addr) => btree.next_item_or_back_address(addr).unwrap(),
-				Err(addr) => addr,
-			},
-			Bound::Unbounded => btree.first_back_address(),
-		};
-
-		let end = match range.end_bound() {
-			Bound::Included(end) => match btree.address_of(end) {
-				Ok(addr) => btree.next_item_or_back_address(addr).unwrap(),
-				Err(addr) => addr,
-			},
-			Bound::Excluded(end) => match btree.address_of(end) {
-				Ok(addr) => addr,
-				Err(addr) => addr,
-			},
-			Bound::Unbounded => btree.first_back_address(),
-		};
-
-		Range { btree, addr, end }
+		Ok(Range {
+			btree,
+			addr: btree.range_start_address(range.start_bound()),
+			end: btree.range_end_address(range.end_bound()),
+		})
 	}
 }
 
@@ -2398,21 +6846,82 @@ where
 {
 	type Item = (&'a K, &'a V);
 
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = self.btree.count_between(self.addr, self.end);
+		(len, Some(len))
+	}
+
 	#[inline]
 	fn next(&mut self) -> Option<(&'a K, &'a V)> {
 		if self.addr != self.end {
-			let item = self.btree.item(self.addr).unwrap();
-			self.addr = self.btree.next_item_or_back_address(self.addr).unwrap();
+			let items = leaf_items_from(self.btree, self.addr);
+			let item = match items {
+				Some(items) => &items[0],
+				None => self.btree.item(self.addr).unwrap(),
+			};
+
+			self.addr = match items {
+				Some(items) if items.len() > 1 => self.addr.next_in_node(),
+				_ => self.btree.next_item_or_back_address(self.addr).unwrap(),
+			};
+
 			Some((item.key(), item.value()))
 		} else {
 			None
 		}
 	}
+
+	#[inline]
+	fn fold<B, F>(mut self, init: B, mut f: F) -> B
+	where
+		F: FnMut(B, (&'a K, &'a V)) -> B,
+	{
+		let mut acc = init;
+		while self.addr != self.end {
+			match leaf_items_from(self.btree, self.addr) {
+				Some(items) => {
+					// Visit the remaining items of the leaf at once,
+					// stopping at the end of the range if it is in the same leaf.
+					let items = if self.end.id == self.addr.id {
+						&items[..(self.end.offset.unwrap() - self.addr.offset.unwrap())]
+					} else {
+						items
+					};
+
+					for item in items {
+						acc = f(acc, (item.key(), item.value()))
+					}
+
+					self.addr = if self.end.id == self.addr.id {
+						self.end
+					} else {
+						let last = Address::new(
+							self.addr.id,
+							(self.addr.offset.unwrap() + items.len() - 1).into(),
+						);
+						self.btree.next_item_or_back_address(last).unwrap()
+					}
+				}
+				None => {
+					let item = self.next().unwrap();
+					acc = f(acc, item)
+				}
+			}
+		}
+
+		acc
+	}
 }
 
 impl<'a, K, V, C: Slab<Node<K, V>>> FusedIterator for Range<'a, K, V, C> where C: SimpleCollectionRef
 {}
 
+impl<'a, K, V, C: Slab<Node<K, V>>> ExactSizeIterator for Range<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
 impl<'a, K, V, C: Slab<Node<K, V>>> DoubleEndedIterator for Range<'a, K, V, C>
 where
 	C: SimpleCollectionRef,
@@ -2420,7 +6929,7 @@ where
 	#[inline]
 	fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
 		if self.addr != self.end {
-			let addr = self.btree.previous_item_address(self.addr).unwrap();
+			let addr = self.btree.previous_item_address(self.end).unwrap();
 			let item = self.btree.item(addr).unwrap();
 			self.end = addr;
 			Some((item.key(), item.value()))
@@ -2431,13 +6940,17 @@ where
 }
 
 pub struct RangeMut<'a, K, V, C> {
-	/// The tree reference.
-	btree: &'a mut BTreeMap<K, V, C>,
-
-	/// Address of the next item or last back address.
-	addr: Address,
+	/// The borrowed items.
+	items: RawItemsMut<'a, K, V, C>,
+}
 
-	end: Address,
+impl<'a, K: fmt::Debug, V: fmt::Debug, C> fmt::Debug for RangeMut<'a, K, V, C> {
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list()
+			.entries(self.items.remaining().map(Item::as_pair))
+			.finish()
+	}
 }
 
 impl<'a, K, V, C: SlabMut<Node<K, V>>> RangeMut<'a, K, V, C>
@@ -2445,65 +6958,92 @@ where
 	C: SimpleCollectionRef,
 	C: SimpleCollectionMut,
 {
-	fn new<T, R>(btree: &'a mut BTreeMap<K, V, C>, range: R) -> Self
+	/// Creates an iterator over the given range.
+	///
+	/// Any bounds give a valid iterator, possibly empty, as long as they are ordered.
+	fn new<T, R>(btree: &'a mut BTreeMap<K, V, C>, range: R) -> Result<Self, InvalidRange>
 	where
 		T: Ord + ?Sized,
 		R: RangeBounds<T>,
 		K: Borrow<T>,
 	{
 		if !is_valid_range(&range) {
-			panic!("Invalid range")
+			return Err(InvalidRange);
 		}
 
-		let addr = match range.start_bound() {
-			Bound::Included(start) => match btree.address_of(start) {
-				Ok(addr) => addr,
-				Err(addr) => addr,
-			},
-			Bound::Excluded(start) => match btree.address_of(start) {
-				Ok(addr) => btree.next_item_or_back_address(addr).unwrap(),
-				Err(addr) => addr,
-			},
-			Bound::Unbounded => btree.first_back_address(),
-		};
+		let addr = btree.range_start_address(range.start_bound());
+		let end = btree.range_end_address(range.end_bound());
 
-		let end = match range.end_bound() {
-			Bound::Included(end) => match btree.address_of(end) {
-				Ok(addr) => btree.next_item_or_back_address(addr).unwrap(),
-				Err(addr) => addr,
-			},
-			Bound::Excluded(end) => match btree.address_of(end) {
-				Ok(addr) => addr,
-				Err(addr) => addr,
-			},
-			Bound::Unbounded => btree.first_back_address(),
-		};
+		Ok(Self::between(btree, addr, end))
+	}
+
+	/// Creates an iterator over the items between the given address
+	/// and the given back address.
+	fn between(btree: &'a mut BTreeMap<K, V, C>, addr: Address, end: Address) -> Self {
+		let len = btree.count_between(addr, end);
+		let first = btree.normalize(addr);
+		let last = btree.previous_item_address(end);
+		RangeMut {
+			items: RawItemsMut::new(btree, first, last, len),
+		}
+	}
+
+	/// Returns the entry that `next` would return, without advancing the iterator.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, i32> = (0..4).map(|i| (i, i)).collect();
+	/// let mut iter = map.range_mut(..);
+	/// assert_eq!(iter.peek(), Some((&0, &0)));
+	/// assert_eq!(iter.peek_back(), Some((&3, &3)));
+	///
+	/// // Add the next value to each value.
+	/// while let Some((_, v)) = iter.next() {
+	///     *v += iter.peek().map_or(0, |(_, next)| *next);
+	/// }
+	///
+	/// assert!(map.values().copied().eq([1, 3, 5, 3]));
+	/// ```
+	#[inline]
+	pub fn peek(&self) -> Option<(&K, &V)> {
+		// SAFETY: the item is not lent out yet, and is borrowed for no longer than `self`.
+		self.items
+			.peek()
+			.map(|item| unsafe { item.as_ref() }.as_pair())
+	}
 
-		RangeMut { btree, addr, end }
+	/// Returns the entry that `next` would return, with a mutable reference to its value,
+	/// without advancing the iterator.
+	#[inline]
+	pub fn peek_mut(&mut self) -> Option<(&K, &mut V)> {
+		self.items.peek().map(|mut item| {
+			// SAFETY: the item is not lent out yet, and is borrowed for no longer than `self`.
+			let (key, value) = unsafe { item.as_mut() }.as_pair_mut();
+			(&*key, value)
+		})
 	}
 
+	/// Returns the entry that `next_back` would return, without advancing the iterator.
 	#[inline]
-	fn next_item(&mut self) -> Option<&'a mut Item<K, V>> {
-		if self.addr != self.end {
-			let addr = self.addr;
-			self.addr = self.btree.next_item_or_back_address(addr).unwrap();
-			let item = self.btree.item_mut(addr).unwrap();
-			Some(unsafe { std::mem::transmute(item) }) // this is safe because only one mutable reference to the same item can be emitted.
-		} else {
-			None
-		}
+	pub fn peek_back(&self) -> Option<(&K, &V)> {
+		// SAFETY: the item is not lent out yet, and is borrowed for no longer than `self`.
+		self.items
+			.peek_back()
+			.map(|item| unsafe { item.as_ref() }.as_pair())
 	}
 
+	/// Returns the entry that `next_back` would return, with a mutable reference to its value,
+	/// without advancing the iterator.
 	#[inline]
-	fn next_back_item(&mut self) -> Option<&'a mut Item<K, V>> {
-		if self.addr != self.end {
-			let addr = self.btree.previous_item_address(self.addr).unwrap();
-			let item = self.btree.item_mut(addr).unwrap();
-			self.end = addr;
-			Some(unsafe { std::mem::transmute(item) }) // this is safe because only one mutable reference to the same item can be emitted.s
-		} else {
-			None
-		}
+	pub fn peek_back_mut(&mut self) -> Option<(&K, &mut V)> {
+		self.items.peek_back().map(|mut item| {
+			// SAFETY: the item is not lent out yet, and is borrowed for no longer than `self`.
+			let (key, value) = unsafe { item.as_mut() }.as_pair_mut();
+			(&*key, value)
+		})
 	}
 }
 
@@ -2514,9 +7054,15 @@ where
 {
 	type Item = (&'a K, &'a mut V);
 
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		let len = self.items.len();
+		(len, Some(len))
+	}
+
 	#[inline]
 	fn next(&mut self) -> Option<(&'a K, &'a mut V)> {
-		self.next_item().map(|item| {
+		self.items.next().map(|item| {
 			let (key, value) = item.as_pair_mut();
 			(key as &'a K, value)
 		})
@@ -2530,6 +7076,13 @@ where
 {
 }
 
+impl<'a, K, V, C: SlabMut<Node<K, V>>> ExactSizeIterator for RangeMut<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+}
+
 impl<'a, K, V, C: SlabMut<Node<K, V>>> DoubleEndedIterator for RangeMut<'a, K, V, C>
 where
 	C: SimpleCollectionRef,
@@ -2537,7 +7090,7 @@ where
 {
 	#[inline]
 	fn next_back(&mut self) -> Option<(&'a K, &'a mut V)> {
-		self.next_back_item().map(|item| {
+		self.items.next_back().map(|item| {
 			let (key, value) = item.as_pair_mut();
 			(key as &'a K, value)
 		})
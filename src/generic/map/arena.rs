@@ -0,0 +1,313 @@
+use super::{BTreeExt, BTreeMap};
+use crate::generic::Node;
+use cc_traits::{
+	Capacity, Collection, CollectionMut, CollectionRef, Get, GetMut, Insert, Len, Remove,
+	SimpleCollectionMut, SimpleCollectionRef, SlabMut,
+};
+
+/// Node container borrowed from a slab shared by multiple trees.
+///
+/// A map using this container only owns its root node identifier and its length:
+/// the nodes themselves are allocated in the shared slab,
+/// next to the nodes of the other trees.
+/// Such a map is created with [`BTreeMap::new_in`],
+/// and detached from the slab with [`BTreeMap::into_root`]
+/// so that the slab can be lent to another tree.
+///
+/// The container does not implement [`cc_traits::Clear`],
+/// since clearing the slab would also clear the other trees.
+/// Instead, it keeps track of the nodes of its tree,
+/// and releases them from the slab when it is dropped,
+/// unless the tree was detached with [`BTreeMap::into_root`].
+/// A detached tree is released with [`ArenaRef::release`].
+pub struct ArenaRef<'a, C> {
+	/// Borrowed slab.
+	slab: &'a mut C,
+
+	/// Bit set of the identifiers of the nodes owned by the tree.
+	owned: Vec<u64>,
+
+	/// Removes a node from the slab.
+	remove: fn(&mut C, usize),
+}
+
+impl<'a, C: Remove<usize>> ArenaRef<'a, C> {
+	/// Borrows the given slab.
+	#[inline]
+	pub fn new(slab: &'a mut C) -> Self {
+		ArenaRef {
+			slab,
+			owned: Vec::new(),
+			remove: |slab, id| {
+				slab.remove(id);
+			},
+		}
+	}
+}
+
+impl<'a, C> ArenaRef<'a, C> {
+	/// Returns the borrowed slab.
+	///
+	/// The nodes of the tree stay in the slab.
+	#[inline]
+	pub fn into_inner(mut self) -> &'a mut C {
+		self.owned = Vec::new();
+		let this = std::mem::ManuallyDrop::new(self);
+		// SAFETY: `this` is never used nor dropped afterward,
+		// and `owned` was already emptied so nothing is leaked.
+		unsafe { std::ptr::read(&this.slab) }
+	}
+
+	/// Releases every node of a tree detached from the borrowed slab.
+	///
+	/// The tree must have been detached from this slab with [`BTreeMap::into_root`].
+	///
+	/// # Example
+	///
+	/// ```
+	/// use slab::Slab;
+	/// use btree_slab::generic::{map::ArenaRef, BTreeMap, Node};
+	///
+	/// let mut slab: Slab<Node<u32, u32>> = Slab::new();
+	///
+	/// let mut map = BTreeMap::new_in(&mut slab);
+	/// map.extend((0..100).map(|i| (i, i)));
+	/// let root = map.into_root();
+	/// assert!(!slab.is_empty());
+	///
+	/// ArenaRef::new(&mut slab).release(root);
+	/// assert!(slab.is_empty());
+	/// ```
+	#[inline]
+	pub fn release<K, V>(&mut self, root: TreeRoot)
+	where
+		C: Remove<usize, Item = Node<K, V>>,
+	{
+		let mut stack: Vec<usize> = root.root.into_iter().collect();
+		while let Some(id) = stack.pop() {
+			self.disown(id);
+			if let Some(node) = self.slab.remove(id) {
+				stack.extend(node.children())
+			}
+		}
+	}
+
+	/// Marks the given node as owned by the tree.
+	#[inline]
+	fn own(&mut self, id: usize) {
+		let word = id / 64;
+		if word >= self.owned.len() {
+			self.owned.resize(word + 1, 0)
+		}
+		self.owned[word] |= 1 << (id % 64)
+	}
+
+	/// Marks the given node as not owned by the tree.
+	#[inline]
+	fn disown(&mut self, id: usize) {
+		if let Some(word) = self.owned.get_mut(id / 64) {
+			*word &= !(1 << (id % 64))
+		}
+	}
+}
+
+impl<'a, C> Drop for ArenaRef<'a, C> {
+	/// Releases the nodes of the tree from the slab.
+	fn drop(&mut self) {
+		for (word_index, word) in std::mem::take(&mut self.owned).into_iter().enumerate() {
+			let mut bits = word;
+			while bits != 0 {
+				let id = word_index * 64 + bits.trailing_zeros() as usize;
+				(self.remove)(self.slab, id);
+				bits &= bits - 1
+			}
+		}
+	}
+}
+
+impl<'a, C: Collection> Collection for ArenaRef<'a, C> {
+	type Item = C::Item;
+}
+
+impl<'a, C: Collection> CollectionRef for ArenaRef<'a, C> {
+	type ItemRef<'b>
+		= &'b C::Item
+	where
+		Self: 'b;
+
+	cc_traits::covariant_item_ref!();
+}
+
+impl<'a, C: Collection> CollectionMut for ArenaRef<'a, C> {
+	type ItemMut<'b>
+		= &'b mut C::Item
+	where
+		Self: 'b;
+
+	cc_traits::covariant_item_mut!();
+}
+
+impl<'a, C: Collection> SimpleCollectionRef for ArenaRef<'a, C> {
+	cc_traits::simple_collection_ref!();
+}
+
+impl<'a, C: Collection> SimpleCollectionMut for ArenaRef<'a, C> {
+	cc_traits::simple_collection_mut!();
+}
+
+impl<'a, C: Len> Len for ArenaRef<'a, C> {
+	/// Returns the number of nodes in the shared slab, including those of the other trees.
+	#[inline]
+	fn len(&self) -> usize {
+		self.slab.len()
+	}
+}
+
+impl<'a, C: Capacity> Capacity for ArenaRef<'a, C> {
+	#[inline]
+	fn capacity(&self) -> usize {
+		self.slab.capacity()
+	}
+}
+
+impl<'a, C: Get<usize> + SimpleCollectionRef> Get<usize> for ArenaRef<'a, C> {
+	#[inline]
+	fn get(&self, id: usize) -> Option<&C::Item> {
+		self.slab.get(id).map(C::into_ref)
+	}
+}
+
+impl<'a, C: GetMut<usize> + SimpleCollectionRef + SimpleCollectionMut> GetMut<usize>
+	for ArenaRef<'a, C>
+{
+	#[inline]
+	fn get_mut(&mut self, id: usize) -> Option<&mut C::Item> {
+		self.slab.get_mut(id).map(C::into_mut)
+	}
+}
+
+impl<'a, C: Insert<Output = usize>> Insert for ArenaRef<'a, C> {
+	type Output = usize;
+
+	#[inline]
+	fn insert(&mut self, node: C::Item) -> usize {
+		let id = self.slab.insert(node);
+		self.own(id);
+		id
+	}
+}
+
+impl<'a, C: Remove<usize>> Remove<usize> for ArenaRef<'a, C> {
+	#[inline]
+	fn remove(&mut self, id: usize) -> Option<C::Item> {
+		self.disown(id);
+		self.slab.remove(id)
+	}
+}
+
+/// Tree detached from a shared slab.
+///
+/// It holds the root node identifier and the length of a tree
+/// whose nodes are stored in a slab shared with other trees.
+/// It is returned by [`BTreeMap::into_root`],
+/// and attached back to its slab with [`BTreeMap::from_root_in`].
+///
+/// This type is deliberately not `Clone`:
+/// attaching the same tree twice would let the two maps modify the same nodes.
+#[derive(Debug, PartialEq, Eq)]
+pub struct TreeRoot {
+	root: Option<usize>,
+	len: usize,
+}
+
+impl TreeRoot {
+	/// Returns the number of items in the tree.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns `true` if the tree contains no items.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.root.is_none()
+	}
+}
+
+impl<'a, K, V, C: SlabMut<Node<K, V>>> BTreeMap<K, V, ArenaRef<'a, C>>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Creates a new empty B-tree allocating its nodes in the given shared slab.
+	///
+	/// Many small trees can be stored in the same slab this way,
+	/// reducing fragmentation.
+	/// Only one tree can borrow the slab at a time:
+	/// use [`BTreeMap::into_root`] to release it,
+	/// and [`BTreeMap::from_root_in`] to work on the tree again.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use slab::Slab;
+	/// use btree_slab::generic::{map::TreeRoot, BTreeMap, Node};
+	///
+	/// let mut slab: Slab<Node<u32, u32>> = Slab::new();
+	///
+	/// let roots: Vec<TreeRoot> = (0..10)
+	///     .map(|i| {
+	///         let mut map = BTreeMap::new_in(&mut slab);
+	///         for j in 0..100 {
+	///             map.insert(j, i);
+	///         }
+	///         map.into_root()
+	///     })
+	///     .collect();
+	///
+	/// for (i, root) in roots.into_iter().enumerate() {
+	///     let map = BTreeMap::from_root_in(&mut slab, root);
+	///     assert_eq!(map.len(), 100);
+	///     assert!(map.values().all(|v| *v == i as u32));
+	/// }
+	/// ```
+	#[inline]
+	pub fn new_in(slab: &'a mut C) -> Self {
+		BTreeMap::with_container(ArenaRef::new(slab))
+	}
+
+	/// Attaches a tree detached by [`BTreeMap::into_root`] back to its shared slab.
+	///
+	/// The slab must be the one the tree was detached from.
+	/// The nodes of the tree are released when the map is dropped,
+	/// unless it is detached again.
+	#[inline]
+	pub fn from_root_in(slab: &'a mut C, root: TreeRoot) -> Self {
+		let mut map = Self::new_in(slab);
+		map.root = root.root;
+		map.len = root.len;
+		map.reset_leaf_bounds();
+
+		let mut stack: Vec<usize> = map.root.into_iter().collect();
+		while let Some(id) = stack.pop() {
+			map.nodes.own(id);
+			stack.extend(map.node(id).children())
+		}
+
+		map
+	}
+
+	/// Detaches the tree from its shared slab, releasing the borrow of the slab.
+	///
+	/// The nodes of the tree stay in the slab.
+	/// Any other state of the map,
+	/// such as its size limits or the addresses obtained from it, is lost.
+	#[inline]
+	pub fn into_root(mut self) -> TreeRoot {
+		self.nodes.owned.clear();
+		TreeRoot {
+			root: self.root_id(),
+			len: self.len(),
+		}
+	}
+}
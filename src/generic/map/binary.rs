@@ -0,0 +1,398 @@
+use super::{BTreeExt, BTreeExtMut, BTreeMap, M};
+use crate::generic::node::{internal::Branch, InternalNode, Item, LeafNode, Node};
+use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
+use smallvec::SmallVec;
+use std::io::{self, Read, Write};
+
+/// Magic bytes starting every encoded map.
+const MAGIC: &[u8; 4] = b"BTSL";
+
+/// Version of the binary format.
+const VERSION: u8 = 1;
+
+const LEAF_TAG: u8 = 0;
+const INTERNAL_TAG: u8 = 1;
+
+/// Type that can be written to and read from the compact binary format
+/// of [`BTreeMap::encode_into`].
+///
+/// Integers and floats are encoded in little-endian,
+/// and sequences are prefixed with their length as a `u64`.
+pub trait BinaryCodec: Sized {
+	/// Writes the value.
+	fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+
+	/// Reads a value written by [`BinaryCodec::encode`].
+	fn decode<R: Read>(reader: &mut R) -> io::Result<Self>;
+}
+
+macro_rules! number_codec {
+	($($ty:ty),*) => {
+		$(
+			impl BinaryCodec for $ty {
+				#[inline]
+				fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+					writer.write_all(&self.to_le_bytes())
+				}
+
+				#[inline]
+				fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+					let mut bytes = [0; std::mem::size_of::<$ty>()];
+					reader.read_exact(&mut bytes)?;
+					Ok(<$ty>::from_le_bytes(bytes))
+				}
+			}
+		)*
+	};
+}
+
+number_codec!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128, f32, f64);
+
+impl BinaryCodec for usize {
+	#[inline]
+	fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		(*self as u64).encode(writer)
+	}
+
+	#[inline]
+	fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+		usize::try_from(u64::decode(reader)?).map_err(invalid_data)
+	}
+}
+
+impl BinaryCodec for isize {
+	#[inline]
+	fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		(*self as i64).encode(writer)
+	}
+
+	#[inline]
+	fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+		isize::try_from(i64::decode(reader)?).map_err(invalid_data)
+	}
+}
+
+impl BinaryCodec for () {
+	#[inline]
+	fn encode<W: Write>(&self, _writer: &mut W) -> io::Result<()> {
+		Ok(())
+	}
+
+	#[inline]
+	fn decode<R: Read>(_reader: &mut R) -> io::Result<Self> {
+		Ok(())
+	}
+}
+
+impl BinaryCodec for bool {
+	#[inline]
+	fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		(*self as u8).encode(writer)
+	}
+
+	#[inline]
+	fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+		match u8::decode(reader)? {
+			0 => Ok(false),
+			1 => Ok(true),
+			_ => Err(invalid_data("invalid boolean")),
+		}
+	}
+}
+
+impl BinaryCodec for char {
+	#[inline]
+	fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		(*self as u32).encode(writer)
+	}
+
+	#[inline]
+	fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+		char::from_u32(u32::decode(reader)?).ok_or_else(|| invalid_data("invalid character"))
+	}
+}
+
+impl BinaryCodec for String {
+	#[inline]
+	fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		self.len().encode(writer)?;
+		writer.write_all(self.as_bytes())
+	}
+
+	#[inline]
+	fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+		let len = usize::decode(reader)?;
+		let mut bytes = Vec::new();
+		reader.take(len as u64).read_to_end(&mut bytes)?;
+		if bytes.len() != len {
+			return Err(io::ErrorKind::UnexpectedEof.into());
+		}
+
+		String::from_utf8(bytes).map_err(invalid_data)
+	}
+}
+
+impl<T: BinaryCodec> BinaryCodec for Vec<T> {
+	#[inline]
+	fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		self.len().encode(writer)?;
+		for item in self {
+			item.encode(writer)?
+		}
+
+		Ok(())
+	}
+
+	#[inline]
+	fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+		let len = usize::decode(reader)?;
+		// The length is not trusted to preallocate.
+		let mut items = Vec::new();
+		for _ in 0..len {
+			items.push(T::decode(reader)?)
+		}
+
+		Ok(items)
+	}
+}
+
+impl<T: BinaryCodec> BinaryCodec for Option<T> {
+	#[inline]
+	fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		match self {
+			Some(value) => {
+				true.encode(writer)?;
+				value.encode(writer)
+			}
+			None => false.encode(writer),
+		}
+	}
+
+	#[inline]
+	fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+		if bool::decode(reader)? {
+			Ok(Some(T::decode(reader)?))
+		} else {
+			Ok(None)
+		}
+	}
+}
+
+impl<A: BinaryCodec, B: BinaryCodec> BinaryCodec for (A, B) {
+	#[inline]
+	fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+		self.0.encode(writer)?;
+		self.1.encode(writer)
+	}
+
+	#[inline]
+	fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+		Ok((A::decode(reader)?, B::decode(reader)?))
+	}
+}
+
+fn invalid_data<E>(e: E) -> io::Error
+where
+	E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+	io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+impl<K, V, C: Slab<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Writes the map in a compact, versioned binary format.
+	///
+	/// The nodes of the tree are written as they are, children first,
+	/// each with its kind and its items.
+	/// Reading it back with [`BTreeMap::decode_from`] restores the same tree
+	/// without reinserting any entry.
+	///
+	/// Requires the `binary` feature.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let map: BTreeMap<u32, String> = (0..100).map(|i| (i, i.to_string())).collect();
+	///
+	/// let mut buffer = Vec::new();
+	/// map.encode_into(&mut buffer).unwrap();
+	///
+	/// let decoded: BTreeMap<u32, String> = BTreeMap::decode_from(&mut buffer.as_slice()).unwrap();
+	/// assert_eq!(decoded, map);
+	/// ```
+	pub fn encode_into<W: Write>(&self, writer: &mut W) -> io::Result<()>
+	where
+		K: BinaryCodec,
+		V: BinaryCodec,
+	{
+		writer.write_all(MAGIC)?;
+		VERSION.encode(writer)?;
+		(M as u32).encode(writer)?;
+		self.len().encode(writer)?;
+
+		match self.root_id() {
+			Some(id) => {
+				true.encode(writer)?;
+				self.encode_node(writer, id)
+			}
+			None => false.encode(writer),
+		}
+	}
+
+	/// Writes the subtree rooted in the given node, children first.
+	fn encode_node<W: Write>(&self, writer: &mut W, id: usize) -> io::Result<()>
+	where
+		K: BinaryCodec,
+		V: BinaryCodec,
+	{
+		let node = self.node(id);
+		for child_id in node.children() {
+			self.encode_node(writer, child_id)?
+		}
+
+		match node {
+			Node::Leaf(_) => LEAF_TAG.encode(writer)?,
+			Node::Internal(_) => INTERNAL_TAG.encode(writer)?,
+		}
+
+		(node.item_count() as u32).encode(writer)?;
+		for offset in 0..node.item_count() {
+			let item = node.item(offset.into()).unwrap();
+			item.key().encode(writer)?;
+			item.value().encode(writer)?
+		}
+
+		Ok(())
+	}
+}
+
+impl<K, V, C: SlabMut<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Reads a map written by [`BTreeMap::encode_into`].
+	///
+	/// The nodes are allocated as they are read, without comparing nor moving the entries
+	/// between nodes.
+	/// The restored tree is then checked with [`BTreeExt::check_invariants`],
+	/// so that malformed input never produces an invalid map.
+	///
+	/// Requires the `binary` feature.
+	///
+	/// # Errors
+	///
+	/// Returns an error of kind [`io::ErrorKind::InvalidData`]
+	/// if the input is not a map encoded with the same format version and B-Tree order,
+	/// or if the decoded tree is not a valid B-Tree.
+	pub fn decode_from<R: Read>(reader: &mut R) -> io::Result<Self>
+	where
+		K: Ord + BinaryCodec,
+		V: BinaryCodec,
+		C: Default,
+	{
+		let mut magic = [0; 4];
+		reader.read_exact(&mut magic)?;
+		if &magic != MAGIC {
+			return Err(invalid_data("not an encoded B-Tree"));
+		}
+
+		let version = u8::decode(reader)?;
+		if version != VERSION {
+			return Err(invalid_data(format!(
+				"unsupported format version {}",
+				version
+			)));
+		}
+
+		let order = u32::decode(reader)?;
+		if order as usize != M {
+			return Err(invalid_data(format!("unsupported B-Tree order {}", order)));
+		}
+
+		let len = usize::decode(reader)?;
+		let mut map = BTreeMap::new();
+
+		if bool::decode(reader)? {
+			// Identifiers of the decoded subtrees waiting for their parent.
+			let mut subtrees = Vec::new();
+			loop {
+				let id = map.decode_node(reader, &mut subtrees)?;
+				if map.node(id).subtree_len() == len {
+					if !subtrees.is_empty() {
+						return Err(invalid_data("unexpected node"));
+					}
+
+					map.set_root(Some(id));
+					break;
+				}
+
+				subtrees.push(id);
+			}
+		} else if len != 0 {
+			return Err(invalid_data("missing root node"));
+		}
+
+		map.check_invariants().map_err(invalid_data)?;
+		Ok(map)
+	}
+
+	/// Reads a node and allocates it, taking its children from the given subtrees.
+	fn decode_node<R: Read>(
+		&mut self,
+		reader: &mut R,
+		subtrees: &mut Vec<usize>,
+	) -> io::Result<usize>
+	where
+		K: BinaryCodec,
+		V: BinaryCodec,
+	{
+		let tag = u8::decode(reader)?;
+		let count = u32::decode(reader)? as usize;
+
+		let node = match tag {
+			LEAF_TAG => {
+				if count == 0 || count > M {
+					return Err(invalid_data("invalid leaf size"));
+				}
+
+				let mut leaf = LeafNode::new(None, decode_item(reader)?);
+				for _ in 1..count {
+					leaf.push_right(decode_item(reader)?);
+				}
+
+				Node::Leaf(leaf)
+			}
+			INTERNAL_TAG => {
+				if count == 0 || count >= M || subtrees.len() <= count {
+					return Err(invalid_data("invalid internal node size"));
+				}
+
+				let mut children = subtrees.drain((subtrees.len() - count - 1)..);
+				let first_child = children.next().unwrap();
+				let mut branches = SmallVec::new();
+				for child in children {
+					branches.push(Branch {
+						item: decode_item(reader)?,
+						child,
+					})
+				}
+
+				Node::Internal(InternalNode::from_parts(None, first_child, branches))
+			}
+			_ => return Err(invalid_data("invalid node kind")),
+		};
+
+		Ok(self.allocate_node(node))
+	}
+}
+
+fn decode_item<K: BinaryCodec, V: BinaryCodec, R: Read>(reader: &mut R) -> io::Result<Item<K, V>> {
+	let key = K::decode(reader)?;
+	let value = V::decode(reader)?;
+	Ok(Item::new(key, value))
+}
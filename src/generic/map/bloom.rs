@@ -0,0 +1,321 @@
+//! Map-level Bloom filter over the keys, consulted before descending the tree.
+//!
+//! [`BTreeMap::contains_key`] and [`BTreeMap::get`] do not consult the filter:
+//! they only require the borrowed key to implement [`Ord`],
+//! and the filter cannot be queried without hashing it.
+//! Adding a [`Hash`] bound to these methods would break every caller
+//! using keys that are not hashable,
+//! and picking the filter only when the key is hashable would require specialization.
+//! The filter is therefore consulted by the separate
+//! [`BTreeMap::contains_key_hinted`] and [`BTreeMap::get_hinted`] methods,
+//! or directly with [`BTreeMap::may_contain_key`].
+use super::BTreeMap;
+use crate::generic::Node;
+use cc_traits::{SimpleCollectionRef, Slab};
+use std::{
+	borrow::Borrow,
+	collections::hash_map::DefaultHasher,
+	hash::{Hash, Hasher},
+};
+
+/// Minimum number of keys a key filter is sized for.
+const MIN_CAPACITY: usize = 64;
+
+/// Maximum number of hash functions of a key filter.
+const MAX_HASHES: u32 = 16;
+
+/// Hashes a key for the key filter.
+///
+/// `DefaultHasher::new` is used so that borrowed forms of a key,
+/// which must hash like the key itself, produce the same hash.
+#[inline]
+fn hash_key<Q: ?Sized + Hash>(key: &Q) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	key.hash(&mut hasher);
+	hasher.finish()
+}
+
+/// Bloom filter over the keys of a map.
+///
+/// Removed keys cannot be cleared from the filter:
+/// they are counted as stale and the filter is rebuilt from the keys of the map
+/// once they make up too large a part of it, or once the map outgrows the filter.
+pub(crate) struct KeyFilter<K> {
+	words: Vec<u64>,
+
+	/// Key hash function, captured when the filter is enabled
+	/// since the map does not require keys to be hashable.
+	hash: fn(&K) -> u64,
+
+	bits_per_key: usize,
+
+	hashes: u32,
+
+	/// Number of keys the filter is sized for.
+	capacity: usize,
+
+	/// Number of keys removed since the last rebuild.
+	stale: usize,
+}
+
+// Not derived, since keys need not be `Clone`.
+impl<K> Clone for KeyFilter<K> {
+	#[inline]
+	fn clone(&self) -> Self {
+		KeyFilter {
+			words: self.words.clone(),
+			hash: self.hash,
+			bits_per_key: self.bits_per_key,
+			hashes: self.hashes,
+			capacity: self.capacity,
+			stale: self.stale,
+		}
+	}
+}
+
+impl<K> KeyFilter<K> {
+	fn new(bits_per_key: usize, hash: fn(&K) -> u64) -> Self {
+		// The optimal number of hash functions is `bits_per_key * ln(2)`.
+		let hashes =
+			((bits_per_key as f64 * std::f64::consts::LN_2).round() as u32).clamp(1, MAX_HASHES);
+
+		KeyFilter {
+			words: Vec::new(),
+			hash,
+			bits_per_key,
+			hashes,
+			capacity: 0,
+			stale: 0,
+		}
+	}
+
+	/// Returns the index of each bit of the given hash.
+	#[inline]
+	fn bits(&self, hash: u64) -> impl Iterator<Item = usize> {
+		let bit_count = (self.words.len() * 64) as u64;
+		let hashes = if bit_count == 0 {
+			0
+		} else {
+			self.hashes as u64
+		};
+
+		// Double hashing.
+		let h1 = hash & 0xffff_ffff;
+		let h2 = (hash >> 32) | 1;
+		(0..hashes).map(move |i| (h1.wrapping_add(i.wrapping_mul(h2)) % bit_count) as usize)
+	}
+
+	#[inline]
+	fn insert_hash(&mut self, hash: u64) {
+		for bit in self.bits(hash) {
+			self.words[bit / 64] |= 1 << (bit % 64)
+		}
+	}
+
+	#[inline]
+	fn contains_hash(&self, hash: u64) -> bool {
+		self.bits(hash)
+			.all(|bit| self.words[bit / 64] & (1 << (bit % 64)) != 0)
+	}
+
+	/// Adds a key to the filter.
+	#[inline]
+	pub(crate) fn insert(&mut self, key: &K) {
+		self.insert_hash((self.hash)(key))
+	}
+
+	/// Records the removal of a key from the map.
+	#[inline]
+	pub(crate) fn remove(&mut self) {
+		self.stale += 1
+	}
+
+	/// Records the replacement of a key by another in the map.
+	#[inline]
+	pub(crate) fn replace(&mut self, old_key: &K, new_key: &K) {
+		let new_hash = (self.hash)(new_key);
+		if (self.hash)(old_key) != new_hash {
+			self.insert_hash(new_hash);
+			self.stale += 1
+		}
+	}
+
+	/// Empties the filter.
+	#[inline]
+	pub(crate) fn clear(&mut self) {
+		for word in &mut self.words {
+			*word = 0
+		}
+
+		self.stale = 0
+	}
+
+	/// Checks if the filter is outgrown by a map with `len` keys,
+	/// or has accumulated too many stale keys.
+	#[inline]
+	pub(crate) fn needs_rebuild(&self, len: usize) -> bool {
+		len > self.capacity || self.stale > self.capacity / 2
+	}
+
+	/// Rebuilds the filter from the keys of the map.
+	fn rebuild<'a>(&mut self, len: usize, keys: impl Iterator<Item = &'a K>)
+	where
+		K: 'a,
+	{
+		self.capacity = std::cmp::max(2 * len, MIN_CAPACITY);
+		let bit_count = self.capacity * self.bits_per_key;
+		self.words.clear();
+		self.words.resize(bit_count.div_ceil(64), 0);
+		self.stale = 0;
+
+		for key in keys {
+			self.insert(key)
+		}
+	}
+}
+
+impl<K, V, C: Slab<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Enables the key filter of the map, using `bits_per_key` bits of memory per key.
+	///
+	/// The key filter is a Bloom filter consulted by [`BTreeMap::contains_key_hinted`]
+	/// and [`BTreeMap::get_hinted`] to skip the descent in the tree
+	/// for most keys that are not in the map.
+	/// With 10 bits per key, about 1% of the missing keys still require a descent.
+	/// The filter is kept up to date by every operation of the map,
+	/// and rebuilt from the keys of the map when it becomes too small
+	/// or when enough keys have been removed.
+	///
+	/// If the filter is already enabled, it is rebuilt with the new number of bits per key.
+	///
+	/// Requires the `bloom` feature.
+	///
+	/// # Panics
+	///
+	/// Panics if `bits_per_key` is `0`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<u32, u32> = (0..1000).map(|i| (i * 2, i)).collect();
+	/// map.enable_key_filter(10);
+	///
+	/// assert_eq!(map.get_hinted(&20), Some(&10));
+	/// assert!(!map.contains_key_hinted(&21));
+	/// ```
+	#[inline]
+	pub fn enable_key_filter(&mut self, bits_per_key: usize)
+	where
+		K: Hash,
+	{
+		assert!(
+			bits_per_key > 0,
+			"a key filter needs at least one bit per key"
+		);
+		let mut filter = KeyFilter::new(bits_per_key, hash_key::<K>);
+		filter.rebuild(self.len, self.keys());
+		self.key_filter = Some(filter)
+	}
+
+	/// Disables the key filter of the map, releasing its memory.
+	///
+	/// Requires the `bloom` feature.
+	#[inline]
+	pub fn disable_key_filter(&mut self) {
+		self.key_filter = None
+	}
+
+	/// Returns `true` if the key filter of the map is enabled.
+	///
+	/// Requires the `bloom` feature.
+	#[inline]
+	pub fn has_key_filter(&self) -> bool {
+		self.key_filter.is_some()
+	}
+
+	/// Returns `false` if the key filter proves that the map does not contain the given key.
+	///
+	/// Returns `true` if the map may contain the key, or if the key filter is disabled.
+	/// This never descends in the tree.
+	///
+	/// Requires the `bloom` feature.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn may_contain_key<Q: ?Sized>(&self, key: &Q) -> bool
+	where
+		K: Borrow<Q>,
+		Q: Hash,
+	{
+		match &self.key_filter {
+			Some(filter) => filter.contains_hash(hash_key(key)),
+			None => true,
+		}
+	}
+
+	/// Returns `true` if the map contains a value for the specified key,
+	/// consulting the key filter first.
+	///
+	/// Unlike [`BTreeMap::contains_key`], this requires the key to be hashable.
+	///
+	/// Requires the `bloom` feature.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn contains_key_hinted<Q: ?Sized>(&self, key: &Q) -> bool
+	where
+		K: Borrow<Q>,
+		Q: Hash + Ord,
+	{
+		self.may_contain_key(key) && self.contains_key(key)
+	}
+
+	/// Returns a reference to the value corresponding to the key,
+	/// consulting the key filter first.
+	///
+	/// Unlike [`BTreeMap::get`], this requires the key to be hashable.
+	///
+	/// Requires the `bloom` feature.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn get_hinted<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+	where
+		K: Borrow<Q>,
+		Q: Hash + Ord,
+	{
+		if self.may_contain_key(key) {
+			self.get(key)
+		} else {
+			None
+		}
+	}
+
+	/// Shares the key filter with `other`, after `moved` keys of this map were moved into it.
+	///
+	/// The copy given to `other` still contains the keys left in this map, counted as stale.
+	#[inline]
+	pub(crate) fn split_key_filter(&mut self, other: &mut Self, moved: usize) {
+		if let Some(filter) = &mut self.key_filter {
+			filter.stale += moved;
+			let mut other_filter = filter.clone();
+			other_filter.stale = self.len;
+			other.key_filter = Some(other_filter);
+			self.sync_key_filter();
+			other.sync_key_filter()
+		}
+	}
+
+	/// Rebuilds the key filter if needed.
+	#[inline]
+	pub(crate) fn sync_key_filter(&mut self) {
+		if let Some(mut filter) = self.key_filter.take() {
+			if filter.needs_rebuild(self.len) {
+				filter.rebuild(self.len, self.keys())
+			}
+
+			self.key_filter = Some(filter)
+		}
+	}
+}
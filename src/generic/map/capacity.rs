@@ -0,0 +1,236 @@
+use super::{BTreeExt, BTreeExtMut, BTreeMap, M};
+use crate::generic::node::{Address, Item, Node};
+use cc_traits::{
+	Capacity, Len, Reserve, SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut, WithCapacity,
+};
+use std::fmt;
+
+/// Error returned by [`BTreeMap::try_insert`] when the node container is full.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct CapacityError<K, V> {
+	/// Number of nodes the insertion needed to allocate.
+	pub required: usize,
+
+	/// Number of free slots in the node container.
+	pub available: usize,
+
+	/// The key that was not inserted.
+	pub key: K,
+
+	/// The value that was not inserted.
+	pub value: V,
+}
+
+impl<K, V> fmt::Display for CapacityError<K, V> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"insertion requires {} new nodes but only {} are available",
+			self.required, self.available
+		)
+	}
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> std::error::Error for CapacityError<K, V> {}
+
+/// Returns the number of nodes of a tree holding `len` items
+/// whose nodes are at least half full.
+///
+/// Nodes are split in two halves when they overflow,
+/// so that this holds for any tree built by insertions only.
+#[inline]
+fn node_count_for(len: usize) -> usize {
+	let mut count = 0;
+	let mut level = len.div_ceil(M / 2);
+	while level > 1 {
+		count += level;
+		level = level.div_ceil(M / 2);
+	}
+
+	count + level
+}
+
+impl<K, V, C> BTreeMap<K, V, C> {
+	/// Creates a new empty map with enough node slots to hold `len` items
+	/// without growing the node container.
+	///
+	/// The number of nodes is estimated from [`M`],
+	/// assuming that nodes are at least half full,
+	/// which holds when the map is built by insertions only.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<u32, u32> = BTreeMap::with_capacity(10000);
+	/// let capacity = map.stats().slab_capacity;
+	///
+	/// for i in 0..10000 {
+	///     map.insert(i, i);
+	/// }
+	///
+	/// assert_eq!(map.stats().slab_capacity, capacity);
+	/// ```
+	#[inline]
+	pub fn with_capacity(len: usize) -> Self
+	where
+		C: WithCapacity,
+	{
+		BTreeMap::with_container(C::with_capacity(node_count_for(len)))
+	}
+}
+
+impl<K, V, C: Slab<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Reserves enough node slots to insert at least `additional` more items
+	/// without growing the node container.
+	///
+	/// The number of nodes is estimated as in [`BTreeMap::with_capacity`].
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<u32, u32> = (0..100).map(|i| (i, i)).collect();
+	/// map.reserve(10000);
+	/// let capacity = map.stats().slab_capacity;
+	///
+	/// for i in 100..10100 {
+	///     map.insert(i, i);
+	/// }
+	///
+	/// assert_eq!(map.stats().slab_capacity, capacity);
+	/// ```
+	#[inline]
+	pub fn reserve(&mut self, additional: usize)
+	where
+		C: Reserve,
+	{
+		let required = node_count_for(self.len().saturating_add(additional));
+		self.nodes
+			.reserve(required.saturating_sub(self.nodes.len()))
+	}
+
+	/// Returns the number of nodes that can be allocated
+	/// without growing the node container.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use slab::Slab;
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, i32> = BTreeMap::with_container(Slab::with_capacity(4));
+	/// assert_eq!(map.spare_capacity(), 4);
+	/// map.insert(1, 1);
+	/// assert_eq!(map.spare_capacity(), 3);
+	/// ```
+	#[inline]
+	pub fn spare_capacity(&self) -> usize
+	where
+		C: Len + Capacity,
+	{
+		self.nodes.capacity().saturating_sub(self.nodes.len())
+	}
+
+	/// Returns the number of nodes allocated by the insertion of an item at the given leaf address.
+	#[inline]
+	fn insertion_node_count(&self, addr: Address) -> usize {
+		if addr.is_nowhere() {
+			return 1;
+		}
+
+		let mut count = 0;
+		let mut id = addr.id;
+		loop {
+			// A node splits when it receives an item while holding its maximum number of items.
+			let node = self.node(id);
+			let max_items = match node {
+				Node::Leaf(_) => M,
+				Node::Internal(_) => M - 1,
+			};
+
+			if node.item_count() < max_items {
+				return count;
+			}
+
+			count += 1;
+			match node.parent() {
+				Some(parent_id) => id = parent_id,
+				None => return count + 1,
+			}
+		}
+	}
+}
+
+impl<K, V, C: SlabMut<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Inserts a key-value pair into the map without growing the node container.
+	///
+	/// This behaves like [`BTreeMap::insert`],
+	/// except that the key and value are given back in a [`CapacityError`]
+	/// if the insertion needs to allocate more nodes than the container can hold
+	/// without growing.
+	/// The map is left untouched in that case.
+	/// This makes the map usable with bounded storage,
+	/// whose insertion would otherwise panic when full.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use slab::Slab;
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, i32> = BTreeMap::with_container(Slab::with_capacity(1));
+	///
+	/// // The first leaf holds 8 items.
+	/// for i in 0..8 {
+	///     assert_eq!(map.try_insert(i, i), Ok(None));
+	/// }
+	///
+	/// // Replacing a value does not allocate.
+	/// assert_eq!(map.try_insert(0, 10), Ok(Some(0)));
+	///
+	/// // The next item requires the leaf to split.
+	/// let err = map.try_insert(8, 8).unwrap_err();
+	/// assert_eq!((err.key, err.value), (8, 8));
+	/// assert_eq!(map.len(), 8);
+	/// ```
+	#[inline]
+	pub fn try_insert(&mut self, key: K, value: V) -> Result<Option<V>, CapacityError<K, V>>
+	where
+		K: Ord,
+		C: Len + Capacity,
+	{
+		let addr = self.address_of(&key);
+
+		#[cfg(feature = "metrics")]
+		self.record(super::Operation::Insert, addr.unwrap_or_else(|addr| addr));
+
+		match addr {
+			Ok(addr) => Ok(Some(self.replace_value_at(addr, value))),
+			Err(addr) => {
+				let required = self.insertion_node_count(addr);
+				let available = self.spare_capacity();
+				if required > available {
+					Err(CapacityError {
+						required,
+						available,
+						key,
+						value,
+					})
+				} else {
+					self.insert_exactly_at(addr, Item::new(key, value), None);
+					Ok(None)
+				}
+			}
+		}
+	}
+}
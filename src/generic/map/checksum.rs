@@ -0,0 +1,271 @@
+use super::{BTreeExt, BTreeMap};
+use crate::generic::node::Node;
+use cc_traits::{SimpleCollectionRef, Slab};
+use std::{
+	borrow::Borrow,
+	collections::HashMap,
+	fmt,
+	hash::{Hash, Hasher},
+};
+
+/// Error returned when a node of a sealed map does not match its recorded checksum.
+///
+/// This happens when the underlying container was modified
+/// without going through the map.
+/// It is returned by the explicit checking methods,
+/// [`BTreeMap::verify`], [`BTreeMap::verify_node`] and [`BTreeMap::checked_get`].
+/// The other methods reading the map panic with this error instead.
+///
+/// Requires the `checksum` feature.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct IntegrityError {
+	/// Identifier of the corrupted (or missing) node.
+	pub node: usize,
+}
+
+impl fmt::Display for IntegrityError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "node {} does not match its checksum", self.node)
+	}
+}
+
+impl std::error::Error for IntegrityError {}
+
+/// Recorded node checksums.
+pub(crate) struct Checksums<K, V> {
+	/// Checksum of each node of the tree.
+	nodes: HashMap<usize, u64>,
+
+	/// Node checksum function, captured when the map is sealed
+	/// since the map does not require keys and values to be hashable.
+	checksum: fn(&Node<K, V>) -> u64,
+}
+
+impl<K, V> Clone for Checksums<K, V> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Checksums {
+			nodes: self.nodes.clone(),
+			checksum: self.checksum,
+		}
+	}
+}
+
+/// 64-bit FNV-1a hasher.
+///
+/// Unlike the standard `DefaultHasher`, the produced hashes are stable across runs.
+struct Fnv(u64);
+
+impl Fnv {
+	fn new() -> Self {
+		Fnv(0xcbf29ce484222325)
+	}
+}
+
+impl Hasher for Fnv {
+	fn finish(&self) -> u64 {
+		self.0
+	}
+
+	fn write(&mut self, bytes: &[u8]) {
+		for b in bytes {
+			self.0 ^= *b as u64;
+			self.0 = self.0.wrapping_mul(0x100000001b3);
+		}
+	}
+}
+
+/// Computes the checksum of a node.
+fn checksum<K: Hash, V: Hash>(node: &Node<K, V>) -> u64 {
+	let mut hasher = Fnv::new();
+	node.parent().hash(&mut hasher);
+	node.item_count().hash(&mut hasher);
+	for offset in 0..node.item_count() {
+		let item = node.item(offset.into()).unwrap();
+		item.key().hash(&mut hasher);
+		item.value().hash(&mut hasher);
+	}
+	for child_id in node.children() {
+		child_id.hash(&mut hasher);
+	}
+	hasher.finish()
+}
+
+impl<K, V, C: Slab<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Computes the checksum of the given node.
+	///
+	/// The checksum covers the parent identifier, the items and the children identifiers of
+	/// the node.
+	///
+	/// Requires the `checksum` feature.
+	#[inline]
+	pub fn node_checksum(&self, id: usize) -> Option<u64>
+	where
+		K: Hash,
+		V: Hash,
+	{
+		self.nodes.get(id).map(|node| checksum(C::into_ref(node)))
+	}
+
+	/// Checks a node accessed in a sealed map against its recorded checksum.
+	///
+	/// `node` is the node found in the container, if any.
+	///
+	/// # Panics
+	///
+	/// Panics with an [`IntegrityError`] if the map is sealed
+	/// and the node is missing or does not match its checksum.
+	#[inline]
+	pub(crate) fn check_node(&self, id: usize, node: Option<&Node<K, V>>) {
+		if let Some(checksums) = &self.checksums {
+			let valid = match (checksums.nodes.get(&id), node) {
+				(Some(expected), Some(node)) => *expected == (checksums.checksum)(node),
+				_ => false,
+			};
+
+			if !valid {
+				panic!("{}", IntegrityError { node: id })
+			}
+		}
+	}
+
+	/// Checks if the map is sealed.
+	///
+	/// See [`BTreeMap::seal`].
+	///
+	/// Requires the `checksum` feature.
+	#[inline]
+	pub fn is_sealed(&self) -> bool {
+		self.checksums.is_some()
+	}
+
+	/// Records the checksum of every node of the tree.
+	///
+	/// The sealed map can then detect modifications of the underlying container
+	/// performed without going through the map.
+	/// Every node read by the map, for instance by [`BTreeMap::get`] or [`BTreeMap::iter`],
+	/// is checked against its checksum, and a mismatch makes the map panic
+	/// with an [`IntegrityError`] instead of reading an inconsistent tree.
+	/// Use [`BTreeMap::verify`] or [`BTreeMap::checked_get`] to get the error instead.
+	/// Any modification performed through the map removes the seal.
+	///
+	/// Reading a sealed map costs a hash of each visited node.
+	///
+	/// Requires the `checksum` feature.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, i32> = (0..100).map(|i| (i, i)).collect();
+	/// map.seal();
+	/// assert!(map.verify().is_ok());
+	/// assert_eq!(map.checked_get(&42), Ok(Some(&42)));
+	///
+	/// map.insert(100, 100);
+	/// assert!(!map.is_sealed());
+	/// ```
+	#[inline]
+	pub fn seal(&mut self)
+	where
+		K: Hash,
+		V: Hash,
+	{
+		self.unseal(); // the nodes are read unchecked while computing the new checksums.
+
+		let mut nodes = HashMap::new();
+		let mut stack: Vec<usize> = self.root.into_iter().collect();
+		while let Some(id) = stack.pop() {
+			nodes.insert(id, self.node_checksum(id).unwrap());
+			stack.extend(self.node(id).children());
+		}
+
+		self.checksums = Some(Checksums {
+			nodes,
+			checksum: checksum::<K, V>,
+		})
+	}
+
+	/// Removes the seal of the map, if any.
+	///
+	/// Requires the `checksum` feature.
+	#[inline]
+	pub fn unseal(&mut self) {
+		self.checksums = None
+	}
+
+	/// Checks that the given node matches its recorded checksum.
+	///
+	/// Always succeeds if the map is not sealed.
+	///
+	/// Requires the `checksum` feature.
+	#[inline]
+	pub fn verify_node(&self, id: usize) -> Result<(), IntegrityError>
+	where
+		K: Hash,
+		V: Hash,
+	{
+		match &self.checksums {
+			Some(checksums) => match (checksums.nodes.get(&id), self.node_checksum(id)) {
+				(Some(expected), Some(found)) if *expected == found => Ok(()),
+				_ => Err(IntegrityError { node: id }),
+			},
+			None => Ok(()),
+		}
+	}
+
+	/// Checks that every node of the tree matches its recorded checksum.
+	///
+	/// Always succeeds if the map is not sealed.
+	///
+	/// Requires the `checksum` feature.
+	#[inline]
+	pub fn verify(&self) -> Result<(), IntegrityError>
+	where
+		K: Hash,
+		V: Hash,
+	{
+		if let Some(checksums) = &self.checksums {
+			for &id in checksums.nodes.keys() {
+				self.verify_node(id)?
+			}
+		}
+
+		Ok(())
+	}
+
+	/// Returns a reference to the value corresponding to the key,
+	/// checking every visited node against its recorded checksum.
+	///
+	/// If the map is not sealed, this is equivalent to [`BTreeMap::get`].
+	///
+	/// Requires the `checksum` feature.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn checked_get<Q: ?Sized>(&self, key: &Q) -> Result<Option<&V>, IntegrityError>
+	where
+		K: Borrow<Q> + Hash,
+		V: Hash,
+		Q: Ord,
+	{
+		let mut id = match self.root {
+			Some(id) => id,
+			None => return Ok(None),
+		};
+
+		loop {
+			self.verify_node(id)?;
+			match self.nodes.get(id) {
+				Some(node) => match C::into_ref(node).get(key) {
+					Ok(value_opt) => return Ok(value_opt),
+					Err(child_id) => id = child_id,
+				},
+				None => return Err(IntegrityError { node: id }),
+			}
+		}
+	}
+}
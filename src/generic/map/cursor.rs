@@ -0,0 +1,624 @@
+use super::{BTreeExt, BTreeExtMut, BTreeMap};
+use crate::generic::node::{Address, Item, Node};
+use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
+use std::{borrow::Borrow, fmt, ops::Bound};
+
+/// Returns the cursor position corresponding to the given address.
+///
+/// The position is the address of the item following the cursor,
+/// or the last valid address of the tree if the cursor is at the end.
+#[inline]
+#[allow(clippy::multiple_bound_locations)]
+fn position<K, V, C: Slab<Node<K, V>>>(map: &BTreeMap<K, V, C>, addr: Address) -> Address
+where
+	C: SimpleCollectionRef,
+{
+	map.normalize(addr)
+		.unwrap_or_else(|| map.last_valid_address())
+}
+
+/// Returns the address of the first item following the given bound.
+///
+/// Keys equal to the bound key are preceding the bound if `equal_before` is `true`.
+#[inline]
+#[allow(clippy::multiple_bound_locations)]
+fn bound_address<K, V, C: Slab<Node<K, V>>, Q: ?Sized>(
+	map: &BTreeMap<K, V, C>,
+	bound: Bound<&Q>,
+	equal_before: bool,
+	unbounded: Address,
+) -> Address
+where
+	C: SimpleCollectionRef,
+	K: Borrow<Q>,
+	Q: Ord,
+{
+	match bound {
+		Bound::Unbounded => unbounded,
+		Bound::Included(key) | Bound::Excluded(key) => {
+			let equal_before = match bound {
+				Bound::Included(_) => equal_before,
+				_ => !equal_before,
+			};
+
+			match map.address_of(key) {
+				Ok(addr) if equal_before => map.next_item_or_back_address(addr).unwrap(),
+				Ok(addr) => addr,
+				Err(addr) => addr,
+			}
+		}
+	}
+}
+
+/// Returns the address of the item following the cursor, if any.
+#[inline]
+#[allow(clippy::multiple_bound_locations)]
+fn next_address<K, V, C: Slab<Node<K, V>>>(
+	map: &BTreeMap<K, V, C>,
+	addr: Address,
+) -> Option<Address>
+where
+	C: SimpleCollectionRef,
+{
+	// The cursor of an empty map is nowhere.
+	if addr.is_nowhere() || map.item(addr).is_none() {
+		None
+	} else {
+		Some(addr)
+	}
+}
+
+/// A cursor over a [`BTreeMap`].
+///
+/// A cursor points to a gap between two entries of the map
+/// (or before the first entry, or after the last one),
+/// and can move in both directions.
+/// Since it holds the address of the following entry,
+/// moving the cursor or accessing its neighbors does not search the tree.
+///
+/// This `struct` is created by the [`cursor_front`](`BTreeMap#method.cursor_front`)
+/// and [`cursor_back`](`BTreeMap#method.cursor_back`) methods on [`BTreeMap`].
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::BTreeMap;
+///
+/// let map: BTreeMap<_, _> = [(1, 'a'), (2, 'b'), (3, 'c')].into_iter().collect();
+/// let mut cursor = map.cursor_front();
+///
+/// assert_eq!(cursor.peek_prev(), None);
+/// assert_eq!(cursor.next(), Some((&1, &'a')));
+/// assert_eq!(cursor.next(), Some((&2, &'b')));
+/// assert_eq!(cursor.prev(), Some((&2, &'b')));
+/// assert_eq!(cursor.peek_prev(), Some((&1, &'a')));
+/// ```
+pub struct Cursor<'a, K, V, C> {
+	map: &'a BTreeMap<K, V, C>,
+
+	/// Address of the next item, or last valid address.
+	addr: Address,
+}
+
+impl<'a, K, V, C> Clone for Cursor<'a, K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Cursor {
+			map: self.map,
+			addr: self.addr,
+		}
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<K, V>>> Cursor<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Creates a new cursor placed just before the item at the given address.
+	#[inline]
+	pub(crate) fn new(map: &'a BTreeMap<K, V, C>, addr: Address) -> Self {
+		Cursor {
+			addr: position(map, addr),
+			map,
+		}
+	}
+
+	/// Returns the address of the entry following the cursor,
+	/// or the last valid address of the tree if the cursor is at the end.
+	#[inline]
+	pub fn address(&self) -> Address {
+		self.addr
+	}
+
+	/// Returns the entry following the cursor without moving it.
+	#[inline]
+	pub fn peek_next(&self) -> Option<(&'a K, &'a V)> {
+		let addr = next_address(self.map, self.addr)?;
+		self.map.item(addr).map(Item::as_pair)
+	}
+
+	/// Returns the entry preceding the cursor without moving it.
+	#[inline]
+	pub fn peek_prev(&self) -> Option<(&'a K, &'a V)> {
+		let addr = self.map.previous_item_address(self.addr)?;
+		self.map.item(addr).map(Item::as_pair)
+	}
+
+	/// Moves the cursor over the next entry and returns it.
+	///
+	/// Returns `None` and leaves the cursor unchanged if it is at the end of the map.
+	#[allow(clippy::should_implement_trait)]
+	#[inline]
+	pub fn next(&mut self) -> Option<(&'a K, &'a V)> {
+		let addr = next_address(self.map, self.addr)?;
+		self.addr = self.map.next_item_or_back_address(addr).unwrap();
+		self.map.item(addr).map(Item::as_pair)
+	}
+
+	/// Moves the cursor over the previous entry and returns it.
+	///
+	/// Returns `None` and leaves the cursor unchanged if it is at the start of the map.
+	#[inline]
+	pub fn prev(&mut self) -> Option<(&'a K, &'a V)> {
+		let addr = self.map.previous_item_address(self.addr)?;
+		self.addr = addr;
+		self.map.item(addr).map(Item::as_pair)
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Debug for Cursor<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Cursor")
+			.field("prev", &self.peek_prev())
+			.field("next", &self.peek_next())
+			.finish()
+	}
+}
+
+/// A mutable cursor over a [`BTreeMap`].
+///
+/// Like a [`Cursor`], it points to a gap between two entries of the map,
+/// but it also allows modifying the values of the entries around it,
+/// and inserting or removing entries at its position without searching the tree.
+///
+/// This `struct` is created by the [`cursor_front_mut`](`BTreeMap#method.cursor_front_mut`)
+/// and [`cursor_back_mut`](`BTreeMap#method.cursor_back_mut`) methods on [`BTreeMap`].
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::BTreeMap;
+///
+/// let mut map: BTreeMap<_, _> = [(1, 'a'), (4, 'd')].into_iter().collect();
+/// let mut cursor = map.cursor_front_mut();
+///
+/// cursor.next();
+/// cursor.insert_before(2, 'b').unwrap();
+/// cursor.insert_after(3, 'c').unwrap();
+/// assert!(cursor.insert_after(5, 'e').is_err());
+/// assert_eq!(cursor.remove_next(), Some((3, 'c')));
+/// if let Some((_, value)) = cursor.next() {
+///     *value = 'D';
+/// }
+///
+/// assert!(map.into_iter().eq([(1, 'a'), (2, 'b'), (4, 'D')]));
+/// ```
+pub struct CursorMut<'a, K, V, C> {
+	map: &'a mut BTreeMap<K, V, C>,
+
+	/// Address of the next item, or last valid address.
+	addr: Address,
+}
+
+impl<'a, K, V, C: SlabMut<Node<K, V>>> CursorMut<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Creates a new cursor placed just before the item at the given address.
+	#[inline]
+	pub(crate) fn new(map: &'a mut BTreeMap<K, V, C>, addr: Address) -> Self {
+		CursorMut {
+			addr: position(map, addr),
+			map,
+		}
+	}
+
+	/// Returns the address of the entry following the cursor,
+	/// or the last valid address of the tree if the cursor is at the end.
+	#[inline]
+	pub fn address(&self) -> Address {
+		self.addr
+	}
+
+	/// Returns a read-only cursor at the same position.
+	#[inline]
+	pub fn as_cursor(&self) -> Cursor<'_, K, V, C> {
+		Cursor {
+			map: self.map,
+			addr: self.addr,
+		}
+	}
+
+	/// Returns the entry following the cursor without moving it.
+	#[inline]
+	pub fn peek_next(&mut self) -> Option<(&K, &mut V)> {
+		let addr = next_address(self.map, self.addr)?;
+		self.map.item_mut(addr).map(|item| {
+			let (key, value) = item.as_pair_mut();
+			(&*key, value)
+		})
+	}
+
+	/// Returns the entry preceding the cursor without moving it.
+	#[inline]
+	pub fn peek_prev(&mut self) -> Option<(&K, &mut V)> {
+		let addr = self.map.previous_item_address(self.addr)?;
+		self.map.item_mut(addr).map(|item| {
+			let (key, value) = item.as_pair_mut();
+			(&*key, value)
+		})
+	}
+
+	/// Moves the cursor over the next entry and returns it.
+	///
+	/// Returns `None` and leaves the cursor unchanged if it is at the end of the map.
+	#[allow(clippy::should_implement_trait)]
+	#[inline]
+	pub fn next(&mut self) -> Option<(&K, &mut V)> {
+		let addr = next_address(self.map, self.addr)?;
+		self.addr = self.map.next_item_or_back_address(addr).unwrap();
+		self.map.item_mut(addr).map(|item| {
+			let (key, value) = item.as_pair_mut();
+			(&*key, value)
+		})
+	}
+
+	/// Moves the cursor over the previous entry and returns it.
+	///
+	/// Returns `None` and leaves the cursor unchanged if it is at the start of the map.
+	#[inline]
+	pub fn prev(&mut self) -> Option<(&K, &mut V)> {
+		let addr = self.map.previous_item_address(self.addr)?;
+		self.addr = addr;
+		self.map.item_mut(addr).map(|item| {
+			let (key, value) = item.as_pair_mut();
+			(&*key, value)
+		})
+	}
+
+	/// Inserts a new entry after the cursor, without checking the order of the keys.
+	///
+	/// The cursor is placed before the new entry.
+	///
+	/// # Correctness
+	///
+	/// The key must be strictly greater than the key before the cursor
+	/// and strictly less than the key after the cursor.
+	/// Otherwise the tree is no longer well-formed and the behavior of every
+	/// other method of the map is unspecified.
+	#[inline]
+	pub fn insert_after_unchecked(&mut self, key: K, value: V) {
+		self.addr = self.map.insert_at(self.addr, Item::new(key, value))
+	}
+
+	/// Inserts a new entry before the cursor, without checking the order of the keys.
+	///
+	/// The cursor is placed after the new entry.
+	///
+	/// # Correctness
+	///
+	/// The key must be strictly greater than the key before the cursor
+	/// and strictly less than the key after the cursor.
+	/// Otherwise the tree is no longer well-formed and the behavior of every
+	/// other method of the map is unspecified.
+	#[inline]
+	pub fn insert_before_unchecked(&mut self, key: K, value: V) {
+		let addr = self.map.insert_at(self.addr, Item::new(key, value));
+		self.addr = self.map.next_item_or_back_address(addr).unwrap()
+	}
+
+	/// Checks that the given key fits at the cursor position.
+	#[inline]
+	fn fits(&self, key: &K) -> bool
+	where
+		K: Ord,
+	{
+		let cursor = self.as_cursor();
+		cursor.peek_prev().is_none_or(|(prev, _)| prev < key)
+			&& cursor.peek_next().is_none_or(|(next, _)| key < next)
+	}
+
+	/// Inserts a new entry after the cursor.
+	///
+	/// The cursor is placed before the new entry.
+	/// If the key is not strictly between the keys around the cursor,
+	/// the entry is not inserted and is returned in the error.
+	#[inline]
+	pub fn insert_after(&mut self, key: K, value: V) -> Result<(), UnorderedKeyError<K, V>>
+	where
+		K: Ord,
+	{
+		if self.fits(&key) {
+			self.insert_after_unchecked(key, value);
+			Ok(())
+		} else {
+			Err(UnorderedKeyError { key, value })
+		}
+	}
+
+	/// Inserts a new entry before the cursor.
+	///
+	/// The cursor is placed after the new entry.
+	/// If the key is not strictly between the keys around the cursor,
+	/// the entry is not inserted and is returned in the error.
+	#[inline]
+	pub fn insert_before(&mut self, key: K, value: V) -> Result<(), UnorderedKeyError<K, V>>
+	where
+		K: Ord,
+	{
+		if self.fits(&key) {
+			self.insert_before_unchecked(key, value);
+			Ok(())
+		} else {
+			Err(UnorderedKeyError { key, value })
+		}
+	}
+
+	/// Removes the entry following the cursor and returns it.
+	///
+	/// The cursor does not move.
+	#[inline]
+	pub fn remove_next(&mut self) -> Option<(K, V)> {
+		let addr = next_address(self.map, self.addr)?;
+		let (item, addr) = self.map.remove_at(addr).unwrap();
+		self.addr = position(self.map, addr);
+		Some(item.into_pair())
+	}
+
+	/// Removes the entry preceding the cursor and returns it.
+	///
+	/// The cursor does not move.
+	#[inline]
+	pub fn remove_prev(&mut self) -> Option<(K, V)> {
+		let addr = self.map.previous_item_address(self.addr)?;
+		let (item, addr) = self.map.remove_at(addr).unwrap();
+		self.addr = position(self.map, addr);
+		Some(item.into_pair())
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: SlabMut<Node<K, V>>> fmt::Debug for CursorMut<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let cursor = self.as_cursor();
+		f.debug_struct("CursorMut")
+			.field("prev", &cursor.peek_prev())
+			.field("next", &cursor.peek_next())
+			.finish()
+	}
+}
+
+/// Error returned by [`CursorMut::insert_after`] and [`CursorMut::insert_before`]
+/// when the key does not fit at the cursor position.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct UnorderedKeyError<K, V> {
+	/// The key that was not inserted.
+	pub key: K,
+
+	/// The value that was not inserted.
+	pub value: V,
+}
+
+impl<K, V> fmt::Display for UnorderedKeyError<K, V> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "key is not properly ordered relative to neighbors")
+	}
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> std::error::Error for UnorderedKeyError<K, V> {}
+
+impl<K, V, C: Slab<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Returns a cursor placed before the first entry of the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let map: BTreeMap<_, _> = (0..100).map(|i| (i, i)).collect();
+	/// let mut cursor = map.cursor_front();
+	/// assert_eq!(cursor.peek_prev(), None);
+	/// assert_eq!(cursor.next(), Some((&0, &0)));
+	/// ```
+	#[inline]
+	pub fn cursor_front(&self) -> Cursor<'_, K, V, C> {
+		Cursor::new(self, self.first_back_address())
+	}
+
+	/// Returns a cursor placed after the last entry of the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let map: BTreeMap<_, _> = (0..100).map(|i| (i, i)).collect();
+	/// let mut cursor = map.cursor_back();
+	/// assert_eq!(cursor.peek_next(), None);
+	/// assert_eq!(cursor.prev(), Some((&99, &99)));
+	/// ```
+	#[inline]
+	pub fn cursor_back(&self) -> Cursor<'_, K, V, C> {
+		Cursor::new(self, self.last_valid_address())
+	}
+
+	/// Returns a cursor placed before the first entry above the given bound.
+	///
+	/// With `Bound::Included(key)`, the cursor is placed before the first entry
+	/// whose key is greater than or equal to `key`.
+	/// With `Bound::Excluded(key)`, it is placed before the first entry
+	/// whose key is greater than `key`.
+	/// With `Bound::Unbounded`, it is placed before the first entry of the map.
+	///
+	/// The first entry above the bound is given by [`Cursor::peek_next`].
+	///
+	/// # Example
+	///
+	/// ```
+	/// use std::ops::Bound;
+	/// use btree_slab::BTreeMap;
+	///
+	/// let map: BTreeMap<_, _> = [(1, 'a'), (2, 'b'), (3, 'c'), (4, 'd')].into_iter().collect();
+	///
+	/// let cursor = map.lower_bound(Bound::Included(&2));
+	/// assert_eq!(cursor.peek_prev(), Some((&1, &'a')));
+	/// assert_eq!(cursor.peek_next(), Some((&2, &'b')));
+	///
+	/// let cursor = map.lower_bound(Bound::Excluded(&2));
+	/// assert_eq!(cursor.peek_next(), Some((&3, &'c')));
+	///
+	/// let cursor = map.lower_bound(Bound::Excluded(&4));
+	/// assert_eq!(cursor.peek_next(), None);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn lower_bound<Q: ?Sized>(&self, bound: Bound<&Q>) -> Cursor<'_, K, V, C>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		Cursor::new(
+			self,
+			bound_address(self, bound, false, self.first_back_address()),
+		)
+	}
+
+	/// Returns a cursor placed after the last entry below the given bound.
+	///
+	/// With `Bound::Included(key)`, the cursor is placed after the last entry
+	/// whose key is less than or equal to `key`.
+	/// With `Bound::Excluded(key)`, it is placed after the last entry
+	/// whose key is less than `key`.
+	/// With `Bound::Unbounded`, it is placed after the last entry of the map.
+	///
+	/// The last entry below the bound is given by [`Cursor::peek_prev`].
+	///
+	/// # Example
+	///
+	/// ```
+	/// use std::ops::Bound;
+	/// use btree_slab::BTreeMap;
+	///
+	/// let map: BTreeMap<_, _> = [(1, 'a'), (2, 'b'), (3, 'c'), (4, 'd')].into_iter().collect();
+	///
+	/// let cursor = map.upper_bound(Bound::Included(&3));
+	/// assert_eq!(cursor.peek_prev(), Some((&3, &'c')));
+	/// assert_eq!(cursor.peek_next(), Some((&4, &'d')));
+	///
+	/// let cursor = map.upper_bound(Bound::Excluded(&3));
+	/// assert_eq!(cursor.peek_prev(), Some((&2, &'b')));
+	///
+	/// let cursor = map.upper_bound(Bound::Excluded(&1));
+	/// assert_eq!(cursor.peek_prev(), None);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn upper_bound<Q: ?Sized>(&self, bound: Bound<&Q>) -> Cursor<'_, K, V, C>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		Cursor::new(
+			self,
+			bound_address(self, bound, true, self.last_valid_address()),
+		)
+	}
+}
+
+impl<K, V, C: SlabMut<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Returns a mutable cursor placed before the first entry of the map.
+	#[inline]
+	pub fn cursor_front_mut(&mut self) -> CursorMut<'_, K, V, C> {
+		let addr = self.first_back_address();
+		CursorMut::new(self, addr)
+	}
+
+	/// Returns a mutable cursor placed after the last entry of the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map = BTreeMap::new();
+	/// let mut cursor = map.cursor_back_mut();
+	/// for i in 0..100 {
+	///     cursor.insert_before(i, i).unwrap();
+	/// }
+	///
+	/// assert!(map.into_iter().eq((0..100).map(|i| (i, i))));
+	/// ```
+	#[inline]
+	pub fn cursor_back_mut(&mut self) -> CursorMut<'_, K, V, C> {
+		let addr = self.last_valid_address();
+		CursorMut::new(self, addr)
+	}
+
+	/// Returns a mutable cursor placed before the first entry above the given bound.
+	///
+	/// See [`BTreeMap::lower_bound`].
+	///
+	/// # Example
+	///
+	/// ```
+	/// use std::ops::Bound;
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<_, _> = [(1, 'a'), (3, 'c')].into_iter().collect();
+	///
+	/// let mut cursor = map.lower_bound_mut(Bound::Excluded(&1));
+	/// cursor.insert_before(2, 'b').unwrap();
+	///
+	/// assert!(map.into_iter().eq([(1, 'a'), (2, 'b'), (3, 'c')]));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn lower_bound_mut<Q: ?Sized>(&mut self, bound: Bound<&Q>) -> CursorMut<'_, K, V, C>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let addr = bound_address(self, bound, false, self.first_back_address());
+		CursorMut::new(self, addr)
+	}
+
+	/// Returns a mutable cursor placed after the last entry below the given bound.
+	///
+	/// See [`BTreeMap::upper_bound`].
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn upper_bound_mut<Q: ?Sized>(&mut self, bound: Bound<&Q>) -> CursorMut<'_, K, V, C>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let addr = bound_address(self, bound, true, self.last_valid_address());
+		CursorMut::new(self, addr)
+	}
+}
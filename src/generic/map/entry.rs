@@ -3,7 +3,7 @@ use crate::generic::{
 	node::{Address, Item, Node},
 };
 use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
-use std::fmt;
+use std::{borrow::Borrow, fmt};
 
 /// A view into a single entry in a map, which may either be vacant or occupied.
 ///
@@ -179,6 +179,30 @@ where
 			Vacant(entry) => entry.insert(Default::default()),
 		}
 	}
+
+	/// Sets the value of the entry, and returns an [`OccupiedEntry`].
+	///
+	/// # Examples
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<&str, String> = BTreeMap::new();
+	/// let entry = map.entry("poneyland").insert_entry("hoho".to_string());
+	///
+	/// assert_eq!(entry.key(), &"poneyland");
+	/// assert_eq!(entry.get(), "hoho");
+	/// ```
+	#[inline]
+	pub fn insert_entry(self, value: V) -> OccupiedEntry<'a, K, V, C> {
+		match self {
+			Occupied(mut entry) => {
+				entry.insert(value);
+				entry
+			}
+			Vacant(entry) => entry.insert_entry(value),
+		}
+	}
 }
 
 impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Debug for Entry<'a, K, V, C>
@@ -267,6 +291,31 @@ where
 		let addr = self.map.insert_at(self.addr, Item::new(self.key, value));
 		self.map.item_mut(addr).unwrap().value_mut()
 	}
+
+	/// Sets the value of the entry with the `VacantEntry`'s key,
+	/// and returns an [`OccupiedEntry`].
+	///
+	/// ## Example
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use btree_slab::generic::map::Entry;
+	///
+	/// let mut map: BTreeMap<&str, u32> = BTreeMap::new();
+	///
+	/// if let Entry::Vacant(o) = map.entry("poneyland") {
+	///     let entry = o.insert_entry(37);
+	///     assert_eq!(entry.remove_entry(), ("poneyland", 37));
+	/// }
+	/// assert!(map.is_empty());
+	/// ```
+	#[inline]
+	pub fn insert_entry(self, value: V) -> OccupiedEntry<'a, K, V, C> {
+		let addr = self.map.insert_at(self.addr, Item::new(self.key, value));
+		OccupiedEntry {
+			map: self.map,
+			addr,
+		}
+	}
 }
 
 impl<'a, K: fmt::Debug, V, C: Slab<Node<K, V>>> fmt::Debug for VacantEntry<'a, K, V, C> {
@@ -276,6 +325,184 @@ impl<'a, K: fmt::Debug, V, C: Slab<Node<K, V>>> fmt::Debug for VacantEntry<'a, K
 	}
 }
 
+/// A vacant slot of a [`BTreeMap`], detached from the map.
+///
+/// This is constructed from the [`vacant_slot`](`BTreeMap#vacant_slot`) method on [`BTreeMap`].
+/// Contrarily to [`VacantEntry`], it does not borrow the map.
+pub struct VacantSlot<K> {
+	pub(crate) key: K,
+	pub(crate) addr: Address,
+	pub(crate) generation: usize,
+}
+
+impl<K> VacantSlot<K> {
+	/// Gets the address of the slot at the time it was created.
+	#[inline]
+	pub fn address(&self) -> Address {
+		self.addr
+	}
+
+	/// Gets a reference to the key of the slot.
+	#[inline]
+	pub fn key(&self) -> &K {
+		&self.key
+	}
+
+	/// Take ownership of the key.
+	#[inline]
+	pub fn into_key(self) -> K {
+		self.key
+	}
+
+	/// Inserts the given value in the slot,
+	/// and returns a mutable reference to it.
+	///
+	/// The slot must have been created from the given map.
+	/// If the map has been modified since the slot creation,
+	/// the key is searched again.
+	/// If the key has been inserted in the meantime,
+	/// or if the slot is not a valid place for the key in the map
+	/// (because it was created from another map),
+	/// the key and the value are returned as an error.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<&str, usize> = BTreeMap::new();
+	/// let slot = map.vacant_slot("poneyland").unwrap();
+	/// map.insert("poneyland", 1);
+	/// assert_eq!(slot.insert(&mut map, 2), Err(("poneyland", 2)));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn insert<V, C: SlabMut<Node<K, V>>>(
+		self,
+		map: &mut BTreeMap<K, V, C>,
+		value: V,
+	) -> Result<&mut V, (K, V)>
+	where
+		K: Ord,
+		C: SimpleCollectionRef,
+		C: SimpleCollectionMut,
+	{
+		let addr = if self.generation == map.generation {
+			if !self.fits(map) {
+				return Err((self.key, value));
+			}
+
+			self.addr
+		} else {
+			match map.address_of(&self.key) {
+				Ok(_) => return Err((self.key, value)),
+				Err(addr) => addr,
+			}
+		};
+
+		let addr = map.insert_exactly_at(addr, Item::new(self.key, value), None);
+		Ok(map.item_mut(addr).unwrap().value_mut())
+	}
+}
+
+impl<K: Ord> VacantSlot<K> {
+	/// Checks that the slot is the place of its key in the given map.
+	///
+	/// This is the case if it is a leaf address
+	/// whose surrounding items are ordered around the key.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	fn fits<V, C: Slab<Node<K, V>>>(&self, map: &BTreeMap<K, V, C>) -> bool
+	where
+		C: SimpleCollectionRef,
+	{
+		if self.addr.is_nowhere() {
+			return map.is_empty();
+		}
+
+		let in_leaf = map
+			.nodes
+			.get(self.addr.id)
+			.is_some_and(|node| match C::into_ref(node) {
+				Node::Leaf(leaf) => self
+					.addr
+					.offset
+					.value()
+					.is_some_and(|offset| offset <= leaf.item_count()),
+				Node::Internal(_) => false,
+			});
+
+		in_leaf
+			&& map
+				.previous_item_address(self.addr)
+				.is_none_or(|prev| map.item(prev).unwrap().key() < &self.key)
+			&& map
+				.normalize(self.addr)
+				.is_none_or(|next| map.item(next).unwrap().key() > &self.key)
+	}
+}
+
+impl<K: fmt::Debug> fmt::Debug for VacantSlot<K> {
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("VacantSlot").field(self.key()).finish()
+	}
+}
+
+/// A persistent handle to an entry of a [`BTreeMap`], detached from the map.
+///
+/// This is constructed from the [`handle`](`BTreeMap#method.handle`) method on [`BTreeMap`],
+/// and resolved into the current address of the entry with
+/// [`resolve`](`BTreeMap#method.resolve`).
+/// Contrarily to an [`Address`], a handle survives insertions and removals in the map:
+/// it caches the address of the entry along with the generation of the map,
+/// and searches the key again only if the map has been structurally modified since.
+pub struct EntryHandle<K> {
+	pub(crate) key: K,
+	pub(crate) addr: Address,
+	pub(crate) generation: usize,
+}
+
+impl<K> EntryHandle<K> {
+	/// Gets the address of the entry at the time it was last resolved.
+	///
+	/// This address may be outdated: use [`BTreeMap::resolve`] to get the current address.
+	#[inline]
+	pub fn cached_address(&self) -> Address {
+		self.addr
+	}
+
+	/// Gets a reference to the key of the entry.
+	#[inline]
+	pub fn key(&self) -> &K {
+		&self.key
+	}
+
+	/// Take ownership of the key.
+	#[inline]
+	pub fn into_key(self) -> K {
+		self.key
+	}
+}
+
+impl<K: Clone> Clone for EntryHandle<K> {
+	#[inline]
+	fn clone(&self) -> Self {
+		EntryHandle {
+			key: self.key.clone(),
+			addr: self.addr,
+			generation: self.generation,
+		}
+	}
+}
+
+impl<K: fmt::Debug> fmt::Debug for EntryHandle<K> {
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("EntryHandle").field(self.key()).finish()
+	}
+}
+
 /// A view into an occupied entry in a [`BTreeMap`].
 /// It is part of the [`Entry`] enum.
 pub struct OccupiedEntry<'a, K, V, C = slab::Slab<Node<K, V>>> {
@@ -326,6 +553,72 @@ where
 	pub fn key(&self) -> &K {
 		self.map.item(self.addr).unwrap().key()
 	}
+
+	/// Moves the entry to the next entry of the map, without any key lookup.
+	///
+	/// Returns the entry unchanged in `Err` if this is the last entry of the map.
+	///
+	/// # Example
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use btree_slab::generic::map::Entry;
+	///
+	/// let mut map: BTreeMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+	///
+	/// if let Entry::Occupied(mut o) = map.entry(7) {
+	///     // Double every value from 7 onward.
+	///     loop {
+	///         *o.get_mut() *= 2;
+	///         o = match o.next() {
+	///             Ok(next) => next,
+	///             Err(_) => break,
+	///         }
+	///     }
+	/// }
+	///
+	/// assert_eq!(map[&6], 6);
+	/// assert_eq!(map[&9], 18);
+	/// ```
+	#[inline]
+	pub fn next(self) -> Result<Self, Self> {
+		match self.map.next_item_address(self.addr) {
+			Some(addr) => Ok(OccupiedEntry {
+				map: self.map,
+				addr,
+			}),
+			None => Err(self),
+		}
+	}
+
+	/// Moves the entry to the previous entry of the map, without any key lookup.
+	///
+	/// Returns the entry unchanged in `Err` if this is the first entry of the map.
+	///
+	/// # Example
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use btree_slab::generic::map::Entry;
+	///
+	/// let mut map: BTreeMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+	///
+	/// if let Entry::Occupied(o) = map.entry(5) {
+	///     let o = o.prev().unwrap();
+	///     assert_eq!(o.key(), &4);
+	///     assert_eq!(o.remove(), 4);
+	/// }
+	///
+	/// assert!(!map.contains_key(&4));
+	/// ```
+	#[inline]
+	pub fn prev(self) -> Result<Self, Self> {
+		match self.map.previous_item_address(self.addr) {
+			Some(addr) => Ok(OccupiedEntry {
+				map: self.map,
+				addr,
+			}),
+			None => Err(self),
+		}
+	}
 }
 
 impl<'a, K, V, C: SlabMut<Node<K, V>>> OccupiedEntry<'a, K, V, C>
@@ -452,6 +745,37 @@ where
 	pub fn remove_entry(self) -> (K, V) {
 		self.map.remove_at(self.addr).unwrap().0.into_pair()
 	}
+
+	/// Take ownership of the key and value from the map,
+	/// and returns the address of the item following the removed one, if any.
+	///
+	/// The returned address is normalized (see [`BTreeExt::normalize`])
+	/// and accounts for any rebalancing caused by the removal,
+	/// so it can be directly used for further positional operations.
+	///
+	/// # Example
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use btree_slab::generic::map::{BTreeExt, Entry};
+	///
+	/// let mut map: BTreeMap<i32, &str> = (0..10).map(|i| (i, "a")).collect();
+	///
+	/// if let Entry::Occupied(o) = map.entry(4) {
+	///     let ((key, _), next) = o.remove_entry_with_address();
+	///     assert_eq!(key, 4);
+	///     assert_eq!(map.item(next.unwrap()).unwrap().key(), &5);
+	/// }
+	///
+	/// if let Entry::Occupied(o) = map.entry(9) {
+	///     let (_, next) = o.remove_entry_with_address();
+	///     assert_eq!(next, None);
+	/// }
+	/// ```
+	#[inline]
+	pub fn remove_entry_with_address(self) -> ((K, V), Option<Address>) {
+		let (item, addr) = self.map.remove_at(self.addr).unwrap();
+		(item.into_pair(), self.map.normalize(addr))
+	}
 }
 
 impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Debug
@@ -467,3 +791,413 @@ where
 			.finish()
 	}
 }
+
+/// Error returned by [`BTreeMap::try_insert_vacant`] when the key is already in the map.
+///
+/// Contains the occupied entry, and the value that was not inserted.
+pub struct OccupiedError<'a, K, V, C = slab::Slab<Node<K, V>>> {
+	/// The entry in the map that was already occupied.
+	pub entry: OccupiedEntry<'a, K, V, C>,
+
+	/// The value which was not inserted, because the entry was already occupied.
+	pub value: V,
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Debug
+	for OccupiedError<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("OccupiedError")
+			.field("key", self.entry.key())
+			.field("old_value", self.entry.get())
+			.field("new_value", &self.value)
+			.finish()
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Display
+	for OccupiedError<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(
+			f,
+			"failed to insert {:?}, key {:?} already exists with value {:?}",
+			self.value,
+			self.entry.key(),
+			self.entry.get(),
+		)
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, V>>> std::error::Error
+	for OccupiedError<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+}
+
+/// A view into a single entry in a map, which may either be vacant or occupied,
+/// looked up from a borrowed key.
+///
+/// This enum is constructed from the [`entry_ref`](`BTreeMap#method.entry_ref`) method on [`BTreeMap`].
+/// The borrowed key is only converted into an owned key when a value is inserted.
+pub enum EntryRef<'a, 'b, K, Q: ?Sized, V, C = slab::Slab<Node<K, V>>> {
+	Vacant(VacantEntryRef<'a, 'b, K, Q, V, C>),
+	Occupied(OccupiedEntry<'a, K, V, C>),
+}
+
+impl<'a, 'b, K, Q: ?Sized, V, C: Slab<Node<K, V>>> EntryRef<'a, 'b, K, Q, V, C>
+where
+	C: SimpleCollectionRef,
+	K: Borrow<Q>,
+{
+	/// Gets the address of the entry in the B-Tree.
+	#[inline]
+	pub fn address(&self) -> Address {
+		match self {
+			EntryRef::Occupied(entry) => entry.address(),
+			EntryRef::Vacant(entry) => entry.address(),
+		}
+	}
+
+	/// Returns a reference to this entry's key.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<String, usize> = BTreeMap::new();
+	/// assert_eq!(map.entry_ref("poneyland").key(), "poneyland");
+	/// ```
+	#[inline]
+	pub fn key(&self) -> &Q {
+		match self {
+			EntryRef::Occupied(entry) => entry.key().borrow(),
+			EntryRef::Vacant(entry) => entry.key(),
+		}
+	}
+}
+
+impl<'a, 'b, K, Q: ?Sized, V, C: SlabMut<Node<K, V>>> EntryRef<'a, 'b, K, Q, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+	Q: ToOwned<Owned = K>,
+{
+	/// Ensures a value is in the entry by inserting the default if empty, and returns
+	/// a mutable reference to the value in the entry.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<String, usize> = BTreeMap::new();
+	/// map.entry_ref("poneyland").or_insert(12);
+	///
+	/// assert_eq!(map["poneyland"], 12);
+	/// ```
+	#[inline]
+	pub fn or_insert(self, default: V) -> &'a mut V {
+		match self {
+			EntryRef::Occupied(entry) => entry.into_mut(),
+			EntryRef::Vacant(entry) => entry.insert(default),
+		}
+	}
+
+	/// Ensures a value is in the entry by inserting the result of the default function if empty,
+	/// and returns a mutable reference to the value in the entry.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<String, usize> = BTreeMap::new();
+	/// map.entry_ref("poneyland").or_insert_with(|| 12);
+	///
+	/// assert_eq!(map["poneyland"], 12);
+	/// ```
+	#[inline]
+	pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+		match self {
+			EntryRef::Occupied(entry) => entry.into_mut(),
+			EntryRef::Vacant(entry) => entry.insert(default()),
+		}
+	}
+
+	/// Ensures a value is in the entry by inserting, if empty, the result of the default function,
+	/// which takes the borrowed key as its argument, and returns a mutable reference to the value
+	/// in the entry.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<String, usize> = BTreeMap::new();
+	/// map.entry_ref("poneyland").or_insert_with_key(|key| key.chars().count());
+	///
+	/// assert_eq!(map["poneyland"], 9);
+	/// ```
+	#[inline]
+	pub fn or_insert_with_key<F: FnOnce(&Q) -> V>(self, default: F) -> &'a mut V {
+		match self {
+			EntryRef::Occupied(entry) => entry.into_mut(),
+			EntryRef::Vacant(entry) => {
+				let value = default(entry.key());
+				entry.insert(value)
+			}
+		}
+	}
+
+	/// Provides in-place mutable access to an occupied entry before any
+	/// potential inserts into the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<String, usize> = BTreeMap::new();
+	///
+	/// map.entry_ref("poneyland")
+	///    .and_modify(|e| { *e += 1 })
+	///    .or_insert(42);
+	/// assert_eq!(map["poneyland"], 42);
+	///
+	/// map.entry_ref("poneyland")
+	///    .and_modify(|e| { *e += 1 })
+	///    .or_insert(42);
+	/// assert_eq!(map["poneyland"], 43);
+	/// ```
+	#[inline]
+	pub fn and_modify<F>(self, f: F) -> Self
+	where
+		F: FnOnce(&mut V),
+	{
+		match self {
+			EntryRef::Occupied(mut entry) => {
+				f(entry.get_mut());
+				EntryRef::Occupied(entry)
+			}
+			EntryRef::Vacant(entry) => EntryRef::Vacant(entry),
+		}
+	}
+
+	/// Ensures a value is in the entry by inserting the default value if empty,
+	/// and returns a mutable reference to the value in the entry.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<String, Option<usize>> = BTreeMap::new();
+	/// map.entry_ref("poneyland").or_default();
+	///
+	/// assert_eq!(map["poneyland"], None);
+	/// ```
+	#[inline]
+	pub fn or_default(self) -> &'a mut V
+	where
+		V: Default,
+	{
+		match self {
+			EntryRef::Occupied(entry) => entry.into_mut(),
+			EntryRef::Vacant(entry) => entry.insert(Default::default()),
+		}
+	}
+
+	/// Sets the value of the entry, and returns an [`OccupiedEntry`].
+	///
+	/// The borrowed key is only converted into an owned key if the entry is vacant.
+	///
+	/// # Examples
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<String, String> = BTreeMap::new();
+	/// let entry = map.entry_ref("poneyland").insert_entry("hoho".to_string());
+	///
+	/// assert_eq!(entry.key(), "poneyland");
+	/// assert_eq!(entry.get(), "hoho");
+	/// ```
+	#[inline]
+	pub fn insert_entry(self, value: V) -> OccupiedEntry<'a, K, V, C> {
+		match self {
+			EntryRef::Occupied(mut entry) => {
+				entry.insert(value);
+				entry
+			}
+			EntryRef::Vacant(entry) => entry.insert_entry(value),
+		}
+	}
+}
+
+impl<'a, 'b, K: fmt::Debug, Q: ?Sized + fmt::Debug, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Debug
+	for EntryRef<'a, 'b, K, Q, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			EntryRef::Occupied(entry) => entry.fmt(f),
+			EntryRef::Vacant(entry) => entry.fmt(f),
+		}
+	}
+}
+
+/// A view into a vacant entry in a [`BTreeMap`], holding a borrowed key.
+/// It is part of the [`EntryRef`] enum.
+pub struct VacantEntryRef<'a, 'b, K, Q: ?Sized, V, C = slab::Slab<Node<K, V>>> {
+	pub(crate) map: &'a mut BTreeMap<K, V, C>,
+	pub(crate) key: &'b Q,
+	pub(crate) addr: Address,
+}
+
+impl<'a, 'b, K, Q: ?Sized, V, C: Slab<Node<K, V>>> VacantEntryRef<'a, 'b, K, Q, V, C> {
+	/// Gets the address of the vacant entry in the B-Tree.
+	#[inline]
+	pub fn address(&self) -> Address {
+		self.addr
+	}
+
+	/// Gets a reference to the borrowed key of the entry.
+	#[inline]
+	pub fn key(&self) -> &'b Q {
+		self.key
+	}
+}
+
+impl<'a, 'b, K, Q: ?Sized, V, C: SlabMut<Node<K, V>>> VacantEntryRef<'a, 'b, K, Q, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+	Q: ToOwned<Owned = K>,
+{
+	/// Sets the value of the entry with an owned copy of the borrowed key,
+	/// and returns a mutable reference to it.
+	///
+	/// ## Example
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use btree_slab::generic::map::EntryRef;
+	///
+	/// let mut map: BTreeMap<String, u32> = BTreeMap::new();
+	///
+	/// if let EntryRef::Vacant(o) = map.entry_ref("poneyland") {
+	///     o.insert(37);
+	/// }
+	/// assert_eq!(map["poneyland"], 37);
+	/// ```
+	#[inline]
+	pub fn insert(self, value: V) -> &'a mut V {
+		let addr = self
+			.map
+			.insert_at(self.addr, Item::new(self.key.to_owned(), value));
+		self.map.item_mut(addr).unwrap().value_mut()
+	}
+
+	/// Sets the value of the entry with an owned copy of the borrowed key,
+	/// and returns an [`OccupiedEntry`].
+	///
+	/// ## Example
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use btree_slab::generic::map::EntryRef;
+	///
+	/// let mut map: BTreeMap<String, u32> = BTreeMap::new();
+	///
+	/// if let EntryRef::Vacant(o) = map.entry_ref("poneyland") {
+	///     let entry = o.insert_entry(37);
+	///     assert_eq!(entry.remove_entry(), ("poneyland".to_string(), 37));
+	/// }
+	/// assert!(map.is_empty());
+	/// ```
+	#[inline]
+	pub fn insert_entry(self, value: V) -> OccupiedEntry<'a, K, V, C> {
+		let addr = self
+			.map
+			.insert_at(self.addr, Item::new(self.key.to_owned(), value));
+		OccupiedEntry {
+			map: self.map,
+			addr,
+		}
+	}
+}
+
+impl<'a, 'b, K, Q: ?Sized + fmt::Debug, V, C: Slab<Node<K, V>>> fmt::Debug
+	for VacantEntryRef<'a, 'b, K, Q, V, C>
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("VacantEntryRef").field(&self.key).finish()
+	}
+}
+
+/// A view into the value associated to a key, passed to the [`update_entry`](`BTreeMap#method.update_entry`) action.
+///
+/// The slot records what happens to the value:
+/// it is kept unless [`UpdateSlot::set`] or [`UpdateSlot::remove`] is called.
+pub struct UpdateSlot<'a, V> {
+	value: &'a mut Option<V>,
+}
+
+impl<'a, V> UpdateSlot<'a, V> {
+	pub(crate) fn new(value: &'a mut Option<V>) -> Self {
+		UpdateSlot { value }
+	}
+
+	/// Returns `true` if a value is associated to the key.
+	#[inline]
+	pub fn is_occupied(&self) -> bool {
+		self.value.is_some()
+	}
+
+	/// Returns a reference to the value associated to the key, if any.
+	#[inline]
+	pub fn get(&self) -> Option<&V> {
+		self.value.as_ref()
+	}
+
+	/// Returns a mutable reference to the value associated to the key, if any.
+	///
+	/// Modifications are kept in the map.
+	#[inline]
+	pub fn get_mut(&mut self) -> Option<&mut V> {
+		self.value.as_mut()
+	}
+
+	/// Associates the given value to the key, returning the previous value if any.
+	#[inline]
+	pub fn set(self, value: V) -> Option<V> {
+		self.value.replace(value)
+	}
+
+	/// Removes the binding from the map, returning the previous value if any.
+	#[inline]
+	pub fn remove(self) -> Option<V> {
+		self.value.take()
+	}
+
+	/// Leaves the binding as it is.
+	///
+	/// This is the default when the slot is dropped.
+	#[inline]
+	pub fn keep(self) {}
+}
+
+impl<'a, V: fmt::Debug> fmt::Debug for UpdateSlot<'a, V> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("UpdateSlot").field(&self.value).finish()
+	}
+}
@@ -1,10 +1,68 @@
 use crate::generic::{
-	map::{BTreeMap, M},
-	node::{Address, Balance, Item, Node, Offset},
+	map::{BTreeMap, Invariant, InvariantError, M},
+	node::{raw::RawItemOps, Address, Balance, Item, Node, Offset},
 };
 use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
 use smallvec::SmallVec;
-use std::{borrow::Borrow, mem::MaybeUninit};
+use std::{
+	borrow::Borrow, iter::FusedIterator, marker::PhantomData, mem::MaybeUninit, ops::RangeBounds,
+};
+
+/// Iterator over the item addresses of a tree, in order.
+///
+/// This is created by [`BTreeExt::addresses`] and [`BTreeExt::addresses_in`].
+pub struct Addresses<'a, K, V, T: ?Sized> {
+	tree: &'a T,
+
+	/// Address of the next item, or last valid address.
+	addr: Address,
+
+	/// Address following the last item.
+	end: Address,
+
+	kv: PhantomData<fn() -> (K, V)>,
+}
+
+impl<'a, K, V, T: ?Sized> Clone for Addresses<'a, K, V, T> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Addresses {
+			tree: self.tree,
+			addr: self.addr,
+			end: self.end,
+			kv: PhantomData,
+		}
+	}
+}
+
+impl<'a, K, V, T: ?Sized + BTreeExt<K, V>> Iterator for Addresses<'a, K, V, T> {
+	type Item = Address;
+
+	#[inline]
+	fn next(&mut self) -> Option<Address> {
+		if self.addr != self.end {
+			let addr = self.addr;
+			self.addr = self.tree.next_item_or_back_address(addr).unwrap();
+			Some(addr)
+		} else {
+			None
+		}
+	}
+}
+
+impl<'a, K, V, T: ?Sized + BTreeExt<K, V>> DoubleEndedIterator for Addresses<'a, K, V, T> {
+	#[inline]
+	fn next_back(&mut self) -> Option<Address> {
+		if self.addr != self.end {
+			self.end = self.tree.previous_item_address(self.end).unwrap();
+			Some(self.end)
+		} else {
+			None
+		}
+	}
+}
+
+impl<'a, K, V, T: ?Sized + BTreeExt<K, V>> FusedIterator for Addresses<'a, K, V, T> {}
 
 /// Extended API.
 ///
@@ -22,6 +80,37 @@ use std::{borrow::Borrow, mem::MaybeUninit};
 ///
 /// Note that a valid address does not always refer to an actual item in the tree.
 /// See the [`Address`] type documentation for more details.
+///
+/// # Degenerate trees
+///
+/// The navigation functions never panic on small trees, and follow this contract:
+///
+///   - An empty tree has no root node.
+///     [`BTreeExt::first_item_address`] and [`BTreeExt::last_item_address`] return `None`,
+///     [`BTreeExt::first_back_address`] and [`BTreeExt::last_valid_address`] return
+///     [`Address::nowhere`], and [`BTreeExt::address_of`] returns `Err(Address::nowhere())`.
+///   - Every navigation function accepts [`Address::nowhere`]:
+///     [`BTreeExt::leaf_address`] returns it unchanged, the others return `None`.
+///   - When the tree has a single leaf (one item, a full leaf, or the root left by
+///     a merge), the first item is at offset `0` and the last valid address is at
+///     offset `item_count` of that leaf.
+///     Going past the last item returns `None`, except for [`BTreeExt::next_back_address`]
+///     which first returns the last valid address,
+///     and [`BTreeExt::next_item_or_back_address`] which then keeps returning it.
+///     Going before the first item returns `None`, except for [`BTreeExt::previous_front_address`]
+///     which first returns offset `-1`.
+///   - An offset of `-1` is accepted by every navigation function and stands just
+///     before the item at offset `0`.
+///     In particular [`BTreeExt::normalize`] turns it into offset `0`.
+///
+/// # Address validation
+///
+/// Passing a stale address, for instance one obtained before a modification of the tree,
+/// may panic deep inside the node code or silently corrupt the tree.
+/// With the `validate-addresses` feature, [`BTreeExt::item`], [`BTreeExtMut::item_mut`],
+/// [`BTreeExtMut::insert_at`] and [`BTreeExtMut::remove_at`] first check
+/// that the address points into an allocated node and within its bounds,
+/// and panic with a clear message otherwise.
 pub trait BTreeExt<K, V> {
 	/// Get the root node id.
 	///
@@ -34,6 +123,7 @@ pub trait BTreeExt<K, V> {
 	fn node(&self, id: usize) -> &Node<K, V>;
 
 	/// Get a reference to the value associated to the given `key` in the node `id`, if any.
+	#[allow(clippy::multiple_bound_locations)]
 	fn get_in<Q: ?Sized>(&self, key: &Q, id: usize) -> Option<&V>
 	where
 		K: Borrow<Q>,
@@ -178,6 +268,7 @@ pub trait BTreeExt<K, V> {
 	/// Returns `Ok(addr)` if the key is used in the tree.
 	/// If the key is not used in the tree then `Err(addr)` is returned,
 	/// where `addr` can be used to insert the missing key.
+	#[allow(clippy::multiple_bound_locations)]
 	fn address_of<Q: ?Sized>(&self, key: &Q) -> Result<Address, Address>
 	where
 		K: Borrow<Q>,
@@ -186,11 +277,96 @@ pub trait BTreeExt<K, V> {
 	/// Search for the address of the given key from the given node `id`.
 	///
 	/// Users should directly use [`BTreeExt::address_of`].
+	#[allow(clippy::multiple_bound_locations)]
 	fn address_in<Q: ?Sized>(&self, id: usize, key: &Q) -> Result<Address, Address>
 	where
 		K: Borrow<Q>,
 		Q: Ord;
 
+	/// Returns an iterator over the addresses of the items of the tree, in order.
+	///
+	/// This is the address sequence followed by [`BTreeExt::next_item_address`]
+	/// from the first item, without accessing the items.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::{generic::map::BTreeExt, BTreeMap};
+	///
+	/// let map: BTreeMap<_, _> = (0..100).map(|i| (i, i)).collect();
+	/// let addresses: Vec<_> = map.addresses().collect();
+	/// assert_eq!(addresses.len(), 100);
+	/// assert_eq!(addresses[10], map.address_of(&10).unwrap());
+	/// assert!(map.addresses().rev().eq(addresses.into_iter().rev()));
+	/// ```
+	#[inline]
+	fn addresses(&self) -> Addresses<'_, K, V, Self> {
+		Addresses {
+			tree: self,
+			addr: self.first_back_address(),
+			end: self.last_valid_address(),
+			kv: PhantomData,
+		}
+	}
+
+	/// Returns an iterator over the addresses of the items in the given key range, in order.
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::{generic::map::BTreeExt, BTreeMap};
+	///
+	/// let map: BTreeMap<_, _> = (0..100).map(|i| (i * 2, i)).collect();
+	/// let keys: Vec<_> = map
+	///     .addresses_in(5..=10)
+	///     .map(|addr| *map.item(addr).unwrap().key())
+	///     .collect();
+	/// assert_eq!(keys, [6, 8, 10]);
+	/// ```
+	#[allow(clippy::multiple_bound_locations)]
+	fn addresses_in<Q: ?Sized, R>(&self, range: R) -> Addresses<'_, K, V, Self>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+		R: RangeBounds<Q>;
+
+	/// Checks that the tree is a valid B-Tree.
+	///
+	/// Unlike [`BTreeExt::validate`], this is available in release builds
+	/// and returns the first broken invariant instead of panicking,
+	/// so that fuzzers and integration tests can report it.
+	/// It visits every node of the tree.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::{generic::map::BTreeExt, BTreeMap};
+	///
+	/// let map: BTreeMap<_, _> = (0..1000).map(|i| (i, i)).collect();
+	/// assert_eq!(map.check_invariants(), Ok(()));
+	/// ```
+	fn check_invariants(&self) -> Result<(), InvariantError>
+	where
+		K: Ord;
+
+	/// Checks the subtree rooted in the given node and returns its depth.
+	///
+	/// Users should directly use [`BTreeExt::check_invariants`].
+	fn check_node_invariants(
+		&self,
+		id: usize,
+		parent: Option<usize>,
+		min: Option<&K>,
+		max: Option<&K>,
+	) -> Result<usize, InvariantError>
+	where
+		K: Ord;
+
 	/// Validate the tree.
 	///
 	/// Panics if the tree is not a valid B-Tree.
@@ -238,9 +414,11 @@ pub trait BTreeExtMut<K, V> {
 	fn node_mut(&mut self, id: usize) -> &mut Node<K, V>;
 
 	/// Get a mutable reference to the value associated to the given `key` in the node `id`, if any.
-	fn get_mut_in(&mut self, key: &K, id: usize) -> Option<&mut V>
+	#[allow(clippy::multiple_bound_locations)]
+	fn get_mut_in<Q: ?Sized>(&mut self, key: &Q, id: usize) -> Option<&mut V>
 	where
-		K: Ord;
+		K: Borrow<Q>,
+		Q: Ord;
 
 	/// Get a mutable reference to the item located at the given address.
 	fn item_mut(&mut self, addr: Address) -> Option<&mut Item<K, V>>;
@@ -276,6 +454,26 @@ pub trait BTreeExtMut<K, V> {
 	/// Replaces the key-value binding at the given address.
 	fn replace_at(&mut self, addr: Address, key: K, value: V) -> (K, V);
 
+	/// Replaces the key at the given address, keeping its value, and returns the old key.
+	///
+	/// The item is updated in place: this avoids removing and reinserting it
+	/// when a key is changed without moving it relative to the other keys,
+	/// for instance when coalescing adjacent ranges.
+	///
+	/// # Correctness
+	///
+	/// The new key must be strictly greater than the previous key in the tree
+	/// and strictly lower than the next one.
+	/// This is checked in debug builds only.
+	///
+	/// # Panics
+	///
+	/// Panics if there is no item at the given address,
+	/// or, in debug builds, if the new key breaks the order between keys.
+	fn replace_key_at(&mut self, addr: Address, key: K) -> K
+	where
+		K: Ord;
+
 	/// Replaces the value at the given address.
 	fn replace_value_at(&mut self, addr: Address, value: V) -> V;
 
@@ -325,10 +523,14 @@ where
 
 	#[inline]
 	fn node(&self, id: usize) -> &Node<K, V> {
-		C::into_ref(self.nodes.get(id).unwrap())
+		let node = self.nodes.get(id).map(C::into_ref);
+		#[cfg(feature = "checksum")]
+		self.check_node(id, node);
+		node.unwrap()
 	}
 
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	fn get_in<Q: ?Sized>(&self, key: &Q, mut id: usize) -> Option<&V>
 	where
 		K: Borrow<Q>,
@@ -337,63 +539,41 @@ where
 		loop {
 			match self.node(id).get(key) {
 				Ok(value_opt) => return value_opt,
-				Err(child_id) => id = child_id,
+				Err(child_id) => {
+					#[cfg(feature = "ord-check")]
+					self.check_child_order::<Q>(id, child_id);
+					id = child_id
+				}
 			}
 		}
 	}
 
 	fn item(&self, addr: Address) -> Option<&Item<K, V>> {
+		#[cfg(feature = "validate-addresses")]
+		self.assert_live_address(addr, "item", true);
 		self.node(addr.id).item(addr.offset)
 	}
 
 	fn first_item_address(&self) -> Option<Address> {
-		match self.root {
-			Some(mut id) => loop {
-				match self.node(id).child_id_opt(0) {
-					Some(child_id) => id = child_id,
-					None => return Some(Address::new(id, 0.into())),
-				}
-			},
-			None => None,
-		}
+		self.leaf_bounds
+			.map(|(first, _)| Address::new(first, 0.into()))
 	}
 
 	fn first_back_address(&self) -> Address {
-		match self.root {
-			Some(mut id) => loop {
-				match self.node(id).child_id_opt(0) {
-					Some(child_id) => id = child_id,
-					None => return Address::new(id, 0.into()), // TODO FIXME thechnically not the first
-				}
-			},
+		match self.leaf_bounds {
+			Some((first, _)) => Address::new(first, 0.into()),
 			None => Address::nowhere(),
 		}
 	}
 
 	fn last_item_address(&self) -> Option<Address> {
-		match self.root {
-			Some(mut id) => loop {
-				let node = self.node(id);
-				let index = node.item_count();
-				match node.child_id_opt(index) {
-					Some(child_id) => id = child_id,
-					None => return Some(Address::new(id, (index - 1).into())),
-				}
-			},
-			None => None,
-		}
+		self.leaf_bounds
+			.map(|(_, last)| Address::new(last, (self.node(last).item_count() - 1).into()))
 	}
 
 	fn last_valid_address(&self) -> Address {
-		match self.root {
-			Some(mut id) => loop {
-				let node = self.node(id);
-				let index = node.item_count();
-				match node.child_id_opt(index) {
-					Some(child_id) => id = child_id,
-					None => return Address::new(id, index.into()),
-				}
-			},
+		match self.leaf_bounds {
+			Some((_, last)) => Address::new(last, self.node(last).item_count().into()),
 			None => Address::nowhere(),
 		}
 	}
@@ -402,6 +582,10 @@ where
 		if addr.is_nowhere() {
 			None
 		} else {
+			if addr.offset.is_before() {
+				addr.offset = 0.into()
+			}
+
 			loop {
 				let node = self.node(addr.id);
 				if addr.offset >= node.item_count() {
@@ -424,8 +608,7 @@ where
 		if !addr.is_nowhere() {
 			loop {
 				let node = self.node(addr.id);
-				match node.child_id_opt(addr.offset.unwrap()) {
-					// TODO unwrap may fail here!
+				match node.child_id_opt(addr.offset.value().unwrap_or(0)) {
 					Some(child_id) => {
 						addr.id = child_id;
 						addr.offset = self.node(child_id).item_count().into()
@@ -448,8 +631,8 @@ where
 		loop {
 			let node = self.node(addr.id);
 
-			match node.child_id_opt(addr.offset.unwrap()) {
-				// TODO unwrap may fail here.
+			// An offset of `-1` precedes the first item, just like offset `0`.
+			match node.child_id_opt(addr.offset.value().unwrap_or(0)) {
 				Some(child_id) => {
 					addr.offset = self.node(child_id).item_count().into();
 					addr.id = child_id;
@@ -654,6 +837,7 @@ where
 		}
 	}
 
+	#[allow(clippy::multiple_bound_locations)]
 	fn address_of<Q: ?Sized>(&self, key: &Q) -> Result<Address, Address>
 	where
 		K: Borrow<Q>,
@@ -665,6 +849,7 @@ where
 		}
 	}
 
+	#[allow(clippy::multiple_bound_locations)]
 	fn address_in<Q: ?Sized>(&self, mut id: usize, key: &Q) -> Result<Address, Address>
 	where
 		K: Borrow<Q>,
@@ -675,36 +860,72 @@ where
 				Ok(offset) => return Ok(Address { id, offset }),
 				Err((offset, None)) => return Err(Address::new(id, offset.into())),
 				Err((_, Some(child_id))) => {
+					#[cfg(feature = "ord-check")]
+					self.check_child_order::<Q>(id, child_id);
 					id = child_id;
 				}
 			}
 		}
 	}
 
-	#[cfg(debug_assertions)]
-	fn validate(&self)
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	fn addresses_in<Q: ?Sized, R>(&self, range: R) -> Addresses<'_, K, V, Self>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+		R: RangeBounds<Q>,
+	{
+		if !super::is_valid_range(&range) {
+			panic!("Invalid range")
+		}
+
+		Addresses {
+			tree: self,
+			addr: self.range_start_address(range.start_bound()),
+			end: self.range_end_address(range.end_bound()),
+			kv: PhantomData,
+		}
+	}
+
+	fn check_invariants(&self) -> Result<(), InvariantError>
 	where
 		K: Ord,
 	{
 		if let Some(id) = self.root {
-			self.validate_node(id, None, None, None);
+			self.check_node_invariants(id, None, None, None)?;
+
+			if self.node(id).subtree_len() != self.len {
+				return Err(InvariantError::new(None, Invariant::Len));
+			}
+		} else if self.len != 0 {
+			return Err(InvariantError::new(None, Invariant::Len));
 		}
+
+		if self.leaf_bounds != self.find_leaf_bounds() {
+			return Err(InvariantError::new(None, Invariant::LeafBounds));
+		}
+
+		Ok(())
 	}
 
-	/// Validate the given node and returns the depth of the node.
-	#[cfg(debug_assertions)]
-	fn validate_node(
+	fn check_node_invariants(
 		&self,
 		id: usize,
 		parent: Option<usize>,
 		mut min: Option<&K>,
 		mut max: Option<&K>,
-	) -> usize
+	) -> Result<usize, InvariantError>
 	where
 		K: Ord,
 	{
-		let node = self.node(id);
-		node.validate(parent, min, max);
+		let node = self
+			.nodes
+			.get(id)
+			.map(C::into_ref)
+			.ok_or(InvariantError::new(Some(id), Invariant::Allocated))?;
+		node.check(parent, min, max)
+			.map_err(|invariant| InvariantError::new(Some(id), invariant))?;
 
 		let mut depth = None;
 		for (i, child_id) in node.children().enumerate() {
@@ -712,20 +933,53 @@ where
 			let min = child_min.or_else(|| min.take());
 			let max = child_max.or_else(|| max.take());
 
-			let child_depth = self.validate_node(child_id, Some(id), min, max);
+			let child_depth = self.check_node_invariants(child_id, Some(id), min, max)?;
 			match depth {
 				None => depth = Some(child_depth),
 				Some(depth) => {
 					if depth != child_depth {
-						panic!("tree not balanced")
+						return Err(InvariantError::new(Some(id), Invariant::Balanced));
 					}
 				}
 			}
 		}
 
-		match depth {
+		// Children are checked first, so that they are known to be allocated.
+		if node.subtree_len() != self.compute_subtree_len(id) {
+			return Err(InvariantError::new(Some(id), Invariant::SubtreeLen));
+		}
+
+		Ok(match depth {
 			Some(depth) => depth + 1,
 			None => 0,
+		})
+	}
+
+	#[cfg(debug_assertions)]
+	fn validate(&self)
+	where
+		K: Ord,
+	{
+		if let Err(e) = self.check_invariants() {
+			panic!("{}", e)
+		}
+	}
+
+	/// Validate the given node and returns the depth of the node.
+	#[cfg(debug_assertions)]
+	fn validate_node(
+		&self,
+		id: usize,
+		parent: Option<usize>,
+		min: Option<&K>,
+		max: Option<&K>,
+	) -> usize
+	where
+		K: Ord,
+	{
+		match self.check_node_invariants(id, parent, min, max) {
+			Ok(depth) => depth,
+			Err(e) => panic!("{}", e),
 		}
 	}
 }
@@ -742,18 +996,23 @@ where
 
 	#[inline]
 	fn set_root_id(&mut self, id: Option<usize>) {
-		self.root = id
+		self.root = id;
+		self.reset_leaf_bounds()
 	}
 
 	#[inline]
 	fn node_mut(&mut self, id: usize) -> &mut Node<K, V> {
+		#[cfg(feature = "checksum")]
+		self.unseal();
 		C::into_mut(self.nodes.get_mut(id).unwrap())
 	}
 
 	#[inline]
-	fn get_mut_in<'a>(&'a mut self, key: &K, mut id: usize) -> Option<&'a mut V>
+	#[allow(clippy::multiple_bound_locations)]
+	fn get_mut_in<'a, Q: ?Sized>(&'a mut self, key: &Q, mut id: usize) -> Option<&'a mut V>
 	where
-		K: Ord,
+		K: Borrow<Q>,
+		Q: Ord,
 	{
 		// The borrow checker is unable to predict that `*self`
 		// is not borrowed more that once at a time.
@@ -762,7 +1021,11 @@ where
 		let value_ptr = loop {
 			match self.node_mut(id).get_mut(key) {
 				Ok(value_opt) => break value_opt.map(|value_ref| value_ref as *mut V),
-				Err(child_id) => id = child_id,
+				Err(child_id) => {
+					#[cfg(feature = "ord-check")]
+					self.check_child_order::<Q>(id, child_id);
+					id = child_id
+				}
 			}
 		};
 
@@ -770,10 +1033,16 @@ where
 	}
 
 	fn item_mut(&mut self, addr: Address) -> Option<&mut Item<K, V>> {
+		#[cfg(feature = "validate-addresses")]
+		self.assert_live_address(addr, "item_mut", true);
 		self.node_mut(addr.id).item_mut(addr.offset)
 	}
 
 	fn insert_at(&mut self, addr: Address, item: Item<K, V>) -> Address {
+		#[cfg(feature = "validate-addresses")]
+		if !addr.is_nowhere() || !self.is_empty() {
+			self.assert_live_address(addr, "insert_at", true)
+		}
 		self.insert_exactly_at(self.leaf_address(addr), item, None)
 	}
 
@@ -783,11 +1052,24 @@ where
 		item: Item<K, V>,
 		opt_right_id: Option<usize>,
 	) -> Address {
-		if addr.is_nowhere() {
+		self.generation = self.generation.wrapping_add(1);
+		#[cfg(feature = "watermark")]
+		self.watermarks.touch(item.key());
+		#[cfg(feature = "bloom")]
+		if let Some(filter) = &mut self.key_filter {
+			filter.insert(item.key())
+		}
+		#[cfg(feature = "structural-hash")]
+		if let Some(h) = &mut self.structural_hash {
+			h.insert(item.key())
+		}
+
+		let new_addr = if addr.is_nowhere() {
 			if self.is_empty() {
 				let new_root = Node::leaf(None, item);
 				let id = self.allocate_node(new_root);
 				self.root = Some(id);
+				self.leaf_bounds = Some((id, id));
 				self.len += 1;
 				Address {
 					id,
@@ -801,20 +1083,91 @@ where
 		} else {
 			self.node_mut(addr.id)
 				.insert(addr.offset, item, opt_right_id);
+			let right_len = opt_right_id.map_or(0, |id| self.node(id).subtree_len());
+			self.add_subtree_len(addr.id, 1 + right_len as isize);
 			let new_addr = self.rebalance(addr.id, addr);
 			self.len += 1;
 			new_addr
-		}
+		};
+
+		#[cfg(feature = "bloom")]
+		self.sync_key_filter();
+		new_addr
 	}
 
 	fn replace_at(&mut self, addr: Address, key: K, value: V) -> (K, V) {
-		self.node_mut(addr.id)
+		#[cfg(feature = "watermark")]
+		self.watermarks.touch(&key);
+		let (old_key, old_value) = self
+			.node_mut(addr.id)
 			.item_mut(addr.offset)
 			.unwrap()
-			.set(key, value)
+			.set(key, value);
+		self.generation = self.generation.wrapping_add(1);
+		#[cfg(feature = "watermark")]
+		self.watermarks.touch(&old_key);
+		#[cfg(feature = "bloom")]
+		if let Some(mut filter) = self.key_filter.take() {
+			filter.replace(&old_key, self.item(addr).unwrap().key());
+			self.key_filter = Some(filter)
+		}
+		#[cfg(feature = "structural-hash")]
+		if let Some(mut h) = self.structural_hash.take() {
+			h.replace(&old_key, self.item(addr).unwrap().key());
+			self.structural_hash = Some(h)
+		}
+		#[cfg(feature = "bloom")]
+		self.sync_key_filter();
+		(old_key, old_value)
+	}
+
+	fn replace_key_at(&mut self, addr: Address, key: K) -> K
+	where
+		K: Ord,
+	{
+		#[cfg(feature = "validate-addresses")]
+		self.assert_live_address(addr, "replace_key_at", false);
+		debug_assert!(
+			self.previous_item_address(addr)
+				.is_none_or(|prev| self.item(prev).unwrap().key() < &key),
+			"replace_key_at: new key is not greater than the previous key"
+		);
+		debug_assert!(
+			self.next_item_address(addr)
+				.is_none_or(|next| self.item(next).unwrap().key() > &key),
+			"replace_key_at: new key is not lower than the next key"
+		);
+
+		#[cfg(feature = "watermark")]
+		self.watermarks.touch(&key);
+		let old_key = self
+			.node_mut(addr.id)
+			.item_mut(addr.offset)
+			.expect("no item at the given address")
+			.set_key(key);
+		self.generation = self.generation.wrapping_add(1);
+		#[cfg(feature = "watermark")]
+		self.watermarks.touch(&old_key);
+		#[cfg(feature = "bloom")]
+		if let Some(mut filter) = self.key_filter.take() {
+			filter.replace(&old_key, self.item(addr).unwrap().key());
+			self.key_filter = Some(filter)
+		}
+		#[cfg(feature = "structural-hash")]
+		if let Some(mut h) = self.structural_hash.take() {
+			h.replace(&old_key, self.item(addr).unwrap().key());
+			self.structural_hash = Some(h)
+		}
+		#[cfg(feature = "bloom")]
+		self.sync_key_filter();
+		old_key
 	}
 
 	fn replace_value_at(&mut self, addr: Address, value: V) -> V {
+		#[cfg(feature = "watermark")]
+		if let Some(item) = self.item(addr) {
+			self.watermarks.touch(item.key())
+		}
 		self.node_mut(addr.id)
 			.item_mut(addr.offset)
 			.unwrap()
@@ -823,10 +1176,18 @@ where
 
 	#[inline]
 	fn remove_at(&mut self, addr: Address) -> Option<(Item<K, V>, Address)> {
+		#[cfg(feature = "validate-addresses")]
+		self.assert_live_address(addr, "remove_at", false);
+		self.generation = self.generation.wrapping_add(1);
+		#[cfg(feature = "watermark")]
+		if let Some(item) = self.item(addr) {
+			self.watermarks.touch(item.key())
+		}
 		self.len -= 1;
-		match self.node_mut(addr.id).leaf_remove(addr.offset) {
+		let result = match self.node_mut(addr.id).leaf_remove(addr.offset) {
 			Some(Ok(item)) => {
 				// removed from a leaf.
+				self.add_subtree_len(addr.id, -1);
 				let addr = self.rebalance(addr.id, addr);
 				Some((item, addr))
 			}
@@ -834,12 +1195,28 @@ where
 				// removed from an internal node.
 				let new_addr = self.next_item_or_back_address(addr).unwrap();
 				let (separator, leaf_id) = self.remove_rightmost_leaf_of(left_child_id);
+				self.add_subtree_len(leaf_id, -1);
 				let item = self.node_mut(addr.id).replace(addr.offset, separator);
 				let addr = self.rebalance(leaf_id, new_addr);
 				Some((item, addr))
 			}
 			None => None,
+		};
+
+		#[cfg(feature = "bloom")]
+		if result.is_some() {
+			if let Some(filter) = &mut self.key_filter {
+				filter.remove()
+			}
+			self.sync_key_filter();
 		}
+
+		#[cfg(feature = "structural-hash")]
+		if let (Some((item, _)), Some(h)) = (&result, &mut self.structural_hash) {
+			h.remove(item.key())
+		}
+
+		result
 	}
 
 	fn update_in<T, F>(&mut self, mut id: usize, key: K, action: F) -> T
@@ -850,6 +1227,8 @@ where
 		loop {
 			match self.node(id).offset_of(&key) {
 				Ok(offset) => unsafe {
+					#[cfg(feature = "watermark")]
+					self.watermarks.touch(&key);
 					let mut value = MaybeUninit::uninit();
 					let item = self.node_mut(id).item_mut(offset).unwrap();
 					std::mem::swap(&mut value, item.maybe_uninit_value_mut());
@@ -890,6 +1269,11 @@ where
 		K: Ord,
 		F: FnOnce(V) -> (Option<V>, T),
 	{
+		#[cfg(feature = "watermark")]
+		if let Some(item) = self.item(addr) {
+			self.watermarks.touch(item.key())
+		}
+
 		unsafe {
 			let mut value = MaybeUninit::uninit();
 			let item = self.node_mut(addr.id).item_mut(addr.offset).unwrap();
@@ -915,20 +1299,37 @@ where
 	#[inline]
 	fn rebalance(&mut self, mut id: usize, mut addr: Address) -> Address {
 		let mut balance = self.node(id).balance();
+		// Offset of the item just inserted in the overflowing node, for the split policy.
+		let mut inserted = if addr.id == id {
+			addr.offset.value()
+		} else {
+			None
+		};
 
 		loop {
 			match balance {
 				Balance::Balanced => break,
 				Balance::Overflow => {
 					assert!(!self.node_mut(id).is_underflowing());
-					let (median_offset, median, right_node) = self.node_mut(id).split();
+					let median_offset = self
+						.split_policy()
+						.median_offset(self.node(id).item_count(), inserted);
+					let (median_offset, median, right_node) =
+						self.node_mut(id).split_around(median_offset);
 					let right_id = self.allocate_node(right_node);
+					self.update_subtree_len(id);
+					if let Some((_, last)) = &mut self.leaf_bounds {
+						if *last == id {
+							*last = right_id
+						}
+					}
 
 					match self.node(id).parent() {
 						Some(parent_id) => {
 							let parent = self.node_mut(parent_id);
 							let offset = parent.child_index(id).unwrap().into();
 							parent.insert(offset, median, Some(right_id));
+							inserted = offset.value();
 
 							// new address.
 							if addr.id == id {
@@ -1025,7 +1426,10 @@ where
 											addr.offset = root.item_count().into()
 										}
 									}
-									None => addr = Address::nowhere(),
+									None => {
+										self.leaf_bounds = None;
+										addr = Address::nowhere()
+									}
 								}
 
 								self.release_node(id);
@@ -1055,7 +1459,10 @@ where
 
 	#[inline]
 	fn allocate_node(&mut self, node: Node<K, V>) -> usize {
+		self.generation = self.generation.wrapping_add(1);
 		let mut children: SmallVec<[usize; M]> = SmallVec::new();
+		#[cfg(feature = "checksum")]
+		self.unseal();
 		let id = self.nodes.insert(node);
 
 		for child_id in self.node(id).children() {
@@ -1066,11 +1473,14 @@ where
 			self.node_mut(child_id).set_parent(Some(id))
 		}
 
+		self.update_subtree_len(id);
 		id
 	}
 
 	#[inline]
 	fn release_node(&mut self, id: usize) -> Node<K, V> {
+		#[cfg(feature = "checksum")]
+		self.unseal();
 		self.nodes.remove(id).unwrap()
 	}
 }
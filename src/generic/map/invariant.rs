@@ -0,0 +1,96 @@
+use std::fmt;
+
+/// Invariant of a B-Tree, checked by [`BTreeExt::check_invariants`](super::BTreeExt::check_invariants).
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Invariant {
+	/// Every node referenced by the tree is allocated in the node storage.
+	Allocated,
+
+	/// The parent recorded in a node is the node referencing it.
+	Parent,
+
+	/// A node other than the root does not hold more items than its capacity.
+	Overflow,
+
+	/// A node other than the root does not hold less items than its minimum.
+	Underflow,
+
+	/// The root is not an internal node without items.
+	EmptyRoot,
+
+	/// The items of a node are stored inline, and not on the heap.
+	InlineStorage,
+
+	/// The items of a node are sorted by key.
+	Sorted,
+
+	/// The keys of a node are between the separators of the node in its parent.
+	Separators,
+
+	/// The subtree length recorded in a node is its number of items and descendant items.
+	SubtreeLen,
+
+	/// Every leaf is at the same depth.
+	Balanced,
+
+	/// The first and last leaves recorded by the map are those of the tree.
+	LeafBounds,
+
+	/// Every leaf is linked to its neighbours, in order.
+	///
+	/// Only checked by `BPlusTreeMap::check_invariants`, with the `bplus` feature.
+	LeafLinks,
+
+	/// The length recorded by the map is its number of items.
+	Len,
+}
+
+impl fmt::Display for Invariant {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Self::Allocated => write!(f, "node is not allocated"),
+			Self::Parent => write!(f, "wrong parent"),
+			Self::Overflow => write!(f, "node is overflowing"),
+			Self::Underflow => write!(f, "node is underflowing"),
+			Self::EmptyRoot => write!(f, "root node is empty"),
+			Self::InlineStorage => write!(f, "node storage spilled on the heap"),
+			Self::Sorted => write!(f, "node items are not sorted"),
+			Self::Separators => write!(f, "node item key is not between its parent separators"),
+			Self::SubtreeLen => write!(f, "wrong subtree length"),
+			Self::Balanced => write!(f, "tree not balanced"),
+			Self::LeafBounds => write!(f, "wrong first or last leaf"),
+			Self::LeafLinks => write!(f, "wrong leaf links"),
+			Self::Len => write!(f, "wrong length"),
+		}
+	}
+}
+
+/// Error returned by [`BTreeExt::check_invariants`](super::BTreeExt::check_invariants)
+/// when the tree is not a valid B-Tree.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct InvariantError {
+	/// Identifier of the node breaking the invariant,
+	/// or `None` if the invariant concerns the whole tree.
+	pub node: Option<usize>,
+
+	/// The broken invariant.
+	pub invariant: Invariant,
+}
+
+impl InvariantError {
+	#[inline]
+	pub(crate) fn new(node: Option<usize>, invariant: Invariant) -> Self {
+		InvariantError { node, invariant }
+	}
+}
+
+impl fmt::Display for InvariantError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self.node {
+			Some(id) => write!(f, "node {}: {}", id, self.invariant),
+			None => self.invariant.fmt(f),
+		}
+	}
+}
+
+impl std::error::Error for InvariantError {}
@@ -0,0 +1,364 @@
+use super::{BTreeExt, BTreeExtMut, BTreeMap, M};
+use crate::generic::node::{Address, Item, Node};
+use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
+use std::fmt;
+
+/// Size limits of a map, enforced by [`BTreeMap::checked_insert`].
+///
+/// No limit is set by default.
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::generic::map::Limits;
+///
+/// let limits = Limits::new().with_max_len(1000).with_max_depth(4);
+/// assert_eq!(limits.max_len(), Some(1000));
+/// assert_eq!(limits.max_depth(), Some(4));
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Limits {
+	max_len: usize,
+	max_depth: usize,
+}
+
+impl Limits {
+	/// Creates limits without any restriction.
+	#[inline]
+	pub const fn new() -> Self {
+		Limits {
+			max_len: usize::MAX,
+			max_depth: usize::MAX,
+		}
+	}
+
+	/// Sets the maximum number of entries.
+	#[inline]
+	pub const fn with_max_len(mut self, max_len: usize) -> Self {
+		self.max_len = max_len;
+		self
+	}
+
+	/// Sets the maximum depth of the tree, a single leaf having a depth of `1`.
+	#[inline]
+	pub const fn with_max_depth(mut self, max_depth: usize) -> Self {
+		self.max_depth = max_depth;
+		self
+	}
+
+	/// Returns the maximum number of entries, if any.
+	#[inline]
+	pub fn max_len(&self) -> Option<usize> {
+		if self.max_len == usize::MAX {
+			None
+		} else {
+			Some(self.max_len)
+		}
+	}
+
+	/// Returns the maximum depth of the tree, if any.
+	#[inline]
+	pub fn max_depth(&self) -> Option<usize> {
+		if self.max_depth == usize::MAX {
+			None
+		} else {
+			Some(self.max_depth)
+		}
+	}
+}
+
+impl Default for Limits {
+	#[inline]
+	fn default() -> Self {
+		Limits::new()
+	}
+}
+
+/// Limit exceeded by an insertion.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Limit {
+	/// Maximum number of entries.
+	Len(usize),
+
+	/// Maximum depth of the tree.
+	Depth(usize),
+}
+
+impl fmt::Display for Limit {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Limit::Len(max) => write!(f, "maximum length of {} reached", max),
+			Limit::Depth(max) => write!(f, "maximum depth of {} reached", max),
+		}
+	}
+}
+
+/// Error returned by [`BTreeMap::checked_insert`] when the insertion would exceed a limit.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct LimitError<K, V> {
+	/// The exceeded limit.
+	pub limit: Limit,
+
+	/// The key that was not inserted.
+	pub key: K,
+
+	/// The value that was not inserted.
+	pub value: V,
+}
+
+impl<K, V> fmt::Display for LimitError<K, V> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.limit.fmt(f)
+	}
+}
+
+impl<K: fmt::Debug, V: fmt::Debug> std::error::Error for LimitError<K, V> {}
+
+/// Returns the maximum depth of a tree holding `len` entries.
+///
+/// Every node but the root holds at least `M / 2 - 1` items,
+/// and the root of a tree of depth greater than `1` holds at least one item.
+/// The depth of a map is hence logarithmic in its length,
+/// and this function gives the exact worst case,
+/// for instance `10` for a million entries and `16` for four billion entries.
+///
+/// Lookups visit one node per level,
+/// and insertions and removals additionally split, merge or rotate
+/// at most one node per level.
+/// A map of bounded length thus has a bounded operation latency,
+/// which can be checked with [`BTreeMap::debug_assert_depth_le`].
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::{BTreeMap, generic::map::max_depth};
+///
+/// const MAX_DEPTH: usize = max_depth(1_000_000);
+///
+/// let map: BTreeMap<_, _> = (0..10_000).map(|i| (i, i)).collect();
+/// assert!(map.depth() <= max_depth(map.len()));
+/// map.debug_assert_depth_le(MAX_DEPTH);
+/// ```
+pub const fn max_depth(len: usize) -> usize {
+	if len == 0 {
+		return 0;
+	}
+
+	// Minimum number of items in a non-root subtree of the current depth.
+	let min_items = M / 2 - 1;
+	let mut min_subtree_len = min_items;
+	let mut depth = 1;
+	loop {
+		// Minimum length of a tree whose root has two such subtrees.
+		let min_len = min_subtree_len.saturating_mul(2).saturating_add(1);
+		if min_len > len || min_len == usize::MAX {
+			return depth;
+		}
+
+		depth += 1;
+		min_subtree_len = min_subtree_len
+			.saturating_mul(min_items + 1)
+			.saturating_add(min_items);
+	}
+}
+
+impl<K, V, C> BTreeMap<K, V, C> {
+	/// Create a new empty B-tree with the given limits.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::{BTreeMap, generic::map::Limits};
+	///
+	/// let mut map = BTreeMap::with_limits(Limits::new().with_max_len(2));
+	/// map.checked_insert(1, 'a').unwrap();
+	/// map.checked_insert(2, 'b').unwrap();
+	/// assert!(map.checked_insert(3, 'c').is_err());
+	/// ```
+	#[inline]
+	pub fn with_limits(limits: Limits) -> BTreeMap<K, V, C>
+	where
+		C: Default,
+	{
+		let mut map = BTreeMap::new();
+		map.set_limits(limits);
+		map
+	}
+
+	/// Returns the limits of the map.
+	#[inline]
+	pub fn limits(&self) -> Limits {
+		self.settings
+			.as_ref()
+			.map_or_else(Limits::new, |settings| settings.limits)
+	}
+
+	/// Sets the limits of the map.
+	///
+	/// The current entries are kept even if they exceed the new limits.
+	#[inline]
+	pub fn set_limits(&mut self, limits: Limits) {
+		self.settings.get_or_insert_with(Default::default).limits = limits
+	}
+}
+
+impl<K, V, C: Slab<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Returns the depth of the tree, a single leaf having a depth of `1`.
+	///
+	/// Returns `0` if the map is empty.
+	///
+	/// The depth is computed in `O(log n)` by walking the leftmost branch of the tree,
+	/// and never exceeds [`max_depth`] of the length of the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<_, _> = (0..8).map(|i| (i, i)).collect();
+	/// assert_eq!(map.depth(), 1);
+	/// map.insert(8, 8);
+	/// assert_eq!(map.depth(), 2);
+	/// ```
+	#[inline]
+	pub fn depth(&self) -> usize {
+		let mut depth = 0;
+		let mut id = self.root_id();
+		while let Some(i) = id {
+			depth += 1;
+			id = self.node(i).child_id_opt(0)
+		}
+
+		depth
+	}
+
+	/// Asserts that the depth of the tree is at most `max`, in debug mode.
+	///
+	/// Real-time code can use this to check that the latency of the operations on the map
+	/// stays within a known bound.
+	/// In release mode, this does nothing.
+	///
+	/// # Panics
+	///
+	/// Panics in debug mode if the depth of the tree is greater than `max`.
+	///
+	/// # Example
+	///
+	/// ```should_panic
+	/// use btree_slab::BTreeMap;
+	///
+	/// let map: BTreeMap<_, _> = (0..100).map(|i| (i, i)).collect();
+	/// map.debug_assert_depth_le(2);
+	/// # #[cfg(not(debug_assertions))]
+	/// # panic!()
+	/// ```
+	#[inline]
+	#[track_caller]
+	pub fn debug_assert_depth_le(&self, max: usize) {
+		debug_assert!(
+			self.depth() <= max,
+			"tree depth {} is greater than {}",
+			self.depth(),
+			max
+		)
+	}
+
+	/// Checks if inserting a new item at the given leaf address would increase the depth.
+	///
+	/// This is the case when the tree is empty or when every node from the leaf to the root
+	/// is full, so that the root is split.
+	#[inline]
+	fn insertion_grows_depth(&self, addr: Address) -> bool {
+		if addr.is_nowhere() {
+			return true;
+		}
+
+		let mut id = addr.id;
+		loop {
+			// A node splits when it receives an item while holding its maximum number of items.
+			let node = self.node(id);
+			let max_items = match node {
+				Node::Leaf(_) => M,
+				Node::Internal(_) => M - 1,
+			};
+
+			if node.item_count() < max_items {
+				return false;
+			}
+
+			match node.parent() {
+				Some(parent_id) => id = parent_id,
+				None => return true,
+			}
+		}
+	}
+}
+
+impl<K, V, C: SlabMut<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Inserts a key-value pair into the map, unless it would exceed the limits of the map.
+	///
+	/// If the map already had this key present, the value is updated and the old
+	/// value is returned: replacing a value never exceeds a limit.
+	/// Otherwise, if inserting the key would exceed the maximum length or depth
+	/// set with [`BTreeMap::set_limits`], the pair is returned in the error.
+	///
+	/// The check only costs a walk from the insertion leaf to the root.
+	/// Other insertion methods do not enforce the limits.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::{BTreeMap, generic::map::{Limit, Limits}};
+	///
+	/// let mut map = BTreeMap::with_limits(Limits::new().with_max_depth(1));
+	/// for i in 0..8 {
+	///     map.checked_insert(i, i).unwrap();
+	/// }
+	///
+	/// let err = map.checked_insert(8, 8).unwrap_err();
+	/// assert_eq!(err.limit, Limit::Depth(1));
+	/// assert_eq!(map.checked_insert(0, 10), Ok(Some(0)));
+	/// ```
+	#[inline]
+	pub fn checked_insert(&mut self, key: K, value: V) -> Result<Option<V>, LimitError<K, V>>
+	where
+		K: Ord,
+	{
+		let addr = self.address_of(&key);
+
+		#[cfg(feature = "metrics")]
+		self.record(super::Operation::Insert, addr.unwrap_or_else(|addr| addr));
+
+		match addr {
+			Ok(addr) => Ok(Some(self.replace_value_at(addr, value))),
+			Err(addr) => {
+				let limits = self.limits();
+				let limit = if self.len() >= limits.max_len {
+					Some(Limit::Len(limits.max_len))
+				} else if limits.max_depth != usize::MAX
+					&& self.insertion_grows_depth(addr)
+					&& self.depth() >= limits.max_depth
+				{
+					Some(Limit::Depth(limits.max_depth))
+				} else {
+					None
+				};
+
+				match limit {
+					Some(limit) => Err(LimitError { limit, key, value }),
+					None => {
+						self.insert_exactly_at(addr, Item::new(key, value), None);
+						Ok(None)
+					}
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,122 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Map operation reported to a [`MetricsSink`].
+///
+/// Only the single-key operations listed below are reported,
+/// along with the [`BTreeSet`](crate::generic::BTreeSet) methods built on them.
+/// Iterations, cursors, range and bulk operations (such as `retain`, `remove_range`,
+/// `append` or `split_off`) and the methods working on addresses are not reported.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Operation {
+	/// Lookup with [`get`](crate::generic::BTreeMap::get),
+	/// [`get_mut`](crate::generic::BTreeMap::get_mut),
+	/// [`get_key_value`](crate::generic::BTreeMap::get_key_value)
+	/// or [`contains_key`](crate::generic::BTreeMap::contains_key).
+	Get,
+
+	/// Insertion with [`insert`](crate::generic::BTreeMap::insert),
+	/// [`insert_at_key`](crate::generic::BTreeMap::insert_at_key),
+	/// [`replace`](crate::generic::BTreeMap::replace),
+	/// [`checked_insert`](crate::generic::BTreeMap::checked_insert),
+	/// [`try_insert`](crate::generic::BTreeMap::try_insert),
+	/// or entry lookup with [`entry`](crate::generic::BTreeMap::entry)
+	/// and [`entry_ref`](crate::generic::BTreeMap::entry_ref),
+	/// whether or not the key was already in the map.
+	Insert,
+
+	/// Removal with [`remove`](crate::generic::BTreeMap::remove),
+	/// [`remove_entry`](crate::generic::BTreeMap::remove_entry),
+	/// [`remove_with_next`](crate::generic::BTreeMap::remove_with_next),
+	/// [`take`](crate::generic::BTreeMap::take),
+	/// [`pop_first`](crate::generic::BTreeMap::pop_first)
+	/// or [`pop_last`](crate::generic::BTreeMap::pop_last)
+	/// (unless the map is empty),
+	/// whether or not the key was in the map.
+	Remove,
+}
+
+/// Receiver of the operations performed on a map.
+///
+/// A sink can be attached to a map with
+/// [`BTreeMap::set_metrics_sink`](crate::generic::BTreeMap::set_metrics_sink).
+///
+/// Requires the `metrics` feature.
+pub trait MetricsSink: Send + Sync {
+	/// Called for each operation performed on the map.
+	///
+	/// The `depth` is the number of nodes visited by the operation's descent in the tree,
+	/// `0` if the map is empty.
+	fn record(&self, op: Operation, depth: usize);
+}
+
+/// Metrics sink counting operations with atomic counters.
+///
+/// Requires the `metrics` feature.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use btree_slab::BTreeMap;
+/// use btree_slab::generic::map::AtomicCounters;
+///
+/// let counters = Arc::new(AtomicCounters::default());
+///
+/// let mut map = BTreeMap::new();
+/// map.set_metrics_sink(counters.clone());
+/// map.insert(1, 'a');
+/// map.get(&1);
+/// map.remove(&1);
+///
+/// assert_eq!(counters.gets(), 1);
+/// assert_eq!(counters.inserts(), 1);
+/// assert_eq!(counters.removes(), 1);
+/// assert_eq!(counters.max_depth(), 1);
+/// ```
+#[derive(Default, Debug)]
+pub struct AtomicCounters {
+	gets: AtomicUsize,
+	inserts: AtomicUsize,
+	removes: AtomicUsize,
+	max_depth: AtomicUsize,
+}
+
+impl AtomicCounters {
+	/// Number of recorded lookups.
+	#[inline]
+	pub fn gets(&self) -> usize {
+		self.gets.load(Ordering::Relaxed)
+	}
+
+	/// Number of recorded insertions.
+	#[inline]
+	pub fn inserts(&self) -> usize {
+		self.inserts.load(Ordering::Relaxed)
+	}
+
+	/// Number of recorded removals.
+	#[inline]
+	pub fn removes(&self) -> usize {
+		self.removes.load(Ordering::Relaxed)
+	}
+
+	/// Maximum depth reached by the recorded operations.
+	#[inline]
+	pub fn max_depth(&self) -> usize {
+		self.max_depth.load(Ordering::Relaxed)
+	}
+}
+
+impl MetricsSink for AtomicCounters {
+	#[inline]
+	fn record(&self, op: Operation, depth: usize) {
+		let counter = match op {
+			Operation::Get => &self.gets,
+			Operation::Insert => &self.inserts,
+			Operation::Remove => &self.removes,
+		};
+
+		counter.fetch_add(1, Ordering::Relaxed);
+		self.max_depth.fetch_max(depth, Ordering::Relaxed);
+	}
+}
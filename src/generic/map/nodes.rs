@@ -0,0 +1,158 @@
+use super::{BTreeExt, BTreeMap};
+use crate::generic::node::{Children, Item, Node};
+use cc_traits::{SimpleCollectionRef, Slab};
+use std::{fmt, iter::FusedIterator};
+
+/// Read-only view of a node of a [`BTreeMap`], visited by [`Nodes`].
+pub struct NodeRef<'a, K, V> {
+	id: usize,
+	depth: usize,
+	node: &'a Node<K, V>,
+}
+
+impl<'a, K, V> NodeRef<'a, K, V> {
+	/// Returns the identifier of the node in the node container.
+	#[inline]
+	pub fn id(&self) -> usize {
+		self.id
+	}
+
+	/// Returns the depth of the node, the root being at depth 0.
+	#[inline]
+	pub fn depth(&self) -> usize {
+		self.depth
+	}
+
+	/// Returns the identifier of the parent node, or `None` for the root.
+	#[inline]
+	pub fn parent(&self) -> Option<usize> {
+		self.node.parent()
+	}
+
+	/// Returns the node.
+	#[inline]
+	pub fn node(&self) -> &'a Node<K, V> {
+		self.node
+	}
+
+	/// Returns `true` if the node is a leaf.
+	#[inline]
+	pub fn is_leaf(&self) -> bool {
+		matches!(self.node, Node::Leaf(_))
+	}
+
+	/// Returns the number of items in the node.
+	#[inline]
+	pub fn item_count(&self) -> usize {
+		self.node.item_count()
+	}
+
+	/// Returns an iterator over the items of the node, in order.
+	#[inline]
+	pub fn items(&self) -> impl DoubleEndedIterator<Item = &'a Item<K, V>> + ExactSizeIterator {
+		let node = self.node;
+		(0..node.item_count()).map(move |i| node.item(i.into()).unwrap())
+	}
+
+	/// Returns an iterator over the identifiers of the children of the node, in order.
+	///
+	/// Leaves have no children.
+	#[inline]
+	pub fn child_ids(&self) -> Children<'a, K, V> {
+		self.node.children()
+	}
+}
+
+impl<'a, K, V> Clone for NodeRef<'a, K, V> {
+	#[inline]
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<'a, K, V> Copy for NodeRef<'a, K, V> {}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug> fmt::Debug for NodeRef<'a, K, V> {
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("NodeRef")
+			.field("id", &self.id)
+			.field("depth", &self.depth)
+			.field("parent", &self.parent())
+			.field(
+				"items",
+				&self.items().map(Item::as_pair).collect::<Vec<_>>(),
+			)
+			.field("children", &self.child_ids().collect::<Vec<_>>())
+			.finish()
+	}
+}
+
+/// Iterator over the nodes of a [`BTreeMap`], in depth-first pre-order.
+///
+/// Each node is visited before its children, and children are visited in order.
+///
+/// This is created by [`BTreeMap::nodes`].
+pub struct Nodes<'a, K, V, C> {
+	btree: &'a BTreeMap<K, V, C>,
+
+	/// Nodes to visit, with their depth, the next one on top.
+	stack: Vec<(usize, usize)>,
+}
+
+impl<'a, K, V, C: Slab<Node<K, V>>> Iterator for Nodes<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = NodeRef<'a, K, V>;
+
+	#[inline]
+	fn next(&mut self) -> Option<NodeRef<'a, K, V>> {
+		let (id, depth) = self.stack.pop()?;
+		let node = self.btree.node(id);
+
+		// Children are pushed in reverse so that the first one is visited first.
+		let start = self.stack.len();
+		self.stack
+			.extend(node.children().map(|child_id| (child_id, depth + 1)));
+		self.stack[start..].reverse();
+
+		Some(NodeRef { id, depth, node })
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<K, V>>> FusedIterator for Nodes<'a, K, V, C> where C: SimpleCollectionRef
+{}
+
+impl<K, V, C: Slab<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Returns an iterator over the nodes of the tree, in depth-first pre-order.
+	///
+	/// Each node is given with its identifier, depth, parent, items and children,
+	/// so that custom analyses of the tree structure (serialization, visualization, statistics)
+	/// can be written without navigating the tree one node at a time.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let map: BTreeMap<u32, u32> = (0..1000).map(|i| (i, i)).collect();
+	///
+	/// let item_count: usize = map.nodes().map(|node| node.item_count()).sum();
+	/// assert_eq!(item_count, 1000);
+	///
+	/// // Leaves are all at the same depth.
+	/// let height = map.nodes().map(|node| node.depth()).max().unwrap();
+	/// assert!(map.nodes().filter(|node| node.is_leaf()).all(|node| node.depth() == height));
+	/// ```
+	#[inline]
+	pub fn nodes(&self) -> Nodes<'_, K, V, C> {
+		Nodes {
+			btree: self,
+			stack: self.root_id().map(|id| (id, 0)).into_iter().collect(),
+		}
+	}
+}
@@ -0,0 +1,65 @@
+use super::{BTreeExt, BTreeMap};
+use crate::generic::node::Node;
+use cc_traits::{SimpleCollectionRef, Slab};
+use std::{any::type_name, borrow::Borrow};
+
+impl<K, V, C: Slab<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Checks that the items of the node `child_id`, child of `id`,
+	/// are strictly between the separators surrounding the child in `id`.
+	///
+	/// Together with the check performed by [`search_min`](crate::utils::search_min)
+	/// on the items of each visited node,
+	/// this detects a key type whose [`Ord`] implementation is inconsistent
+	/// as soon as the search goes through the misplaced item.
+	///
+	/// Requires the `ord-check` feature.
+	///
+	/// # Panics
+	///
+	/// Panics if an item of the child is not strictly between the separators.
+	#[allow(clippy::multiple_bound_locations)]
+	pub(crate) fn check_child_order<Q: ?Sized>(&self, id: usize, child_id: usize)
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let node = self.node(id);
+		let index = node.child_index(child_id).unwrap();
+		let (min, max) = node.separators(index);
+
+		let child = self.node(child_id);
+		let count = child.item_count();
+		if count == 0 {
+			return;
+		}
+
+		if let Some(min) = min {
+			let first = child.item(0.into()).unwrap().key();
+			if min.borrow() >= first.borrow() {
+				panic!(
+					"inconsistent `Ord` implementation for `{}`: the first item of node {} is not greater than the separator at offset {} of its parent node {}",
+					type_name::<Q>(),
+					child_id,
+					index - 1,
+					id
+				)
+			}
+		}
+
+		if let Some(max) = max {
+			let last = child.item((count - 1).into()).unwrap().key();
+			if max.borrow() <= last.borrow() {
+				panic!(
+					"inconsistent `Ord` implementation for `{}`: the last item of node {} is not lower than the separator at offset {} of its parent node {}",
+					type_name::<Q>(),
+					child_id,
+					index,
+					id
+				)
+			}
+		}
+	}
+}
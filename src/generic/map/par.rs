@@ -0,0 +1,767 @@
+use super::{
+	raw::{path_to, RawNode, RawNodes},
+	BTreeExt, BTreeExtMut, BTreeMap, IntoIter,
+};
+use crate::generic::node::{Item, Node, LEAF_CAPACITY};
+use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
+use rayon::iter::{
+	plumbing::{bridge_unindexed, Folder, UnindexedConsumer, UnindexedProducer},
+	IntoParallelIterator, ParallelIterator,
+};
+use smallvec::SmallVec;
+use std::{collections::HashMap, marker::PhantomData, ptr::NonNull};
+
+/// Part of a tree visited by a [`NodeProducer`].
+#[derive(Clone, Copy)]
+enum Part<H> {
+	/// Every item of the subtree rooted in the given node.
+	Subtree(usize),
+
+	/// A single item of an internal node.
+	Item(H),
+}
+
+/// Node read by a [`NodeProducer`].
+struct Loaded<H> {
+	/// Handles to the items of the node.
+	items: SmallVec<[H; LEAF_CAPACITY]>,
+
+	/// Children of the node, empty for a leaf.
+	children: SmallVec<[usize; LEAF_CAPACITY]>,
+}
+
+/// Tree visited by a [`NodeProducer`].
+///
+/// The items of the tree are given out through handles, obtained by reading the nodes.
+/// A node must not be read once any of its items was given out,
+/// and each item must be given out or discarded at most once.
+trait Tree: Copy + Send {
+	/// Handle to an item.
+	type Handle: Copy + Send;
+
+	/// Item given out by the tree.
+	type Item;
+
+	/// Reads the node `id`.
+	///
+	/// # Safety
+	///
+	/// None of the items of the node must have been given out or discarded.
+	unsafe fn load(&self, id: usize) -> Loaded<Self::Handle>;
+
+	/// Gives out the item behind the given handle.
+	///
+	/// # Safety
+	///
+	/// The item must not have been given out or discarded.
+	unsafe fn give(&self, handle: Self::Handle) -> Self::Item;
+
+	/// Discards the given part of the tree, which will not be visited.
+	///
+	/// # Safety
+	///
+	/// None of the items of the part must have been given out or discarded.
+	#[inline]
+	unsafe fn discard(&self, _part: Part<Self::Handle>) {}
+}
+
+/// Producer visiting a sequence of subtrees and items, in order.
+///
+/// The parts that are not visited, because the consumer is full,
+/// are discarded.
+struct NodeProducer<T: Tree> {
+	tree: T,
+	parts: Vec<Part<T::Handle>>,
+}
+
+impl<T: Tree> NodeProducer<T> {
+	/// Creates a producer visiting the whole tree rooted in `root`.
+	fn new(tree: T, root: Option<usize>) -> Self {
+		NodeProducer {
+			tree,
+			parts: root.map(Part::Subtree).into_iter().collect(),
+		}
+	}
+
+	/// Folds every item of the given part, in order.
+	///
+	/// # Safety
+	///
+	/// None of the items of the part must have been given out or discarded.
+	unsafe fn fold_part<F>(&self, part: Part<T::Handle>, folder: F) -> F
+	where
+		F: Folder<T::Item>,
+	{
+		if folder.full() {
+			self.tree.discard(part);
+			return folder;
+		}
+
+		match part {
+			Part::Subtree(id) => {
+				let node = self.tree.load(id);
+				let mut folder = folder;
+				for (i, &handle) in node.items.iter().enumerate() {
+					if let Some(&child) = node.children.get(i) {
+						folder = self.fold_part(Part::Subtree(child), folder)
+					}
+
+					folder = self.fold_part(Part::Item(handle), folder)
+				}
+
+				if let Some(&child) = node.children.get(node.items.len()) {
+					folder = self.fold_part(Part::Subtree(child), folder)
+				}
+
+				folder
+			}
+			Part::Item(handle) => folder.consume(self.tree.give(handle)),
+		}
+	}
+}
+
+impl<T: Tree> UnindexedProducer for NodeProducer<T> {
+	type Item = T::Item;
+
+	fn split(mut self) -> (Self, Option<Self>) {
+		// A single internal subtree is split into its children and items.
+		if let [Part::Subtree(id)] = self.parts[..] {
+			// SAFETY: the subtree was not visited yet.
+			let node = unsafe { self.tree.load(id) };
+			if !node.children.is_empty() {
+				self.parts.clear();
+				for (i, &child) in node.children.iter().enumerate() {
+					if i > 0 {
+						self.parts.push(Part::Item(node.items[i - 1]))
+					}
+
+					self.parts.push(Part::Subtree(child))
+				}
+			}
+		}
+
+		if self.parts.len() < 2 {
+			return (self, None);
+		}
+
+		let right = NodeProducer {
+			tree: self.tree,
+			parts: self.parts.split_off(self.parts.len() / 2),
+		};
+
+		(self, Some(right))
+	}
+
+	fn fold_with<F>(mut self, mut folder: F) -> F
+	where
+		F: Folder<Self::Item>,
+	{
+		for part in std::mem::take(&mut self.parts) {
+			// SAFETY: each part is visited once.
+			folder = unsafe { self.fold_part(part, folder) }
+		}
+
+		folder
+	}
+}
+
+impl<T: Tree> Drop for NodeProducer<T> {
+	fn drop(&mut self) {
+		for part in self.parts.drain(..) {
+			// SAFETY: the parts left were not visited.
+			unsafe { self.tree.discard(part) }
+		}
+	}
+}
+
+/// Pointers to the nodes of a tree, indexed by node identifier.
+///
+/// Threads cannot access the node container concurrently,
+/// so the container gives a pointer to each node of the tree (see [`RawNodes`])
+/// before the tree is shared between threads.
+/// This takes a time proportional to the size of the tree.
+struct NodeTable<K, V>(HashMap<usize, NonNull<Node<K, V>>>);
+
+impl<K, V> NodeTable<K, V> {
+	/// Gets a pointer to each node of the subtrees rooted in `roots`.
+	#[allow(clippy::multiple_bound_locations)]
+	fn new<C: SlabMut<Node<K, V>>>(nodes: &mut C, roots: impl IntoIterator<Item = usize>) -> Self
+	where
+		C: SimpleCollectionMut,
+	{
+		let nodes = RawNodes::new(nodes);
+		let mut table = HashMap::new();
+		let mut stack: Vec<usize> = roots.into_iter().collect();
+		while let Some(id) = stack.pop() {
+			// SAFETY: the container is borrowed and holds the nodes of the tree,
+			// none of which is lent out yet.
+			let node = unsafe { nodes.get(id) };
+			stack.extend(unsafe { node.as_ref() }.children());
+			table.insert(id, node);
+		}
+
+		NodeTable(table)
+	}
+}
+
+/// Tree borrowed immutably.
+struct SharedTree<'a, K, V, C>(&'a BTreeMap<K, V, C>);
+
+impl<'a, K, V, C> Clone for SharedTree<'a, K, V, C> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<'a, K, V, C> Copy for SharedTree<'a, K, V, C> {}
+
+impl<'a, K: Sync, V: Sync, C: Slab<Node<K, V>>> Tree for SharedTree<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: Sync,
+{
+	type Handle = &'a Item<K, V>;
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	unsafe fn load(&self, id: usize) -> Loaded<&'a Item<K, V>> {
+		let node = self.0.node(id);
+		let items = match node {
+			Node::Leaf(leaf) => leaf.items().iter().collect(),
+			Node::Internal(node) => node.branches().iter().map(|branch| &branch.item).collect(),
+		};
+
+		Loaded {
+			items,
+			children: node.children().collect(),
+		}
+	}
+
+	#[inline]
+	unsafe fn give(&self, item: &'a Item<K, V>) -> (&'a K, &'a V) {
+		item.as_pair()
+	}
+}
+
+/// Pointer to an item of a tree read through a [`NodeTable`].
+struct RawItem<K, V>(NonNull<Item<K, V>>);
+
+impl<K, V> Clone for RawItem<K, V> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<K, V> Copy for RawItem<K, V> {}
+
+// SAFETY: the pointer is only used through the tree it comes from,
+// which is `Send` only if its items can be given out to another thread.
+unsafe impl<K, V> Send for RawItem<K, V> {}
+
+impl<K, V> RawItem<K, V> {
+	/// Reads the node `id` mutably, converting it to a [`Loaded`] node.
+	///
+	/// # Safety
+	///
+	/// Same as [`RawNode::load`].
+	#[inline]
+	unsafe fn load(nodes: &NodeTable<K, V>, id: usize) -> Loaded<Self> {
+		let RawNode {
+			items, children, ..
+		} = RawNode::load(id, nodes.0[&id], false);
+		Loaded {
+			items: items.into_iter().map(RawItem).collect(),
+			children,
+		}
+	}
+}
+
+/// Tree borrowed mutably, giving out mutable references to its values.
+///
+/// The nodes are only accessed through the pointers of the table.
+struct MutTree<'t, 'a, K, V> {
+	nodes: &'t NodeTable<K, V>,
+	marker: PhantomData<(&'a K, &'a mut V)>,
+}
+
+impl<'t, 'a, K, V> Clone for MutTree<'t, 'a, K, V> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<'t, 'a, K, V> Copy for MutTree<'t, 'a, K, V> {}
+
+// SAFETY: the tree gives out `(&K, &mut V)` pairs, which are `Send` under these conditions.
+unsafe impl<'t, 'a, K: Sync, V: Send> Send for MutTree<'t, 'a, K, V> {}
+
+impl<'t, 'a, K: Sync, V: Send> Tree for MutTree<'t, 'a, K, V> {
+	type Handle = RawItem<K, V>;
+	type Item = (&'a K, &'a mut V);
+
+	#[inline]
+	unsafe fn load(&self, id: usize) -> Loaded<RawItem<K, V>> {
+		RawItem::load(self.nodes, id)
+	}
+
+	#[inline]
+	unsafe fn give(&self, item: RawItem<K, V>) -> (&'a K, &'a mut V) {
+		let (key, value) = (*item.0.as_ptr()).as_pair_mut();
+		(key, value)
+	}
+}
+
+/// Tree whose items are moved out.
+///
+/// The items that are not moved out are dropped in place.
+/// Either way, the nodes must then be released without dropping them.
+struct OwnedTree<'t, K, V> {
+	nodes: &'t NodeTable<K, V>,
+}
+
+impl<'t, K, V> Clone for OwnedTree<'t, K, V> {
+	fn clone(&self) -> Self {
+		*self
+	}
+}
+
+impl<'t, K, V> Copy for OwnedTree<'t, K, V> {}
+
+// SAFETY: the tree gives out `(K, V)` pairs, which are `Send` under these conditions.
+unsafe impl<'t, K: Send, V: Send> Send for OwnedTree<'t, K, V> {}
+
+impl<'t, K: Send, V: Send> Tree for OwnedTree<'t, K, V> {
+	type Handle = RawItem<K, V>;
+	type Item = (K, V);
+
+	#[inline]
+	unsafe fn load(&self, id: usize) -> Loaded<RawItem<K, V>> {
+		RawItem::load(self.nodes, id)
+	}
+
+	#[inline]
+	unsafe fn give(&self, item: RawItem<K, V>) -> (K, V) {
+		std::ptr::read(item.0.as_ptr()).into_pair()
+	}
+
+	unsafe fn discard(&self, part: Part<RawItem<K, V>>) {
+		match part {
+			Part::Subtree(id) => {
+				let node = self.load(id);
+				for item in node.items {
+					self.discard(Part::Item(item))
+				}
+
+				for child in node.children {
+					self.discard(Part::Subtree(child))
+				}
+			}
+			Part::Item(item) => std::ptr::drop_in_place(item.0.as_ptr()),
+		}
+	}
+}
+
+/// Adds the parts covering the items from the end of the `front` path
+/// to the end of the `back` path, both included, in the subtree of the node starting the paths.
+///
+/// The paths are given by [`path_to`]: their last offset is the offset of an item,
+/// and the others are child indexes.
+/// A missing path means that the items are not bounded on this side.
+/// The identifiers of the nodes read on the way are added to `read`.
+///
+/// # Safety
+///
+/// None of the items of the nodes on the paths must have been given out or discarded.
+unsafe fn range_parts<K, V>(
+	nodes: &RawNodes<K, V>,
+	front: Option<&[(usize, usize)]>,
+	back: Option<&[(usize, usize)]>,
+	parts: &mut Vec<Part<RawItem<K, V>>>,
+	read: &mut Vec<usize>,
+) {
+	let id = front.or(back).unwrap()[0].0;
+	let node = nodes.load(id, false);
+	read.push(id);
+
+	if node.children.is_empty() {
+		let start = front.map_or(0, |path| path[0].1);
+		let end = back.map_or(node.items.len(), |path| path[0].1 + 1);
+		parts.extend(
+			node.items[start..end]
+				.iter()
+				.map(|&item| Part::Item(RawItem(item))),
+		);
+		return;
+	}
+
+	// Positions in the sequence of children and items of the node,
+	// where child `i` is at position `2 * i` and item `i` at position `2 * i + 1`.
+	let start = match front {
+		None => 0,
+		Some(&[(_, offset)]) => 2 * offset + 1,
+		Some(path) => 2 * path[0].1,
+	};
+
+	let end = match back {
+		None => 2 * node.items.len(),
+		Some(&[(_, offset)]) => 2 * offset + 1,
+		Some(path) => 2 * path[0].1,
+	};
+
+	for position in start..=end {
+		let i = position / 2;
+		if position % 2 == 1 {
+			parts.push(Part::Item(RawItem(node.items[i])))
+		} else {
+			let front = front.filter(|_| position == start).map(|path| &path[1..]);
+			let back = back.filter(|_| position == end).map(|path| &path[1..]);
+			if front.is_none() && back.is_none() {
+				parts.push(Part::Subtree(node.children[i]))
+			} else {
+				range_parts(nodes, front, back, parts, read)
+			}
+		}
+	}
+}
+
+/// Map whose items were all moved out or dropped in place.
+///
+/// The given nodes, which are the nodes of the tree left in the container,
+/// are released without being dropped when it is dropped.
+struct MovedOut<K, V, C: SlabMut<Node<K, V>>>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	btree: BTreeMap<K, V, C>,
+	nodes: Vec<usize>,
+}
+
+impl<K, V, C: SlabMut<Node<K, V>>> Drop for MovedOut<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	fn drop(&mut self) {
+		for id in self.nodes.drain(..) {
+			let node = self.btree.release_node(id);
+			std::mem::forget(node); // do not call `drop` on the node since items have been moved.
+		}
+
+		self.btree.root = None;
+		self.btree.len = 0;
+		self.btree.leaf_bounds = None
+	}
+}
+
+/// Parallel iterator over the entries of a [`BTreeMap`].
+///
+/// The work is split along the subtrees of the map:
+/// the root is divided into its children subtrees and separating items,
+/// which are themselves divided until each part is small enough.
+///
+/// This is created by [`BTreeMap::par_iter`].
+/// Requires the `rayon` feature.
+pub struct ParIter<'a, K, V, C> {
+	btree: &'a BTreeMap<K, V, C>,
+}
+
+impl<'a, K, V, C> Clone for ParIter<'a, K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		ParIter { btree: self.btree }
+	}
+}
+
+impl<'a, K: Sync, V: Sync, C: Slab<Node<K, V>>> ParallelIterator for ParIter<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: Sync,
+{
+	type Item = (&'a K, &'a V);
+
+	fn drive_unindexed<U>(self, consumer: U) -> U::Result
+	where
+		U: UnindexedConsumer<Self::Item>,
+	{
+		let producer = NodeProducer::new(SharedTree(self.btree), self.btree.root_id());
+		bridge_unindexed(producer, consumer)
+	}
+}
+
+/// Parallel iterator over the entries of a [`BTreeMap`], with mutable references to the values.
+///
+/// The work is split along the subtrees of the map, like [`ParIter`].
+/// The node container gives a pointer to each node of the map when the iterator is created,
+/// so that the references given to different threads never alias.
+///
+/// This is created by [`BTreeMap::par_iter_mut`].
+/// Requires the `rayon` feature.
+pub struct ParIterMut<'a, K, V> {
+	nodes: NodeTable<K, V>,
+	root: Option<usize>,
+	marker: PhantomData<(&'a K, &'a mut V)>,
+}
+
+// Same as `(&'a K, &'a mut V)`.
+unsafe impl<'a, K: Sync, V: Send> Send for ParIterMut<'a, K, V> {}
+
+impl<'a, K: Sync, V: Send> ParallelIterator for ParIterMut<'a, K, V> {
+	type Item = (&'a K, &'a mut V);
+
+	fn drive_unindexed<U>(self, consumer: U) -> U::Result
+	where
+		U: UnindexedConsumer<Self::Item>,
+	{
+		let tree = MutTree {
+			nodes: &self.nodes,
+			marker: PhantomData,
+		};
+
+		bridge_unindexed(NodeProducer::new(tree, self.root), consumer)
+	}
+}
+
+/// Owning parallel iterator over the entries of a [`BTreeMap`].
+///
+/// The work is split along the subtrees of the map, like [`ParIter`],
+/// and the entries are moved out of the nodes in place.
+/// The entries that are not visited, for instance by [`ParallelIterator::find_any`],
+/// are dropped.
+///
+/// This is created by the [`IntoParallelIterator`] implementations of [`BTreeMap`]
+/// and [`IntoIter`].
+/// Requires the `rayon` feature.
+pub struct IntoParIter<K, V, C> {
+	iter: IntoIter<K, V, C>,
+}
+
+impl<K: Send, V: Send, C: SlabMut<Node<K, V>>> ParallelIterator for IntoParIter<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+	C: Send,
+{
+	type Item = (K, V);
+
+	fn drive_unindexed<U>(self, consumer: U) -> U::Result
+	where
+		U: UnindexedConsumer<Self::Item>,
+	{
+		let IntoIter {
+			btree,
+			addr,
+			end,
+			len,
+		} = self.iter;
+		let mut btree = MovedOut {
+			btree,
+			nodes: Vec::new(),
+		};
+
+		let mut parts = Vec::new();
+		if len == btree.btree.len() {
+			parts.extend(btree.btree.root_id().map(Part::Subtree))
+		} else if len > 0 {
+			// Some items were already moved out by the sequential iterator.
+			// The nodes on the paths to the first and last items left are still there,
+			// as well as the node of `end` and its ancestors, even if all their items
+			// were moved out (see `IntoIter::next_back_entry`).
+			let map = &mut btree.btree;
+			let last = match end {
+				Some(end) => map.previous_item_address(end),
+				None => map.last_item_address(),
+			};
+
+			let front = path_to(map, addr.unwrap(), 0);
+			let back = path_to(map, last.unwrap(), 0);
+			let nodes = RawNodes::new(&mut map.nodes);
+			// SAFETY: the items between `addr` and `last` were not moved out yet.
+			unsafe {
+				range_parts(
+					&nodes,
+					Some(&front),
+					Some(&back),
+					&mut parts,
+					&mut btree.nodes,
+				)
+			}
+
+			let mut id = end.map(|end| end.id);
+			while let Some(node_id) = id.filter(|id| !btree.nodes.contains(id)) {
+				btree.nodes.push(node_id);
+				id = btree.btree.node(node_id).parent()
+			}
+		}
+
+		let roots: Vec<_> = parts
+			.iter()
+			.filter_map(|part| match part {
+				Part::Subtree(id) => Some(*id),
+				Part::Item(_) => None,
+			})
+			.collect();
+		let nodes = NodeTable::new(&mut btree.btree.nodes, roots);
+		btree.nodes.extend(nodes.0.keys());
+
+		let producer = NodeProducer {
+			tree: OwnedTree { nodes: &nodes },
+			parts,
+		};
+		bridge_unindexed(producer, consumer)
+	}
+}
+
+impl<K: Sync, V: Sync, C: Slab<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: Sync,
+{
+	/// Gets a parallel iterator over the entries of the map.
+	///
+	/// The entries are not visited in order,
+	/// but order-preserving operations such as `collect` keep them sorted by key.
+	///
+	/// Requires the `rayon` feature.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use rayon::prelude::*;
+	///
+	/// let map: BTreeMap<u64, u64> = (0..10000).map(|i| (i, i)).collect();
+	/// let sum: u64 = map.par_iter().map(|(_, v)| v).sum();
+	/// assert_eq!(sum, 49995000);
+	/// ```
+	#[inline]
+	pub fn par_iter(&self) -> ParIter<'_, K, V, C> {
+		ParIter { btree: self }
+	}
+}
+
+impl<K: Sync, V: Send, C: SlabMut<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Gets a parallel iterator over the entries of the map,
+	/// with mutable references to the values.
+	///
+	/// Creating the iterator takes a time proportional to the size of the map.
+	///
+	/// Requires the `rayon` feature.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use rayon::prelude::*;
+	///
+	/// let mut map: BTreeMap<u64, u64> = (0..10000).map(|i| (i, i)).collect();
+	/// map.par_iter_mut().for_each(|(k, v)| *v += k);
+	/// assert_eq!(map[&42], 84);
+	/// ```
+	#[inline]
+	pub fn par_iter_mut(&mut self) -> ParIterMut<'_, K, V> {
+		#[cfg(feature = "checksum")]
+		self.unseal(); // the items may be modified.
+
+		ParIterMut {
+			nodes: NodeTable::new(&mut self.nodes, self.root),
+			root: self.root,
+			marker: PhantomData,
+		}
+	}
+}
+
+impl<'a, K: Sync, V: Sync, C: Slab<Node<K, V>>> IntoParallelIterator for &'a BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: Sync,
+{
+	type Item = (&'a K, &'a V);
+	type Iter = ParIter<'a, K, V, C>;
+
+	#[inline]
+	fn into_par_iter(self) -> ParIter<'a, K, V, C> {
+		self.par_iter()
+	}
+}
+
+impl<'a, K: Sync, V: Send, C: SlabMut<Node<K, V>>> IntoParallelIterator
+	for &'a mut BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	type Item = (&'a K, &'a mut V);
+	type Iter = ParIterMut<'a, K, V>;
+
+	#[inline]
+	fn into_par_iter(self) -> ParIterMut<'a, K, V> {
+		self.par_iter_mut()
+	}
+}
+
+impl<K: Send, V: Send, C: SlabMut<Node<K, V>>> IntoParallelIterator for BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+	C: Send,
+{
+	type Item = (K, V);
+	type Iter = IntoParIter<K, V, C>;
+
+	/// Moves the entries out of the map, and iterates over them in parallel.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use rayon::prelude::*;
+	///
+	/// let map: BTreeMap<u64, String> = (0..100).map(|i| (i, i.to_string())).collect();
+	/// let strings: Vec<String> = map.into_par_iter().map(|(_, s)| s).collect();
+	/// assert_eq!(strings[42], "42");
+	/// ```
+	#[inline]
+	fn into_par_iter(self) -> IntoParIter<K, V, C> {
+		IntoParIter {
+			iter: self.into_iter(),
+		}
+	}
+}
+
+impl<K: Send, V: Send, C: SlabMut<Node<K, V>>> IntoParallelIterator for IntoIter<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+	C: Send,
+{
+	type Item = (K, V);
+	type Iter = IntoParIter<K, V, C>;
+
+	/// Moves the entries left out of the map, and iterates over them in parallel.
+	///
+	/// The work is split along the subtrees holding the entries left.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	/// use rayon::prelude::*;
+	///
+	/// let map: BTreeMap<u64, u64> = (0..100).map(|i| (i, i)).collect();
+	/// let mut iter = map.into_iter();
+	/// assert_eq!(iter.next(), Some((0, 0)));
+	/// assert_eq!(iter.next_back(), Some((99, 99)));
+	///
+	/// let keys: Vec<u64> = iter.into_par_iter().map(|(k, _)| k).collect();
+	/// assert_eq!(keys, (1..99).collect::<Vec<_>>());
+	/// ```
+	#[inline]
+	fn into_par_iter(self) -> IntoParIter<K, V, C> {
+		IntoParIter { iter: self }
+	}
+}
@@ -0,0 +1,472 @@
+use super::{BTreeExt, BTreeMap};
+use crate::generic::node::{Address, Item, Node, LEAF_CAPACITY};
+use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
+use smallvec::SmallVec;
+use std::{marker::PhantomData, ptr::NonNull};
+
+/// Node container of a mutably borrowed tree, accessed through raw pointers.
+///
+/// The mutable iterators of [`BTreeMap`] lend out references to items
+/// of many nodes at the same time.
+/// A node is accessed through [`cc_traits::GetMut`] the first time an iterator end enters it,
+/// and only through the returned pointer from then on.
+/// Since nodes are never entered twice, creating an iterator and moving it to the next item
+/// only accesses the nodes on the way.
+pub(super) struct RawNodes<K, V> {
+	/// Mutably borrowed node container.
+	container: NonNull<()>,
+
+	/// Gets a pointer to a node of the container.
+	get: unsafe fn(NonNull<()>, usize) -> NonNull<Node<K, V>>,
+}
+
+/// Gets a pointer to the node `id` of the container `C`.
+///
+/// # Safety
+///
+/// `container` must point to a mutably borrowed `C` holding the node `id`.
+#[allow(clippy::multiple_bound_locations)]
+unsafe fn get_node<K, V, C: SlabMut<Node<K, V>>>(
+	container: NonNull<()>,
+	id: usize,
+) -> NonNull<Node<K, V>>
+where
+	C: SimpleCollectionMut,
+{
+	let nodes = &mut *container.cast::<C>().as_ptr();
+	NonNull::from(C::into_mut(nodes.get_mut(id).unwrap()))
+}
+
+impl<K, V> RawNodes<K, V> {
+	#[allow(clippy::multiple_bound_locations)]
+	pub(super) fn new<C: SlabMut<Node<K, V>>>(nodes: &mut C) -> Self
+	where
+		C: SimpleCollectionMut,
+	{
+		RawNodes {
+			container: NonNull::from(nodes).cast(),
+			get: get_node::<K, V, C>,
+		}
+	}
+
+	/// Returns a pointer to the given node.
+	///
+	/// # Safety
+	///
+	/// The container must still be borrowed, and hold the node.
+	#[inline]
+	pub(super) unsafe fn get(&self, id: usize) -> NonNull<Node<K, V>> {
+		(self.get)(self.container, id)
+	}
+
+	/// Reads the given node.
+	///
+	/// # Safety
+	///
+	/// Same as [`RawNode::load`].
+	/// The container must still be borrowed, and hold the node.
+	#[inline]
+	pub(super) unsafe fn load(&self, id: usize, shared: bool) -> RawNode<K, V> {
+		RawNode::load(id, self.get(id), shared)
+	}
+}
+
+/// Node read once, before any of its items is lent out.
+pub(super) struct RawNode<K, V> {
+	id: usize,
+
+	/// Pointers to the items of the node.
+	pub(super) items: SmallVec<[NonNull<Item<K, V>>; LEAF_CAPACITY]>,
+
+	/// Children of the node, empty for a leaf.
+	pub(super) children: SmallVec<[usize; LEAF_CAPACITY]>,
+}
+
+impl<K, V> RawNode<K, V> {
+	/// Reads the node `id`, pointed to by `ptr`.
+	///
+	/// # Safety
+	///
+	/// None of the items of the node must be lent out,
+	/// since the node is borrowed mutably (or immutably if `shared` is `true`)
+	/// to get pointers to its items.
+	/// Once an item pointer given by a mutable read is used,
+	/// the node must not be read again.
+	pub(super) unsafe fn load(id: usize, ptr: NonNull<Node<K, V>>, shared: bool) -> Self {
+		let children = ptr.as_ref().children().collect();
+		let items = if shared {
+			match ptr.as_ref() {
+				Node::Leaf(leaf) => leaf.items().iter().map(NonNull::from).collect(),
+				Node::Internal(node) => node
+					.branches()
+					.iter()
+					.map(|branch| NonNull::from(&branch.item))
+					.collect(),
+			}
+		} else {
+			match &mut *ptr.as_ptr() {
+				Node::Leaf(leaf) => leaf.items_mut().iter_mut().map(NonNull::from).collect(),
+				Node::Internal(node) => node.items_mut().map(NonNull::from).collect(),
+			}
+		};
+
+		RawNode {
+			id,
+			items,
+			children,
+		}
+	}
+
+	fn is_leaf(&self) -> bool {
+		self.children.is_empty()
+	}
+}
+
+impl<K, V> Clone for RawNode<K, V> {
+	fn clone(&self) -> Self {
+		RawNode {
+			id: self.id,
+			items: self.items.clone(),
+			children: self.children.clone(),
+		}
+	}
+}
+
+/// Node on the path from the root to one end of the iterator.
+///
+/// In a leaf, the offset is the offset of the next item for the front end,
+/// and the offset following the next item for the back end.
+/// In an internal node, it is the index of the child containing the end.
+/// Once this child is done, the next item is the item at this offset for the front end,
+/// and the item before it for the back end.
+struct Frame<K, V> {
+	node: RawNode<K, V>,
+	offset: usize,
+}
+
+impl<K, V> Clone for Frame<K, V> {
+	fn clone(&self) -> Self {
+		Frame {
+			node: self.node.clone(),
+			offset: self.offset,
+		}
+	}
+}
+
+/// Reads the node `id` entered by one end of the iterator.
+///
+/// If it is already on the path of the other end, it must not be read again
+/// since some of its items may be lent out.
+///
+/// # Safety
+///
+/// If the node is not on the path of the other end, none of its items must be lent out.
+unsafe fn enter<K, V>(
+	nodes: &RawNodes<K, V>,
+	other: &[Frame<K, V>],
+	id: usize,
+	shared: bool,
+) -> RawNode<K, V> {
+	match other.iter().find(|frame| frame.node.id == id) {
+		Some(frame) => frame.node.clone(),
+		None => nodes.load(id, shared),
+	}
+}
+
+/// Moves the front end past its next item.
+///
+/// # Safety
+///
+/// There must be an item left after the next one,
+/// and the nodes that are not on the path of either end must have no item lent out.
+unsafe fn advance_front<K, V>(
+	path: &mut Vec<Frame<K, V>>,
+	other: &[Frame<K, V>],
+	nodes: &RawNodes<K, V>,
+	shared: bool,
+) {
+	let frame = path.last_mut().unwrap();
+	frame.offset += 1;
+	if frame.node.is_leaf() {
+		while path
+			.last()
+			.is_some_and(|frame| frame.offset >= frame.node.items.len())
+		{
+			path.pop();
+		}
+	} else {
+		let mut id = frame.node.children[frame.offset];
+		loop {
+			let node = enter(nodes, other, id, shared);
+			let first_child = node.children.first().copied();
+			path.push(Frame { node, offset: 0 });
+			match first_child {
+				Some(child) => id = child,
+				None => break,
+			}
+		}
+	}
+}
+
+/// Moves the back end past its next item.
+///
+/// # Safety
+///
+/// Same as [`advance_front`].
+unsafe fn advance_back<K, V>(
+	path: &mut Vec<Frame<K, V>>,
+	other: &[Frame<K, V>],
+	nodes: &RawNodes<K, V>,
+	shared: bool,
+) {
+	let frame = path.last_mut().unwrap();
+	frame.offset -= 1;
+	if frame.node.is_leaf() {
+		while path.last().is_some_and(|frame| frame.offset == 0) {
+			path.pop();
+		}
+	} else {
+		let mut id = frame.node.children[frame.offset];
+		loop {
+			let node = enter(nodes, other, id, shared);
+			let last_child = node.children.last().copied();
+			let offset = node.items.len();
+			path.push(Frame { node, offset });
+			match last_child {
+				Some(child) => id = child,
+				None => break,
+			}
+		}
+	}
+}
+
+/// Mutable borrow of a B-Tree lending out mutable references to its items.
+///
+/// This is the unsafe core of the mutable iterators.
+/// The tree is only accessed when the borrow is created, to compute the paths to both ends.
+/// From then on, the nodes are only accessed through pointers given by the node container
+/// (see [`RawNodes`]), and each node is read once, when one end enters it,
+/// before any of its items is lent out.
+/// Both ends share the nodes they have in common, so that a node is never read again
+/// while one of its items is lent out.
+/// No reference to the tree or to a node is ever created while an item is lent out.
+///
+/// # Invariants
+///
+/// - The pointers come from a `&'a mut BTreeMap`, hence they are valid and unaliased for `'a`.
+/// - The tree is never modified through this borrow, except for the lent out items.
+/// - Each item is lent out at most once, as one end moves past it.
+pub(crate) struct RawItemsMut<'a, K, V, C> {
+	nodes: RawNodes<K, V>,
+
+	/// Path from the root to the next item of the front end.
+	front: Vec<Frame<K, V>>,
+
+	/// Path from the root to the next item of the back end.
+	back: Vec<Frame<K, V>>,
+
+	/// Number of items left.
+	len: usize,
+
+	marker: PhantomData<&'a mut BTreeMap<K, V, C>>,
+}
+
+// Same as `&'a mut BTreeMap<K, V, C>`.
+unsafe impl<'a, K, V, C> Send for RawItemsMut<'a, K, V, C> where BTreeMap<K, V, C>: Send {}
+
+// Same as `&'a mut BTreeMap<K, V, C>`.
+unsafe impl<'a, K, V, C> Sync for RawItemsMut<'a, K, V, C> where BTreeMap<K, V, C>: Sync {}
+
+impl<'a, K, V, C: SlabMut<Node<K, V>>> RawItemsMut<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Borrows the `len` items of the tree starting at the `first` address
+	/// and ending at the `last` address, both included.
+	///
+	/// The addresses must be item addresses, unless `len` is 0.
+	pub fn new(
+		btree: &'a mut BTreeMap<K, V, C>,
+		first: Option<Address>,
+		last: Option<Address>,
+		len: usize,
+	) -> Self {
+		#[cfg(feature = "checksum")]
+		btree.unseal(); // the items may be modified.
+
+		if len == 0 {
+			return RawItemsMut {
+				nodes: RawNodes::new(&mut btree.nodes),
+				front: Vec::new(),
+				back: Vec::new(),
+				len,
+				marker: PhantomData,
+			};
+		}
+
+		// The paths are computed before any item is lent out.
+		let front_path = path_to(btree, first.unwrap(), 0);
+		let back_path = path_to(btree, last.unwrap(), 1);
+		let nodes = RawNodes::new(&mut btree.nodes);
+
+		// SAFETY: no item is lent out yet,
+		// and the nodes shared by both paths are read only once.
+		unsafe {
+			let mut front = Vec::with_capacity(front_path.len());
+			for (id, offset) in front_path {
+				let node = enter(&nodes, &[], id, false);
+				front.push(Frame { node, offset })
+			}
+
+			let mut back = Vec::with_capacity(back_path.len());
+			for (id, offset) in back_path {
+				let node = enter(&nodes, &front, id, false);
+				back.push(Frame { node, offset })
+			}
+
+			RawItemsMut {
+				nodes,
+				front,
+				back,
+				len,
+				marker: PhantomData,
+			}
+		}
+	}
+}
+
+/// Returns the path from the root to the item at `addr`,
+/// as a list of node identifiers and frame offsets.
+///
+/// The offset of the item is increased by `delta` in the last frame.
+#[allow(clippy::multiple_bound_locations)]
+pub(super) fn path_to<K, V, C: Slab<Node<K, V>>>(
+	btree: &BTreeMap<K, V, C>,
+	addr: Address,
+	delta: usize,
+) -> Vec<(usize, usize)>
+where
+	C: SimpleCollectionRef,
+{
+	let mut path = vec![(addr.id, addr.offset.unwrap() + delta)];
+	let mut id = addr.id;
+	while let Some(parent) = btree.node(id).parent() {
+		path.push((parent, btree.node(parent).child_index(id).unwrap()));
+		id = parent
+	}
+
+	path.reverse();
+	path
+}
+
+impl<'a, K, V, C> RawItemsMut<'a, K, V, C> {
+	/// Returns the number of items left.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns a pointer to the next item of the front end, if any.
+	///
+	/// The item is not lent out.
+	#[inline]
+	pub fn peek(&self) -> Option<NonNull<Item<K, V>>> {
+		if self.len > 0 {
+			let frame = self.front.last().unwrap();
+			Some(frame.node.items[frame.offset])
+		} else {
+			None
+		}
+	}
+
+	/// Returns a pointer to the next item of the back end, if any.
+	///
+	/// The item is not lent out.
+	#[inline]
+	pub fn peek_back(&self) -> Option<NonNull<Item<K, V>>> {
+		if self.len > 0 {
+			let frame = self.back.last().unwrap();
+			Some(frame.node.items[frame.offset - 1])
+		} else {
+			None
+		}
+	}
+
+	/// Lends out the next item of the front end.
+	#[inline]
+	pub fn next(&mut self) -> Option<&'a mut Item<K, V>> {
+		let item = self.peek()?;
+		self.len -= 1;
+		if self.len > 0 {
+			// SAFETY: an item is left, and the nodes that are not on either path
+			// have not been entered yet.
+			unsafe { advance_front(&mut self.front, &self.back, &self.nodes, false) }
+		}
+
+		// SAFETY: each item is lent out once, as the front end moves past it,
+		// and it is valid for `'a`.
+		Some(unsafe { &mut *item.as_ptr() })
+	}
+
+	/// Lends out the next item of the back end.
+	#[inline]
+	pub fn next_back(&mut self) -> Option<&'a mut Item<K, V>> {
+		let item = self.peek_back()?;
+		self.len -= 1;
+		if self.len > 0 {
+			// SAFETY: an item is left, and the nodes that are not on either path
+			// have not been entered yet.
+			unsafe { advance_back(&mut self.back, &self.front, &self.nodes, false) }
+		}
+
+		// SAFETY: each item is lent out once, as the back end moves past it,
+		// and it is valid for `'a`.
+		Some(unsafe { &mut *item.as_ptr() })
+	}
+
+	/// Returns an iterator over the items left, without lending them out.
+	#[inline]
+	pub fn remaining(&self) -> Remaining<'_, K, V> {
+		Remaining {
+			nodes: &self.nodes,
+			front: self.front.clone(),
+			back: &self.back,
+			len: self.len,
+		}
+	}
+}
+
+/// Iterator over the items left in a [`RawItemsMut`].
+///
+/// The nodes that are not yet entered by either end are read immutably.
+pub(crate) struct Remaining<'b, K, V> {
+	nodes: &'b RawNodes<K, V>,
+	front: Vec<Frame<K, V>>,
+	back: &'b [Frame<K, V>],
+	len: usize,
+}
+
+impl<'b, K, V> Iterator for Remaining<'b, K, V> {
+	type Item = &'b Item<K, V>;
+
+	#[inline]
+	fn next(&mut self) -> Option<&'b Item<K, V>> {
+		if self.len > 0 {
+			let frame = self.front.last().unwrap();
+			let item = frame.node.items[frame.offset];
+			self.len -= 1;
+			if self.len > 0 {
+				// SAFETY: an item is left, and the nodes that are not on either path
+				// have no item lent out, so they can be read.
+				unsafe { advance_front(&mut self.front, self.back, self.nodes, true) }
+			}
+
+			// SAFETY: the item is not lent out, and the borrow of the `RawItemsMut`
+			// prevents lending it out while this reference lives.
+			Some(unsafe { item.as_ref() })
+		} else {
+			None
+		}
+	}
+}
@@ -0,0 +1,126 @@
+use super::BTreeMap;
+use crate::generic::Node;
+use cc_traits::{SimpleCollectionRef, Slab};
+use std::{fmt, ops::Deref, sync::Arc};
+
+/// Immutable snapshot of a [`BTreeMap`].
+///
+/// A snapshot is created by [`BTreeMap::freeze`].
+/// It gives access to the read-only API of the map through [`Deref`],
+/// and is cheap to clone since every clone shares the same nodes.
+/// It is `Send` and `Sync` whenever the map is,
+/// so that readers on other threads can query a consistent state of the map
+/// while the writer builds a new one.
+///
+/// # Example
+///
+/// ```
+/// use std::thread;
+/// use btree_slab::BTreeMap;
+///
+/// let map: BTreeMap<u32, u32> = (0..100).map(|i| (i, i * 2)).collect();
+/// let snapshot = map.freeze();
+///
+/// let readers: Vec<_> = (0..4)
+///     .map(|t| {
+///         let snapshot = snapshot.clone();
+///         thread::spawn(move || snapshot.range(t * 10..t * 10 + 10).map(|(_, v)| *v).sum::<u32>())
+///     })
+///     .collect();
+///
+/// for (t, reader) in readers.into_iter().enumerate() {
+///     let t = t as u32;
+///     assert_eq!(reader.join().unwrap(), (t * 10..t * 10 + 10).map(|i| i * 2).sum::<u32>());
+/// }
+/// ```
+pub struct BTreeSnapshot<K, V, C> {
+	map: Arc<BTreeMap<K, V, C>>,
+}
+
+impl<K, V, C> BTreeSnapshot<K, V, C> {
+	/// Returns the map, if this is its only snapshot.
+	///
+	/// Otherwise the snapshot is returned unchanged.
+	#[inline]
+	pub fn try_into_map(self) -> Result<BTreeMap<K, V, C>, Self> {
+		Arc::try_unwrap(self.map).map_err(|map| BTreeSnapshot { map })
+	}
+
+	/// Returns a mutable copy of the map,
+	/// cloning it unless this is its only snapshot.
+	#[inline]
+	pub fn into_map(self) -> BTreeMap<K, V, C>
+	where
+		K: Clone,
+		V: Clone,
+		C: Clone,
+	{
+		Arc::try_unwrap(self.map).unwrap_or_else(|map| (*map).clone())
+	}
+
+	/// Returns `true` if the two snapshots share the same nodes.
+	#[inline]
+	pub fn ptr_eq(&self, other: &Self) -> bool {
+		Arc::ptr_eq(&self.map, &other.map)
+	}
+}
+
+impl<K, V, C> Clone for BTreeSnapshot<K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		BTreeSnapshot {
+			map: self.map.clone(),
+		}
+	}
+}
+
+impl<K, V, C> Deref for BTreeSnapshot<K, V, C> {
+	type Target = BTreeMap<K, V, C>;
+
+	#[inline]
+	fn deref(&self) -> &BTreeMap<K, V, C> {
+		&self.map
+	}
+}
+
+impl<K, V, C> AsRef<BTreeMap<K, V, C>> for BTreeSnapshot<K, V, C> {
+	#[inline]
+	fn as_ref(&self) -> &BTreeMap<K, V, C> {
+		&self.map
+	}
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, C: Slab<Node<K, V>>> fmt::Debug for BTreeSnapshot<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.map.fmt(f)
+	}
+}
+
+impl<K, V, C> BTreeMap<K, V, C> {
+	/// Turns the map into an immutable snapshot that can be shared between threads.
+	///
+	/// This does not copy the nodes of the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<_, _> = (0..10).map(|i| (i, i)).collect();
+	/// let snapshot = map.clone().freeze();
+	///
+	/// map.insert(10, 10);
+	/// assert_eq!(snapshot.len(), 10);
+	/// assert_eq!(snapshot.get(&10), None);
+	/// ```
+	#[inline]
+	pub fn freeze(self) -> BTreeSnapshot<K, V, C> {
+		BTreeSnapshot {
+			map: Arc::new(self),
+		}
+	}
+}
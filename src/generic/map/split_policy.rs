@@ -0,0 +1,103 @@
+use super::{BTreeMap, M};
+
+/// Minimum number of items of a node other than the root.
+const MIN_ITEMS: usize = M / 2 - 1;
+
+/// Where overflowing nodes are split, set with [`BTreeMap::set_split_policy`].
+///
+/// When an insertion makes a node overflow, the node is split in two around a median item
+/// moved up to its parent.
+/// Splitting in the middle leaves both nodes half-full, which is the best choice for random
+/// insertions, but sequential insertions then only ever fill the right node,
+/// leaving every other node half-empty.
+///
+/// Whatever the policy, both nodes keep at least the minimum number of items
+/// required by the B-Tree invariants (`M / 2 - 1`), so the median offset is clamped
+/// to stay within these bounds.
+/// The minimum fill itself is not tunable: it is what guarantees the depth bound given by
+/// [`max_depth`](super::max_depth).
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::{BTreeMap, generic::map::SplitPolicy};
+///
+/// let mut middle: BTreeMap<u32, ()> = BTreeMap::new();
+/// let mut adaptive: BTreeMap<u32, ()> = BTreeMap::new();
+/// adaptive.set_split_policy(SplitPolicy::Adaptive);
+///
+/// for i in 0..10000 {
+///     middle.insert(i, ());
+///     adaptive.insert(i, ());
+/// }
+///
+/// assert!(adaptive.stats().nodes < middle.stats().nodes);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum SplitPolicy {
+	/// Splits nodes in the middle.
+	///
+	/// This is the default.
+	#[default]
+	Middle,
+
+	/// Keeps the given percentage of the items in the left node.
+	///
+	/// For instance `Ratio(60)` keeps about 60% of the items on the left,
+	/// which favors insertions in increasing order.
+	Ratio(u8),
+
+	/// Splits toward the inserted item.
+	///
+	/// A node overflowing because of an item inserted at its end keeps as many items as
+	/// possible on the left, and conversely for an item inserted at its start.
+	/// Other nodes are split in the middle.
+	/// Sequential insertions, in either order, then leave nodes as full as possible.
+	Adaptive,
+}
+
+impl SplitPolicy {
+	/// Returns the offset of the median item of an overflowing node holding `item_count` items,
+	/// knowing the offset of the item that was just inserted in the node, if any.
+	///
+	/// The median item is moved to the parent node, the items before it stay in the node,
+	/// and the items after it go to a new right node.
+	#[inline]
+	pub fn median_offset(&self, item_count: usize, inserted: Option<usize>) -> usize {
+		let min = MIN_ITEMS;
+		let max = item_count - 1 - MIN_ITEMS;
+		let middle = (item_count - 1) / 2;
+
+		let offset = match self {
+			Self::Middle => middle,
+			Self::Ratio(percent) => item_count * (*percent).min(100) as usize / 100,
+			Self::Adaptive => match inserted {
+				Some(0) => min,
+				Some(offset) if offset + 1 == item_count => max,
+				_ => middle,
+			},
+		};
+
+		offset.clamp(min, max)
+	}
+}
+
+impl<K, V, C> BTreeMap<K, V, C> {
+	/// Returns the split policy of the map.
+	#[inline]
+	pub fn split_policy(&self) -> SplitPolicy {
+		self.settings
+			.as_ref()
+			.map_or(SplitPolicy::Middle, |settings| settings.split_policy)
+	}
+
+	/// Sets where the nodes of the map are split when they overflow.
+	///
+	/// The current nodes are left as they are.
+	#[inline]
+	pub fn set_split_policy(&mut self, policy: SplitPolicy) {
+		self.settings
+			.get_or_insert_with(Default::default)
+			.split_policy = policy
+	}
+}
@@ -0,0 +1,150 @@
+use super::{BTreeExt, BTreeMap, M};
+use crate::generic::node::Node;
+use cc_traits::{Capacity, Len, SimpleCollectionRef, Slab};
+
+/// Statistics about the nodes of a map and their storage.
+///
+/// Returned by [`BTreeMap::stats`].
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::BTreeMap;
+///
+/// let map: BTreeMap<_, _> = (0..1000).map(|i| (i, i)).collect();
+/// let stats = map.stats();
+///
+/// assert_eq!(stats.items, 1000);
+/// assert_eq!(stats.nodes, stats.leaves + stats.internal_nodes);
+/// assert_eq!(stats.nodes, stats.slab_len);
+/// assert!(stats.leaves > stats.internal_nodes);
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct Stats {
+	/// Number of items in the map.
+	pub items: usize,
+
+	/// Depth of the tree, a single leaf having a depth of `1`.
+	pub depth: usize,
+
+	/// Number of nodes in the tree.
+	pub nodes: usize,
+
+	/// Number of leaves in the tree.
+	pub leaves: usize,
+
+	/// Number of internal nodes in the tree.
+	pub internal_nodes: usize,
+
+	/// Number of items stored in leaves.
+	pub leaf_items: usize,
+
+	/// Number of items stored in internal nodes.
+	pub internal_items: usize,
+
+	/// Number of occupied slots in the node storage.
+	///
+	/// It is equal to `nodes` unless nodes are leaked in the storage.
+	pub slab_len: usize,
+
+	/// Number of slots allocated by the node storage.
+	pub slab_capacity: usize,
+}
+
+impl Stats {
+	/// Returns the average fill factor of the leaves,
+	/// between `0` and `1`, or `0` if there are no leaves.
+	///
+	/// A leaf holds up to [`M`] items.
+	#[inline]
+	pub fn leaf_fill_factor(&self) -> f64 {
+		ratio(self.leaf_items, self.leaves * M)
+	}
+
+	/// Returns the average fill factor of the internal nodes,
+	/// between `0` and `1`, or `0` if there are no internal nodes.
+	///
+	/// An internal node holds up to `M - 1` items.
+	#[inline]
+	pub fn internal_fill_factor(&self) -> f64 {
+		ratio(self.internal_items, self.internal_nodes * (M - 1))
+	}
+
+	/// Returns the average fill factor of all the nodes,
+	/// between `0` and `1`, or `0` if the tree is empty.
+	#[inline]
+	pub fn fill_factor(&self) -> f64 {
+		ratio(
+			self.leaf_items + self.internal_items,
+			self.leaves * M + self.internal_nodes * (M - 1),
+		)
+	}
+
+	/// Returns the ratio of occupied slots in the node storage,
+	/// between `0` and `1`, or `0` if nothing is allocated.
+	#[inline]
+	pub fn slab_utilization(&self) -> f64 {
+		ratio(self.slab_len, self.slab_capacity)
+	}
+}
+
+#[inline]
+fn ratio(a: usize, b: usize) -> f64 {
+	if b == 0 {
+		0.0
+	} else {
+		a as f64 / b as f64
+	}
+}
+
+impl<K, V, C: Slab<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Computes statistics about the nodes of the map and their storage.
+	///
+	/// This visits every node of the tree.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<_, _> = (0..1000).map(|i| (i, i)).collect();
+	/// map.retain(|k, _| k % 10 == 0);
+	///
+	/// let stats = map.stats();
+	/// assert_eq!(stats.items, 100);
+	/// assert_eq!(stats.depth, map.depth());
+	/// assert!(stats.slab_utilization() <= 1.0);
+	/// ```
+	pub fn stats(&self) -> Stats
+	where
+		C: Len + Capacity,
+	{
+		let mut stats = Stats {
+			items: self.len(),
+			depth: self.depth(),
+			slab_len: self.nodes.len(),
+			slab_capacity: self.nodes.capacity(),
+			..Stats::default()
+		};
+
+		for id in self.reachable_node_ids() {
+			let node = self.node(id);
+			stats.nodes += 1;
+			match node {
+				Node::Leaf(_) => {
+					stats.leaves += 1;
+					stats.leaf_items += node.item_count()
+				}
+				Node::Internal(_) => {
+					stats.internal_nodes += 1;
+					stats.internal_items += node.item_count()
+				}
+			}
+		}
+
+		stats
+	}
+}
@@ -0,0 +1,217 @@
+use super::BTreeMap;
+use crate::generic::Node;
+use cc_traits::{SimpleCollectionRef, Slab};
+use std::{
+	collections::hash_map::DefaultHasher,
+	hash::{Hash, Hasher},
+};
+
+/// Hashes a key for the structural hash.
+///
+/// `DefaultHasher::new` is used so that two maps with the same keys
+/// have the same structural hash.
+#[inline]
+fn hash_key<K: Hash>(key: &K) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	key.hash(&mut hasher);
+	hasher.finish()
+}
+
+/// Rolling hash of the keys of a map.
+///
+/// It is the wrapping sum of the hashes of the keys,
+/// so that it does not depend on the shape of the tree
+/// and is updated in constant time when a key is inserted or removed.
+pub(crate) struct StructuralHash<K> {
+	/// Key hash function, captured when the hash is enabled
+	/// since the map does not require keys to be hashable.
+	hash: fn(&K) -> u64,
+
+	sum: u64,
+}
+
+// Not derived, since keys need not be `Clone`.
+impl<K> Clone for StructuralHash<K> {
+	#[inline]
+	fn clone(&self) -> Self {
+		StructuralHash {
+			hash: self.hash,
+			sum: self.sum,
+		}
+	}
+}
+
+impl<K> StructuralHash<K> {
+	/// Creates the structural hash of the given keys.
+	fn new<'a>(hash: fn(&K) -> u64, keys: impl Iterator<Item = &'a K>) -> Self
+	where
+		K: 'a,
+	{
+		let mut h = StructuralHash { hash, sum: 0 };
+		h.sum = h.sum_of(keys);
+		h
+	}
+
+	/// Computes the sum of the hashes of the given keys.
+	#[inline]
+	fn sum_of<'a>(&self, keys: impl Iterator<Item = &'a K>) -> u64
+	where
+		K: 'a,
+	{
+		keys.fold(0, |sum, key| sum.wrapping_add((self.hash)(key)))
+	}
+
+	/// Adds a key to the hash.
+	#[inline]
+	pub(crate) fn insert(&mut self, key: &K) {
+		self.sum = self.sum.wrapping_add((self.hash)(key))
+	}
+
+	/// Removes a key from the hash.
+	#[inline]
+	pub(crate) fn remove(&mut self, key: &K) {
+		self.sum = self.sum.wrapping_sub((self.hash)(key))
+	}
+
+	/// Records the replacement of a key by another in the map.
+	#[inline]
+	pub(crate) fn replace(&mut self, old_key: &K, new_key: &K) {
+		self.remove(old_key);
+		self.insert(new_key)
+	}
+
+	/// Resets the hash of an empty map.
+	#[inline]
+	pub(crate) fn clear(&mut self) {
+		self.sum = 0
+	}
+}
+
+impl<K, V, C: Slab<Node<K, V>>> BTreeMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Enables the structural hash of the map.
+	///
+	/// The structural hash is a rolling hash of the keys of the map,
+	/// updated by every insertion and removal in constant time.
+	/// It lets [`BTreeMap::maybe_eq_fast`] tell apart most maps with different keys
+	/// without comparing their entries.
+	/// Values are not covered by the hash,
+	/// since they can be modified in place through mutable references.
+	///
+	/// Enabling the hash visits every key of the map.
+	/// If the hash is already enabled, it is recomputed.
+	///
+	/// Requires the `structural-hash` feature.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut a: BTreeMap<u32, u32> = (0..1000).map(|i| (i, i)).collect();
+	/// let mut b = a.clone();
+	/// a.enable_structural_hash();
+	/// b.enable_structural_hash();
+	/// assert_eq!(a.structural_hash(), b.structural_hash());
+	///
+	/// b.remove(&10);
+	/// b.insert(1000, 1000);
+	/// assert!(!a.maybe_eq_fast(&b));
+	/// ```
+	#[inline]
+	pub fn enable_structural_hash(&mut self)
+	where
+		K: Hash,
+	{
+		self.structural_hash = Some(StructuralHash::new(hash_key::<K>, self.keys()))
+	}
+
+	/// Disables the structural hash of the map.
+	///
+	/// Requires the `structural-hash` feature.
+	#[inline]
+	pub fn disable_structural_hash(&mut self) {
+		self.structural_hash = None
+	}
+
+	/// Returns the structural hash of the map, if enabled.
+	///
+	/// Two maps with the same keys have the same structural hash,
+	/// whatever their values and the shape of their trees.
+	///
+	/// Requires the `structural-hash` feature.
+	#[inline]
+	pub fn structural_hash(&self) -> Option<u64> {
+		self.structural_hash.as_ref().map(|h| h.sum)
+	}
+
+	/// Returns `false` if the two maps are certainly not equal.
+	///
+	/// Returns `true` if the maps may be equal.
+	/// This runs in constant time:
+	/// the lengths of the maps are compared,
+	/// then their structural hashes if both are enabled.
+	/// Use [`BTreeMap::eq_fast`] to fall back to a full comparison.
+	///
+	/// Requires the `structural-hash` feature.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn maybe_eq_fast<W, D: Slab<Node<K, W>>>(&self, other: &BTreeMap<K, W, D>) -> bool
+	where
+		D: SimpleCollectionRef,
+	{
+		self.len() == other.len()
+			&& match (self.structural_hash(), other.structural_hash()) {
+				(Some(a), Some(b)) => a == b,
+				_ => true,
+			}
+	}
+
+	/// Compares the two maps, checking [`BTreeMap::maybe_eq_fast`] first.
+	///
+	/// Entries are only compared when the lengths and structural hashes are equal.
+	///
+	/// Requires the `structural-hash` feature.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut a: BTreeMap<u32, u32> = (0..1000).map(|i| (i, i)).collect();
+	/// let mut b = a.clone();
+	/// a.enable_structural_hash();
+	/// b.enable_structural_hash();
+	/// assert!(a.eq_fast(&b));
+	///
+	/// // Same keys, different values.
+	/// b.insert(0, 1);
+	/// assert!(a.maybe_eq_fast(&b));
+	/// assert!(!a.eq_fast(&b));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn eq_fast<D: Slab<Node<K, V>>>(&self, other: &BTreeMap<K, V, D>) -> bool
+	where
+		K: PartialEq,
+		V: PartialEq,
+		D: SimpleCollectionRef,
+	{
+		self.maybe_eq_fast(other) && self == other
+	}
+
+	/// Gives `other` its structural hash, after the keys of `other` were moved out of this map.
+	#[inline]
+	pub(crate) fn split_structural_hash(&mut self, other: &mut Self) {
+		if let Some(h) = &mut self.structural_hash {
+			let moved = h.sum_of(other.keys());
+			h.sum = h.sum.wrapping_sub(moved);
+			other.structural_hash = Some(StructuralHash {
+				hash: h.hash,
+				sum: moved,
+			})
+		}
+	}
+}
@@ -0,0 +1,266 @@
+use super::BTreeMap;
+use std::{
+	cmp::Ordering,
+	fmt,
+	ops::{Bound, RangeBounds},
+	sync::atomic::{self, AtomicU32},
+};
+
+/// Maximum number of watermarks attached to a map.
+///
+/// Requires the `watermark` feature.
+pub const MAX_WATERMARKS: usize = 32;
+
+/// Watermark identifier.
+///
+/// Requires the `watermark` feature.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct WatermarkId(usize);
+
+impl WatermarkId {
+	/// Returns the index of the watermark, lower than [`MAX_WATERMARKS`].
+	#[inline]
+	pub fn index(&self) -> usize {
+		self.0
+	}
+}
+
+/// Error returned when [`MAX_WATERMARKS`] watermarks are already attached to the map.
+///
+/// Requires the `watermark` feature.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct WatermarkLimitReached;
+
+impl fmt::Display for WatermarkLimitReached {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "at most {} watermarks can be attached", MAX_WATERMARKS)
+	}
+}
+
+impl std::error::Error for WatermarkLimitReached {}
+
+/// Set of dirty watermarks, returned by [`BTreeMap::take_dirty`].
+///
+/// Requires the `watermark` feature.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Debug)]
+pub struct DirtyWatermarks(u32);
+
+impl DirtyWatermarks {
+	/// Returns `true` if no watermark is dirty.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.0 == 0
+	}
+
+	/// Returns the number of dirty watermarks.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.0.count_ones() as usize
+	}
+
+	/// Returns `true` if the given watermark is dirty.
+	#[inline]
+	pub fn contains(&self, id: WatermarkId) -> bool {
+		self.0 & (1 << id.0) != 0
+	}
+
+	/// Returns an iterator over the dirty watermarks, by increasing index.
+	#[inline]
+	pub fn iter(&self) -> impl Iterator<Item = WatermarkId> {
+		let bits = self.0;
+		(0..MAX_WATERMARKS)
+			.filter(move |i| bits & (1 << i) != 0)
+			.map(WatermarkId)
+	}
+}
+
+type KeyRange<K> = (Bound<K>, Bound<K>);
+
+/// Watermark ranges attached to a map, with their dirty flags.
+///
+/// Dirty flags are stored in an atomic integer so that they can be set
+/// while the keys of the map are borrowed, without making the map `!Sync`.
+pub(crate) struct Watermarks<K> {
+	ranges: Vec<Option<KeyRange<K>>>,
+
+	/// Key comparison function, captured when the first watermark is attached
+	/// since the map does not require keys to be ordered.
+	cmp: Option<fn(&K, &K) -> Ordering>,
+
+	dirty: AtomicU32,
+}
+
+impl<K> Default for Watermarks<K> {
+	fn default() -> Self {
+		Watermarks::new()
+	}
+}
+
+impl<K: Clone> Clone for Watermarks<K> {
+	fn clone(&self) -> Self {
+		Watermarks {
+			ranges: self.ranges.clone(),
+			cmp: self.cmp,
+			dirty: AtomicU32::new(self.dirty_bits()),
+		}
+	}
+}
+
+impl<K> Watermarks<K> {
+	/// Creates an empty set of watermarks.
+	#[inline]
+	pub const fn new() -> Self {
+		Watermarks {
+			ranges: Vec::new(),
+			cmp: None,
+			dirty: AtomicU32::new(0),
+		}
+	}
+
+	#[inline]
+	fn dirty_bits(&self) -> u32 {
+		self.dirty.load(atomic::Ordering::Relaxed)
+	}
+
+	#[inline]
+	fn mark(&self, bits: u32) {
+		if bits != 0 {
+			self.dirty.fetch_or(bits, atomic::Ordering::Relaxed);
+		}
+	}
+
+	#[inline]
+	fn unmark(&mut self, index: usize) {
+		*self.dirty.get_mut() &= !(1 << index)
+	}
+
+	/// Marks as dirty every watermark containing the given key.
+	#[inline]
+	pub(crate) fn touch(&self, key: &K) {
+		if let Some(cmp) = self.cmp {
+			let mut bits = 0;
+			for (i, range) in self.ranges.iter().enumerate() {
+				if let Some(range) = range {
+					if contains(range, key, cmp) {
+						bits |= 1 << i
+					}
+				}
+			}
+
+			self.mark(bits)
+		}
+	}
+
+	/// Marks every watermark as dirty.
+	#[inline]
+	pub(crate) fn touch_all(&self) {
+		let mut bits = 0;
+		for (i, range) in self.ranges.iter().enumerate() {
+			if range.is_some() {
+				bits |= 1 << i
+			}
+		}
+
+		self.mark(bits)
+	}
+}
+
+fn contains<K>(range: &KeyRange<K>, key: &K, cmp: fn(&K, &K) -> Ordering) -> bool {
+	let after_start = match &range.0 {
+		Bound::Included(start) => cmp(start, key) != Ordering::Greater,
+		Bound::Excluded(start) => cmp(start, key) == Ordering::Less,
+		Bound::Unbounded => true,
+	};
+
+	after_start
+		&& match &range.1 {
+			Bound::Included(end) => cmp(key, end) != Ordering::Greater,
+			Bound::Excluded(end) => cmp(key, end) == Ordering::Less,
+			Bound::Unbounded => true,
+		}
+}
+
+impl<K, V, C> BTreeMap<K, V, C> {
+	/// Attaches a watermark to the given key range.
+	///
+	/// The watermark becomes dirty whenever a key of the range is inserted or removed,
+	/// or its value replaced, by an operation of the map.
+	/// Values modified through mutable references are not tracked.
+	/// Clearing the map marks every watermark as dirty.
+	///
+	/// Requires the `watermark` feature.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMap;
+	///
+	/// let mut map: BTreeMap<i32, i32> = (0..10).map(|i| (i, i)).collect();
+	/// let low = map.add_watermark(..5).unwrap();
+	/// let high = map.add_watermark(5..).unwrap();
+	///
+	/// map.insert(7, 0);
+	/// map.remove(&8);
+	///
+	/// let dirty = map.take_dirty();
+	/// assert!(!dirty.contains(low));
+	/// assert!(dirty.contains(high));
+	/// assert!(map.take_dirty().is_empty());
+	/// ```
+	#[inline]
+	pub fn add_watermark<R>(&mut self, range: R) -> Result<WatermarkId, WatermarkLimitReached>
+	where
+		K: Ord + Clone,
+		R: RangeBounds<K>,
+	{
+		let range = (range.start_bound().cloned(), range.end_bound().cloned());
+		let watermarks = &mut self.watermarks;
+		watermarks.cmp = Some(K::cmp);
+
+		let index = match watermarks.ranges.iter().position(Option::is_none) {
+			Some(index) => index,
+			None if watermarks.ranges.len() < MAX_WATERMARKS => {
+				watermarks.ranges.push(None);
+				watermarks.ranges.len() - 1
+			}
+			None => return Err(WatermarkLimitReached),
+		};
+
+		watermarks.ranges[index] = Some(range);
+		watermarks.unmark(index);
+		Ok(WatermarkId(index))
+	}
+
+	/// Detaches the given watermark.
+	///
+	/// Returns `false` if the watermark was not attached.
+	///
+	/// Requires the `watermark` feature.
+	#[inline]
+	pub fn remove_watermark(&mut self, id: WatermarkId) -> bool {
+		let watermarks = &mut self.watermarks;
+		match watermarks.ranges.get_mut(id.0).and_then(Option::take) {
+			Some(_) => {
+				watermarks.unmark(id.0);
+				true
+			}
+			None => false,
+		}
+	}
+
+	/// Returns `true` if the given watermark is dirty.
+	///
+	/// Requires the `watermark` feature.
+	#[inline]
+	pub fn is_dirty(&self, id: WatermarkId) -> bool {
+		DirtyWatermarks(self.watermarks.dirty_bits()).contains(id)
+	}
+
+	/// Returns the set of dirty watermarks and marks them all as clean.
+	///
+	/// Requires the `watermark` feature.
+	#[inline]
+	pub fn take_dirty(&mut self) -> DirtyWatermarks {
+		DirtyWatermarks(std::mem::take(self.watermarks.dirty.get_mut()))
+	}
+}
@@ -0,0 +1,705 @@
+use crate::generic::{
+	map::{self, BTreeExt, BTreeExtMut},
+	node::{Address, Item, Node},
+	BTreeMap,
+};
+use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
+use std::{
+	borrow::Borrow,
+	cmp::Ordering,
+	fmt,
+	iter::{DoubleEndedIterator, ExactSizeIterator, FromIterator, FusedIterator},
+	ops::{Bound, RangeBounds},
+};
+
+/// Key of a [`BTreeMultiMap`] entry.
+///
+/// Equal keys are told apart by their insertion sequence number,
+/// so that the underlying B-Tree only holds distinct keys,
+/// and duplicates are sorted by insertion order.
+pub struct MultiKey<K> {
+	key: K,
+	seq: u64,
+}
+
+impl<K> MultiKey<K> {
+	/// Returns the key.
+	#[inline]
+	pub fn key(&self) -> &K {
+		&self.key
+	}
+
+	/// Unwraps the key.
+	#[inline]
+	pub fn into_key(self) -> K {
+		self.key
+	}
+}
+
+impl<K: Clone> Clone for MultiKey<K> {
+	#[inline]
+	fn clone(&self) -> Self {
+		MultiKey {
+			key: self.key.clone(),
+			seq: self.seq,
+		}
+	}
+}
+
+impl<K: fmt::Debug> fmt::Debug for MultiKey<K> {
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.key.fmt(f)
+	}
+}
+
+impl<K: Ord> PartialEq for MultiKey<K> {
+	#[inline]
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl<K: Ord> Eq for MultiKey<K> {}
+
+impl<K: Ord> PartialOrd for MultiKey<K> {
+	#[inline]
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl<K: Ord> Ord for MultiKey<K> {
+	#[inline]
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.key
+			.cmp(&other.key)
+			.then_with(|| self.seq.cmp(&other.seq))
+	}
+}
+
+/// An ordered map allowing duplicate keys, based on a B-Tree.
+///
+/// Every entry is stored in its own item of the underlying [`BTreeMap`],
+/// instead of grouping the values of a key in a collection,
+/// so that entries keep the memory locality of the node container.
+/// Entries with equal keys are sorted by insertion order.
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::BTreeMultiMap;
+///
+/// let mut map = BTreeMultiMap::new();
+/// map.insert("b", 1);
+/// map.insert("a", 2);
+/// map.insert("b", 3);
+///
+/// assert_eq!(map.len(), 3);
+/// assert_eq!(map.count("b"), 2);
+/// assert!(map.get_all("b").eq([&1, &3]));
+/// assert!(map.iter().eq([(&"a", &2), (&"b", &1), (&"b", &3)]));
+/// ```
+pub struct BTreeMultiMap<K, V, C> {
+	map: BTreeMap<MultiKey<K>, V, C>,
+
+	/// Sequence number of the next inserted entry.
+	seq: u64,
+}
+
+impl<K, V, C> BTreeMultiMap<K, V, C> {
+	/// Makes a new, empty `BTreeMultiMap`.
+	#[inline]
+	pub fn new() -> Self
+	where
+		C: Default,
+	{
+		BTreeMultiMap {
+			map: BTreeMap::new(),
+			seq: 0,
+		}
+	}
+
+	/// Returns the number of entries in the map, counting duplicates.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.map.len()
+	}
+
+	/// Returns `true` if the map contains no entries.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.map.is_empty()
+	}
+
+	/// Returns the underlying map.
+	#[inline]
+	pub fn as_map(&self) -> &BTreeMap<MultiKey<K>, V, C> {
+		&self.map
+	}
+}
+
+impl<K, V, C: Default> Default for BTreeMultiMap<K, V, C> {
+	#[inline]
+	fn default() -> Self {
+		BTreeMultiMap::new()
+	}
+}
+
+impl<K: Clone, V: Clone, C: Clone> Clone for BTreeMultiMap<K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		BTreeMultiMap {
+			map: self.map.clone(),
+			seq: self.seq,
+		}
+	}
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, C: Slab<Node<MultiKey<K>, V>>> fmt::Debug
+	for BTreeMultiMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_map()
+			.entries(self.map.iter().map(|(k, v)| (k.key(), v)))
+			.finish()
+	}
+}
+
+impl<K, V, C: Slab<Node<MultiKey<K>, V>>> BTreeMultiMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Returns the leaf address of the first item whose key does not satisfy `pred`.
+	///
+	/// `pred` must hold for a prefix of the keys only.
+	fn partition_address<F>(&self, pred: F) -> Address
+	where
+		F: Fn(&K) -> bool,
+	{
+		let mut id = match self.map.root_id() {
+			Some(id) => id,
+			None => return Address::nowhere(),
+		};
+
+		loop {
+			let node = self.map.node(id);
+			let (mut lo, mut hi) = (0, node.item_count());
+			while lo < hi {
+				let mid = (lo + hi) / 2;
+				if pred(node.item(mid.into()).unwrap().key().key()) {
+					lo = mid + 1
+				} else {
+					hi = mid
+				}
+			}
+
+			match node.child_id_opt(lo) {
+				Some(child_id) => id = child_id,
+				None => return Address::new(id, lo.into()),
+			}
+		}
+	}
+
+	/// Returns the address of the first item whose key does not satisfy `pred`,
+	/// or the last valid address if there is none.
+	fn partition_item_address<F>(&self, pred: F) -> Address
+	where
+		F: Fn(&K) -> bool,
+	{
+		let addr = self.partition_address(pred);
+		self.map
+			.normalize(addr)
+			.unwrap_or_else(|| self.map.last_valid_address())
+	}
+
+	/// Returns the addresses of the first entry with the given key,
+	/// and of the first entry with a greater key.
+	#[allow(clippy::multiple_bound_locations)]
+	fn key_addresses<Q: ?Sized>(&self, key: &Q) -> (Address, Address)
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		(
+			self.partition_item_address(|k| k.borrow() < key),
+			self.partition_item_address(|k| k.borrow() <= key),
+		)
+	}
+
+	/// Returns the first value inserted with the given key.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiMap;
+	///
+	/// let map: BTreeMultiMap<_, _> = [(1, "a"), (1, "b")].into_iter().collect();
+	/// assert_eq!(map.get(&1), Some(&"a"));
+	/// assert_eq!(map.get(&2), None);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
+	where
+		K: Ord + Borrow<Q>,
+		Q: Ord,
+	{
+		self.get_all(key).next()
+	}
+
+	/// Gets an iterator over the values of the given key, in insertion order.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiMap;
+	///
+	/// let map: BTreeMultiMap<_, _> = [(1, "a"), (2, "b"), (1, "c")].into_iter().collect();
+	/// assert!(map.get_all(&1).eq([&"a", &"c"]));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn get_all<Q: ?Sized>(&self, key: &Q) -> Values<'_, K, V, C>
+	where
+		K: Ord + Borrow<Q>,
+		Q: Ord,
+	{
+		let (start, end) = self.key_addresses(key);
+		Values {
+			inner: self.range_between(start, end),
+		}
+	}
+
+	/// Returns the number of values of the given key.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiMap;
+	///
+	/// let map: BTreeMultiMap<_, _> = [(1, "a"), (2, "b"), (1, "c")].into_iter().collect();
+	/// assert_eq!(map.count(&1), 2);
+	/// assert_eq!(map.count(&3), 0);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn count<Q: ?Sized>(&self, key: &Q) -> usize
+	where
+		K: Ord + Borrow<Q>,
+		Q: Ord,
+	{
+		self.get_all(key).len()
+	}
+
+	/// Returns `true` if the map contains at least one value for the given key.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn contains_key<Q: ?Sized>(&self, key: &Q) -> bool
+	where
+		K: Ord + Borrow<Q>,
+		Q: Ord,
+	{
+		self.get(key).is_some()
+	}
+
+	/// Returns the first entry of the map.
+	#[inline]
+	pub fn first_key_value(&self) -> Option<(&K, &V)> {
+		self.map.first_key_value().map(|(k, v)| (k.key(), v))
+	}
+
+	/// Returns the last entry of the map.
+	#[inline]
+	pub fn last_key_value(&self) -> Option<(&K, &V)> {
+		self.map.last_key_value().map(|(k, v)| (k.key(), v))
+	}
+
+	/// Gets an iterator over the entries of the map, sorted by key,
+	/// entries with equal keys being sorted by insertion order.
+	#[inline]
+	pub fn iter(&self) -> Iter<'_, K, V, C> {
+		Iter {
+			inner: self.map.iter(),
+		}
+	}
+
+	/// Constructs a double-ended iterator over a sub-range of entries in the map,
+	/// including every entry of the keys in the range.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiMap;
+	///
+	/// let map: BTreeMultiMap<_, _> = [(1, 'a'), (2, 'b'), (2, 'c'), (3, 'd')].into_iter().collect();
+	/// assert!(map.range(2..).map(|(_, v)| *v).eq(['b', 'c', 'd']));
+	/// assert!(map.range(..=2).rev().map(|(_, v)| *v).eq(['c', 'b', 'a']));
+	/// ```
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn range<Q: ?Sized, R>(&self, range: R) -> Range<'_, K, V, C>
+	where
+		K: Ord + Borrow<Q>,
+		Q: Ord,
+		R: RangeBounds<Q>,
+	{
+		let start = match range.start_bound() {
+			Bound::Included(start) => self.partition_item_address(|k| k.borrow() < start),
+			Bound::Excluded(start) => self.partition_item_address(|k| k.borrow() <= start),
+			Bound::Unbounded => self.map.first_back_address(),
+		};
+
+		let end = match range.end_bound() {
+			Bound::Included(end) => self.partition_item_address(|k| k.borrow() <= end),
+			Bound::Excluded(end) => self.partition_item_address(|k| k.borrow() < end),
+			Bound::Unbounded => self.map.last_valid_address(),
+		};
+
+		match self.map.range_between(start, end) {
+			Ok(inner) => Range { inner },
+			Err(_) => panic!("Invalid range"),
+		}
+	}
+
+	/// Constructs an iterator between two addresses given by [`Self::partition_item_address`],
+	/// which are always ordered.
+	#[inline]
+	fn range_between(&self, start: Address, end: Address) -> Range<'_, K, V, C>
+	where
+		K: Ord,
+	{
+		Range {
+			inner: self.map.range_between(start, end).unwrap(),
+		}
+	}
+}
+
+impl<K: Ord, V, C: SlabMut<Node<MultiKey<K>, V>>> BTreeMultiMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Inserts a key-value pair into the map,
+	/// after every entry with an equal key.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiMap;
+	///
+	/// let mut map = BTreeMultiMap::new();
+	/// map.insert(1, "a");
+	/// map.insert(1, "b");
+	/// assert!(map.get_all(&1).eq([&"a", &"b"]));
+	/// ```
+	#[inline]
+	pub fn insert(&mut self, key: K, value: V) {
+		let addr = self.partition_address(|k| k <= &key);
+		let key = MultiKey { key, seq: self.seq };
+		self.seq += 1;
+		self.map.insert_at(addr, Item::new(key, value));
+	}
+
+	/// Removes the first value inserted with the given key, and returns it.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiMap;
+	///
+	/// let mut map: BTreeMultiMap<_, _> = [(1, "a"), (1, "b")].into_iter().collect();
+	/// assert_eq!(map.remove_one(&1), Some("a"));
+	/// assert_eq!(map.remove_one(&1), Some("b"));
+	/// assert_eq!(map.remove_one(&1), None);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn remove_one<Q: ?Sized>(&mut self, key: &Q) -> Option<V>
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		if self.is_empty() {
+			return None;
+		}
+
+		let addr = self.partition_item_address(|k| k.borrow() < key);
+		match self.map.item(addr) {
+			Some(item) if item.key().key().borrow() == key => {
+				let (item, _) = self.map.remove_at(addr).unwrap();
+				Some(item.into_value())
+			}
+			_ => None,
+		}
+	}
+
+	/// Removes every value of the given key.
+	///
+	/// Returns the number of removed values.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiMap;
+	///
+	/// let mut map: BTreeMultiMap<_, _> = [(1, "a"), (2, "b"), (1, "c")].into_iter().collect();
+	/// assert_eq!(map.remove_all(&1), 2);
+	/// assert_eq!(map.remove_all(&1), 0);
+	/// assert_eq!(map.len(), 1);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn remove_all<Q: ?Sized>(&mut self, key: &Q) -> usize
+	where
+		K: Borrow<Q>,
+		Q: Ord,
+	{
+		let mut count = 0;
+		while self.remove_one(key).is_some() {
+			count += 1
+		}
+
+		count
+	}
+
+	/// Clears the map, removing all entries.
+	#[inline]
+	pub fn clear(&mut self)
+	where
+		C: cc_traits::Clear,
+	{
+		self.map.clear()
+	}
+}
+
+impl<K: Ord, V, C: SlabMut<Node<MultiKey<K>, V>> + Default> FromIterator<(K, V)>
+	for BTreeMultiMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn from_iter<T>(iter: T) -> Self
+	where
+		T: IntoIterator<Item = (K, V)>,
+	{
+		let mut map = BTreeMultiMap::new();
+		map.extend(iter);
+		map
+	}
+}
+
+impl<K: Ord, V, C: SlabMut<Node<MultiKey<K>, V>>> Extend<(K, V)> for BTreeMultiMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn extend<T>(&mut self, iter: T)
+	where
+		T: IntoIterator<Item = (K, V)>,
+	{
+		for (key, value) in iter {
+			self.insert(key, value);
+		}
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<MultiKey<K>, V>>> IntoIterator for &'a BTreeMultiMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	type IntoIter = Iter<'a, K, V, C>;
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn into_iter(self) -> Iter<'a, K, V, C> {
+		self.iter()
+	}
+}
+
+/// Iterator over the entries of a [`BTreeMultiMap`].
+pub struct Iter<'a, K, V, C> {
+	inner: map::Iter<'a, MultiKey<K>, V, C>,
+}
+
+impl<'a, K, V, C> Clone for Iter<'a, K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Iter {
+			inner: self.inner.clone(),
+		}
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<MultiKey<K>, V>>> fmt::Debug
+	for Iter<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<MultiKey<K>, V>>> Iterator for Iter<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<(&'a K, &'a V)> {
+		self.inner.next().map(|(k, v)| (k.key(), v))
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<MultiKey<K>, V>>> DoubleEndedIterator for Iter<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+		self.inner.next_back().map(|(k, v)| (k.key(), v))
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<MultiKey<K>, V>>> FusedIterator for Iter<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
+impl<'a, K, V, C: Slab<Node<MultiKey<K>, V>>> ExactSizeIterator for Iter<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
+/// Iterator over a sub-range of entries of a [`BTreeMultiMap`].
+pub struct Range<'a, K, V, C> {
+	inner: map::Range<'a, MultiKey<K>, V, C>,
+}
+
+impl<'a, K, V, C> Clone for Range<'a, K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Range {
+			inner: self.inner.clone(),
+		}
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<MultiKey<K>, V>>> fmt::Debug
+	for Range<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<MultiKey<K>, V>>> Iterator for Range<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<(&'a K, &'a V)> {
+		self.inner.next().map(|(k, v)| (k.key(), v))
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<MultiKey<K>, V>>> DoubleEndedIterator for Range<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+		self.inner.next_back().map(|(k, v)| (k.key(), v))
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<MultiKey<K>, V>>> FusedIterator for Range<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
+impl<'a, K, V, C: Slab<Node<MultiKey<K>, V>>> ExactSizeIterator for Range<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
+/// Iterator over the values of a key in a [`BTreeMultiMap`], in insertion order.
+///
+/// This is created by [`BTreeMultiMap::get_all`].
+pub struct Values<'a, K, V, C> {
+	inner: Range<'a, K, V, C>,
+}
+
+impl<'a, K, V, C> Clone for Values<'a, K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Values {
+			inner: self.inner.clone(),
+		}
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<MultiKey<K>, V>>> Iterator for Values<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = &'a V;
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<&'a V> {
+		self.inner.next().map(|(_, v)| v)
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<MultiKey<K>, V>>> DoubleEndedIterator for Values<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<&'a V> {
+		self.inner.next_back().map(|(_, v)| v)
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<MultiKey<K>, V>>> FusedIterator for Values<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
+impl<'a, K, V, C: Slab<Node<MultiKey<K>, V>>> ExactSizeIterator for Values<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
@@ -0,0 +1,603 @@
+use crate::generic::{
+	map::{self, BTreeExt, BTreeExtMut},
+	node::Node,
+	BTreeMap,
+};
+use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
+use std::{
+	borrow::Borrow,
+	fmt,
+	hash::{Hash, Hasher},
+	iter::{DoubleEndedIterator, ExactSizeIterator, FromIterator, FusedIterator},
+};
+
+/// An ordered multiset based on a B-Tree.
+///
+/// Each distinct value is stored once in an underlying [`BTreeMap`],
+/// associated to its number of occurrences in the multiset.
+///
+/// It is a logic error for an item to be modified in such a way that the item's ordering relative
+/// to any other item, as determined by the [`Ord`] trait, changes while it is in the multiset.
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::BTreeMultiSet;
+///
+/// let mut set = BTreeMultiSet::new();
+/// set.insert("b");
+/// set.insert("a");
+/// set.insert("b");
+///
+/// assert_eq!(set.len(), 3);
+/// assert_eq!(set.count("b"), 2);
+/// assert!(set.iter().eq(["a", "b", "b"].iter()));
+/// assert!(set.counts().eq([(&"a", 1), (&"b", 2)]));
+/// ```
+#[derive(Clone)]
+pub struct BTreeMultiSet<T, C> {
+	/// Number of occurrences of each value.
+	map: BTreeMap<T, usize, C>,
+
+	/// Number of values, counting multiplicity.
+	len: usize,
+}
+
+impl<T, C> BTreeMultiSet<T, C> {
+	/// Makes a new, empty `BTreeMultiSet`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// # #![allow(unused_mut)]
+	/// use btree_slab::BTreeMultiSet;
+	///
+	/// let mut set: BTreeMultiSet<i32> = BTreeMultiSet::new();
+	/// ```
+	#[inline]
+	pub fn new() -> Self
+	where
+		C: Default,
+	{
+		Self::default()
+	}
+
+	/// Returns the number of values in the multiset, counting multiplicity.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiSet;
+	///
+	/// let mut set = BTreeMultiSet::new();
+	/// set.insert(1);
+	/// set.insert(1);
+	/// assert_eq!(set.len(), 2);
+	/// ```
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.len
+	}
+
+	/// Returns the number of distinct values in the multiset.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiSet;
+	///
+	/// let mut set = BTreeMultiSet::new();
+	/// set.insert(1);
+	/// set.insert(1);
+	/// set.insert(2);
+	/// assert_eq!(set.distinct_len(), 2);
+	/// ```
+	#[inline]
+	pub fn distinct_len(&self) -> usize {
+		self.map.len()
+	}
+
+	/// Returns `true` if the multiset contains no elements.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiSet;
+	///
+	/// let mut set = BTreeMultiSet::new();
+	/// assert!(set.is_empty());
+	/// set.insert(1);
+	/// assert!(!set.is_empty());
+	/// ```
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+}
+
+impl<T, C: Default> Default for BTreeMultiSet<T, C> {
+	fn default() -> Self {
+		BTreeMultiSet {
+			map: BTreeMap::default(),
+			len: 0,
+		}
+	}
+}
+
+impl<T, C: Slab<Node<T, usize>>> BTreeMultiSet<T, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Returns the number of occurrences of the given value in the multiset.
+	///
+	/// The value may be any borrowed form of the set's value type,
+	/// but the ordering on the borrowed form *must* match the
+	/// ordering on the value type.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiSet;
+	///
+	/// let set: BTreeMultiSet<_> = [1, 2, 2].iter().cloned().collect();
+	/// assert_eq!(set.count(&1), 1);
+	/// assert_eq!(set.count(&2), 2);
+	/// assert_eq!(set.count(&3), 0);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn count<Q: ?Sized>(&self, value: &Q) -> usize
+	where
+		T: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map.get(value).copied().unwrap_or(0)
+	}
+
+	/// Returns `true` if the multiset contains at least one occurrence of the value.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiSet;
+	///
+	/// let set: BTreeMultiSet<_> = [1, 2, 2].iter().cloned().collect();
+	/// assert_eq!(set.contains(&1), true);
+	/// assert_eq!(set.contains(&3), false);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn contains<Q: ?Sized>(&self, value: &Q) -> bool
+	where
+		T: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map.contains_key(value)
+	}
+
+	/// Gets an iterator that visits the values in ascending order,
+	/// each value being repeated as many times as it occurs in the multiset.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiSet;
+	///
+	/// let set: BTreeMultiSet<_> = [3, 1, 3].iter().cloned().collect();
+	/// let mut it = set.iter();
+	/// assert_eq!(it.next(), Some(&1));
+	/// assert_eq!(it.next(), Some(&3));
+	/// assert_eq!(it.next(), Some(&3));
+	/// assert_eq!(it.next(), None);
+	/// ```
+	#[inline]
+	pub fn iter(&self) -> Iter<'_, T, C> {
+		Iter {
+			inner: self.map.iter(),
+			front: None,
+			back: None,
+			len: self.len,
+		}
+	}
+
+	/// Gets an iterator that visits the distinct values in ascending order,
+	/// along with their number of occurrences.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiSet;
+	///
+	/// let set: BTreeMultiSet<_> = [3, 1, 3].iter().cloned().collect();
+	/// let mut it = set.counts();
+	/// assert_eq!(it.next(), Some((&1, 1)));
+	/// assert_eq!(it.next(), Some((&3, 2)));
+	/// assert_eq!(it.next(), None);
+	/// ```
+	#[inline]
+	pub fn counts(&self) -> Counts<'_, T, C> {
+		Counts {
+			inner: self.map.iter(),
+		}
+	}
+}
+
+impl<T: Ord, C: SlabMut<Node<T, usize>>> BTreeMultiSet<T, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Clears the multiset, removing all values.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiSet;
+	///
+	/// let mut set = BTreeMultiSet::new();
+	/// set.insert(1);
+	/// set.clear();
+	/// assert!(set.is_empty());
+	/// ```
+	#[inline]
+	pub fn clear(&mut self)
+	where
+		C: cc_traits::Clear,
+	{
+		self.map.clear();
+		self.len = 0
+	}
+
+	/// Adds an occurrence of the given value to the multiset.
+	///
+	/// Returns the number of occurrences of the value before the insertion.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiSet;
+	///
+	/// let mut set = BTreeMultiSet::new();
+	/// assert_eq!(set.insert(2), 0);
+	/// assert_eq!(set.insert(2), 1);
+	/// assert_eq!(set.len(), 2);
+	/// ```
+	#[inline]
+	pub fn insert(&mut self, value: T) -> usize {
+		let count = self.map.entry(value).or_insert(0);
+		let previous = *count;
+		*count += 1;
+		self.len += 1;
+		previous
+	}
+
+	/// Removes one occurrence of the given value from the multiset.
+	///
+	/// Returns whether the value was present in the multiset.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiSet;
+	///
+	/// let mut set: BTreeMultiSet<_> = [2, 2].iter().cloned().collect();
+	/// assert_eq!(set.remove_one(&2), true);
+	/// assert_eq!(set.count(&2), 1);
+	/// assert_eq!(set.remove_one(&2), true);
+	/// assert_eq!(set.remove_one(&2), false);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn remove_one<Q: ?Sized>(&mut self, value: &Q) -> bool
+	where
+		T: Borrow<Q>,
+		Q: Ord,
+	{
+		match self.map.address_of(value) {
+			Ok(addr) => {
+				let count = self.map.item_mut(addr).unwrap().value_mut();
+				if *count > 1 {
+					*count -= 1
+				} else {
+					self.map.remove_at(addr);
+				}
+
+				self.len -= 1;
+				true
+			}
+			Err(_) => false,
+		}
+	}
+
+	/// Removes every occurrence of the given value from the multiset.
+	///
+	/// Returns the number of removed occurrences.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeMultiSet;
+	///
+	/// let mut set: BTreeMultiSet<_> = [1, 2, 2].iter().cloned().collect();
+	/// assert_eq!(set.remove_all(&2), 2);
+	/// assert_eq!(set.remove_all(&2), 0);
+	/// assert_eq!(set.len(), 1);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn remove_all<Q: ?Sized>(&mut self, value: &Q) -> usize
+	where
+		T: Borrow<Q>,
+		Q: Ord,
+	{
+		match self.map.remove(value) {
+			Some(count) => {
+				self.len -= count;
+				count
+			}
+			None => 0,
+		}
+	}
+}
+
+impl<T: Ord, C: SlabMut<Node<T, usize>> + Default> FromIterator<T> for BTreeMultiSet<T, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn from_iter<I>(iter: I) -> Self
+	where
+		I: IntoIterator<Item = T>,
+	{
+		let mut set = BTreeMultiSet::new();
+		set.extend(iter);
+		set
+	}
+}
+
+impl<T: Ord, C: SlabMut<Node<T, usize>>> Extend<T> for BTreeMultiSet<T, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn extend<I>(&mut self, iter: I)
+	where
+		I: IntoIterator<Item = T>,
+	{
+		for t in iter {
+			self.insert(t);
+		}
+	}
+}
+
+impl<'a, T: 'a + Ord + Copy, C: SlabMut<Node<T, usize>>> Extend<&'a T> for BTreeMultiSet<T, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn extend<I>(&mut self, iter: I)
+	where
+		I: IntoIterator<Item = &'a T>,
+	{
+		self.extend(iter.into_iter().copied())
+	}
+}
+
+impl<'a, T, C: Slab<Node<T, usize>>> IntoIterator for &'a BTreeMultiSet<T, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = &'a T;
+	type IntoIter = Iter<'a, T, C>;
+
+	#[inline]
+	fn into_iter(self) -> Iter<'a, T, C> {
+		self.iter()
+	}
+}
+
+impl<T: PartialEq, C: Slab<Node<T, usize>>> PartialEq for BTreeMultiSet<T, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn eq(&self, other: &Self) -> bool {
+		self.len == other.len && self.map.eq(&other.map)
+	}
+}
+
+impl<T: Eq, C: Slab<Node<T, usize>>> Eq for BTreeMultiSet<T, C> where C: SimpleCollectionRef {}
+
+impl<T: Hash, C: Slab<Node<T, usize>>> Hash for BTreeMultiSet<T, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn hash<H: Hasher>(&self, h: &mut H) {
+		self.map.hash(h)
+	}
+}
+
+/// Formats the multiset as a map from each distinct value to its multiplicity.
+impl<T: fmt::Debug, C: Slab<Node<T, usize>>> fmt::Debug for BTreeMultiSet<T, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_map().entries(self.counts()).finish()
+	}
+}
+
+/// Iterator over the values of a [`BTreeMultiSet`], repeated according to their multiplicity.
+pub struct Iter<'a, T, C> {
+	inner: map::Iter<'a, T, usize, C>,
+
+	/// Value currently visited from the front, with its number of remaining occurrences.
+	front: Option<(&'a T, usize)>,
+
+	/// Value currently visited from the back, with its number of remaining occurrences.
+	back: Option<(&'a T, usize)>,
+
+	/// Number of remaining values, counting multiplicity.
+	len: usize,
+}
+
+impl<'a, T, C> Clone for Iter<'a, T, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Iter {
+			inner: self.inner.clone(),
+			front: self.front,
+			back: self.back,
+			len: self.len,
+		}
+	}
+}
+
+impl<'a, T: fmt::Debug, C: Slab<Node<T, usize>>> fmt::Debug for Iter<'a, T, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
+impl<'a, T, C: Slab<Node<T, usize>>> Iterator for Iter<'a, T, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = &'a T;
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		(self.len, Some(self.len))
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<&'a T> {
+		loop {
+			if let Some((t, count)) = &mut self.front {
+				if *count > 0 {
+					*count -= 1;
+					self.len -= 1;
+					return Some(*t);
+				}
+			}
+
+			match self.inner.next() {
+				Some((t, count)) => self.front = Some((t, *count)),
+				None => {
+					return match &mut self.back {
+						Some((t, count)) if *count > 0 => {
+							*count -= 1;
+							self.len -= 1;
+							Some(*t)
+						}
+						_ => None,
+					}
+				}
+			}
+		}
+	}
+}
+
+impl<'a, T, C: Slab<Node<T, usize>>> DoubleEndedIterator for Iter<'a, T, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<&'a T> {
+		loop {
+			if let Some((t, count)) = &mut self.back {
+				if *count > 0 {
+					*count -= 1;
+					self.len -= 1;
+					return Some(*t);
+				}
+			}
+
+			match self.inner.next_back() {
+				Some((t, count)) => self.back = Some((t, *count)),
+				None => {
+					return match &mut self.front {
+						Some((t, count)) if *count > 0 => {
+							*count -= 1;
+							self.len -= 1;
+							Some(*t)
+						}
+						_ => None,
+					}
+				}
+			}
+		}
+	}
+}
+
+impl<'a, T, C: Slab<Node<T, usize>>> FusedIterator for Iter<'a, T, C> where C: SimpleCollectionRef {}
+impl<'a, T, C: Slab<Node<T, usize>>> ExactSizeIterator for Iter<'a, T, C> where
+	C: SimpleCollectionRef
+{
+}
+
+/// Iterator over the distinct values of a [`BTreeMultiSet`] and their multiplicity.
+pub struct Counts<'a, T, C> {
+	inner: map::Iter<'a, T, usize, C>,
+}
+
+impl<'a, T, C> Clone for Counts<'a, T, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Counts {
+			inner: self.inner.clone(),
+		}
+	}
+}
+
+impl<'a, T: fmt::Debug, C: Slab<Node<T, usize>>> fmt::Debug for Counts<'a, T, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
+impl<'a, T, C: Slab<Node<T, usize>>> Iterator for Counts<'a, T, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = (&'a T, usize);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<(&'a T, usize)> {
+		self.inner.next().map(|(t, count)| (t, *count))
+	}
+}
+
+impl<'a, T, C: Slab<Node<T, usize>>> DoubleEndedIterator for Counts<'a, T, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<(&'a T, usize)> {
+		self.inner.next_back().map(|(t, count)| (t, *count))
+	}
+}
+
+impl<'a, T, C: Slab<Node<T, usize>>> FusedIterator for Counts<'a, T, C> where C: SimpleCollectionRef {}
+impl<'a, T, C: Slab<Node<T, usize>>> ExactSizeIterator for Counts<'a, T, C> where
+	C: SimpleCollectionRef
+{
+}
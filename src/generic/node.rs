@@ -1,15 +1,148 @@
-use std::{borrow::Borrow, cmp::Ordering, fmt};
+use crate::generic::map::{Invariant, M};
+use std::{borrow::Borrow, cmp::Ordering, fmt, mem};
 
 mod addr;
+#[cfg(feature = "compact-address")]
+mod addr32;
 pub mod internal;
 mod item;
 mod leaf;
 
 pub use addr::Address;
+#[cfg(feature = "compact-address")]
+pub use addr32::{Address32, AddressOverflow};
 pub use internal::Internal as InternalNode;
 pub use item::Item;
+
+/// Raw, unsafe access to items.
+///
+/// See [`RawItemOps`](raw::RawItemOps) for the invariants that must be upheld.
+pub mod raw {
+	pub use super::item::RawItemOps;
+}
 pub use leaf::Leaf as LeafNode;
 
+/// Node identifier, as stored in the nodes.
+///
+/// With the `compact-address` feature, nodes store the identifiers of their parent
+/// and first child on 32 bits, like [`Address32`].
+/// Node methods and the public [`Branch::child`](internal::Branch::child) field
+/// still use `usize` identifiers.
+#[cfg(feature = "compact-address")]
+type NodeId = u32;
+
+#[cfg(not(feature = "compact-address"))]
+type NodeId = usize;
+
+/// Converts a node identifier into its stored representation.
+///
+/// # Panics
+///
+/// With the `compact-address` feature,
+/// panics if the identifier does not fit in 32 bits.
+#[inline]
+fn store_id(id: usize) -> NodeId {
+	#[cfg(feature = "compact-address")]
+	{
+		match u32::try_from(id) {
+			Ok(stored) if stored != u32::MAX => stored,
+			_ => panic!("node identifier {} does not fit in 32 bits", id),
+		}
+	}
+
+	#[cfg(not(feature = "compact-address"))]
+	{
+		id
+	}
+}
+
+/// Converts a stored node identifier back into a `usize`.
+#[inline]
+fn load_id(id: NodeId) -> usize {
+	#[cfg(feature = "compact-address")]
+	{
+		id as usize
+	}
+
+	#[cfg(not(feature = "compact-address"))]
+	{
+		id
+	}
+}
+
+/// Stores an optional parent identifier, `NodeId::MAX` meaning no parent.
+#[inline]
+fn store_parent(parent: Option<usize>) -> NodeId {
+	parent.map(store_id).unwrap_or(NodeId::MAX)
+}
+
+/// Loads an optional parent identifier stored with [`store_parent`].
+#[inline]
+fn load_parent(parent: NodeId) -> Option<usize> {
+	if parent == NodeId::MAX {
+		None
+	} else {
+		Some(load_id(parent))
+	}
+}
+
+/// Number of items a leaf node stores inline.
+///
+/// A leaf holds up to `M` items, plus one while it overflows before being split.
+pub const LEAF_CAPACITY: usize = M + 1;
+
+/// Number of branches an internal node stores inline.
+///
+/// An internal node holds up to `M - 1` branches, plus one while it overflows before being split.
+pub const INTERNAL_CAPACITY: usize = M;
+
+// Layout assumptions: overflowing nodes must never spill their items on the heap.
+const _: () = assert!(M >= 4, "the order of the B-Trees must be at least 4");
+const _: () = assert!(
+	LEAF_CAPACITY > M,
+	"leaves must hold an overflowing item inline"
+);
+const _: () = assert!(
+	INTERNAL_CAPACITY >= M,
+	"internal nodes must hold an overflowing branch inline"
+);
+
+/// Returns the size in bytes of a node, that is the size of a slot in the node container.
+///
+/// Nodes have the Rust representation:
+/// their layout is fixed for a given compiler and set of type parameters,
+/// but may change between compiler versions or with [`M`].
+/// Code that allocates node containers outside of this crate
+/// (for instance in a shared memory segment) can check its assumptions at compile time.
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::generic::node::{item_size, node_size, LEAF_CAPACITY};
+///
+/// const _: () = assert!(node_size::<u64, u64>() >= LEAF_CAPACITY * item_size::<u64, u64>());
+/// ```
+#[inline]
+pub const fn node_size<K, V>() -> usize {
+	mem::size_of::<Node<K, V>>()
+}
+
+/// Returns the alignment in bytes of a node.
+///
+/// See [`node_size`].
+#[inline]
+pub const fn node_align<K, V>() -> usize {
+	mem::align_of::<Node<K, V>>()
+}
+
+/// Returns the size in bytes of an item.
+///
+/// See [`node_size`].
+#[inline]
+pub const fn item_size<K, V>() -> usize {
+	mem::size_of::<Item<K, V>>()
+}
+
 /// Type identifier by a key.
 ///
 /// This is implemented by [`Item`] and [`internal::Branch`].
@@ -67,17 +200,7 @@ impl Offset {
 
 impl PartialOrd for Offset {
 	fn partial_cmp(&self, offset: &Offset) -> Option<Ordering> {
-		if self.0 == usize::MAX || offset.0 == usize::MAX {
-			if self.0 == usize::MAX && offset.0 == usize::MAX {
-				Some(Ordering::Equal)
-			} else if self.0 == usize::MAX {
-				Some(Ordering::Less)
-			} else {
-				Some(Ordering::Greater)
-			}
-		} else {
-			self.0.partial_cmp(&offset.0)
-		}
+		Some(self.cmp(offset))
 	}
 }
 
@@ -164,6 +287,16 @@ pub struct WouldUnderflow;
 pub type PoppedItem<K, V> = (Offset, Item<K, V>, Option<usize>);
 
 /// B-tree node.
+///
+/// # Memory layout
+///
+/// Items are stored inline: a leaf holds an array of [`LEAF_CAPACITY`] items
+/// and an internal node an array of [`INTERNAL_CAPACITY`] branches
+/// (an item and a child id), along with the parent id
+/// and the number of items in its subtree.
+/// Nodes never allocate on their own,
+/// so the memory used by a map is the memory of its node container.
+/// The size of a node for given key and value types is given by [`node_size`].
 #[derive(Clone)]
 pub enum Node<K, V> {
 	/// Internal node.
@@ -237,6 +370,25 @@ impl<K, V> Node<K, V> {
 		}
 	}
 
+	/// Returns the number of items in the subtree rooted in this node.
+	#[inline]
+	pub fn subtree_len(&self) -> usize {
+		match self {
+			Node::Internal(node) => node.subtree_len(),
+			Node::Leaf(leaf) => leaf.item_count(),
+		}
+	}
+
+	/// Sets the number of items in the subtree rooted in this node.
+	///
+	/// This does nothing on a leaf, whose subtree is itself.
+	#[inline]
+	pub fn set_subtree_len(&mut self, len: usize) {
+		if let Node::Internal(node) = self {
+			node.set_subtree_len(len)
+		}
+	}
+
 	#[inline]
 	pub fn child_count(&self) -> usize {
 		match self {
@@ -270,6 +422,7 @@ impl<K, V> Node<K, V> {
 	}
 
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn get<Q: ?Sized>(&self, key: &Q) -> Result<Option<&V>, usize>
 	where
 		K: Borrow<Q>,
@@ -285,6 +438,7 @@ impl<K, V> Node<K, V> {
 	}
 
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Result<Option<&mut V>, usize>
 	where
 		K: Borrow<Q>,
@@ -305,6 +459,7 @@ impl<K, V> Node<K, V> {
 	/// this funtion returns the index and id of the child that may match the key,
 	/// or `Err(None)` if it is a leaf.
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn offset_of<Q: ?Sized>(&self, key: &Q) -> Result<Offset, (usize, Option<usize>)>
 	where
 		K: Borrow<Q>,
@@ -376,6 +531,47 @@ impl<K, V> Node<K, V> {
 		}
 	}
 
+	/// Split the overflowing node around the item at the given offset.
+	/// Return the length of the node after split, the median item and the right node.
+	#[inline]
+	pub fn split_around(&mut self, median_offset: usize) -> (usize, Item<K, V>, Node<K, V>) {
+		match self {
+			Node::Internal(node) => {
+				let (len, item, right_node) = node.split_around(median_offset);
+				(len, item, Node::Internal(right_node))
+			}
+			Node::Leaf(leaf) => {
+				let (len, item, right_leaf) = leaf.split_around(median_offset);
+				(len, item, Node::Leaf(right_leaf))
+			}
+		}
+	}
+
+	/// Split the node before the item at the given offset.
+	/// Return the right node, whose first child is `opt_first_child_id` if it is an internal node.
+	#[inline]
+	pub fn split_off(&mut self, offset: usize, opt_first_child_id: Option<usize>) -> Node<K, V> {
+		match self {
+			Node::Internal(node) => {
+				Node::Internal(node.split_off(offset, opt_first_child_id.unwrap()))
+			}
+			Node::Leaf(leaf) => Node::Leaf(leaf.split_off(offset)),
+		}
+	}
+
+	/// Split the internal node around the item at the given offset.
+	/// Return the item and the right node.
+	#[inline]
+	pub fn split_at(&mut self, offset: usize) -> (Item<K, V>, Node<K, V>) {
+		match self {
+			Node::Internal(node) => {
+				let (item, right_node) = node.split_at(offset);
+				(item, Node::Internal(right_node))
+			}
+			_ => panic!("only internal nodes can be split around an item"),
+		}
+	}
+
 	#[inline]
 	pub fn merge(
 		&mut self,
@@ -500,7 +696,7 @@ impl<K, V> Node<K, V> {
 	}
 
 	#[inline]
-	pub fn children(&self) -> Children<K, V> {
+	pub fn children(&self) -> Children<'_, K, V> {
 		match self {
 			Node::Leaf(_) => Children::Leaf,
 			Node::Internal(node) => node.children(),
@@ -508,7 +704,7 @@ impl<K, V> Node<K, V> {
 	}
 
 	#[inline]
-	pub fn children_with_separators(&self) -> ChildrenWithSeparators<K, V> {
+	pub fn children_with_separators(&self) -> ChildrenWithSeparators<'_, K, V> {
 		match self {
 			Node::Leaf(_) => ChildrenWithSeparators::Leaf,
 			Node::Internal(node) => node.children_with_separators(),
@@ -531,6 +727,38 @@ impl<K, V> Node<K, V> {
 		}
 	}
 
+	/// Write the label of the node in the Mermaid flowchart syntax.
+	///
+	/// Requires the `mermaid` feature.
+	#[cfg(feature = "mermaid")]
+	#[inline]
+	pub fn mermaid_write_label<W: std::io::Write>(&self, f: &mut W) -> std::io::Result<()>
+	where
+		K: std::fmt::Display,
+		V: std::fmt::Display,
+	{
+		match self {
+			Node::Leaf(leaf) => leaf.mermaid_write_label(f),
+			Node::Internal(node) => node.mermaid_write_label(f),
+		}
+	}
+
+	/// Checks the invariants of this node, given its expected parent and separators.
+	pub fn check(
+		&self,
+		parent: Option<usize>,
+		min: Option<&K>,
+		max: Option<&K>,
+	) -> Result<(), Invariant>
+	where
+		K: Ord,
+	{
+		match self {
+			Node::Leaf(leaf) => leaf.check(parent, min, max),
+			Node::Internal(node) => node.check(parent, min, max),
+		}
+	}
+
 	#[cfg(debug_assertions)]
 	pub fn validate(&self, parent: Option<usize>, min: Option<&K>, max: Option<&K>)
 	where
@@ -109,7 +109,7 @@ impl Address {
 	#[inline]
 	pub fn nowhere() -> Address {
 		Address {
-			id: std::usize::MAX,
+			id: usize::MAX,
 			offset: 0.into(),
 		}
 	}
@@ -117,7 +117,14 @@ impl Address {
 	/// Checks if the address is nowhere.
 	#[inline]
 	pub fn is_nowhere(&self) -> bool {
-		self.id == std::usize::MAX
+		self.id == usize::MAX
+	}
+
+	/// Address of the next offset in the same node.
+	#[inline]
+	pub(crate) fn next_in_node(mut self) -> Address {
+		self.offset.incr();
+		self
 	}
 }
 
@@ -0,0 +1,121 @@
+use super::{Address, Offset};
+use std::{convert::TryFrom, fmt};
+
+/// Packed item address.
+///
+/// Compact representation of an [`Address`] using a 32-bit node identifier
+/// and a 16-bit offset, with a stable (`repr(C)`) layout.
+/// This is useful to reduce the size of auxiliary data structures storing
+/// many addresses.
+///
+/// The `nowhere` address and the `-1` offset are preserved by the conversion.
+///
+/// # Example
+///
+/// ```
+/// use std::convert::TryFrom;
+/// use btree_slab::generic::node::{Address, Address32};
+///
+/// let addr = Address::new(3, 2.into());
+/// let packed = Address32::try_from(addr).unwrap();
+/// assert_eq!(Address::from(packed), addr);
+///
+/// assert!(Address32::try_from(Address::new(1 << 40, 0.into())).is_err());
+/// ```
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[repr(C)]
+pub struct Address32 {
+	/// Identifier of the node.
+	pub id: u32,
+
+	/// Offset in the node.
+	pub offset: u16,
+}
+
+impl Address32 {
+	/// Address in the empty tree.
+	///
+	/// See [`Address::nowhere`].
+	#[inline]
+	pub fn nowhere() -> Address32 {
+		Address32 {
+			id: u32::MAX,
+			offset: 0,
+		}
+	}
+
+	/// Checks if the address is nowhere.
+	#[inline]
+	pub fn is_nowhere(&self) -> bool {
+		self.id == u32::MAX
+	}
+}
+
+/// Error raised when an [`Address`] does not fit in an [`Address32`].
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AddressOverflow(pub Address);
+
+impl fmt::Display for AddressOverflow {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "address {} does not fit in 32 bits", self.0)
+	}
+}
+
+impl std::error::Error for AddressOverflow {}
+
+impl TryFrom<Address> for Address32 {
+	type Error = AddressOverflow;
+
+	#[inline]
+	fn try_from(addr: Address) -> Result<Address32, AddressOverflow> {
+		let id = if addr.is_nowhere() {
+			u32::MAX
+		} else {
+			match u32::try_from(addr.id) {
+				Ok(id) if id != u32::MAX => id,
+				_ => return Err(AddressOverflow(addr)),
+			}
+		};
+
+		let offset = match addr.offset.value() {
+			Some(offset) => match u16::try_from(offset) {
+				Ok(offset) if offset != u16::MAX => offset,
+				_ => return Err(AddressOverflow(addr)),
+			},
+			None => u16::MAX,
+		};
+
+		Ok(Address32 { id, offset })
+	}
+}
+
+impl From<Address32> for Address {
+	#[inline]
+	fn from(addr: Address32) -> Address {
+		let id = if addr.is_nowhere() {
+			usize::MAX
+		} else {
+			addr.id as usize
+		};
+
+		let offset = if addr.offset == u16::MAX {
+			Offset::before()
+		} else {
+			(addr.offset as usize).into()
+		};
+
+		Address::new(id, offset)
+	}
+}
+
+impl fmt::Display for Address32 {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		Address::from(*self).fmt(f)
+	}
+}
+
+impl fmt::Debug for Address32 {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		Address::from(*self).fmt(f)
+	}
+}
@@ -1,9 +1,12 @@
 use crate::{
 	generic::{
-		map::M,
-		node::{Balance, Children, ChildrenWithSeparators, Item, Keyed, Offset, WouldUnderflow},
+		map::{Invariant, M},
+		node::{
+			load_id, load_parent, store_id, store_parent, Balance, Children,
+			ChildrenWithSeparators, Item, Keyed, NodeId, Offset, WouldUnderflow, INTERNAL_CAPACITY,
+		},
 	},
-	utils::binary_search_min,
+	utils::search_min,
 };
 use smallvec::SmallVec;
 use std::{borrow::Borrow, cmp::Ordering};
@@ -72,9 +75,15 @@ pub struct InsertionError<K, V> {
 /// An internal node is a node where each item is surrounded by edges to child nodes.
 #[derive(Clone)]
 pub struct Internal<K, V> {
-	parent: usize,
-	first_child: usize,
-	other_children: SmallVec<[Branch<K, V>; M]>,
+	parent: NodeId,
+
+	/// Number of items in the subtree rooted in this node.
+	///
+	/// It is maintained by the tree, since the node does not know its children.
+	len: usize,
+
+	first_child: NodeId,
+	other_children: SmallVec<[Branch<K, V>; INTERNAL_CAPACITY]>,
 }
 
 impl<K, V> Internal<K, V> {
@@ -93,8 +102,9 @@ impl<K, V> Internal<K, V> {
 		});
 
 		Internal {
-			parent: parent.unwrap_or(std::usize::MAX),
-			first_child: left_id,
+			parent: store_parent(parent),
+			len: 0,
+			first_child: store_id(left_id),
 			other_children,
 		}
 	}
@@ -123,16 +133,12 @@ impl<K, V> Internal<K, V> {
 
 	#[inline]
 	pub fn parent(&self) -> Option<usize> {
-		if self.parent == std::usize::MAX {
-			None
-		} else {
-			Some(self.parent)
-		}
+		load_parent(self.parent)
 	}
 
 	#[inline]
 	pub fn set_parent(&mut self, p: Option<usize>) {
-		self.parent = p.unwrap_or(std::usize::MAX);
+		self.parent = store_parent(p);
 	}
 
 	#[inline]
@@ -140,6 +146,18 @@ impl<K, V> Internal<K, V> {
 		self.other_children.len()
 	}
 
+	/// Returns the number of items in the subtree rooted in this node.
+	#[inline]
+	pub fn subtree_len(&self) -> usize {
+		self.len
+	}
+
+	/// Sets the number of items in the subtree rooted in this node.
+	#[inline]
+	pub fn set_subtree_len(&mut self, len: usize) {
+		self.len = len
+	}
+
 	#[inline]
 	pub fn child_count(&self) -> usize {
 		1usize + self.item_count()
@@ -147,7 +165,7 @@ impl<K, V> Internal<K, V> {
 
 	#[inline]
 	pub fn first_child_id(&self) -> usize {
-		self.first_child
+		load_id(self.first_child)
 	}
 
 	#[inline]
@@ -155,9 +173,38 @@ impl<K, V> Internal<K, V> {
 		self.other_children.as_ref()
 	}
 
+	/// Returns mutable references to the items of the node, in order.
+	#[inline]
+	pub fn items_mut(&mut self) -> impl Iterator<Item = &mut Item<K, V>> {
+		self.other_children
+			.iter_mut()
+			.map(|branch| &mut branch.item)
+	}
+
+	/// Returns the first child id and the branches of the node.
+	#[inline]
+	pub fn into_parts(self) -> (usize, SmallVec<[Branch<K, V>; INTERNAL_CAPACITY]>) {
+		(load_id(self.first_child), self.other_children)
+	}
+
+	/// Builds an internal node from its first child id and its branches.
+	#[inline]
+	pub fn from_parts(
+		parent: Option<usize>,
+		first_child: usize,
+		other_children: SmallVec<[Branch<K, V>; INTERNAL_CAPACITY]>,
+	) -> Internal<K, V> {
+		Internal {
+			parent: store_parent(parent),
+			len: 0,
+			first_child: store_id(first_child),
+			other_children,
+		}
+	}
+
 	#[inline]
 	pub fn child_index(&self, id: usize) -> Option<usize> {
-		if self.first_child == id {
+		if load_id(self.first_child) == id {
 			Some(0)
 		} else {
 			for i in 0..self.other_children.len() {
@@ -173,7 +220,7 @@ impl<K, V> Internal<K, V> {
 	#[inline]
 	pub fn child_id(&self, index: usize) -> usize {
 		if index == 0 {
-			self.first_child
+			load_id(self.first_child)
 		} else {
 			self.other_children[index - 1].child
 		}
@@ -182,7 +229,7 @@ impl<K, V> Internal<K, V> {
 	#[inline]
 	pub fn child_id_opt(&self, index: usize) -> Option<usize> {
 		if index == 0 {
-			Some(self.first_child)
+			Some(load_id(self.first_child))
 		} else {
 			self.other_children.get(index - 1).map(|b| b.child)
 		}
@@ -206,12 +253,13 @@ impl<K, V> Internal<K, V> {
 	}
 
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn get<Q: ?Sized>(&self, key: &Q) -> Result<&V, usize>
 	where
 		K: Borrow<Q>,
 		Q: Ord,
 	{
-		match binary_search_min(&self.other_children, key) {
+		match search_min(&self.other_children, key) {
 			Some(offset) => {
 				let b = &self.other_children[offset];
 				if b.item.key().borrow() == key {
@@ -220,17 +268,18 @@ impl<K, V> Internal<K, V> {
 					Err(b.child)
 				}
 			}
-			None => Err(self.first_child),
+			None => Err(load_id(self.first_child)),
 		}
 	}
 
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Result<&mut V, usize>
 	where
 		K: Borrow<Q>,
 		Q: Ord,
 	{
-		match binary_search_min(&self.other_children, key) {
+		match search_min(&self.other_children, key) {
 			Some(offset) => {
 				let b = &mut self.other_children[offset];
 				if b.item.key().borrow() == key {
@@ -239,7 +288,7 @@ impl<K, V> Internal<K, V> {
 					Err(b.child)
 				}
 			}
-			None => Err(self.first_child),
+			None => Err(load_id(self.first_child)),
 		}
 	}
 
@@ -248,12 +297,13 @@ impl<K, V> Internal<K, V> {
 	/// If the key matches no item in this node,
 	/// this funtion returns the index and id of the child that may match the key.
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn offset_of<Q: ?Sized>(&self, key: &Q) -> Result<Offset, (usize, usize)>
 	where
 		K: Borrow<Q>,
 		Q: Ord,
 	{
-		match binary_search_min(&self.other_children, key) {
+		match search_min(&self.other_children, key) {
 			Some(offset) => {
 				if self.other_children[offset].item.key().borrow() == key {
 					Ok(offset.into())
@@ -262,19 +312,22 @@ impl<K, V> Internal<K, V> {
 					Err((offset + 1, id))
 				}
 			}
-			None => Err((0, self.first_child)),
+			None => Err((0, load_id(self.first_child))),
 		}
 	}
 
 	#[inline]
-	pub fn children(&self) -> Children<K, V> {
-		Children::Internal(Some(self.first_child), self.other_children.as_ref().iter())
+	pub fn children(&self) -> Children<'_, K, V> {
+		Children::Internal(
+			Some(load_id(self.first_child)),
+			self.other_children.as_ref().iter(),
+		)
 	}
 
 	#[inline]
-	pub fn children_with_separators(&self) -> ChildrenWithSeparators<K, V> {
+	pub fn children_with_separators(&self) -> ChildrenWithSeparators<'_, K, V> {
 		ChildrenWithSeparators::Internal(
-			Some(self.first_child),
+			Some(load_id(self.first_child)),
 			None,
 			self.other_children.as_ref().iter().peekable(),
 		)
@@ -308,7 +361,7 @@ impl<K, V> Internal<K, V> {
 	where
 		K: Ord,
 	{
-		match binary_search_min(&self.other_children, &key) {
+		match search_min(&self.other_children, &key) {
 			Some(i) => {
 				if self.other_children[i].item.key() == &key {
 					std::mem::swap(&mut value, self.other_children[i].item.value_mut());
@@ -326,7 +379,7 @@ impl<K, V> Internal<K, V> {
 				key,
 				value,
 				child_offset: 0,
-				child_id: self.first_child,
+				child_id: load_id(self.first_child),
 			}),
 		}
 	}
@@ -334,7 +387,7 @@ impl<K, V> Internal<K, V> {
 	// /// Get the offset of the item with the given key.
 	// #[inline]
 	// pub fn key_offset(&self, key: &K) -> Result<usize, (usize, usize)> {
-	// 	match binary_search_min(&self.other_children, key) {
+	// 	match search_min(&self.other_children, key) {
 	// 		Some(i) => {
 	// 			if self.other_children[i].item.key() == key {
 	// 				Ok(i)
@@ -380,17 +433,25 @@ impl<K, V> Internal<K, V> {
 
 	#[inline]
 	pub fn split(&mut self) -> (usize, Item<K, V>, Internal<K, V>) {
-		assert!(self.is_overflowing()); // implies self.other_children.len() >= 4
-
 		// Index of the median-key item in `other_children`.
-		let median_i = (self.other_children.len() - 1) / 2; // Since M is at least 3, `median_i` is at least 1.
+		// Since M is at least 3, it is at least 1.
+		self.split_around((self.other_children.len() - 1) / 2)
+	}
+
+	/// Splits the overflowing node around the item at the given offset.
+	///
+	/// Both nodes must hold enough items after that.
+	#[inline]
+	pub fn split_around(&mut self, median_i: usize) -> (usize, Item<K, V>, Internal<K, V>) {
+		assert!(self.is_overflowing()); // implies self.other_children.len() >= 4
 
 		let right_other_children = self.other_children.drain(median_i + 1..).collect();
 		let median = self.other_children.pop().unwrap();
 
 		let right_node = Internal {
 			parent: self.parent,
-			first_child: median.child,
+			len: 0,
+			first_child: store_id(median.child),
 			other_children: right_other_children,
 		};
 
@@ -400,6 +461,34 @@ impl<K, V> Internal<K, V> {
 		(self.other_children.len(), median.item, right_node)
 	}
 
+	/// Splits the node before the item at the given offset.
+	///
+	/// Returns a new node, with the same parent, holding the items from `offset` on
+	/// and their right children, and whose first child is `first_child`.
+	/// Both nodes may underflow after that.
+	#[inline]
+	pub fn split_off(&mut self, offset: usize, first_child: usize) -> Internal<K, V> {
+		Internal {
+			parent: self.parent,
+			len: 0,
+			first_child: store_id(first_child),
+			other_children: self.other_children.drain(offset..).collect(),
+		}
+	}
+
+	/// Splits the node around the item at the given offset.
+	///
+	/// Returns the item and a new node, with the same parent,
+	/// holding the items following it and their children.
+	/// Both nodes may underflow after that.
+	#[inline]
+	pub fn split_at(&mut self, offset: usize) -> (Item<K, V>, Internal<K, V>) {
+		let mut right_node = self.split_off(offset + 1, 0);
+		let separator = self.other_children.pop().unwrap();
+		right_node.first_child = store_id(separator.child);
+		(separator.item, right_node)
+	}
+
 	/// Merge the children at the given indexes.
 	///
 	/// It is supposed that `left_index` is `right_index-1`.
@@ -428,10 +517,10 @@ impl<K, V> Internal<K, V> {
 			0,
 			Branch {
 				item,
-				child: self.first_child,
+				child: load_id(self.first_child),
 			},
 		);
-		self.first_child = child_id
+		self.first_child = store_id(child_id)
 	}
 
 	#[inline]
@@ -439,9 +528,9 @@ impl<K, V> Internal<K, V> {
 		if self.item_count() <= UNDERFLOW {
 			Err(WouldUnderflow)
 		} else {
-			let child_id = self.first_child;
+			let child_id = load_id(self.first_child);
 			let first = self.other_children.remove(0);
-			self.first_child = first.child;
+			self.first_child = store_id(first.child);
 			Ok((first.item, child_id))
 		}
 	}
@@ -472,7 +561,7 @@ impl<K, V> Internal<K, V> {
 		let offset = self.other_children.len();
 		self.other_children.push(Branch {
 			item: separator,
-			child: other.first_child,
+			child: load_id(other.first_child),
 		});
 
 		self.other_children.append(&mut other.other_children);
@@ -490,49 +579,77 @@ impl<K, V> Internal<K, V> {
 		V: std::fmt::Display,
 	{
 		write!(f, "<c0> |")?;
-		let mut i = 1;
-		for branch in &self.other_children {
+		for (i, branch) in self.other_children.iter().enumerate() {
 			write!(
 				f,
 				"{{{}|<c{}> {}}} |",
 				branch.item.key(),
-				i,
+				i + 1,
 				branch.item.value()
 			)?;
-			i += 1;
 		}
 
 		Ok(())
 	}
 
-	#[cfg(debug_assertions)]
-	pub fn validate(&self, parent: Option<usize>, min: Option<&K>, max: Option<&K>)
+	/// Write the label of the internal node in the Mermaid flowchart syntax.
+	///
+	/// Requires the `mermaid` feature.
+	#[cfg(feature = "mermaid")]
+	#[inline]
+	pub fn mermaid_write_label<W: std::io::Write>(&self, f: &mut W) -> std::io::Result<()>
+	where
+		K: std::fmt::Display,
+		V: std::fmt::Display,
+	{
+		for (i, branch) in self.other_children.iter().enumerate() {
+			if i > 0 {
+				write!(f, " | ")?;
+			}
+
+			write!(f, "{}: {}", branch.item.key(), branch.item.value())?;
+		}
+
+		Ok(())
+	}
+
+	/// Checks the invariants of this node, given its expected parent and separators.
+	pub fn check(
+		&self,
+		parent: Option<usize>,
+		min: Option<&K>,
+		max: Option<&K>,
+	) -> Result<(), Invariant>
 	where
 		K: Ord,
 	{
 		if self.parent() != parent {
-			panic!("wrong parent")
+			return Err(Invariant::Parent);
 		}
 
 		if min.is_some() || max.is_some() {
 			// not root
 			match self.balance() {
-				Balance::Overflow => panic!("internal node is overflowing"),
-				Balance::Underflow(_) => panic!("internal node is underflowing"),
+				Balance::Overflow => return Err(Invariant::Overflow),
+				Balance::Underflow(_) => return Err(Invariant::Underflow),
 				_ => (),
 			}
 		} else if self.item_count() == 0 {
-			panic!("root node is empty")
+			return Err(Invariant::EmptyRoot);
+		}
+
+		if self.other_children.spilled() {
+			return Err(Invariant::InlineStorage);
 		}
 
 		if !self.other_children.windows(2).all(|w| w[0] < w[1]) {
-			panic!("internal node items are not sorted")
+			return Err(Invariant::Sorted);
 		}
 
 		if let Some(min) = min {
 			if let Some(b) = self.other_children.first() {
 				if min >= b.item.key() {
-					panic!("internal node item key is greater than right separator")
+					return Err(Invariant::Separators);
 				}
 			}
 		}
@@ -540,9 +657,21 @@ impl<K, V> Internal<K, V> {
 		if let Some(max) = max {
 			if let Some(b) = self.other_children.last() {
 				if max <= b.item.key() {
-					panic!("internal node item key is less than left separator")
+					return Err(Invariant::Separators);
 				}
 			}
 		}
+
+		Ok(())
+	}
+
+	#[cfg(debug_assertions)]
+	pub fn validate(&self, parent: Option<usize>, min: Option<&K>, max: Option<&K>)
+	where
+		K: Ord,
+	{
+		if let Err(invariant) = self.check(parent, min, max) {
+			panic!("{}", invariant)
+		}
 	}
 }
@@ -31,6 +31,12 @@ impl<K, V> AsRef<Item<K, V>> for Item<K, V> {
 }
 
 impl<K, V> Item<K, V> {
+	/// Creates a new item with the given key and value.
+	///
+	/// The safe methods of `Item` all preserve the initialization of its key and value.
+	/// Only [`RawItemOps`], exported in the [`raw`](crate::generic::node::raw) module,
+	/// allows working on uninitialized items.
+	#[inline]
 	pub fn new(key: K, value: V) -> Item<K, V> {
 		Item {
 			key: MaybeUninit::new(key),
@@ -84,11 +90,6 @@ impl<K, V> Item<K, V> {
 		unsafe { old_value.assume_init() }
 	}
 
-	#[inline]
-	pub fn maybe_uninit_value_mut(&mut self) -> &mut MaybeUninit<V> {
-		&mut self.value
-	}
-
 	#[inline]
 	pub fn into_key(self) -> K {
 		let (key, value) = self.into_inner();
@@ -123,25 +124,102 @@ impl<K, V> Item<K, V> {
 		unsafe { (key.assume_init(), value.assume_init()) }
 	}
 
-	/// Drop the key but not the value which is assumed uninitialized.
+	#[inline]
+	fn into_inner(mut self) -> (MaybeUninit<K>, MaybeUninit<V>) {
+		let mut key = MaybeUninit::uninit();
+		let mut value = MaybeUninit::uninit();
+		std::mem::swap(&mut key, &mut self.key);
+		std::mem::swap(&mut value, &mut self.value);
+		std::mem::forget(self);
+		(key, value)
+	}
+}
+
+mod sealed {
+	pub trait Sealed {}
+
+	impl<K, V> Sealed for super::Item<K, V> {}
+}
+
+/// Raw operations on items, exposing their possibly uninitialized content.
+///
+/// An item normally always holds an initialized key and value.
+/// These operations allow the value to be temporarily moved out of the item
+/// (for instance to pass it by value to a closure) without moving the item itself.
+/// They are used to implement the map operations and can be used to extend them,
+/// but the caller is responsible for upholding the following invariant:
+///
+/// **the key and value of an item must be initialized whenever the item is accessed
+/// through any other method, or dropped.**
+///
+/// An item whose value has been moved out must then either receive a new value
+/// through [`RawItemOps::maybe_uninit_value_mut`], or be disposed of with
+/// [`RawItemOps::forget_value`].
+///
+/// This trait is sealed: it is only implemented by [`Item`].
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::generic::node::{raw::RawItemOps, Item};
+/// use std::mem::MaybeUninit;
+///
+/// let mut item = Item::new(1, "a".to_string());
+///
+/// // Move the value out and write a new one back.
+/// unsafe {
+///     let value = std::mem::replace(item.maybe_uninit_value_mut(), MaybeUninit::uninit());
+///     let value = value.assume_init() + "b";
+///     item.maybe_uninit_value_mut().write(value);
+/// }
+///
+/// assert_eq!(item.value(), "ab");
+/// ```
+pub trait RawItemOps: sealed::Sealed {
+	type Key;
+	type Value;
+
+	/// Returns a mutable reference to the possibly uninitialized value of the item.
 	///
 	/// # Safety
 	///
-	/// The value must be uninitialized.
+	/// If the value is moved out (or replaced by an uninitialized value),
+	/// a value must be written back before the item is accessed by any other method
+	/// or dropped, unless the item is disposed of with [`RawItemOps::forget_value`].
+	unsafe fn maybe_uninit_value_mut(&mut self) -> &mut MaybeUninit<Self::Value>;
+
+	/// Drops the key but not the value, which is assumed uninitialized.
+	///
+	/// # Safety
+	///
+	/// The value must be uninitialized (moved out).
+	unsafe fn forget_value(self);
+
+	/// Splits the item into its key and value, without dropping them.
+	///
+	/// This is safe by itself, but reading the returned key or value
+	/// requires them to be initialized.
+	fn into_raw_parts(self) -> (MaybeUninit<Self::Key>, MaybeUninit<Self::Value>);
+}
+
+impl<K, V> RawItemOps for Item<K, V> {
+	type Key = K;
+	type Value = V;
+
 	#[inline]
-	pub unsafe fn forget_value(self) {
+	unsafe fn maybe_uninit_value_mut(&mut self) -> &mut MaybeUninit<V> {
+		&mut self.value
+	}
+
+	#[inline]
+	unsafe fn forget_value(self) {
 		let (key, _) = self.into_inner();
 		std::mem::drop(key.assume_init())
 	}
 
 	#[inline]
-	pub fn into_inner(mut self) -> (MaybeUninit<K>, MaybeUninit<V>) {
-		let mut key = MaybeUninit::uninit();
-		let mut value = MaybeUninit::uninit();
-		std::mem::swap(&mut key, &mut self.key);
-		std::mem::swap(&mut value, &mut self.value);
-		std::mem::forget(self);
-		(key, value)
+	fn into_raw_parts(self) -> (MaybeUninit<K>, MaybeUninit<V>) {
+		self.into_inner()
 	}
 }
 
@@ -1,17 +1,19 @@
 use crate::{
 	generic::{
-		map::M,
-		node::{Balance, Item, Offset, WouldUnderflow},
+		map::{Invariant, M},
+		node::{
+			load_parent, store_parent, Balance, Item, NodeId, Offset, WouldUnderflow, LEAF_CAPACITY,
+		},
 	},
-	utils::binary_search_min,
+	utils::search_min,
 };
 use smallvec::SmallVec;
-use std::borrow::Borrow;
+use std::{borrow::Borrow, convert::Infallible};
 
 #[derive(Clone)]
 pub struct Leaf<K, V> {
-	parent: usize,
-	items: SmallVec<[Item<K, V>; M + 1]>,
+	parent: NodeId,
+	items: SmallVec<[Item<K, V>; LEAF_CAPACITY]>,
 }
 
 impl<K, V> Leaf<K, V> {
@@ -21,23 +23,19 @@ impl<K, V> Leaf<K, V> {
 		items.push(item);
 
 		Leaf {
-			parent: parent.unwrap_or(std::usize::MAX),
+			parent: store_parent(parent),
 			items,
 		}
 	}
 
 	#[inline]
 	pub fn parent(&self) -> Option<usize> {
-		if self.parent == std::usize::MAX {
-			None
-		} else {
-			Some(self.parent)
-		}
+		load_parent(self.parent)
 	}
 
 	#[inline]
 	pub fn set_parent(&mut self, p: Option<usize>) {
-		self.parent = p.unwrap_or(std::usize::MAX);
+		self.parent = store_parent(p);
 	}
 
 	#[inline]
@@ -51,17 +49,72 @@ impl<K, V> Leaf<K, V> {
 	}
 
 	#[inline]
-	pub fn iter(&self) -> std::slice::Iter<Item<K, V>> {
+	pub fn items_mut(&mut self) -> &mut [Item<K, V>] {
+		self.items.as_mut()
+	}
+
+	#[inline]
+	pub fn iter(&self) -> std::slice::Iter<'_, Item<K, V>> {
 		self.items.as_ref().iter()
 	}
 
+	/// Creates a leaf without parent nor items.
+	///
+	/// It is used as a placeholder while a node is temporarily taken out of its slot,
+	/// and as a starting point when building a tree bottom-up.
+	#[inline]
+	pub(crate) fn empty() -> Leaf<K, V> {
+		Leaf {
+			parent: NodeId::MAX,
+			items: SmallVec::new(),
+		}
+	}
+
+	/// Converts the values of this leaf, in order, keeping its keys.
+	///
+	/// The returned leaf has no parent.
+	#[inline]
+	pub fn map_values<W, F>(self, f: &mut F) -> Leaf<K, W>
+	where
+		F: FnMut(&K, V) -> W,
+	{
+		match self.try_map_values(&mut |key, value| Ok::<_, Infallible>(f(key, value))) {
+			Ok(leaf) => leaf,
+			Err(e) => match e {},
+		}
+	}
+
+	/// Converts the values of this leaf, in order, keeping its keys,
+	/// stopping at the first error.
+	///
+	/// The returned leaf has no parent.
+	#[inline]
+	pub fn try_map_values<W, E, F>(self, f: &mut F) -> Result<Leaf<K, W>, E>
+	where
+		F: FnMut(&K, V) -> Result<W, E>,
+	{
+		Ok(Leaf {
+			parent: NodeId::MAX,
+			items: self
+				.items
+				.into_iter()
+				.map(|item| {
+					let (key, value) = item.into_pair();
+					let value = f(&key, value)?;
+					Ok(Item::new(key, value))
+				})
+				.collect::<Result<_, E>>()?,
+		})
+	}
+
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn get<Q: ?Sized>(&self, key: &Q) -> Option<&V>
 	where
 		K: Borrow<Q>,
 		Q: Ord,
 	{
-		match binary_search_min(&self.items, key) {
+		match search_min(&self.items, key) {
 			Some(i) => {
 				let item = &self.items[i];
 				if item.key().borrow() == key {
@@ -75,12 +128,13 @@ impl<K, V> Leaf<K, V> {
 	}
 
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn get_mut<Q: ?Sized>(&mut self, key: &Q) -> Option<&mut V>
 	where
 		K: Borrow<Q>,
 		Q: Ord,
 	{
-		match binary_search_min(&self.items, key) {
+		match search_min(&self.items, key) {
 			Some(i) => {
 				let item = &mut self.items[i];
 				if item.key().borrow() == key {
@@ -95,12 +149,13 @@ impl<K, V> Leaf<K, V> {
 
 	/// Find the offset of the item matching the given key.
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn offset_of<Q: ?Sized>(&self, key: &Q) -> Result<Offset, Offset>
 	where
 		K: Borrow<Q>,
 		Q: Ord,
 	{
-		match binary_search_min(&self.items, key) {
+		match search_min(&self.items, key) {
 			Some(i) => {
 				if self.items[i].key().borrow() == key {
 					Ok(i.into())
@@ -133,7 +188,7 @@ impl<K, V> Leaf<K, V> {
 	where
 		K: Ord,
 	{
-		match binary_search_min(&self.items, &key) {
+		match search_min(&self.items, &key) {
 			Some(i) => {
 				if self.items[i].key() == &key {
 					std::mem::swap(&mut value, self.items[i].value_mut());
@@ -152,9 +207,15 @@ impl<K, V> Leaf<K, V> {
 
 	#[inline]
 	pub fn split(&mut self) -> (usize, Item<K, V>, Leaf<K, V>) {
-		assert!(self.is_overflowing());
+		self.split_around((self.items.len() - 1) / 2)
+	}
 
-		let median_i = (self.items.len() - 1) / 2;
+	/// Splits the overflowing leaf around the item at the given offset.
+	///
+	/// Both leaves must hold enough items after that.
+	#[inline]
+	pub fn split_around(&mut self, median_i: usize) -> (usize, Item<K, V>, Leaf<K, V>) {
+		assert!(self.is_overflowing());
 
 		let right_items = self.items.drain(median_i + 1..).collect();
 		let median = self.items.pop().unwrap();
@@ -170,6 +231,18 @@ impl<K, V> Leaf<K, V> {
 		(self.items.len(), median, right_leaf)
 	}
 
+	/// Splits the leaf at the given offset.
+	///
+	/// Returns a new leaf holding the items from `offset` on,
+	/// with the same parent. The leaf may underflow after that.
+	#[inline]
+	pub fn split_off(&mut self, offset: usize) -> Leaf<K, V> {
+		Leaf {
+			parent: self.parent,
+			items: self.items.drain(offset..).collect(),
+		}
+	}
+
 	#[inline]
 	pub fn append(&mut self, separator: Item<K, V>, mut other: Leaf<K, V>) -> Offset {
 		let offset = self.items.len();
@@ -272,32 +345,62 @@ impl<K, V> Leaf<K, V> {
 		Ok(())
 	}
 
-	#[cfg(debug_assertions)]
-	pub fn validate(&self, parent: Option<usize>, min: Option<&K>, max: Option<&K>)
+	/// Write the label of the leaf in the Mermaid flowchart syntax.
+	///
+	/// Requires the `mermaid` feature.
+	#[cfg(feature = "mermaid")]
+	#[inline]
+	pub fn mermaid_write_label<W: std::io::Write>(&self, f: &mut W) -> std::io::Result<()>
+	where
+		K: std::fmt::Display,
+		V: std::fmt::Display,
+	{
+		for (i, item) in self.items.iter().enumerate() {
+			if i > 0 {
+				write!(f, " | ")?;
+			}
+
+			write!(f, "{}: {}", item.key(), item.value())?;
+		}
+
+		Ok(())
+	}
+
+	/// Checks the invariants of this node, given its expected parent and separators.
+	pub fn check(
+		&self,
+		parent: Option<usize>,
+		min: Option<&K>,
+		max: Option<&K>,
+	) -> Result<(), Invariant>
 	where
 		K: Ord,
 	{
 		if self.parent() != parent {
-			panic!("wrong parent")
+			return Err(Invariant::Parent);
 		}
 
 		if min.is_some() || max.is_some() {
 			// not root
 			match self.balance() {
-				Balance::Overflow => panic!("leaf is overflowing"),
-				Balance::Underflow(_) => panic!("leaf is underflowing"),
+				Balance::Overflow => return Err(Invariant::Overflow),
+				Balance::Underflow(_) => return Err(Invariant::Underflow),
 				_ => (),
 			}
 		}
 
+		if self.items.spilled() {
+			return Err(Invariant::InlineStorage);
+		}
+
 		if !self.items.windows(2).all(|w| w[0] < w[1]) {
-			panic!("leaf items are not sorted")
+			return Err(Invariant::Sorted);
 		}
 
 		if let Some(min) = min {
 			if let Some(item) = self.items.first() {
 				if min >= item.key() {
-					panic!("leaf item key is greater than right separator")
+					return Err(Invariant::Separators);
 				}
 			}
 		}
@@ -305,9 +408,21 @@ impl<K, V> Leaf<K, V> {
 		if let Some(max) = max {
 			if let Some(item) = self.items.last() {
 				if max <= item.key() {
-					panic!("leaf item key is less than left separator")
+					return Err(Invariant::Separators);
 				}
 			}
 		}
+
+		Ok(())
+	}
+
+	#[cfg(debug_assertions)]
+	pub fn validate(&self, parent: Option<usize>, min: Option<&K>, max: Option<&K>)
+	where
+		K: Ord,
+	{
+		if let Err(invariant) = self.check(parent, min, max) {
+			panic!("{}", invariant)
+		}
 	}
 }
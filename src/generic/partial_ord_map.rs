@@ -0,0 +1,466 @@
+use crate::generic::{
+	map::{self, BTreeExt, BTreeExtMut, BTreeMap},
+	node::{Address, Item, Node},
+};
+use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
+use std::{
+	cmp::Ordering,
+	fmt,
+	iter::{DoubleEndedIterator, ExactSizeIterator, FusedIterator},
+	ops::{Bound, RangeBounds},
+};
+
+/// Error returned when a key cannot be compared with itself,
+/// such as a floating point `NaN`.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct IncomparableKey;
+
+impl fmt::Display for IncomparableKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "incomparable key")
+	}
+}
+
+impl std::error::Error for IncomparableKey {}
+
+/// Key wrapper providing a total order over comparable keys.
+///
+/// Only keys comparable with themselves are ever wrapped by a [`PartialOrdMap`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[repr(transparent)]
+pub struct Total<K>(K);
+
+impl<K: PartialOrd> Total<K> {
+	/// Wraps the given key reference, if it is comparable with itself.
+	#[inline]
+	fn from_ref(key: &K) -> Result<&Self, IncomparableKey> {
+		if is_comparable(key) {
+			// SAFETY: `Total` is a transparent wrapper around `K`.
+			Ok(unsafe { &*(key as *const K as *const Total<K>) })
+		} else {
+			Err(IncomparableKey)
+		}
+	}
+}
+
+impl<K> Total<K> {
+	/// Returns the wrapped key.
+	#[inline]
+	pub fn get(&self) -> &K {
+		&self.0
+	}
+
+	/// Unwraps the key.
+	#[inline]
+	pub fn into_inner(self) -> K {
+		self.0
+	}
+}
+
+impl<K: PartialOrd> Eq for Total<K> {}
+
+impl<K: PartialOrd> PartialOrd for Total<K> {
+	#[inline]
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl<K: PartialOrd> Ord for Total<K> {
+	#[inline]
+	fn cmp(&self, other: &Self) -> Ordering {
+		// Wrapped keys are comparable with themselves, and the map looks keys up with
+		// `PartialOrdMap::address_of`, which rejects keys incomparable with the stored ones.
+		self.0.partial_cmp(&other.0).unwrap_or(Ordering::Equal)
+	}
+}
+
+#[inline]
+fn is_comparable<K: PartialOrd>(key: &K) -> bool {
+	key.partial_cmp(key).is_some()
+}
+
+/// An ordered map whose keys only implement [`PartialOrd`], based on a B-Tree.
+///
+/// Methods taking a key return an [`IncomparableKey`] error
+/// if the key cannot be compared with itself (such as `f64::NAN`),
+/// or, for the lookups, insertions and removals,
+/// with one of the keys compared to it in the map.
+/// Such keys are never stored in the map.
+///
+/// # Correctness
+///
+/// The ranges only check that their bounds are comparable with themselves.
+/// It is a logic error for two keys that are comparable with themselves
+/// to be incomparable with each other when calling [`PartialOrdMap::range`].
+/// This is never the case for floating point numbers.
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::PartialOrdMap;
+/// use btree_slab::generic::partial_ord_map::IncomparableKey;
+///
+/// let mut map = PartialOrdMap::new();
+/// map.insert(1.5, "a").unwrap();
+/// map.insert(-0.5, "b").unwrap();
+///
+/// assert_eq!(map.insert(f64::NAN, "c"), Err(IncomparableKey));
+/// assert_eq!(map.get(&1.5), Ok(Some(&"a")));
+/// assert!(map.iter().eq([(&-0.5, &"b"), (&1.5, &"a")]));
+/// ```
+pub struct PartialOrdMap<K, V, C> {
+	map: BTreeMap<Total<K>, V, C>,
+}
+
+impl<K, V, C> PartialOrdMap<K, V, C> {
+	/// Makes a new, empty `PartialOrdMap`.
+	#[inline]
+	pub fn new() -> Self
+	where
+		C: Default,
+	{
+		PartialOrdMap {
+			map: BTreeMap::new(),
+		}
+	}
+
+	/// Returns the number of elements in the map.
+	#[inline]
+	pub fn len(&self) -> usize {
+		self.map.len()
+	}
+
+	/// Returns `true` if the map contains no elements.
+	#[inline]
+	pub fn is_empty(&self) -> bool {
+		self.map.is_empty()
+	}
+
+	/// Returns the underlying map.
+	#[inline]
+	pub fn as_map(&self) -> &BTreeMap<Total<K>, V, C> {
+		&self.map
+	}
+}
+
+impl<K, V, C: Default> Default for PartialOrdMap<K, V, C> {
+	#[inline]
+	fn default() -> Self {
+		PartialOrdMap::new()
+	}
+}
+
+impl<K: Clone, V: Clone, C: Clone> Clone for PartialOrdMap<K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		PartialOrdMap {
+			map: self.map.clone(),
+		}
+	}
+}
+
+impl<K: fmt::Debug, V: fmt::Debug, C: Slab<Node<Total<K>, V>>> fmt::Debug for PartialOrdMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_map()
+			.entries(self.map.iter().map(|(k, v)| (k.get(), v)))
+			.finish()
+	}
+}
+
+impl<K: PartialOrd, V, C: Slab<Node<Total<K>, V>>> PartialOrdMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	/// Finds the address of the given key in the tree, like [`BTreeExt::address_of`].
+	///
+	/// Returns an error if the key is incomparable with itself
+	/// or with one of the keys it is compared to.
+	#[inline]
+	fn address_of(&self, key: &K) -> Result<Result<Address, Address>, IncomparableKey> {
+		if !is_comparable(key) {
+			return Err(IncomparableKey);
+		}
+
+		let mut id = match self.map.root_id() {
+			Some(id) => id,
+			None => return Ok(Err(Address::nowhere())),
+		};
+
+		loop {
+			let node = self.map.node(id);
+			let (mut start, mut end) = (0, node.item_count());
+			while start < end {
+				let mid = (start + end) / 2;
+				match node.item(mid.into()).unwrap().key().0.partial_cmp(key) {
+					Some(Ordering::Less) => start = mid + 1,
+					Some(Ordering::Greater) => end = mid,
+					Some(Ordering::Equal) => return Ok(Ok(Address::new(id, mid.into()))),
+					None => return Err(IncomparableKey),
+				}
+			}
+
+			match node.child_id_opt(start) {
+				Some(child_id) => id = child_id,
+				None => return Ok(Err(Address::new(id, start.into()))),
+			}
+		}
+	}
+
+	/// Returns a reference to the value corresponding to the key.
+	#[inline]
+	pub fn get(&self, key: &K) -> Result<Option<&V>, IncomparableKey> {
+		Ok(self
+			.address_of(key)?
+			.ok()
+			.map(|addr| self.map.item(addr).unwrap().value()))
+	}
+
+	/// Returns `true` if the map contains a value for the specified key.
+	#[inline]
+	pub fn contains_key(&self, key: &K) -> Result<bool, IncomparableKey> {
+		Ok(self.address_of(key)?.is_ok())
+	}
+
+	/// Returns the first key-value pair in the map.
+	#[inline]
+	pub fn first_key_value(&self) -> Option<(&K, &V)> {
+		self.map.first_key_value().map(|(k, v)| (&k.0, v))
+	}
+
+	/// Returns the last key-value pair in the map.
+	#[inline]
+	pub fn last_key_value(&self) -> Option<(&K, &V)> {
+		self.map.last_key_value().map(|(k, v)| (&k.0, v))
+	}
+
+	/// Gets an iterator over the entries of the map, sorted by key.
+	#[inline]
+	pub fn iter(&self) -> Iter<'_, K, V, C> {
+		Iter {
+			inner: self.map.iter(),
+		}
+	}
+
+	/// Constructs a double-ended iterator over a sub-range of elements in the map.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::PartialOrdMap;
+	///
+	/// let mut map = PartialOrdMap::new();
+	/// for i in 0..10 {
+	///     map.insert(i as f64 / 2.0, i).unwrap();
+	/// }
+	///
+	/// let range = map.range(1.0..2.0).unwrap();
+	/// assert!(range.map(|(_, v)| *v).eq(2..4));
+	/// assert!(map.range(f64::NAN..).is_err());
+	/// ```
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	#[inline]
+	pub fn range<R>(&self, range: R) -> Result<Range<'_, K, V, C>, IncomparableKey>
+	where
+		R: RangeBounds<K>,
+	{
+		let start = total_bound(range.start_bound())?;
+		let end = total_bound(range.end_bound())?;
+		Ok(Range {
+			inner: self.map.range((start, end)),
+		})
+	}
+}
+
+impl<K: PartialOrd, V, C: SlabMut<Node<Total<K>, V>>> PartialOrdMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	/// Returns a mutable reference to the value corresponding to the key.
+	#[inline]
+	pub fn get_mut(&mut self, key: &K) -> Result<Option<&mut V>, IncomparableKey> {
+		match self.address_of(key)? {
+			Ok(addr) => Ok(Some(self.map.item_mut(addr).unwrap().value_mut())),
+			Err(_) => Ok(None),
+		}
+	}
+
+	/// Inserts a key-value pair into the map.
+	///
+	/// If the map already had this key present, the value is updated and the old
+	/// value is returned.
+	/// If the key is incomparable, the key-value pair is dropped.
+	#[inline]
+	pub fn insert(&mut self, key: K, value: V) -> Result<Option<V>, IncomparableKey> {
+		match self.address_of(&key)? {
+			Ok(addr) => Ok(Some(self.map.replace_value_at(addr, value))),
+			Err(addr) => {
+				self.map
+					.insert_exactly_at(addr, Item::new(Total(key), value), None);
+				Ok(None)
+			}
+		}
+	}
+
+	/// Removes a key from the map, returning the value at the key if the key
+	/// was previously in the map.
+	#[inline]
+	pub fn remove(&mut self, key: &K) -> Result<Option<V>, IncomparableKey> {
+		match self.address_of(key)? {
+			Ok(addr) => Ok(Some(self.map.remove_at(addr).unwrap().0.into_value())),
+			Err(_) => Ok(None),
+		}
+	}
+
+	/// Clears the map, removing all elements.
+	#[inline]
+	pub fn clear(&mut self)
+	where
+		C: cc_traits::Clear,
+	{
+		self.map.clear()
+	}
+}
+
+#[inline]
+fn total_bound<K: PartialOrd>(bound: Bound<&K>) -> Result<Bound<&Total<K>>, IncomparableKey> {
+	Ok(match bound {
+		Bound::Included(key) => Bound::Included(Total::from_ref(key)?),
+		Bound::Excluded(key) => Bound::Excluded(Total::from_ref(key)?),
+		Bound::Unbounded => Bound::Unbounded,
+	})
+}
+
+impl<'a, K: PartialOrd, V, C: Slab<Node<Total<K>, V>>> IntoIterator for &'a PartialOrdMap<K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	type IntoIter = Iter<'a, K, V, C>;
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn into_iter(self) -> Iter<'a, K, V, C> {
+		self.iter()
+	}
+}
+
+/// Iterator over the entries of a [`PartialOrdMap`].
+pub struct Iter<'a, K, V, C> {
+	inner: map::Iter<'a, Total<K>, V, C>,
+}
+
+impl<'a, K, V, C> Clone for Iter<'a, K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Iter {
+			inner: self.inner.clone(),
+		}
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<Total<K>, V>>> fmt::Debug for Iter<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<Total<K>, V>>> Iterator for Iter<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		self.inner.size_hint()
+	}
+
+	#[inline]
+	fn next(&mut self) -> Option<(&'a K, &'a V)> {
+		self.inner.next().map(|(k, v)| (&k.0, v))
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<Total<K>, V>>> DoubleEndedIterator for Iter<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+		self.inner.next_back().map(|(k, v)| (&k.0, v))
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<Total<K>, V>>> FusedIterator for Iter<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
+impl<'a, K, V, C: Slab<Node<Total<K>, V>>> ExactSizeIterator for Iter<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
+
+/// Iterator over a sub-range of entries of a [`PartialOrdMap`].
+pub struct Range<'a, K, V, C> {
+	inner: map::Range<'a, Total<K>, V, C>,
+}
+
+impl<'a, K, V, C> Clone for Range<'a, K, V, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Range {
+			inner: self.inner.clone(),
+		}
+	}
+}
+
+impl<'a, K: fmt::Debug, V: fmt::Debug, C: Slab<Node<Total<K>, V>>> fmt::Debug for Range<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list().entries(self.clone()).finish()
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<Total<K>, V>>> Iterator for Range<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	type Item = (&'a K, &'a V);
+
+	#[inline]
+	fn next(&mut self) -> Option<(&'a K, &'a V)> {
+		self.inner.next().map(|(k, v)| (&k.0, v))
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<Total<K>, V>>> DoubleEndedIterator for Range<'a, K, V, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn next_back(&mut self) -> Option<(&'a K, &'a V)> {
+		self.inner.next_back().map(|(k, v)| (&k.0, v))
+	}
+}
+
+impl<'a, K, V, C: Slab<Node<Total<K>, V>>> FusedIterator for Range<'a, K, V, C> where
+	C: SimpleCollectionRef
+{
+}
@@ -1,11 +1,18 @@
-use crate::generic::{map, node::Node, BTreeMap};
-use cc_traits::{SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut};
+use crate::generic::{
+	map::{self, CapacityError},
+	node::Node,
+	BTreeMap,
+};
+use cc_traits::{
+	Capacity, Len, Reserve, SimpleCollectionMut, SimpleCollectionRef, Slab, SlabMut, WithCapacity,
+};
 use std::{
 	borrow::Borrow,
 	cmp::Ordering,
+	fmt,
 	hash::{Hash, Hasher},
 	iter::{DoubleEndedIterator, ExactSizeIterator, FromIterator, FusedIterator, Peekable},
-	ops::RangeBounds,
+	ops::{BitAnd, BitOr, BitXor, Bound, RangeBounds, Sub},
 };
 
 /// A set based on a B-Tree.
@@ -42,6 +49,61 @@ impl<T, C> BTreeSet<T, C> {
 		Self::default()
 	}
 
+	/// Makes a new, empty `BTreeSet` using the given container to allocate nodes.
+	///
+	/// The container is expected to be empty.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use slab::Slab;
+	/// use btree_slab::BTreeSet;
+	///
+	/// let mut set: BTreeSet<i32> = BTreeSet::with_container(Slab::with_capacity(16));
+	/// set.insert(1);
+	/// assert!(set.contains(&1));
+	/// ```
+	///
+	/// Like [`BTreeMap::with_container`], this is a `const fn`.
+	#[inline]
+	pub const fn with_container(nodes: C) -> Self {
+		BTreeSet {
+			map: BTreeMap::with_container(nodes),
+		}
+	}
+
+	/// Creates a new empty set with enough node slots to hold `len` elements
+	/// without growing the node container.
+	///
+	/// See [`BTreeMap::with_capacity`].
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let mut set: BTreeSet<u32> = BTreeSet::with_capacity(1000);
+	///
+	/// for i in 0..1000 {
+	///     assert!(set.try_insert(i).is_ok());
+	/// }
+	/// ```
+	#[inline]
+	pub fn with_capacity(len: usize) -> Self
+	where
+		C: WithCapacity,
+	{
+		BTreeSet {
+			map: BTreeMap::with_capacity(len),
+		}
+	}
+
+	/// Wraps the given unit-valued map.
+	#[inline]
+	pub(crate) fn from_map(map: BTreeMap<T, (), C>) -> Self {
+		BTreeSet { map }
+	}
+
 	/// Returns the number of elements in the set.
 	///
 	/// # Example
@@ -77,6 +139,31 @@ impl<T, C> BTreeSet<T, C> {
 	}
 }
 
+#[cfg(feature = "std-slab")]
+impl<T> BTreeSet<T, slab::Slab<Node<T, ()>>> {
+	/// Empty set.
+	///
+	/// Unlike [`BTreeSet::new`], this can be used to initialize `static` items.
+	///
+	/// Requires the `std-slab` feature.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use std::sync::Mutex;
+	/// use btree_slab::BTreeSet;
+	///
+	/// static NAMES: Mutex<BTreeSet<&str>> = Mutex::new(BTreeSet::EMPTY);
+	///
+	/// NAMES.lock().unwrap().insert("a");
+	/// assert!(NAMES.lock().unwrap().contains("a"));
+	/// ```
+	// With the `watermark` feature, the set holds atomic dirty flags,
+	// but each use of the constant creates a new set.
+	#[allow(clippy::declare_interior_mutable_const)]
+	pub const EMPTY: Self = BTreeSet::with_container(slab::Slab::new());
+}
+
 impl<T, C: Default> Default for BTreeSet<T, C> {
 	fn default() -> Self {
 		BTreeSet {
@@ -117,11 +204,36 @@ where
 	/// assert_eq!(set_iter.next(), None);
 	/// ```
 	#[inline]
-	pub fn iter(&self) -> Iter<T, C> {
+	pub fn iter(&self) -> Iter<'_, T, C> {
 		Iter {
 			inner: self.map.keys(),
 		}
 	}
+
+	/// Reserves enough node slots to insert at least `additional` more elements
+	/// without growing the node container.
+	///
+	/// See [`BTreeMap::reserve`].
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let mut set: BTreeSet<u32> = BTreeSet::new();
+	/// set.reserve(1000);
+	///
+	/// for i in 0..1000 {
+	///     assert!(set.try_insert(i).is_ok());
+	/// }
+	/// ```
+	#[inline]
+	pub fn reserve(&mut self, additional: usize)
+	where
+		C: Reserve,
+	{
+		self.map.reserve(additional)
+	}
 }
 
 impl<T: Ord, C: Slab<Node<T, ()>>> BTreeSet<T, C>
@@ -144,6 +256,7 @@ where
 	/// assert_eq!(set.contains(&4), false);
 	/// ```
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn contains<Q: ?Sized>(&self, value: &Q) -> bool
 	where
 		T: Borrow<Q>,
@@ -168,6 +281,7 @@ where
 	/// assert_eq!(set.get(&4), None);
 	/// ```
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn get<Q: ?Sized>(&self, value: &Q) -> Option<&T>
 	where
 		T: Borrow<Q>,
@@ -202,7 +316,8 @@ where
 	/// assert_eq!(Some(&5), set.range(4..).next());
 	/// ```
 	#[inline]
-	pub fn range<K: ?Sized, R>(&self, range: R) -> Range<T, C>
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn range<K: ?Sized, R>(&self, range: R) -> Range<'_, T, C>
 	where
 		K: Ord,
 		T: Borrow<K>,
@@ -213,6 +328,33 @@ where
 		}
 	}
 
+	/// Constructs a double-ended iterator over a sub-range of elements in the set,
+	/// or returns an error if the range bounds are not ordered.
+	///
+	/// This is the non-panicking version of [`BTreeSet::range`].
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let set: BTreeSet<i32> = (0..10).collect();
+	/// assert!(set.try_range(3..5).unwrap().eq(&[3, 4]));
+	/// assert!(set.try_range(5..3).is_err());
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn try_range<K: ?Sized, R>(&self, range: R) -> Result<Range<'_, T, C>, map::InvalidRange>
+	where
+		K: Ord,
+		T: Borrow<K>,
+		R: RangeBounds<K>,
+	{
+		Ok(Range {
+			inner: self.map.try_range(range)?,
+		})
+	}
+
 	/// Visits the values representing the union,
 	/// i.e., all the values in `self` or `other`, without duplicates,
 	/// in ascending order.
@@ -232,6 +374,7 @@ where
 	/// assert_eq!(union, [1, 2]);
 	/// ```
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn union<'a, D: Slab<Node<T, ()>>>(
 		&'a self,
 		other: &'a BTreeSet<T, D>,
@@ -266,6 +409,7 @@ where
 	/// assert_eq!(intersection, [2]);
 	/// ```
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn intersection<'a, D: Slab<Node<T, ()>>>(
 		&'a self,
 		other: &'a BTreeSet<T, D>,
@@ -300,6 +444,7 @@ where
 	/// assert_eq!(diff, [1]);
 	/// ```
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn difference<'a, D: Slab<Node<T, ()>>>(
 		&'a self,
 		other: &'a BTreeSet<T, D>,
@@ -334,6 +479,7 @@ where
 	/// assert_eq!(sym_diff, [1, 3]);
 	/// ```
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn symmetric_difference<'a, D: Slab<Node<T, ()>>>(
 		&'a self,
 		other: &'a BTreeSet<T, D>,
@@ -365,6 +511,7 @@ where
 	/// assert_eq!(a.is_disjoint(&b), false);
 	/// ```
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn is_disjoint<D: Slab<Node<T, ()>>>(&self, other: &BTreeSet<T, D>) -> bool
 	where
 		D: SimpleCollectionRef,
@@ -390,6 +537,7 @@ where
 	/// assert_eq!(set.is_subset(&sup), false);
 	/// ```
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn is_subset<D: Slab<Node<T, ()>>>(&self, other: &BTreeSet<T, D>) -> bool
 	where
 		D: SimpleCollectionRef,
@@ -418,6 +566,7 @@ where
 	/// assert_eq!(set.is_superset(&sub), true);
 	/// ```
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn is_superset<D: Slab<Node<T, ()>>>(&self, other: &BTreeSet<T, D>) -> bool
 	where
 		D: SimpleCollectionRef,
@@ -425,6 +574,59 @@ where
 		other.is_subset(self)
 	}
 
+	/// Returns `true` if every value of the set is a key of the given map.
+	///
+	/// Both collections are walked once, in order, without cloning any key.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::{BTreeMap, BTreeSet};
+	///
+	/// let index: BTreeMap<_, _> = [(1, "a"), (2, "b"), (3, "c")].into_iter().collect();
+	/// let mut required = BTreeSet::new();
+	///
+	/// required.insert(1);
+	/// required.insert(3);
+	/// assert!(required.is_subset_of_keys(&index));
+	/// required.insert(4);
+	/// assert!(!required.is_subset_of_keys(&index));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn is_subset_of_keys<V, D: Slab<Node<T, V>>>(&self, map: &BTreeMap<T, V, D>) -> bool
+	where
+		D: SimpleCollectionRef,
+	{
+		self.len() <= map.len() && is_sorted_subset(self.iter(), map.keys())
+	}
+
+	/// Returns `true` if every key of the given map is a value of the set.
+	///
+	/// Both collections are walked once, in order, without cloning any key.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::{BTreeMap, BTreeSet};
+	///
+	/// let set: BTreeSet<_> = [1, 2, 3].into_iter().collect();
+	/// let mut map = BTreeMap::new();
+	///
+	/// map.insert(1, "a");
+	/// assert!(set.is_superset_of_keys(&map));
+	/// map.insert(4, "d");
+	/// assert!(!set.is_superset_of_keys(&map));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn is_superset_of_keys<V, D: Slab<Node<T, V>>>(&self, map: &BTreeMap<T, V, D>) -> bool
+	where
+		D: SimpleCollectionRef,
+	{
+		map.len() <= self.len() && is_sorted_subset(map.keys(), self.iter())
+	}
+
 	/// Returns a reference to the first value in the set, if any.
 	/// This value is always the minimum of all values in the set.
 	///
@@ -442,7 +644,7 @@ where
 	/// ```
 	#[inline]
 	pub fn first(&self) -> Option<&T> {
-		self.map.first_key_value().map(|(k, _)| k)
+		self.map.first_key()
 	}
 
 	/// Returns a reference to the last value in the set, if any.
@@ -462,7 +664,61 @@ where
 	/// ```
 	#[inline]
 	pub fn last(&self) -> Option<&T> {
-		self.map.last_key_value().map(|(k, _)| k)
+		self.map.last_key()
+	}
+
+	/// Returns a reference to the smallest value in the set satisfying the given lower bound.
+	///
+	/// With `Included(x)` this is the first value greater than or equal to `x`,
+	/// with `Excluded(x)` the first value strictly greater than `x`,
+	/// and with `Unbounded` the first value of the set.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	/// use std::ops::Bound::{Excluded, Included};
+	///
+	/// let set: BTreeSet<_> = [1, 3, 5].iter().cloned().collect();
+	/// assert_eq!(set.lower_bound(Included(&3)), Some(&3));
+	/// assert_eq!(set.lower_bound(Excluded(&3)), Some(&5));
+	/// assert_eq!(set.lower_bound(Excluded(&5)), None);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn lower_bound<Q: ?Sized>(&self, bound: Bound<&Q>) -> Option<&T>
+	where
+		T: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map.min_key_in((bound, Bound::Unbounded))
+	}
+
+	/// Returns a reference to the greatest value in the set satisfying the given upper bound.
+	///
+	/// With `Included(x)` this is the last value less than or equal to `x`,
+	/// with `Excluded(x)` the last value strictly less than `x`,
+	/// and with `Unbounded` the last value of the set.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	/// use std::ops::Bound::{Excluded, Included};
+	///
+	/// let set: BTreeSet<_> = [1, 3, 5].iter().cloned().collect();
+	/// assert_eq!(set.upper_bound(Included(&3)), Some(&3));
+	/// assert_eq!(set.upper_bound(Excluded(&3)), Some(&1));
+	/// assert_eq!(set.upper_bound(Excluded(&1)), None);
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn upper_bound<Q: ?Sized>(&self, bound: Bound<&Q>) -> Option<&T>
+	where
+		T: Borrow<Q>,
+		Q: Ord,
+	{
+		self.map.max_key_in((Bound::Unbounded, bound))
 	}
 }
 
@@ -519,6 +775,30 @@ where
 		self.map.insert(element, ()).is_none()
 	}
 
+	/// Adds a value to the set without growing the node container.
+	///
+	/// If the insertion needs more nodes than the container can hold without growing,
+	/// the value is given back in a [`CapacityError`] and the set is left untouched.
+	/// See [`BTreeMap::try_insert`] for more details.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use slab::Slab;
+	/// use btree_slab::BTreeSet;
+	///
+	/// let mut set: BTreeSet<i32> = BTreeSet::with_container(Slab::with_capacity(1));
+	/// assert_eq!(set.try_insert(2), Ok(true));
+	/// assert_eq!(set.try_insert(2), Ok(false));
+	/// ```
+	#[inline]
+	pub fn try_insert(&mut self, element: T) -> Result<bool, CapacityError<T, ()>>
+	where
+		C: Len + Capacity,
+	{
+		self.map.try_insert(element, ()).map(|old| old.is_none())
+	}
+
 	/// Removes a value from the set. Returns whether the value was
 	/// present in the set.
 	///
@@ -538,6 +818,7 @@ where
 	/// assert_eq!(set.remove(&2), false);
 	/// ```
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn remove<Q: ?Sized>(&mut self, value: &Q) -> bool
 	where
 		T: Borrow<Q>,
@@ -562,6 +843,7 @@ where
 	/// assert_eq!(set.take(&2), None);
 	/// ```
 	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
 	pub fn take<Q: ?Sized>(&mut self, value: &Q) -> Option<T>
 	where
 		T: Borrow<Q>,
@@ -655,6 +937,113 @@ where
 		self.drain_filter(|v| !f(v));
 	}
 
+	/// Retains only the elements of the given range specified by the predicate.
+	///
+	/// In other words, remove all elements `e` in `range` such that `f(&e)` returns `false`.
+	/// Elements outside of the range are not visited.
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let mut set: BTreeSet<i32> = (1..=6).collect();
+	/// // Keep only the even numbers greater than 3.
+	/// set.retain_in(4.., |&k| k % 2 == 0);
+	/// assert!(set.iter().eq([1, 2, 3, 4, 6].iter()));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn retain_in<U: ?Sized, R, F>(&mut self, range: R, mut f: F)
+	where
+		U: Ord,
+		T: Borrow<U>,
+		R: RangeBounds<U>,
+		F: FnMut(&T) -> bool,
+	{
+		self.map.retain_in(range, |t, _| f(t))
+	}
+
+	/// Retains only the elements specified by the predicate,
+	/// visiting the elements in ascending order while `scan` returns `true`.
+	///
+	/// See [`BTreeMap::retain_while`].
+	/// Returns the number of removed elements.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let mut set: BTreeSet<i32> = (0..100).collect();
+	/// assert_eq!(set.retain_while(|&v| v < 10, |&v| v % 2 == 0), 5);
+	/// assert_eq!(set.len(), 95);
+	/// ```
+	#[inline]
+	pub fn retain_while<S, F>(&mut self, scan: S, mut f: F) -> usize
+	where
+		S: FnMut(&T) -> bool,
+		F: FnMut(&T) -> bool,
+	{
+		self.map.retain_while(scan, |t, _| f(t))
+	}
+
+	/// Converts this set into a map, computing the value of each key with `f`.
+	///
+	/// `f` is called on each element in ascending order.
+	/// The nodes are moved as they are into the storage of the map:
+	/// no key is compared and the shape of the tree is kept.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::{BTreeMap, BTreeSet};
+	///
+	/// let set: BTreeSet<_> = (0..100).collect();
+	/// let map: BTreeMap<_, _> = set.into_map_with(|i| i * 2);
+	/// assert_eq!(map.len(), 100);
+	/// assert_eq!(map.get(&21), Some(&42));
+	/// ```
+	#[inline]
+	pub fn into_map_with<V, D, F>(self, mut f: F) -> BTreeMap<T, V, D>
+	where
+		D: SlabMut<Node<T, V>> + Default,
+		D: SimpleCollectionRef,
+		D: SimpleCollectionMut,
+		F: FnMut(&T) -> V,
+	{
+		self.map.map_values_into(|t, ()| f(t))
+	}
+
+	/// Creates a set from the keys of the given map, dropping the values.
+	///
+	/// This is equivalent to [`BTreeMap::into_key_set`].
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::{BTreeMap, BTreeSet};
+	///
+	/// let map: BTreeMap<_, _> = [(1, 'a'), (2, 'b')].into_iter().collect();
+	/// let set: BTreeSet<_> = BTreeSet::from_keys(map);
+	/// assert!(set.iter().eq([1, 2].iter()));
+	/// ```
+	#[inline]
+	pub fn from_keys<V, D>(map: BTreeMap<T, V, D>) -> Self
+	where
+		C: Default,
+		D: SlabMut<Node<T, V>>,
+		D: SimpleCollectionRef,
+		D: SimpleCollectionMut,
+	{
+		map.into_key_set()
+	}
+
 	/// Moves all elements from `other` into `Self`, leaving `other` empty.
 	///
 	/// # Example
@@ -691,6 +1080,36 @@ where
 		self.map.append(&mut other.map);
 	}
 
+	/// Splits the collection into two at the given value.
+	/// Returns everything after the given value, including the value.
+	///
+	/// Like [`BTreeMap::split_off`](crate::generic::BTreeMap::split_off),
+	/// the tree is cut along the path leading to the value and no value is reinserted.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let mut a: BTreeSet<_> = [1, 2, 3, 17, 41].into_iter().collect();
+	/// let b = a.split_off(&3);
+	///
+	/// assert!(a.iter().eq(&[1, 2]));
+	/// assert!(b.iter().eq(&[3, 17, 41]));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn split_off<Q: ?Sized>(&mut self, value: &Q) -> Self
+	where
+		T: Borrow<Q>,
+		Q: Ord,
+		C: Default,
+	{
+		BTreeSet {
+			map: self.map.split_off(value),
+		}
+	}
+
 	/// Creates an iterator which uses a closure to determine if a value should be removed.
 	///
 	/// If the closure returns true, then the value is removed and yielded.
@@ -698,11 +1117,13 @@ where
 	/// by the iterator.
 	///
 	/// If the iterator is only partially consumed or not consumed at all, each of the remaining
-	/// values will still be subjected to the closure and removed and dropped if it returns true.
+	/// values will still be subjected to the closure and removed and dropped if it returns true,
+	/// unless [`DrainFilter::keep_rest`] is called.
 	///
-	/// It is unspecified how many more values will be subjected to the closure
-	/// if a panic occurs in the closure, or if a panic occurs while dropping a value, or if the
-	/// `DrainFilter` itself is leaked.
+	/// If the closure panics, the panic is propagated and the closure is never called again:
+	/// the value and all the remaining values stay in the set.
+	/// If a panic occurs while dropping a removed value, the remaining values stay in the set.
+	/// If the `DrainFilter` value is leaked, the remaining values stay in the set.
 	///
 	/// # Example
 	///
@@ -724,6 +1145,275 @@ where
 	{
 		DrainFilter::new(self, pred)
 	}
+
+	/// Creates an iterator which uses a closure to determine if a value
+	/// in the given range should be removed.
+	///
+	/// This behaves like [`BTreeSet::drain_filter`],
+	/// but only visits the values in the range,
+	/// starting at the address of the range start
+	/// instead of walking the whole tree.
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let mut set: BTreeSet<i32> = (0..100).collect();
+	/// let evens: Vec<_> = set.drain_filter_range(10..20, |v| v % 2 == 0).collect();
+	///
+	/// assert!(evens.into_iter().eq((10..20).step_by(2)));
+	/// assert_eq!(set.len(), 95);
+	/// assert!(set.contains(&8));
+	/// assert!(!set.contains(&12));
+	/// ```
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn drain_filter_range<'a, U: ?Sized, R, F>(
+		&'a mut self,
+		range: R,
+		pred: F,
+	) -> DrainFilter<'a, T, C, F>
+	where
+		U: Ord,
+		T: Borrow<U>,
+		R: RangeBounds<U>,
+		F: 'a + FnMut(&T) -> bool,
+	{
+		DrainFilter {
+			pred,
+			inner: self.map.drain_filter_range_inner(range),
+		}
+	}
+
+	/// Removes the values in the given range, and returns them in a new set.
+	///
+	/// This behaves like [`BTreeMap::remove_range`]:
+	/// the values outside of the range are neither compared nor moved.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let mut set: BTreeSet<_> = (0..100).collect();
+	/// let removed = set.remove_range(10..90);
+	///
+	/// assert!(removed.iter().copied().eq(10..90));
+	/// assert!(set.iter().copied().eq((0..10).chain(90..100)));
+	/// ```
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	#[inline]
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn remove_range<U: ?Sized, R>(&mut self, range: R) -> Self
+	where
+		U: Ord,
+		T: Borrow<U>,
+		R: RangeBounds<U>,
+		C: Default,
+	{
+		BTreeSet {
+			map: self.map.remove_range(range),
+		}
+	}
+
+	/// Replaces the values in the given range by the values of `iter`,
+	/// and returns the removed values in a new set.
+	///
+	/// The values of `iter` must be strictly increasing and contained in the range.
+	/// The range is removed like with [`BTreeSet::remove_range`],
+	/// a tree is built bottom-up from `iter` without any comparison,
+	/// and the three trees are joined together.
+	/// Only the nodes along the range bounds are rebalanced.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let mut set: BTreeSet<_> = (0..100).collect();
+	/// let removed = set.replace_range(10..90, (10..90).step_by(10));
+	///
+	/// assert!(removed.iter().copied().eq(10..90));
+	/// assert!(set.iter().copied().eq((0..10).chain((10..90).step_by(10)).chain(90..100)));
+	/// ```
+	///
+	/// # Panics
+	///
+	/// Panics if range `start > end`.
+	/// Panics if range `start == end` and both bounds are `Excluded`.
+	///
+	/// In debug mode, panics if the values of `iter` are not strictly increasing
+	/// or not contained in the range.
+	/// Otherwise, the misplaced values are inserted one by one
+	/// and the set stays consistent.
+	#[allow(clippy::multiple_bound_locations)]
+	pub fn replace_range<U: ?Sized, R, I>(&mut self, range: R, iter: I) -> Self
+	where
+		U: Ord,
+		T: Borrow<U>,
+		R: RangeBounds<U>,
+		I: IntoIterator<Item = T>,
+		C: Default,
+	{
+		let removed = self
+			.map
+			.remove_range((range.start_bound(), range.end_bound()));
+
+		let mut right = match range.end_bound() {
+			Bound::Included(value) | Bound::Excluded(value) => self.map.split_off(value),
+			Bound::Unbounded => BTreeMap::new(),
+		};
+
+		let mut middle = BTreeSet::from_sorted_iter(iter);
+		debug_assert!(
+			middle.iter().zip(middle.iter().skip(1)).all(|(a, b)| a < b),
+			"values are not strictly increasing"
+		);
+		debug_assert!(
+			middle.iter().all(|value| range.contains(value.borrow())),
+			"values are not in the range"
+		);
+
+		self.map.append(&mut middle.map);
+		self.map.append(&mut right);
+
+		BTreeSet { map: removed }
+	}
+
+	/// Builds a set from an iterator of strictly increasing values,
+	/// without any comparison.
+	#[inline]
+	fn from_sorted_iter<I>(iter: I) -> Self
+	where
+		I: IntoIterator<Item = T>,
+		C: Default,
+	{
+		let mut map = BTreeMap::new();
+		map.push_back_sorted(iter.into_iter().map(|value| (value, ())));
+		BTreeSet { map }
+	}
+}
+
+impl<T: Ord + Clone, C: SlabMut<Node<T, ()>> + Default, D: Slab<Node<T, ()>>> Sub<&BTreeSet<T, D>>
+	for &BTreeSet<T, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+	D: SimpleCollectionRef,
+{
+	type Output = BTreeSet<T, C>;
+
+	/// Returns the difference of `self` and `rhs` as a new `BTreeSet<T>`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let a: BTreeSet<_> = [1, 2, 3].into_iter().collect();
+	/// let b: BTreeSet<_> = [3, 4, 5].into_iter().collect();
+	///
+	/// let result = &a - &b;
+	/// assert!(result.iter().eq(&[1, 2]));
+	/// ```
+	#[inline]
+	fn sub(self, rhs: &BTreeSet<T, D>) -> BTreeSet<T, C> {
+		BTreeSet::from_sorted_iter(self.difference(rhs).cloned())
+	}
+}
+
+impl<T: Ord + Clone, C: SlabMut<Node<T, ()>> + Default, D: Slab<Node<T, ()>>>
+	BitXor<&BTreeSet<T, D>> for &BTreeSet<T, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+	D: SimpleCollectionRef,
+{
+	type Output = BTreeSet<T, C>;
+
+	/// Returns the symmetric difference of `self` and `rhs` as a new `BTreeSet<T>`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let a: BTreeSet<_> = [1, 2, 3].into_iter().collect();
+	/// let b: BTreeSet<_> = [2, 3, 4].into_iter().collect();
+	///
+	/// let result = &a ^ &b;
+	/// assert!(result.iter().eq(&[1, 4]));
+	/// ```
+	#[inline]
+	fn bitxor(self, rhs: &BTreeSet<T, D>) -> BTreeSet<T, C> {
+		BTreeSet::from_sorted_iter(self.symmetric_difference(rhs).cloned())
+	}
+}
+
+impl<T: Ord + Clone, C: SlabMut<Node<T, ()>> + Default, D: Slab<Node<T, ()>>>
+	BitAnd<&BTreeSet<T, D>> for &BTreeSet<T, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+	D: SimpleCollectionRef,
+{
+	type Output = BTreeSet<T, C>;
+
+	/// Returns the intersection of `self` and `rhs` as a new `BTreeSet<T>`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let a: BTreeSet<_> = [1, 2, 3].into_iter().collect();
+	/// let b: BTreeSet<_> = [2, 3, 4].into_iter().collect();
+	///
+	/// let result = &a & &b;
+	/// assert!(result.iter().eq(&[2, 3]));
+	/// ```
+	#[inline]
+	fn bitand(self, rhs: &BTreeSet<T, D>) -> BTreeSet<T, C> {
+		BTreeSet::from_sorted_iter(self.intersection(rhs).cloned())
+	}
+}
+
+impl<T: Ord + Clone, C: SlabMut<Node<T, ()>> + Default, D: Slab<Node<T, ()>>> BitOr<&BTreeSet<T, D>>
+	for &BTreeSet<T, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+	D: SimpleCollectionRef,
+{
+	type Output = BTreeSet<T, C>;
+
+	/// Returns the union of `self` and `rhs` as a new `BTreeSet<T>`.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let a: BTreeSet<_> = [1, 2, 3].into_iter().collect();
+	/// let b: BTreeSet<_> = [3, 4, 5].into_iter().collect();
+	///
+	/// let result = &a | &b;
+	/// assert!(result.iter().eq(&[1, 2, 3, 4, 5]));
+	/// ```
+	#[inline]
+	fn bitor(self, rhs: &BTreeSet<T, D>) -> BTreeSet<T, C> {
+		BTreeSet::from_sorted_iter(self.union(rhs).cloned())
+	}
 }
 
 impl<T: Clone, C: Clone> Clone for BTreeSet<T, C> {
@@ -756,6 +1446,29 @@ where
 	}
 }
 
+/// Builds the set bottom-up with [`BTreeMap::from_unsorted_vec`].
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::BTreeSet;
+///
+/// let set = BTreeSet::from([3, 1, 2, 1]);
+/// assert!(set.into_iter().eq([1, 2, 3]));
+/// ```
+impl<T: Ord, C: SlabMut<Node<T, ()>> + Default, const N: usize> From<[T; N]> for BTreeSet<T, C>
+where
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn from(items: [T; N]) -> Self {
+		BTreeSet::from_map(BTreeMap::from_unsorted_vec(
+			items.into_iter().map(|t| (t, ())).collect(),
+		))
+	}
+}
+
 impl<T, C: SlabMut<Node<T, ()>>> IntoIterator for BTreeSet<T, C>
 where
 	C: SimpleCollectionRef,
@@ -861,10 +1574,59 @@ where
 	}
 }
 
+impl<T: fmt::Debug, C: Slab<Node<T, ()>>> fmt::Debug for BTreeSet<T, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_set().entries(self.iter()).finish()
+	}
+}
+
+/// Checks that every item of the sorted iterator `sub` is in the sorted iterator `sup`.
+fn is_sorted_subset<'a, T: 'a + Ord>(
+	sub: impl Iterator<Item = &'a T>,
+	mut sup: impl Iterator<Item = &'a T>,
+) -> bool {
+	'sub: for item in sub {
+		for candidate in sup.by_ref() {
+			match candidate.cmp(item) {
+				Ordering::Less => (),
+				Ordering::Equal => continue 'sub,
+				Ordering::Greater => return false,
+			}
+		}
+
+		return false;
+	}
+
+	true
+}
+
 pub struct Iter<'a, T, C> {
 	inner: map::Keys<'a, T, (), C>,
 }
 
+impl<'a, T, C> Clone for Iter<'a, T, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Iter {
+			inner: self.inner.clone(),
+		}
+	}
+}
+
+impl<'a, T: fmt::Debug, C: Slab<Node<T, ()>>> fmt::Debug for Iter<'a, T, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.inner.fmt(f)
+	}
+}
+
 impl<'a, T, C: Slab<Node<T, ()>>> Iterator for Iter<'a, T, C>
 where
 	C: SimpleCollectionRef,
@@ -899,6 +1661,16 @@ pub struct IntoIter<T, C> {
 	inner: map::IntoKeys<T, (), C>,
 }
 
+impl<T: fmt::Debug, C: Slab<Node<T, ()>>> fmt::Debug for IntoIter<T, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		self.inner.fmt(f)
+	}
+}
+
 impl<T, C: SlabMut<Node<T, ()>>> Iterator for IntoIter<T, C>
 where
 	C: SimpleCollectionRef,
@@ -950,6 +1722,21 @@ where
 	it2: Peekable<Iter<'a, T, D>>,
 }
 
+impl<'a, T: fmt::Debug, C: Slab<Node<T, ()>>, D: Slab<Node<T, ()>>> fmt::Debug
+	for Union<'a, T, C, D>
+where
+	C: SimpleCollectionRef,
+	D: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("Union")
+			.field(&self.it1)
+			.field(&self.it2)
+			.finish()
+	}
+}
+
 impl<'a, T: Ord, C: Slab<Node<T, ()>>, D: Slab<Node<T, ()>>> Iterator for Union<'a, T, C, D>
 where
 	C: SimpleCollectionRef,
@@ -998,6 +1785,21 @@ where
 	it2: Peekable<Iter<'a, T, D>>,
 }
 
+impl<'a, T: fmt::Debug, C: Slab<Node<T, ()>>, D: Slab<Node<T, ()>>> fmt::Debug
+	for Intersection<'a, T, C, D>
+where
+	C: SimpleCollectionRef,
+	D: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("Intersection")
+			.field(&self.it1)
+			.field(&self.it2)
+			.finish()
+	}
+}
+
 impl<'a, T: Ord, C: Slab<Node<T, ()>>, D: Slab<Node<T, ()>>> Iterator for Intersection<'a, T, C, D>
 where
 	C: SimpleCollectionRef,
@@ -1057,6 +1859,21 @@ where
 	it2: Peekable<Iter<'a, T, D>>,
 }
 
+impl<'a, T: fmt::Debug, C: Slab<Node<T, ()>>, D: Slab<Node<T, ()>>> fmt::Debug
+	for Difference<'a, T, C, D>
+where
+	C: SimpleCollectionRef,
+	D: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("Difference")
+			.field(&self.it1)
+			.field(&self.it2)
+			.finish()
+	}
+}
+
 impl<'a, T: Ord, C: Slab<Node<T, ()>>, D: Slab<Node<T, ()>>> Iterator for Difference<'a, T, C, D>
 where
 	C: SimpleCollectionRef,
@@ -1117,6 +1934,21 @@ where
 	it2: Peekable<Iter<'a, T, D>>,
 }
 
+impl<'a, T: fmt::Debug, C: Slab<Node<T, ()>>, D: Slab<Node<T, ()>>> fmt::Debug
+	for SymmetricDifference<'a, T, C, D>
+where
+	C: SimpleCollectionRef,
+	D: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("SymmetricDifference")
+			.field(&self.it1)
+			.field(&self.it2)
+			.finish()
+	}
+}
+
 impl<'a, T: Ord, C: Slab<Node<T, ()>>, D: Slab<Node<T, ()>>> Iterator
 	for SymmetricDifference<'a, T, C, D>
 where
@@ -1161,6 +1993,13 @@ where
 {
 }
 
+/// An iterator produced by calling [`BTreeSet::drain_filter`].
+///
+/// Dropping the iterator drains the remaining matching values,
+/// unless the predicate panicked or [`DrainFilter::keep_rest`] was called.
+///
+/// The iterator is [`Send`] (resp. [`Sync`]) whenever the values,
+/// node container and predicate are.
 pub struct DrainFilter<'a, T, C: SlabMut<Node<T, ()>>, F>
 where
 	F: FnMut(&T) -> bool,
@@ -1172,6 +2011,20 @@ where
 	inner: map::DrainFilterInner<'a, T, (), C>,
 }
 
+impl<'a, T: fmt::Debug, C: SlabMut<Node<T, ()>>, F> fmt::Debug for DrainFilter<'a, T, C, F>
+where
+	F: FnMut(&T) -> bool,
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("DrainFilter")
+			.field(&self.inner.peek().map(|(t, _)| t))
+			.finish()
+	}
+}
+
 impl<'a, T: 'a, C: SlabMut<Node<T, ()>>, F> DrainFilter<'a, T, C, F>
 where
 	F: FnMut(&T) -> bool,
@@ -1185,6 +2038,42 @@ where
 			inner: map::DrainFilterInner::new(&mut set.map),
 		}
 	}
+
+	/// Consumes the iterator, keeping the values that were not yet visited in the set.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let mut set: BTreeSet<i32> = (0..8).collect();
+	/// let mut drain = set.drain_filter(|v| v % 2 == 0);
+	/// assert_eq!(drain.next(), Some(0));
+	/// drain.keep_rest();
+	///
+	/// assert!(set.into_iter().eq([1, 2, 3, 4, 5, 6, 7]));
+	/// ```
+	#[inline]
+	pub fn keep_rest(mut self) {
+		self.inner.keep_rest()
+	}
+
+	/// Returns the next value to be passed to the predicate, without visiting it.
+	///
+	/// # Example
+	///
+	/// ```
+	/// use btree_slab::BTreeSet;
+	///
+	/// let mut set: BTreeSet<i32> = (0..8).collect();
+	/// let mut drain = set.drain_filter(|v| v % 2 == 0);
+	/// assert_eq!(drain.next(), Some(0));
+	/// assert_eq!(drain.peek(), Some(&1));
+	/// ```
+	#[inline]
+	pub fn peek(&self) -> Option<&T> {
+		self.inner.peek().map(|(t, _)| t)
+	}
 }
 
 impl<'a, T, C: SlabMut<Node<T, ()>>, F> FusedIterator for DrainFilter<'a, T, C, F>
@@ -1222,11 +2111,8 @@ where
 	C: SimpleCollectionMut,
 {
 	fn drop(&mut self) {
-		loop {
-			if self.next().is_none() {
-				break;
-			}
-		}
+		let pred = &mut self.pred;
+		self.inner.drop_rest(&mut |t, _| (*pred)(t))
 	}
 }
 
@@ -1234,6 +2120,27 @@ pub struct Range<'a, T, C> {
 	inner: map::Range<'a, T, (), C>,
 }
 
+impl<'a, T, C> Clone for Range<'a, T, C> {
+	#[inline]
+	fn clone(&self) -> Self {
+		Range {
+			inner: self.inner.clone(),
+		}
+	}
+}
+
+impl<'a, T: fmt::Debug, C: Slab<Node<T, ()>>> fmt::Debug for Range<'a, T, C>
+where
+	C: SimpleCollectionRef,
+{
+	#[inline]
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_list()
+			.entries(self.inner.clone().map(|(t, _)| t))
+			.finish()
+	}
+}
+
 impl<'a, T, C: Slab<Node<T, ()>>> Iterator for Range<'a, T, C>
 where
 	C: SimpleCollectionRef,
@@ -1262,3 +2169,4 @@ where
 }
 
 impl<'a, T, C: Slab<Node<T, ()>>> FusedIterator for Range<'a, T, C> where C: SimpleCollectionRef {}
+impl<'a, T, C: Slab<Node<T, ()>>> ExactSizeIterator for Range<'a, T, C> where C: SimpleCollectionRef {}
@@ -10,7 +10,8 @@
 //! By default, the `Slab` type (from the `slab` crate) is used, which means
 //! that every node of the tree are allocated in a contiguous memory region,
 //! reducing the number of allocations needed.
-//! In theory, another type could be used to store the entire B-Tree on the stack.
+//! Another type can be used to store the entire B-Tree on the stack,
+//! such as the fixed-capacity `FixedSlab` type provided in the `fixed_slab` module.
 //!
 //! ## Usage
 //!
@@ -86,11 +87,53 @@
 //! [`btree-range-map`](https://crates.io/crates/btree-range-map) crate.
 use slab::Slab;
 
+#[cfg(feature = "std-slab")]
+pub mod compat;
+pub mod fixed_slab;
 pub mod generic;
 pub mod utils;
 
+#[cfg(feature = "testing")]
+pub mod testing;
+
 /// B-Tree map based on `Slab`.
 pub type BTreeMap<K, V> = generic::BTreeMap<K, V, Slab<generic::Node<K, V>>>;
 
+/// Immutable snapshot of a B-Tree map based on `Slab`.
+pub type BTreeSnapshot<K, V> = generic::map::BTreeSnapshot<K, V, Slab<generic::Node<K, V>>>;
+
 /// B-Tree set based on `Slab`.
 pub type BTreeSet<T> = generic::BTreeSet<T, Slab<generic::Node<T, ()>>>;
+
+/// B-Tree multiset based on `Slab`.
+pub type BTreeMultiSet<T> = generic::BTreeMultiSet<T, Slab<generic::Node<T, usize>>>;
+
+/// B-Tree map allowing duplicate keys, based on `Slab`.
+pub type BTreeMultiMap<K, V> =
+	generic::BTreeMultiMap<K, V, Slab<generic::Node<generic::multimap::MultiKey<K>, V>>>;
+
+/// B-Tree map with `PartialOrd` keys based on `Slab`.
+pub type PartialOrdMap<K, V> =
+	generic::PartialOrdMap<K, V, Slab<generic::Node<generic::partial_ord_map::Total<K>, V>>>;
+
+/// B-Tree map with keys sorted by a custom ordering `O`, based on `Slab`.
+pub type CompareMap<K, V, O> =
+	generic::CompareMap<K, V, O, Slab<generic::Node<generic::compare_map::Ordered<K, O>, V>>>;
+
+/// B-Tree map storing its values in a separate `Slab`.
+pub type IndirectBTreeMap<K, V> =
+	generic::IndirectBTreeMap<K, V, Slab<generic::Node<K, usize>>, Slab<V>>;
+
+/// B-Tree map with type-erased values based on `Slab`.
+///
+/// Requires the `dyn-map` feature.
+#[cfg(feature = "dyn-map")]
+pub type DynBTreeMap<K> =
+	generic::DynBTreeMap<K, Slab<generic::Node<K, generic::dyn_map::DynValue>>>;
+
+/// B+Tree map based on `Slab`.
+///
+/// Requires the `bplus` feature.
+#[cfg(feature = "bplus")]
+pub type BPlusTreeMap<K, V> =
+	generic::BPlusTreeMap<K, V, Slab<generic::bplus_map::BPlusNode<K, V>>>;
@@ -0,0 +1,423 @@
+//! Model checking against the standard `BTreeMap`.
+//!
+//! This module provides an operation-based harness
+//! applying the same sequence of operations to a [`BTreeMap`]
+//! and to a [`std::collections::BTreeMap`] used as reference model.
+//! After each operation, the returned values are compared,
+//! the tree invariants are validated
+//! and the content of both maps is checked to be equal.
+//!
+//! Operations are plain data, so that they can be generated by
+//! any property testing framework, or by [`random_ops`].
+//!
+//! Requires the `testing` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use btree_slab::testing::{check_ops, random_ops};
+//!
+//! for seed in 0..10 {
+//!     check_ops(random_ops(seed, 1000, 64))
+//! }
+//! ```
+use crate::generic::{
+	map::{BTreeExt, Entry},
+	BTreeMap, Node,
+};
+use cc_traits::{Clear, SimpleCollectionMut, SimpleCollectionRef, SlabMut};
+use slab::Slab;
+use std::{
+	collections::{btree_map, BTreeMap as Model},
+	fmt,
+	ops::{Bound, RangeBounds},
+};
+
+/// Operation applied to both a map and its model.
+///
+/// Range bounds must be valid for the standard `BTreeMap`:
+/// the start must not be greater than the end,
+/// and they must not be both excluded and equal.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Op<K, V> {
+	/// Inserts the given entry, comparing the replaced values.
+	Insert(K, V),
+
+	/// Removes the given key, comparing the removed values.
+	Remove(K),
+
+	/// Compares the entries in the given range, in both directions.
+	Range(Bound<K>, Bound<K>),
+
+	/// Replaces the values in the given range through mutable references.
+	RangeMut(Bound<K>, Bound<K>, V),
+
+	/// Drains the entries in the given range with [`BTreeMap::drain_filter`],
+	/// comparing the drained entries.
+	DrainFilter(Bound<K>, Bound<K>),
+
+	/// Retains only the entries in the given range.
+	Retain(Bound<K>, Bound<K>),
+
+	/// Inserts the given entry if the key is vacant using [`BTreeMap::entry`],
+	/// comparing the resulting values.
+	EntryInsert(K, V),
+
+	/// Removes the given key using [`BTreeMap::entry`], comparing the removed entries.
+	EntryRemove(K),
+
+	/// Removes the first entry, comparing the removed entries.
+	PopFirst,
+
+	/// Removes the last entry, comparing the removed entries.
+	PopLast,
+
+	/// Removes every entry.
+	Clear,
+}
+
+/// Map paired with its reference model.
+///
+/// Every operation is applied to both maps,
+/// and panics as soon as they diverge or the map is not a valid B-Tree.
+///
+/// Requires the `testing` feature.
+///
+/// # Example
+///
+/// ```
+/// use btree_slab::testing::{ModelChecker, Op};
+/// use std::ops::Bound;
+///
+/// let mut checker: ModelChecker<u32, u32> = ModelChecker::new();
+/// checker.apply(Op::Insert(1, 10));
+/// checker.apply(Op::Insert(2, 20));
+/// checker.apply(Op::RangeMut(Bound::Excluded(1), Bound::Unbounded, 0));
+/// checker.apply(Op::Remove(1));
+///
+/// assert_eq!(checker.map().get(&2), Some(&0));
+/// ```
+pub struct ModelChecker<K, V, C = Slab<Node<K, V>>> {
+	map: BTreeMap<K, V, C>,
+	model: Model<K, V>,
+
+	/// Number of operations applied so far.
+	step: usize,
+}
+
+impl<K, V, C: Default> ModelChecker<K, V, C> {
+	/// Creates a new checker with an empty map.
+	#[inline]
+	pub fn new() -> Self {
+		ModelChecker {
+			map: BTreeMap::new(),
+			model: Model::new(),
+			step: 0,
+		}
+	}
+}
+
+impl<K, V, C: Default> Default for ModelChecker<K, V, C> {
+	#[inline]
+	fn default() -> Self {
+		Self::new()
+	}
+}
+
+impl<K, V, C> ModelChecker<K, V, C> {
+	/// Returns the checked map.
+	#[inline]
+	pub fn map(&self) -> &BTreeMap<K, V, C> {
+		&self.map
+	}
+
+	/// Returns the reference model.
+	#[inline]
+	pub fn model(&self) -> &Model<K, V> {
+		&self.model
+	}
+
+	/// Consumes the checker and returns the checked map.
+	#[inline]
+	pub fn into_map(self) -> BTreeMap<K, V, C> {
+		self.map
+	}
+}
+
+impl<K, V, C: SlabMut<Node<K, V>>> ModelChecker<K, V, C>
+where
+	K: Ord + Clone + fmt::Debug,
+	V: Clone + PartialEq + fmt::Debug,
+	C: SimpleCollectionRef,
+	C: SimpleCollectionMut,
+	C: Clear,
+{
+	/// Applies the given operation to the map and its model,
+	/// then checks them.
+	///
+	/// # Panics
+	///
+	/// Panics if the map and its model diverge,
+	/// or if the map is not a valid B-Tree.
+	pub fn apply(&mut self, op: Op<K, V>) {
+		let step = self.step;
+		self.step += 1;
+
+		match &op {
+			Op::Insert(key, value) => {
+				assert_eq!(
+					self.map.insert(key.clone(), value.clone()),
+					self.model.insert(key.clone(), value.clone()),
+					"step {}: {:?}",
+					step,
+					op
+				)
+			}
+			Op::Remove(key) => {
+				assert_eq!(
+					self.map.remove(key),
+					self.model.remove(key),
+					"step {}: {:?}",
+					step,
+					op
+				)
+			}
+			Op::Range(start, end) => {
+				let range = (start.as_ref(), end.as_ref());
+				assert!(
+					self.map.range(range).eq(self.model.range(range)),
+					"step {}: {:?}",
+					step,
+					op
+				);
+				assert!(
+					self.map
+						.range(range)
+						.rev()
+						.eq(self.model.range(range).rev()),
+					"step {}: {:?}",
+					step,
+					op
+				)
+			}
+			Op::RangeMut(start, end, value) => {
+				let range = (start.as_ref(), end.as_ref());
+				for (_, v) in self.map.range_mut(range) {
+					*v = value.clone()
+				}
+
+				for (_, v) in self.model.range_mut(range) {
+					*v = value.clone()
+				}
+			}
+			Op::DrainFilter(start, end) => {
+				let range = (start.as_ref(), end.as_ref());
+				let drained: Vec<_> = self.map.drain_filter(|k, _| range.contains(k)).collect();
+
+				let keys: Vec<K> = self.model.range(range).map(|(k, _)| k.clone()).collect();
+				let expected: Vec<_> = keys
+					.into_iter()
+					.map(|k| self.model.remove_entry(&k).unwrap())
+					.collect();
+
+				assert_eq!(drained, expected, "step {}: {:?}", step, op)
+			}
+			Op::Retain(start, end) => {
+				let range = (start.as_ref(), end.as_ref());
+				self.map.retain(|k, _| range.contains(k));
+				self.model.retain(|k, _| range.contains(k))
+			}
+			Op::EntryInsert(key, value) => {
+				let inserted = self.map.entry(key.clone()).or_insert(value.clone()).clone();
+				let expected = self.model.entry(key.clone()).or_insert(value.clone());
+				assert_eq!(&inserted, expected, "step {}: {:?}", step, op)
+			}
+			Op::EntryRemove(key) => {
+				let removed = match self.map.entry(key.clone()) {
+					Entry::Occupied(entry) => Some(entry.remove_entry()),
+					Entry::Vacant(_) => None,
+				};
+
+				let expected = match self.model.entry(key.clone()) {
+					btree_map::Entry::Occupied(entry) => Some(entry.remove_entry()),
+					btree_map::Entry::Vacant(_) => None,
+				};
+
+				assert_eq!(removed, expected, "step {}: {:?}", step, op)
+			}
+			Op::PopFirst => {
+				assert_eq!(
+					self.map.pop_first(),
+					self.model.pop_first(),
+					"step {}: {:?}",
+					step,
+					op
+				)
+			}
+			Op::PopLast => {
+				assert_eq!(
+					self.map.pop_last(),
+					self.model.pop_last(),
+					"step {}: {:?}",
+					step,
+					op
+				)
+			}
+			Op::Clear => {
+				self.map.clear();
+				self.model.clear()
+			}
+		}
+
+		if let Err(e) = self.map.check_invariants() {
+			panic!("step {}: {:?}: invalid tree: {}", step, op, e)
+		}
+
+		self.check_content(step, &op)
+	}
+
+	/// Checks that the map and its model hold the same entries.
+	fn check_content(&self, step: usize, op: &Op<K, V>) {
+		assert_eq!(
+			self.map.len(),
+			self.model.len(),
+			"step {}: {:?}: wrong length",
+			step,
+			op
+		);
+		assert!(
+			self.map.iter().eq(self.model.iter()),
+			"step {}: {:?}: wrong entries",
+			step,
+			op
+		);
+		assert!(
+			self.map.iter().rev().eq(self.model.iter().rev()),
+			"step {}: {:?}: wrong reversed entries",
+			step,
+			op
+		)
+	}
+}
+
+/// Applies the given operations to an empty map and its model,
+/// checking them after each operation.
+///
+/// Requires the `testing` feature.
+///
+/// # Panics
+///
+/// Panics if the map and its model diverge,
+/// or if the map is not a valid B-Tree.
+pub fn check_ops<K, V, I>(ops: I)
+where
+	K: Ord + Clone + fmt::Debug,
+	V: Clone + PartialEq + fmt::Debug,
+	I: IntoIterator<Item = Op<K, V>>,
+{
+	let mut checker: ModelChecker<K, V> = ModelChecker::new();
+	for op in ops {
+		checker.apply(op)
+	}
+}
+
+/// Small deterministic pseudo-random number generator (SplitMix64).
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+	fn next(&mut self) -> u64 {
+		self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+		let mut z = self.0;
+		z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+		z ^ (z >> 31)
+	}
+
+	/// Returns a number in `0..n`.
+	fn below(&mut self, n: u32) -> u32 {
+		(self.next() % n as u64) as u32
+	}
+
+	fn key(&mut self, keys: u32) -> u32 {
+		self.below(keys)
+	}
+
+	/// Returns valid range bounds over `0..keys`.
+	fn bounds(&mut self, keys: u32) -> (Bound<u32>, Bound<u32>) {
+		let mut a = self.key(keys);
+		let mut b = self.key(keys);
+		if a > b {
+			std::mem::swap(&mut a, &mut b)
+		}
+
+		let start = match self.below(3) {
+			0 => Bound::Included(a),
+			1 => Bound::Excluded(a),
+			_ => Bound::Unbounded,
+		};
+
+		let end = match self.below(3) {
+			0 => Bound::Included(b),
+			1 if a != b || !matches!(start, Bound::Excluded(_)) => Bound::Excluded(b),
+			_ => Bound::Unbounded,
+		};
+
+		(start, end)
+	}
+}
+
+/// Generates `len` pseudo-random operations over the keys `0..keys`.
+///
+/// The same seed always gives the same operations.
+/// Insertions are more frequent than removals so that the map grows
+/// enough to exercise splits and merges,
+/// and bulk removals are rare enough not to empty it too often.
+///
+/// Requires the `testing` feature.
+///
+/// # Panics
+///
+/// Panics if `keys` is 0.
+pub fn random_ops(seed: u64, len: usize, keys: u32) -> Vec<Op<u32, u32>> {
+	assert!(keys > 0, "no keys to generate operations on");
+	let mut rng = SplitMix64(seed);
+	(0..len)
+		.map(|_| match rng.below(100) {
+			0..=34 => Op::Insert(rng.key(keys), rng.next() as u32),
+			35..=54 => Op::Remove(rng.key(keys)),
+			55..=64 => {
+				let (start, end) = rng.bounds(keys);
+				Op::Range(start, end)
+			}
+			65..=69 => {
+				let (start, end) = rng.bounds(keys);
+				Op::RangeMut(start, end, rng.next() as u32)
+			}
+			70..=79 => Op::EntryInsert(rng.key(keys), rng.next() as u32),
+			80..=89 => Op::EntryRemove(rng.key(keys)),
+			90..=92 => {
+				let (start, end) = rng.bounds(keys);
+				Op::DrainFilter(start, end)
+			}
+			93..=94 => {
+				let (a, b) = rng.bounds(keys);
+				// Retaining a range removes everything around it,
+				// so the retained range is widened.
+				let start = match a {
+					Bound::Included(a) | Bound::Excluded(a) => Bound::Included(a / 4),
+					Bound::Unbounded => Bound::Unbounded,
+				};
+				let end = match b {
+					Bound::Included(b) | Bound::Excluded(b) => {
+						Bound::Included(b.saturating_add((keys - b) * 3 / 4))
+					}
+					Bound::Unbounded => Bound::Unbounded,
+				};
+				Op::Retain(start, end)
+			}
+			95..=96 => Op::PopFirst,
+			97..=98 => Op::PopLast,
+			_ => Op::Clear,
+		})
+		.collect()
+}
@@ -4,7 +4,98 @@ use std::borrow::Borrow;
 /// Search in `sorted_slice` for the item with the nearest key smaller or equal to the given one.
 ///
 /// `sorted_slice` is assumed to be sorted.
+/// This is the search used in nodes:
+/// a linear scan with the `linear-search` feature, a binary search otherwise.
+///
+/// With the `ord-check` feature, this panics if `sorted_slice` is not sorted
+/// (see `check_sorted`).
+#[inline]
+#[allow(clippy::multiple_bound_locations)]
+pub fn search_min<T: Keyed, Q: ?Sized>(sorted_slice: &[T], key: &Q) -> Option<usize>
+where
+	T::Key: Borrow<Q>,
+	Q: Ord,
+{
+	#[cfg(feature = "ord-check")]
+	check_sorted(sorted_slice, key);
+
+	#[cfg(feature = "linear-search")]
+	{
+		linear_search_min(sorted_slice, key)
+	}
+	#[cfg(not(feature = "linear-search"))]
+	{
+		binary_search_min(sorted_slice, key)
+	}
+}
+
+/// Checks that the keys of `sorted_slice` are strictly increasing,
+/// and that their [`Ord`] implementation is consistent with the given searched key.
+///
+/// Requires the `ord-check` feature.
+///
+/// # Panics
+///
+/// Panics with the offending offsets if two consecutive keys are not strictly increasing,
+/// if comparing them in reverse order does not give the opposite result,
+/// or if a key is not equal to itself.
+#[cfg(feature = "ord-check")]
+#[track_caller]
+#[allow(clippy::multiple_bound_locations)]
+pub fn check_sorted<T: Keyed, Q: ?Sized>(sorted_slice: &[T], key: &Q)
+where
+	T::Key: Borrow<Q>,
+	Q: Ord,
+{
+	let type_name = std::any::type_name::<Q>();
+
+	if key.cmp(key) != std::cmp::Ordering::Equal {
+		panic!(
+			"inconsistent `Ord` implementation for `{}`: the searched key is not equal to itself",
+			type_name
+		)
+	}
+
+	for (i, pair) in sorted_slice.windows(2).enumerate() {
+		let (a, b) = (pair[0].key().borrow(), pair[1].key().borrow());
+		if a.cmp(b) != std::cmp::Ordering::Less || b.cmp(a) != std::cmp::Ordering::Greater {
+			panic!(
+				"inconsistent `Ord` implementation for `{}`: node items at offsets {} and {} are not strictly increasing",
+				type_name,
+				i,
+				i + 1
+			)
+		}
+	}
+}
+
+/// Search in `sorted_slice` for the item with the nearest key smaller or equal to the given one,
+/// comparing the key with every item.
+///
+/// `sorted_slice` is assumed to be sorted.
+/// Nodes hold at most `M + 1` items: for keys that are cheap to compare, such as integers,
+/// counting the smaller or equal keys without branching on each comparison
+/// is faster than the unpredictable branches of a binary search.
+#[inline]
+#[allow(clippy::multiple_bound_locations)]
+pub fn linear_search_min<T: Keyed, Q: ?Sized>(sorted_slice: &[T], key: &Q) -> Option<usize>
+where
+	T::Key: Borrow<Q>,
+	Q: Ord,
+{
+	sorted_slice
+		.iter()
+		.map(|item| (item.key().borrow() <= key) as usize)
+		.sum::<usize>()
+		.checked_sub(1)
+}
+
+/// Search in `sorted_slice` for the item with the nearest key smaller or equal to the given one,
+/// using a binary search.
+///
+/// `sorted_slice` is assumed to be sorted.
 #[inline]
+#[allow(clippy::multiple_bound_locations)]
 pub fn binary_search_min<T: Keyed, Q: ?Sized>(sorted_slice: &[T], key: &Q) -> Option<usize>
 where
 	T::Key: Borrow<Q>,
@@ -0,0 +1,78 @@
+//! Compile-time checks of the `Send` and `Sync` guarantees of the collections and their iterators.
+use btree_slab::{
+	generic::{map, multiset, set},
+	BTreeMap, BTreeMultiSet, BTreeSet,
+};
+use slab::Slab;
+
+type C = Slab<btree_slab::generic::Node<u32, u32>>;
+type D = Slab<btree_slab::generic::Node<u32, ()>>;
+type E = Slab<btree_slab::generic::Node<u32, usize>>;
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn collections() {
+	assert_send_sync::<BTreeMap<u32, u32>>();
+	assert_send_sync::<BTreeSet<u32>>();
+	assert_send_sync::<BTreeMultiSet<u32>>();
+	assert_send_sync::<btree_slab::BTreeSnapshot<u32, u32>>();
+	assert_send_sync::<map::TreeRoot>();
+	assert_send_sync::<map::ArenaRef<'static, C>>();
+	assert_send_sync::<map::EntryHandle<u32>>();
+	assert_send_sync::<map::VacantSlot<u32>>();
+}
+
+#[test]
+fn map_iterators() {
+	assert_send_sync::<map::Iter<'static, u32, u32, C>>();
+	assert_send_sync::<map::IterMut<'static, u32, u32, C>>();
+	assert_send_sync::<map::EntriesMut<'static, u32, u32, C>>();
+	assert_send_sync::<map::RangeEntriesMut<'static, u32, u32, C>>();
+	assert_send_sync::<map::RangeEntry<'static, 'static, u32, u32, C>>();
+	assert_send_sync::<map::IntoIter<u32, u32, C>>();
+	assert_send_sync::<map::Keys<'static, u32, u32, C>>();
+	assert_send_sync::<map::IntoKeys<u32, u32, C>>();
+	assert_send_sync::<map::Values<'static, u32, u32, C>>();
+	assert_send_sync::<map::ValuesMut<'static, u32, u32, C>>();
+	assert_send_sync::<map::IntoValues<u32, u32, C>>();
+	assert_send_sync::<map::IntoEntries<u32, u32, C>>();
+	assert_send_sync::<map::Range<'static, u32, u32, C>>();
+	assert_send_sync::<map::RangeMut<'static, u32, u32, C>>();
+	assert_send_sync::<map::DrainFilter<'static, u32, u32, C, fn(&u32, &mut u32) -> bool>>();
+	assert_send_sync::<map::Cursor<'static, u32, u32, C>>();
+	assert_send_sync::<map::CursorMut<'static, u32, u32, C>>();
+	assert_send_sync::<map::Entry<'static, u32, u32, C>>();
+	assert_send_sync::<map::OccupiedEntry<'static, u32, u32, C>>();
+	assert_send_sync::<map::VacantEntry<'static, u32, u32, C>>();
+}
+
+#[test]
+fn set_iterators() {
+	assert_send_sync::<set::Iter<'static, u32, D>>();
+	assert_send_sync::<set::IntoIter<u32, D>>();
+	assert_send_sync::<set::Range<'static, u32, D>>();
+	assert_send_sync::<set::Union<'static, u32, D, D>>();
+	assert_send_sync::<set::Intersection<'static, u32, D, D>>();
+	assert_send_sync::<set::Difference<'static, u32, D, D>>();
+	assert_send_sync::<set::SymmetricDifference<'static, u32, D, D>>();
+	assert_send_sync::<set::DrainFilter<'static, u32, D, fn(&u32) -> bool>>();
+	assert_send_sync::<multiset::Iter<'static, u32, E>>();
+	assert_send_sync::<multiset::Counts<'static, u32, E>>();
+}
+
+#[test]
+fn iter_mut_across_threads() {
+	let mut map: BTreeMap<u32, u32> = (0..100).map(|i| (i, i)).collect();
+	let mut iter = map.iter_mut();
+
+	std::thread::scope(|s| {
+		s.spawn(|| {
+			for (k, v) in &mut iter {
+				*v += k
+			}
+		});
+	});
+
+	assert!(map.into_iter().eq((0..100).map(|i| (i, i * 2))));
+}
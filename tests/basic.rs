@@ -7,9 +7,11 @@ use btree_slab::{
 };
 use rand::{rngs::SmallRng, seq::SliceRandom, SeedableRng};
 
+#[allow(clippy::redundant_static_lifetimes)]
 const SEED: &'static [u8; 32] = b"testseedtestseedtestseedtestseed";
 
 #[test]
+#[allow(clippy::redundant_pattern_matching)]
 pub fn insert() {
 	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
 
@@ -24,6 +26,7 @@ pub fn insert() {
 }
 
 #[test]
+#[allow(clippy::needless_borrow)]
 pub fn remove() {
 	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
 
@@ -45,6 +48,7 @@ pub fn remove() {
 }
 
 #[test]
+#[allow(clippy::single_match)]
 pub fn item_addresses() {
 	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
 
@@ -140,75 +144,2439 @@ pub fn remove_addresses() {
 	}
 }
 
+#[test]
+pub fn insert_remove_at_key() {
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+
+	for (key, value) in &ITEMS {
+		let (old, addr) = btree.insert_at_key(*key, *value);
+		assert_eq!(old, None);
+		assert_eq!(btree.item(addr).unwrap().key(), key);
+	}
+
+	let mut items = ITEMS;
+	items.sort();
+
+	for (i, (key, value)) in items.iter().enumerate() {
+		let (removed, next) = btree.remove_with_next(key).unwrap();
+		btree.validate();
+		assert_eq!(removed, *value);
+		assert_eq!(
+			next.map(|addr| btree.item(addr).unwrap().key()),
+			items.get(i + 1).map(|(key, _)| key)
+		);
+	}
+}
+
 #[test]
 pub fn update() {
 	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
 
-	for (key, value) in &ITEMS {
-		if key % 2 == 0 {
-			btree.insert(*key, *value);
+	for (key, value) in &ITEMS {
+		if key % 2 == 0 {
+			btree.insert(*key, *value);
+		}
+	}
+
+	for (key, value) in &ITEMS {
+		btree.update(*key, |current_value| match current_value {
+			Some(current_value) => {
+				if current_value % 2 == 0 {
+					(None, ())
+				} else {
+					(Some(10000 - *value), ())
+				}
+			}
+			None => (Some(*value), ()),
+		});
+
+		btree.validate();
+	}
+
+	for (key, value) in &ITEMS {
+		let shoud_be_present = *key % 2 == 1 || *value % 2 == 1;
+
+		match btree.get(key) {
+			Some(current_value) => {
+				if !shoud_be_present {
+					panic!("binding {}:{} should not be present", *key, *value);
+				}
+
+				if *key % 2 == 0 && *value % 2 == 1 {
+					assert_eq!(*current_value, 10000 - *value)
+				} else {
+					assert_eq!(*current_value, *value)
+				}
+			}
+			None => {
+				if shoud_be_present {
+					panic!("binding {}:{} should be present", *key, *value);
+				}
+			}
+		}
+	}
+}
+
+#[test]
+pub fn drain_all() {
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+
+	for (key, value) in &ITEMS {
+		btree.insert(*key, *value);
+	}
+
+	let mut drained = btree.drain_filter(|_, _| true).collect::<Vec<_>>();
+	assert!(btree.is_empty());
+
+	while let Some((key, value)) = btree.pop_first() {
+		drained.push((key, value));
+	}
+
+	assert_eq!(drained.len(), ITEMS.len());
+
+	for (key, value) in &ITEMS {
+		assert!(drained.contains(&(*key, *value)));
+	}
+}
+
+#[test]
+pub fn index_mut() {
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+
+	for (key, value) in &ITEMS {
+		btree.insert(*key, *value);
+	}
+
+	for (key, _) in &ITEMS {
+		btree[key] += 1;
+	}
+
+	for (key, value) in &ITEMS {
+		assert_eq!(btree[key], *value + 1);
+		assert_eq!(*btree.get_expect(key, "missing key"), *value + 1);
+	}
+}
+
+#[test]
+pub fn replace_contents() {
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+	btree.insert(0, 0);
+
+	for k in 0..ITEMS.len() {
+		let mut fresh: BTreeMap<usize, usize> = ITEMS[..k].iter().cloned().collect();
+		btree.replace_contents_from(&mut fresh);
+		btree.validate();
+		assert!(fresh.is_empty());
+		assert_eq!(btree.len(), k);
+
+		for (key, value) in &ITEMS[..k] {
+			assert_eq!(btree.get(key), Some(value));
+		}
+	}
+}
+
+#[test]
+pub fn vacant_slots() {
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+
+	// Slots created before any modification are filled in place,
+	// the others are searched again.
+	let slots: Vec<_> = ITEMS
+		.iter()
+		.map(|(key, _)| btree.vacant_slot(*key).unwrap())
+		.collect();
+
+	for (slot, (key, value)) in slots.into_iter().zip(&ITEMS) {
+		assert_eq!(slot.key(), key);
+		assert_eq!(slot.insert(&mut btree, *value), Ok(&mut value.clone()));
+		btree.validate();
+	}
+
+	for (key, value) in &ITEMS {
+		assert!(btree.vacant_slot(*key).is_none());
+		assert_eq!(btree.get(key), Some(value));
+	}
+}
+
+#[test]
+pub fn vacant_slot_other_map() {
+	let a: BTreeMap<usize, usize> = [(1, 1), (2, 2), (3, 3)].into_iter().collect();
+	let mut b: BTreeMap<usize, usize> = [(10, 10), (20, 20), (30, 30)].into_iter().collect();
+
+	let slot = a.vacant_slot(4).unwrap();
+	assert_eq!(slot.insert(&mut b, 4), Err((4, 4)));
+	assert!(b.keys().eq(&[10, 20, 30]));
+
+	let slot = a.vacant_slot(0).unwrap();
+	assert_eq!(slot.insert(&mut b, 0), Ok(&mut 0));
+	assert!(b.keys().eq(&[0, 10, 20, 30]));
+	b.validate();
+}
+
+#[test]
+pub fn merged_with() {
+	for k in 0..ITEMS.len() {
+		let btree: BTreeMap<usize, usize> = ITEMS[..k].iter().cloned().collect();
+		let mut other: Vec<_> = ITEMS[k / 2..].to_vec();
+		other.sort();
+
+		let mut expected = std::collections::BTreeMap::new();
+		for (key, value) in &ITEMS[k / 2..] {
+			expected.insert(*key, *value);
+		}
+		for (key, value) in &ITEMS[..k] {
+			*expected.entry(*key).or_insert(0) += *value;
+		}
+
+		let merged = btree.merged_with(other, |_, a, b| a + b);
+		merged.validate();
+		assert!(merged.into_iter().eq(expected));
+	}
+}
+
+#[test]
+pub fn from_unsorted_vec_packed() {
+	for len in 0..2000usize {
+		let items: Vec<_> = (0..len).rev().map(|i| (i, i * 2)).collect();
+		let btree: BTreeMap<usize, usize> = BTreeMap::from_unsorted_vec(items);
+		btree.validate();
+		assert_eq!(btree.len(), len);
+		assert!(btree
+			.iter()
+			.map(|(k, v)| (*k, *v))
+			.eq((0..len).map(|i| (i, i * 2))));
+
+		let stats = btree.stats();
+		if len > 0 {
+			assert_eq!(stats.leaves, (len + 1).div_ceil(9));
+		}
+	}
+}
+
+#[test]
+pub fn merged_with_keeps_settings() {
+	use btree_slab::generic::map::{Limits, SplitPolicy};
+
+	let limits = Limits::new().with_max_len(20);
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::with_limits(limits);
+	btree.set_split_policy(SplitPolicy::Adaptive);
+	#[cfg(feature = "bloom")]
+	btree.enable_key_filter(10);
+	#[cfg(feature = "structural-hash")]
+	btree.enable_structural_hash();
+	btree.extend((0..10).map(|i| (i * 2, i)));
+
+	let mut merged = btree.merged_with((0..10).map(|i| (i * 2 + 1, i)), |_, a, _| a);
+
+	assert!(merged.keys().copied().eq(0..20));
+	assert_eq!(merged.limits(), limits);
+	assert_eq!(merged.split_policy(), SplitPolicy::Adaptive);
+	merged.validate();
+
+	#[cfg(feature = "bloom")]
+	{
+		assert!(merged.has_key_filter());
+		assert!((0..20).all(|key| merged.may_contain_key(&key)));
+	}
+
+	#[cfg(feature = "structural-hash")]
+	{
+		let mut fresh: BTreeMap<usize, usize> = (0..20).map(|i| (i, i)).collect();
+		fresh.enable_structural_hash();
+		assert_eq!(merged.structural_hash(), fresh.structural_hash());
+	}
+
+	assert!(merged.checked_insert(20, 20).is_err());
+}
+
+#[test]
+pub fn extend_from_range() {
+	let src: BTreeMap<usize, usize> = ITEMS.iter().cloned().collect();
+
+	for k in 0..ITEMS.len() {
+		let mut btree: BTreeMap<usize, usize> =
+			ITEMS[..k].iter().map(|(key, _)| (*key, 0)).collect();
+		let mut expected: std::collections::BTreeMap<usize, usize> =
+			btree.clone().into_iter().collect();
+
+		let range = 2000..7000;
+		btree.extend_from_range(&src, range.clone());
+		btree.validate();
+
+		for (key, value) in src.range(range) {
+			expected.insert(*key, *value);
+		}
+
+		assert!(btree.into_iter().eq(expected));
+	}
+}
+
+#[cfg(feature = "compact-address")]
+#[test]
+pub fn compact_node_ids() {
+	use btree_slab::generic::node::{InternalNode, LeafNode};
+
+	let mut node: InternalNode<usize, usize> = InternalNode::binary(Some(7), 1, Item::new(0, 0), 2);
+	assert_eq!(node.parent(), Some(7));
+	assert_eq!(node.child_id(0), 1);
+	assert_eq!(node.child_id(1), 2);
+	node.set_parent(None);
+	assert_eq!(node.parent(), None);
+
+	let (first_child, branches) = node.into_parts();
+	assert_eq!(first_child, 1);
+	assert_eq!(branches[0].child, 2usize);
+
+	let large_id = u32::MAX as usize;
+	assert!(std::panic::catch_unwind(|| {
+		InternalNode::<usize, usize>::binary(None, large_id, Item::new(0, 0), 2)
+	})
+	.is_err());
+	assert!(std::panic::catch_unwind(|| LeafNode::new(Some(large_id), Item::new(0, 0))).is_err());
+
+	let leaf: LeafNode<usize, usize> = LeafNode::new(Some(3), Item::new(0, 0));
+	assert_eq!(leaf.parent(), Some(3));
+
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+	btree.extend(ITEMS.iter().copied());
+	btree.validate();
+	assert!(ITEMS
+		.iter()
+		.all(|(key, value)| btree.get(key) == Some(value)));
+}
+
+#[cfg(feature = "watermark")]
+#[test]
+pub fn watermarks() {
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+	let ranges = [0..2000, 2000..5000, 5000..10000];
+	let ids: Vec<_> = ranges
+		.iter()
+		.map(|range| btree.add_watermark(range.clone()).unwrap())
+		.collect();
+
+	for (key, value) in &ITEMS {
+		btree.insert(*key, *value);
+		let dirty = btree.take_dirty();
+		assert_eq!(dirty.len(), 1);
+		for (range, id) in ranges.iter().zip(&ids) {
+			assert_eq!(dirty.contains(*id), range.contains(key));
+		}
+	}
+
+	for (key, _) in &ITEMS {
+		btree.update(*key, |value| (value.map(|v| v + 1), ()));
+		assert!(btree.take_dirty().iter().eq(ranges
+			.iter()
+			.zip(&ids)
+			.filter(|(range, _)| range.contains(key))
+			.map(|(_, id)| *id)));
+	}
+
+	for (key, _) in &ITEMS {
+		btree.remove(key);
+		assert_eq!(btree.take_dirty().len(), 1);
+	}
+
+	assert!(btree.remove_watermark(ids[1]));
+	assert!(!btree.remove_watermark(ids[1]));
+	btree.insert(3000, 0);
+	assert!(btree.take_dirty().is_empty());
+}
+
+#[test]
+pub fn drain_filter_panic() {
+	use std::panic::{catch_unwind, AssertUnwindSafe};
+
+	// Panic while iterating: the predicate is not called again on drop.
+	let mut btree: BTreeMap<usize, usize> = (0..100).map(|i| (i, i)).collect();
+	let mut calls = 0;
+	let result = catch_unwind(AssertUnwindSafe(|| {
+		let mut drain = btree.drain_filter(|k, _| {
+			calls += 1;
+			if *k == 50 {
+				panic!("predicate panic")
+			}
+			k % 2 == 0
+		});
+		while drain.next().is_some() {}
+	}));
+	assert!(result.is_err());
+	assert_eq!(calls, 51);
+	btree.validate();
+	assert!(btree
+		.keys()
+		.copied()
+		.eq((0..50).filter(|k| k % 2 == 1).chain(50..100)));
+
+	// Panic while dropping: the remaining items are kept.
+	let mut btree: BTreeMap<usize, usize> = (0..100).map(|i| (i, i)).collect();
+	let mut calls = 0;
+	let result = catch_unwind(AssertUnwindSafe(|| {
+		btree.drain_filter(|k, _| {
+			calls += 1;
+			if *k == 50 {
+				panic!("predicate panic")
+			}
+			k % 2 == 0
+		});
+	}));
+	assert!(result.is_err());
+	assert_eq!(calls, 51);
+	btree.validate();
+	assert_eq!(btree.len(), 75);
+}
+
+#[test]
+pub fn drain_filter_keep_rest() {
+	let mut btree: BTreeMap<usize, usize> = (0..100).map(|i| (i, i)).collect();
+	let mut drain = btree.drain_filter(|k, _| k % 2 == 0);
+	assert_eq!(drain.nth(9), Some((18, 18)));
+	drain.keep_rest();
+
+	btree.validate();
+	assert_eq!(btree.len(), 90);
+	assert!(btree
+		.keys()
+		.copied()
+		.eq((0..20).filter(|k| k % 2 == 1).chain(20..100)));
+}
+
+#[test]
+pub fn drain_filter_peek() {
+	let mut btree: BTreeMap<usize, usize> = (0..100).map(|i| (i, i)).collect();
+	let mut drain = btree.drain_filter(|k, _| k % 3 != 0);
+	let mut drained = Vec::new();
+	while let Some((k, _)) = drain.peek() {
+		if *k >= 60 {
+			break;
+		}
+
+		let expected = (*k..100).find(|k| k % 3 != 0);
+		let next = drain.next().map(|(k, _)| k);
+		assert_eq!(next, expected);
+		drained.extend(next);
+	}
+	assert_eq!(drain.peek(), Some((&60, &60)));
+	drain.keep_rest();
+
+	btree.validate();
+	assert_eq!(drained, (0..60).filter(|k| k % 3 != 0).collect::<Vec<_>>());
+	assert!(btree
+		.keys()
+		.copied()
+		.eq((0..60).filter(|k| k % 3 == 0).chain(60..100)));
+
+	let mut drain = btree.drain_filter(|_, _| true);
+	assert_eq!(drain.by_ref().count(), 60);
+	assert_eq!(drain.peek(), None);
+}
+
+#[test]
+pub fn retain_while() {
+	let mut btree: BTreeMap<usize, usize> = (0..1000).map(|i| (i, i)).collect();
+	let mut scanned = 0;
+	let removed = btree.retain_while(
+		|k| *k < 500,
+		|k, v| {
+			scanned += 1;
+			*v += 1;
+			k % 3 != 0
+		},
+	);
+
+	btree.validate();
+	assert_eq!(scanned, 500);
+	assert_eq!(removed, 167);
+	assert_eq!(btree.len(), 833);
+	assert!(btree.iter().map(|(k, v)| (*k, *v)).eq((0..1000)
+		.filter(|k| *k >= 500 || k % 3 != 0)
+		.map(|k| (k, if k < 500 { k + 1 } else { k }))));
+
+	assert_eq!(btree.retain_while(|_| true, |_, _| true), 0);
+	assert_eq!(btree.retain_while(|_| true, |_, _| false), 833);
+	assert!(btree.is_empty());
+}
+
+#[test]
+pub fn drain_filter_send_sync() {
+	fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+	let mut btree: BTreeMap<usize, String> = BTreeMap::new();
+	let drain = btree.drain_filter(|_, _| true);
+	assert_send_sync(&drain);
+}
+
+#[test]
+pub fn limits() {
+	use btree_slab::generic::map::{Limit, Limits};
+
+	for max_depth in 1..4 {
+		let mut btree: BTreeMap<usize, usize> =
+			BTreeMap::with_limits(Limits::new().with_max_depth(max_depth).with_max_len(90));
+
+		for (i, (key, value)) in ITEMS.iter().enumerate() {
+			let mut unchecked = btree.clone();
+			unchecked.insert(*key, *value);
+
+			match btree.checked_insert(*key, *value) {
+				Ok(_) => assert!(btree.depth() <= max_depth),
+				Err(e) => {
+					assert_eq!((e.key, e.value), (*key, *value));
+					match e.limit {
+						Limit::Depth(d) => {
+							assert_eq!(d, max_depth);
+							assert!(unchecked.depth() > max_depth)
+						}
+						Limit::Len(l) => {
+							assert_eq!(l, 90);
+							assert_eq!(btree.len(), 90);
+							assert!(i >= 90)
+						}
+					}
+				}
+			}
+
+			btree.validate();
+		}
+	}
+}
+
+#[test]
+pub fn max_depth() {
+	use btree_slab::generic::map::max_depth;
+
+	assert_eq!(max_depth(0), 0);
+	assert_eq!(max_depth(1), 1);
+	assert_eq!(max_depth(6), 1);
+	assert_eq!(max_depth(7), 2);
+	assert_eq!(max_depth(1_000_000), 10);
+	assert_eq!(max_depth(4_000_000_000), 16);
+	assert_eq!(max_depth(usize::MAX), max_depth(usize::MAX - 1));
+
+	// Sequential insertions followed by removals produce sparse nodes.
+	let mut btree: BTreeMap<usize, usize> = (0..10_000).map(|i| (i, i)).collect();
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut keys: Vec<_> = (0..10_000).collect();
+	keys.shuffle(&mut rng);
+	for key in keys {
+		btree.remove(&key);
+		assert!(btree.depth() <= max_depth(btree.len()));
+		btree.debug_assert_depth_le(max_depth(btree.len()));
+	}
+}
+
+#[test]
+pub fn append_keeps_settings() {
+	use btree_slab::generic::map::{Limits, SplitPolicy};
+
+	let limits = Limits::new().with_max_len(2);
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::with_limits(limits);
+	btree.set_split_policy(SplitPolicy::Adaptive);
+	#[cfg(feature = "bloom")]
+	btree.enable_key_filter(10);
+	#[cfg(feature = "structural-hash")]
+	btree.enable_structural_hash();
+
+	let mut other: BTreeMap<usize, usize> = (0..10).map(|i| (i, i)).collect();
+	btree.append(&mut other);
+
+	assert!(other.is_empty());
+	assert!(btree.keys().copied().eq(0..10));
+	assert_eq!(btree.limits(), limits);
+	assert_eq!(btree.split_policy(), SplitPolicy::Adaptive);
+	assert_eq!(other.limits(), Limits::new());
+	assert_eq!(other.split_policy(), SplitPolicy::Middle);
+	assert!(btree.checked_insert(10, 10).is_err());
+
+	#[cfg(feature = "bloom")]
+	{
+		assert!(btree.has_key_filter());
+		assert!((0..10).all(|key| btree.may_contain_key(&key)));
+		assert!(!other.has_key_filter());
+	}
+
+	#[cfg(feature = "structural-hash")]
+	{
+		let mut fresh: BTreeMap<usize, usize> = (0..10).map(|i| (i, i)).collect();
+		fresh.enable_structural_hash();
+		assert_eq!(btree.structural_hash(), fresh.structural_hash());
+		assert_eq!(other.structural_hash(), None);
+	}
+}
+
+#[test]
+pub fn split_policy() {
+	use btree_slab::generic::map::SplitPolicy;
+
+	let policies = [
+		SplitPolicy::Middle,
+		SplitPolicy::Ratio(0),
+		SplitPolicy::Ratio(60),
+		SplitPolicy::Ratio(100),
+		SplitPolicy::Adaptive,
+	];
+
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut shuffled: Vec<usize> = (0..1000).collect();
+	shuffled.shuffle(&mut rng);
+
+	for policy in policies {
+		let orders: [Vec<usize>; 3] = [
+			(0..1000).collect(),
+			(0..1000).rev().collect(),
+			shuffled.clone(),
+		];
+
+		for keys in orders {
+			let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+			btree.set_split_policy(policy);
+			for key in &keys {
+				btree.insert(*key, *key);
+			}
+			btree.validate();
+			assert!(btree.keys().copied().eq(0..1000));
+
+			for key in &keys[..500] {
+				btree.remove(key);
+			}
+			btree.validate();
+		}
+	}
+
+	let fill_factor = |policy, keys: &mut dyn Iterator<Item = usize>| {
+		let mut btree: BTreeMap<usize, ()> = BTreeMap::new();
+		btree.set_split_policy(policy);
+		for key in keys {
+			btree.insert(key, ());
+		}
+		btree.stats().leaf_fill_factor()
+	};
+
+	let middle = fill_factor(SplitPolicy::Middle, &mut (0..10_000));
+	assert!(fill_factor(SplitPolicy::Ratio(60), &mut (0..10_000)) > middle);
+	assert!(fill_factor(SplitPolicy::Adaptive, &mut (0..10_000)) > middle);
+	assert!(fill_factor(SplitPolicy::Adaptive, &mut (0..10_000).rev()) > middle);
+}
+
+#[test]
+pub fn retain_in() {
+	for (start, end) in [(0, 100), (10, 20), (0, 1), (37, 91), (50, 50)] {
+		let mut map: BTreeMap<usize, usize> = (0..100).map(|i| (i, i)).collect();
+		let mut visited = Vec::new();
+		map.retain_in(start..end, |k, v| {
+			visited.push(*k);
+			*v += 1;
+			k % 3 == 0
+		});
+		map.validate();
+
+		assert!(visited.into_iter().eq(start..end));
+		let expected = (0..100)
+			.filter(|i| !(start..end).contains(i) || i % 3 == 0)
+			.map(|i| (i, if (start..end).contains(&i) { i + 1 } else { i }));
+		assert!(map.into_iter().eq(expected));
+	}
+}
+
+#[test]
+pub fn drain_filter_range() {
+	let mut rng = SmallRng::from_seed(*SEED);
+	for (start, end) in [(0, 100), (10, 20), (0, 1), (37, 91), (50, 50), (95, 200)] {
+		let mut items = ITEMS;
+		items.shuffle(&mut rng);
+		let mut map: BTreeMap<usize, usize> = items.iter().copied().collect();
+		let mut sorted = items.to_vec();
+		sorted.sort_unstable();
+
+		let drained: Vec<_> = map
+			.drain_filter_range(start..end, |k, _| k % 2 == 0)
+			.collect();
+		map.validate();
+
+		let (expected, kept): (Vec<_>, Vec<_>) = sorted
+			.into_iter()
+			.partition(|(k, _)| (start..end).contains(k) && k % 2 == 0);
+		assert_eq!(drained, expected);
+		assert!(map.into_iter().eq(kept));
+	}
+}
+
+#[test]
+pub fn compact() {
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut items = ITEMS;
+	items.shuffle(&mut rng);
+
+	// Churn the map so that its node container is fragmented.
+	let mut btree: BTreeMap<usize, usize> = (0..10000).map(|i| (i, i)).collect();
+	btree.retain(|k, _| k % 50 == 0);
+	for (key, value) in items {
+		btree.insert(key * 7 + 1, value);
+	}
+
+	let clone = btree.clone_compact();
+	clone.validate();
+	assert!(clone == btree);
+
+	let capacity = btree.stats().slab_capacity;
+	btree.compact();
+	btree.validate();
+	assert!(clone == btree);
+	assert!(btree.stats().slab_capacity < capacity);
+	assert_eq!(btree.stats().slab_len, btree.stats().nodes);
+}
+
+#[test]
+pub fn key_set_conversion() {
+	for n in [0, 1, 8, 9, 100, 1000] {
+		let map: BTreeMap<usize, String> = (0..n).map(|i| (i, i.to_string())).collect();
+		let depth = map.depth();
+
+		let set: btree_slab::BTreeSet<usize> = map.into_key_set();
+		assert!(set.iter().copied().eq(0..n));
+
+		let mut visited = Vec::new();
+		let map: BTreeMap<usize, usize> = set.into_map_with(|&i| {
+			visited.push(i);
+			i * 2
+		});
+		map.validate();
+		assert!(visited.into_iter().eq(0..n));
+		assert_eq!(map.depth(), depth);
+		assert!(map.into_iter().eq((0..n).map(|i| (i, i * 2))));
+	}
+}
+
+#[cfg(feature = "bloom")]
+#[test]
+pub fn key_filter() {
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut keys: Vec<usize> = (0..10000).map(|i| i * 2).collect();
+	keys.shuffle(&mut rng);
+
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+	btree.insert(keys[0], 0);
+	btree.enable_key_filter(10);
+
+	// The filter is rebuilt as the map grows.
+	for &key in &keys[1..] {
+		btree.insert(key, key);
+	}
+
+	let check = |btree: &BTreeMap<usize, usize>| {
+		let mut false_positives = 0;
+		for key in 0..20000 {
+			let contained = btree.contains_key(&key);
+			assert_eq!(btree.contains_key_hinted(&key), contained);
+			assert_eq!(btree.get_hinted(&key), btree.get(&key));
+			if !contained && btree.may_contain_key(&key) {
+				false_positives += 1
+			}
+		}
+
+		false_positives
+	};
+
+	assert!(check(&btree) < 500);
+
+	// Removed keys are eventually dropped from the filter.
+	for &key in &keys[..8000] {
+		btree.remove(&key);
+	}
+	btree.retain(|k, _| k % 3 != 0);
+	assert!(check(&btree) < 500);
+
+	btree.clear();
+	assert!(btree.has_key_filter());
+	assert!(!btree.may_contain_key(&keys[9000]));
+	btree.insert(1, 1);
+	assert!(btree.contains_key_hinted(&1));
+
+	btree.disable_key_filter();
+	assert!(btree.may_contain_key(&3));
+}
+
+#[cfg(feature = "binary")]
+#[test]
+pub fn binary_encoding() {
+	for len in [0, 1, 8, 9, 100, 5000] {
+		let mut btree: BTreeMap<usize, String> = (0..len).map(|i| (i, i.to_string())).collect();
+		btree.retain(|k, _| k % 3 != 0);
+
+		let mut buffer = Vec::new();
+		btree.encode_into(&mut buffer).unwrap();
+		let decoded: BTreeMap<usize, String> =
+			BTreeMap::decode_from(&mut buffer.as_slice()).unwrap();
+		decoded.validate();
+		assert_eq!(decoded, btree);
+		assert_eq!(decoded.depth(), btree.depth());
+
+		// Truncated or corrupted inputs are rejected.
+		if !buffer.is_empty() {
+			let truncated = &buffer[..buffer.len() - 1];
+			assert!(BTreeMap::<usize, String>::decode_from(&mut &truncated[..]).is_err());
+		}
+		buffer[4] += 1;
+		assert!(BTreeMap::<usize, String>::decode_from(&mut buffer.as_slice()).is_err());
+	}
+
+	// Unordered keys are detected.
+	let btree: BTreeMap<u32, ()> = (0..4).map(|i| (i, ())).collect();
+	let mut buffer = Vec::new();
+	btree.encode_into(&mut buffer).unwrap();
+	let first_key = buffer.len() - 16;
+	buffer[first_key] = 9;
+	assert_eq!(
+		BTreeMap::<u32, ()>::decode_from(&mut buffer.as_slice())
+			.unwrap_err()
+			.kind(),
+		std::io::ErrorKind::InvalidData
+	);
+}
+
+#[cfg(feature = "ord-check")]
+#[test]
+#[should_panic(expected = "inconsistent `Ord` implementation")]
+pub fn ord_check() {
+	use std::{cell::Cell, cmp::Ordering};
+
+	thread_local! {
+		static REVERSED: Cell<bool> = const { Cell::new(false) };
+	}
+
+	/// Key whose ordering can be reversed while it is in the map.
+	#[derive(PartialEq, Eq)]
+	struct Fickle(usize);
+
+	impl PartialOrd for Fickle {
+		fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+			Some(self.cmp(other))
+		}
+	}
+
+	impl Ord for Fickle {
+		fn cmp(&self, other: &Self) -> Ordering {
+			if REVERSED.with(Cell::get) {
+				other.0.cmp(&self.0)
+			} else {
+				self.0.cmp(&other.0)
+			}
+		}
+	}
+
+	let btree: BTreeMap<Fickle, usize> = (0..100).map(|i| (Fickle(i), i)).collect();
+	assert_eq!(btree.get(&Fickle(50)), Some(&50));
+
+	REVERSED.with(|reversed| reversed.set(true));
+	btree.get(&Fickle(50));
+}
+
+#[cfg(feature = "structural-hash")]
+#[test]
+pub fn structural_hash() {
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut keys: Vec<usize> = (0..2000).collect();
+	keys.shuffle(&mut rng);
+
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+	btree.enable_structural_hash();
+	for &key in &keys {
+		btree.insert(key, key);
+	}
+
+	// The rolling hash matches the hash computed from scratch.
+	let check = |btree: &BTreeMap<usize, usize>| {
+		let mut fresh = btree.clone();
+		fresh.enable_structural_hash();
+		assert_eq!(btree.structural_hash(), fresh.structural_hash());
+		assert!(btree.eq_fast(&fresh));
+	};
+	check(&btree);
+
+	for &key in &keys[..500] {
+		btree.remove(&key);
+	}
+	btree.retain(|k, _| k % 3 != 0);
+	check(&btree);
+
+	let mut right = btree.split_off(&1000);
+	check(&btree);
+	check(&right);
+
+	let middle = right.remove_range(1200..1600);
+	check(&right);
+	check(&middle);
+
+	btree.append(&mut right);
+	check(&btree);
+
+	let other: BTreeMap<usize, usize> = btree.clone();
+	btree.remove(&1999);
+	btree.insert(2000, 2000);
+	assert!(!btree.maybe_eq_fast(&other));
+	assert!(!btree.eq_fast(&other));
+
+	btree.clear();
+	assert_eq!(btree.structural_hash(), Some(0));
+	btree.disable_structural_hash();
+	assert_eq!(btree.structural_hash(), None);
+}
+
+#[test]
+pub fn entry_ref() {
+	let mut btree: BTreeMap<String, usize> = BTreeMap::new();
+	let mut std_map = std::collections::BTreeMap::new();
+
+	for (i, (key, _)) in ITEMS.iter().enumerate() {
+		let key = (key % 37).to_string();
+		*btree.entry_ref(key.as_str()).or_default() += i;
+		*std_map.entry(key).or_default() += i;
+	}
+
+	btree.validate();
+	assert!(btree.iter().eq(std_map.iter()));
+
+	for i in 0..74 {
+		let key = (i % 37).to_string();
+		let entry = btree.entry_ref(key.as_str()).insert_entry(i);
+		assert_eq!(entry.key(), &key);
+		assert_eq!(*entry.get(), i);
+	}
+
+	btree.validate();
+	assert!(btree
+		.iter()
+		.all(|(k, v)| k.parse::<usize>().unwrap() + 37 == *v));
+}
+
+#[test]
+pub fn from_unsorted_vec() {
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut items: Vec<(usize, usize)> = (0..5000).map(|i| (i % 1500, i)).collect();
+	items.shuffle(&mut rng);
+
+	let std_map: std::collections::BTreeMap<_, _> = items.iter().copied().collect();
+	let btree: BTreeMap<usize, usize> = BTreeMap::from_unsorted_vec(items.clone());
+	btree.validate();
+	assert!(btree.iter().eq(std_map.iter()));
+
+	let btree: BTreeMap<usize, Vec<usize>> = BTreeMap::from_unsorted_vec_with(
+		items.iter().map(|&(k, v)| (k, vec![v])).collect(),
+		|_, mut a, b| {
+			a.extend(b);
+			a
+		},
+	);
+	btree.validate();
+	for (key, values) in &btree {
+		let expected: Vec<_> = items
+			.iter()
+			.filter(|(k, _)| k == key)
+			.map(|(_, v)| *v)
+			.collect();
+		assert_eq!(*values, expected);
+	}
+
+	#[cfg(feature = "rayon")]
+	{
+		let btree: BTreeMap<usize, usize> =
+			BTreeMap::par_from_unsorted_vec_with(items, |_, _, last| last);
+		btree.validate();
+		assert!(btree.iter().eq(std_map.iter()));
+	}
+}
+
+#[test]
+pub fn assign_values_in_order() {
+	let mut btree: BTreeMap<usize, usize> = ITEMS.iter().copied().collect();
+	let keys: Vec<_> = btree.keys().copied().collect();
+
+	btree
+		.assign_values_in_order(keys.iter().map(|k| k * 2))
+		.unwrap();
+	btree.validate();
+	assert!(btree.iter().all(|(k, v)| *v == k * 2));
+
+	let err = btree.assign_values_in_order(0..10).unwrap_err();
+	assert_eq!(err, btree_slab::generic::map::LengthMismatch::TooFew(10));
+	assert!(btree.values().take(10).copied().eq(0..10));
+	assert_eq!(btree[&keys[10]], keys[10] * 2);
+}
+
+#[test]
+pub fn split_off() {
+	let mut rng = SmallRng::from_seed(*SEED);
+
+	for size in [0, 1, 5, 8, 9, 17, 50, 200, 1000] {
+		// Keys are even so that missing keys can be used as split points.
+		let mut keys: Vec<usize> = (0..size).map(|i| i * 2).collect();
+		keys.shuffle(&mut rng);
+
+		for at in (0..=size * 2 + 1).step_by(size / 20 + 1) {
+			let mut map: BTreeMap<usize, usize> = BTreeMap::new();
+			for &key in &keys {
+				map.insert(key, key);
+			}
+
+			let mut right = map.split_off(&at);
+			map.validate();
+			right.validate();
+
+			assert!(map
+				.keys()
+				.copied()
+				.eq((0..size).map(|i| i * 2).filter(|k| *k < at)));
+			assert!(right
+				.keys()
+				.copied()
+				.eq((0..size).map(|i| i * 2).filter(|k| *k >= at)));
+			assert_eq!(map.len() + right.len(), size);
+			assert_eq!(
+				map.occupied_node_ids().count(),
+				map.reachable_node_ids().count()
+			);
+
+			map.insert(at, 0);
+			right.insert(at + 1, 0);
+			map.validate();
+			right.validate();
+		}
+	}
+}
+
+#[test]
+pub fn append_disjoint() {
+	let sizes = [0, 1, 2, 5, 8, 9, 17, 60, 300, 2000];
+
+	for a in sizes {
+		for b in sizes {
+			for b_is_after in [true, false] {
+				let offset = if b_is_after { 10_000 } else { 0 };
+				let mut left: BTreeMap<usize, usize> =
+					(0..a).map(|i| (10_000 - offset + i, i)).collect();
+				let mut right: BTreeMap<usize, usize> = (0..b).map(|i| (offset + i, i)).collect();
+
+				let mut expected: Vec<_> = left
+					.iter()
+					.chain(right.iter())
+					.map(|(k, v)| (*k, *v))
+					.collect();
+				expected.sort_unstable();
+
+				left.append(&mut right);
+				left.validate();
+				assert!(right.is_empty());
+				assert_eq!(left.len(), a + b);
+				assert_eq!(
+					left.occupied_node_ids().count(),
+					left.reachable_node_ids().count()
+				);
+				assert!(left.into_iter().eq(expected));
+			}
+		}
+	}
+}
+
+#[test]
+pub fn entry_std_parity() {
+	use btree_slab::generic::map::Entry;
+
+	let mut map: BTreeMap<usize, usize> = (0..100).map(|i| (i * 2, i)).collect();
+
+	for i in 0..200 {
+		assert_eq!(map.entry(i).key(), &i);
+		*map.entry(i).and_modify(|v| *v += 1).or_default() += 1;
+	}
+	map.validate();
+	assert!(map
+		.iter()
+		.all(|(k, v)| *v == if k % 2 == 0 { k / 2 + 2 } else { 1 }));
+
+	match map.entry(1000) {
+		Entry::Vacant(entry) => assert_eq!(entry.into_key(), 1000),
+		Entry::Occupied(_) => panic!("unexpected occupied entry"),
+	}
+
+	for i in 0..200 {
+		let entry = map.entry(i).insert_entry(i);
+		assert_eq!(entry.remove_entry(), (i, i));
+	}
+	map.validate();
+	assert!(map.is_empty());
+}
+
+#[test]
+pub fn pop_first_last() {
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut items = ITEMS;
+	items.shuffle(&mut rng);
+
+	let mut btree: BTreeMap<usize, usize> = items.iter().copied().collect();
+	let mut sorted = items.to_vec();
+	sorted.sort_unstable();
+
+	for i in 0..items.len() {
+		let expected = if i % 3 == 0 {
+			sorted.pop()
+		} else {
+			Some(sorted.remove(0))
+		};
+		let popped = if i % 3 == 0 {
+			btree.pop_last()
+		} else {
+			btree.pop_first()
+		};
+		assert_eq!(popped, expected);
+		btree.validate();
+
+		assert_eq!(btree.first_key_value(), sorted.first().map(|(k, v)| (k, v)));
+		assert_eq!(btree.last_key_value(), sorted.last().map(|(k, v)| (k, v)));
+	}
+
+	assert!(btree.is_empty());
+}
+
+#[test]
+pub fn set_operators() {
+	use btree_slab::BTreeSet;
+	use rand::Rng;
+
+	let mut rng = SmallRng::from_seed(*SEED);
+	for _ in 0..20 {
+		let a: std::collections::BTreeSet<u32> = (0..rng.gen_range(0..200))
+			.map(|_| rng.gen_range(0..300))
+			.collect();
+		let b: std::collections::BTreeSet<u32> = (0..rng.gen_range(0..200))
+			.map(|_| rng.gen_range(0..300))
+			.collect();
+		let sa: BTreeSet<u32> = a.iter().copied().collect();
+		let sb: BTreeSet<u32> = b.iter().copied().collect();
+
+		for (result, expected) in [
+			(&sa - &sb, &a - &b),
+			(&sa ^ &sb, &a ^ &b),
+			(&sa & &sb, &a & &b),
+			(&sa | &sb, &a | &b),
+		] {
+			assert_eq!(result.len(), expected.len());
+			assert!(result.iter().eq(expected.iter()));
+		}
+	}
+}
+
+#[test]
+pub fn rank_select() {
+	use rand::Rng;
+
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+	let mut model = std::collections::BTreeMap::new();
+
+	for _ in 0..2000 {
+		let key = rng.gen_range(0..500);
+		if rng.gen_range(0..3) == 0 {
+			assert_eq!(btree.remove(&key), model.remove(&key));
+		} else {
+			assert_eq!(btree.insert(key, key), model.insert(key, key));
+		}
+	}
+	btree.validate();
+
+	let sorted: Vec<usize> = model.keys().copied().collect();
+	for (i, key) in sorted.iter().enumerate() {
+		assert_eq!(btree.get_nth(i), Some((key, key)));
+		assert_eq!(btree.rank_of(key), Ok(i));
+	}
+	assert_eq!(btree.get_nth(sorted.len()), None);
+
+	for key in 0..510 {
+		assert_eq!(btree.rank_of(&key), sorted.binary_search(&key));
+	}
+
+	let mut right = btree.split_off(&250);
+	btree.validate();
+	right.validate();
+	assert_eq!(right.rank_of(&250), model.get(&250).map(|_| 0).ok_or(0));
+	assert_eq!(
+		right.range(300..).len(),
+		sorted.iter().filter(|k| **k >= 300).count()
+	);
+
+	btree.append(&mut right);
+	btree.validate();
+	for (i, key) in sorted.iter().enumerate() {
+		assert_eq!(btree.get_nth(i), Some((key, key)));
+	}
+}
+
+#[test]
+pub fn shared_arena() {
+	use btree_slab::generic::{self, Node};
+	use slab::Slab;
+
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut slab: Slab<Node<usize, usize>> = Slab::new();
+	let mut roots: Vec<_> = (0..5)
+		.map(|_| Some(generic::BTreeMap::new_in(&mut slab).into_root()))
+		.collect();
+
+	// Interleave the insertions so that the nodes of the trees are mixed in the slab.
+	for _ in 0..20 {
+		for (t, root) in roots.iter_mut().enumerate() {
+			let mut map = generic::BTreeMap::from_root_in(&mut slab, root.take().unwrap());
+			let mut items = ITEMS;
+			items.shuffle(&mut rng);
+			for (key, value) in &items[..10] {
+				map.insert(*key, *value + t);
+			}
+			map.validate();
+			*root = Some(map.into_root());
+		}
+	}
+
+	for (t, root) in roots.into_iter().enumerate() {
+		let map = generic::BTreeMap::from_root_in(&mut slab, root.unwrap());
+		map.validate();
+		assert!(map.iter().all(|(k, v)| ITEMS.contains(&(*k, *v - t))));
+		assert!(map.into_iter().count() > 0);
+	}
+
+	assert!(slab.is_empty());
+}
+
+#[test]
+pub fn arena_release() {
+	use btree_slab::generic::{self, map::ArenaRef, Node};
+	use slab::Slab;
+
+	let mut slab: Slab<Node<usize, usize>> = Slab::new();
+	let roots: Vec<_> = (0..5)
+		.map(|t| {
+			let mut map = generic::BTreeMap::new_in(&mut slab);
+			map.extend(ITEMS.iter().map(|(key, value)| (*key, *value + t)));
+			map.into_root()
+		})
+		.collect();
+
+	// Dropped maps release their nodes.
+	let node_count = slab.len();
+	{
+		let mut map = generic::BTreeMap::new_in(&mut slab);
+		map.extend(ITEMS.iter().cloned());
+	}
+	assert_eq!(slab.len(), node_count);
+
+	let mut roots = roots.into_iter();
+	{
+		let mut map = generic::BTreeMap::from_root_in(&mut slab, roots.next().unwrap());
+		map.remove(&ITEMS[0].0);
+	}
+	assert!(slab.len() < node_count);
+
+	let mut arena = ArenaRef::new(&mut slab);
+	for root in roots {
+		arena.release(root);
+	}
+	drop(arena);
+
+	assert!(slab.is_empty());
+}
+
+#[test]
+pub fn try_insert() {
+	use slab::Slab;
+
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut items = ITEMS;
+	items.shuffle(&mut rng);
+
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::with_container(Slab::with_capacity(6));
+	let capacity = btree.spare_capacity();
+	let mut inserted = 0;
+	for (key, value) in items {
+		match btree.try_insert(key, value) {
+			Ok(None) => inserted += 1,
+			Ok(Some(_)) => panic!("duplicate key"),
+			Err(e) => {
+				assert_eq!((e.key, e.value), (key, value));
+				assert!(e.required > e.available);
+				assert_eq!(e.available, btree.spare_capacity());
+			}
+		}
+		btree.validate();
+		assert_eq!(btree.len(), inserted);
+		assert_eq!(btree.spare_capacity() + btree.stats().nodes, capacity);
+	}
+
+	assert!(inserted > 8 && inserted < items.len());
+}
+
+#[test]
+pub fn fixed_slab() {
+	use btree_slab::{fixed_slab::FixedSlab, generic};
+	use rand::Rng;
+
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut btree: generic::BTreeMap<usize, usize, FixedSlab<generic::Node<usize, usize>, 32>> =
+		generic::BTreeMap::new();
+	let mut model = std::collections::BTreeMap::new();
+
+	for _ in 0..2000 {
+		let key = rng.gen_range(0..300);
+		if rng.gen() {
+			match btree.try_insert(key, key) {
+				Ok(old) => assert_eq!(old, model.insert(key, key)),
+				Err(_) => assert!(!model.contains_key(&key)),
+			}
+		} else {
+			assert_eq!(btree.remove(&key), model.remove(&key));
+		}
+		btree.validate();
+	}
+
+	assert!(btree.iter().eq(model.iter()));
+	btree.clear();
+	assert_eq!(btree.spare_capacity(), 32);
+}
+
+#[test]
+pub fn lower_upper_bound() {
+	use std::ops::Bound;
+
+	let btree: BTreeMap<usize, usize> = (0..1000).map(|i| (i * 2, i)).collect();
+
+	for key in 0..2002 {
+		let included = btree.lower_bound(Bound::Included(&key)).peek_next();
+		assert_eq!(included, btree.range(key..).next());
+
+		let excluded = btree.lower_bound(Bound::Excluded(&key)).peek_next();
+		assert_eq!(
+			excluded,
+			btree.range((Bound::Excluded(key), Bound::Unbounded)).next()
+		);
+
+		let included = btree.upper_bound(Bound::Included(&key)).peek_prev();
+		assert_eq!(included, btree.range(..=key).next_back());
+
+		let excluded = btree.upper_bound(Bound::Excluded(&key)).peek_prev();
+		assert_eq!(excluded, btree.range(..key).next_back());
+	}
+
+	assert_eq!(btree.lower_bound(Bound::Unbounded).peek_prev(), None);
+	assert_eq!(btree.upper_bound(Bound::Unbounded).peek_next(), None);
+
+	let empty: BTreeMap<usize, usize> = BTreeMap::new();
+	assert_eq!(empty.lower_bound(Bound::Included(&0)).peek_next(), None);
+	assert_eq!(empty.upper_bound(Bound::Included(&0)).peek_prev(), None);
+}
+
+#[test]
+pub fn snapshot() {
+	fn assert_send_sync<T: Send + Sync>(_: &T) {}
+
+	let mut btree: BTreeMap<usize, usize> = ITEMS.iter().cloned().collect();
+	let snapshot = btree.clone().freeze();
+	assert_send_sync(&snapshot);
+
+	btree.clear();
+	let readers: Vec<_> = (0..4)
+		.map(|_| {
+			let snapshot = snapshot.clone();
+			std::thread::spawn(move || ITEMS.iter().all(|(key, _)| snapshot.contains_key(key)))
+		})
+		.collect();
+
+	for reader in readers {
+		assert!(reader.join().unwrap())
+	}
+
+	let copy = snapshot.clone();
+	assert!(copy.ptr_eq(&snapshot));
+	let snapshot = snapshot.try_into_map().unwrap_err();
+	drop(copy);
+
+	let len = snapshot.len();
+	let map = snapshot.try_into_map().unwrap();
+	assert_eq!(map.len(), len);
+	map.validate();
+}
+
+#[test]
+pub fn indirect_map() {
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut items = ITEMS;
+	items.shuffle(&mut rng);
+
+	let mut std_map = std::collections::BTreeMap::new();
+	let mut map = btree_slab::IndirectBTreeMap::new();
+
+	for (key, value) in &items {
+		assert_eq!(map.insert(*key, *value), std_map.insert(*key, *value));
+	}
+
+	assert!(map.iter().eq(std_map.iter()));
+	assert!(map
+		.range(100..5000)
+		.rev()
+		.eq(std_map.range(100..5000).rev()));
+	assert_eq!(map.value_slab().len(), std_map.len());
+
+	for (i, (key, _)) in items.iter().enumerate() {
+		if i % 2 == 0 {
+			assert_eq!(map.remove(key), std_map.remove(key));
+		} else if let Some(value) = map.get_mut(key) {
+			*value += 1;
+			*std_map.get_mut(key).unwrap() += 1;
+		}
+	}
+
+	assert!(map.iter().eq(std_map.iter()));
+	assert_eq!(map.value_slab().len(), std_map.len());
+	map.as_map().validate();
+}
+
+#[test]
+pub fn static_collections() {
+	use btree_slab::BTreeSet;
+	use std::sync::Mutex;
+
+	static MAP: Mutex<BTreeMap<usize, usize>> = Mutex::new(BTreeMap::EMPTY);
+	static SET: Mutex<BTreeSet<usize>> = Mutex::new(BTreeSet::EMPTY);
+
+	for (key, value) in &ITEMS {
+		MAP.lock().unwrap().insert(*key, *value);
+		SET.lock().unwrap().insert(*key);
+	}
+
+	let map = MAP.lock().unwrap();
+	map.validate();
+	assert_eq!(map.len(), ITEMS.len());
+	assert!(map.keys().eq(SET.lock().unwrap().iter()));
+}
+
+#[test]
+pub fn indirect_map_boxed_values() {
+	use slab::Slab;
+
+	let mut map: btree_slab::IndirectBTreeMap<usize, Box<[u8]>> =
+		btree_slab::IndirectBTreeMap::with_containers(Slab::new(), Slab::with_capacity(1000));
+
+	for i in 0..1000 {
+		map.insert(i, vec![i as u8; i].into_boxed_slice());
+	}
+
+	for i in 0..1000 {
+		assert_eq!(map.get_deref(&i), Some(&vec![i as u8; i][..]));
+	}
+
+	let (tree, values) = map.into_parts();
+	tree.validate();
+	assert_eq!(values.len(), 1000);
+	for (key, index) in &tree {
+		assert_eq!(values[*index].len(), *key);
+	}
+}
+
+#[test]
+pub fn into_iter_mut_ref() {
+	let mut btree: BTreeMap<usize, usize> = (0..100).map(|i| (i, i)).collect();
+
+	for (key, value) in &mut btree {
+		*value += key;
+	}
+
+	assert!(btree.into_iter().eq((0..100).map(|i| (i, i * 2))));
+}
+
+#[test]
+pub fn from_array() {
+	let btree = BTreeMap::from(ITEMS);
+	let std_map = std::collections::BTreeMap::from(ITEMS);
+	assert!(btree.iter().eq(std_map.iter()));
+	btree.validate();
+
+	let set = btree_slab::BTreeSet::from([5, 3, 8, 3, 1]);
+	assert!(set.into_iter().eq([1, 3, 5, 8]));
+
+	let empty: BTreeMap<usize, usize> = BTreeMap::from([]);
+	assert!(empty.is_empty());
+}
+
+#[test]
+pub fn remove_range() {
+	use std::ops::{Bound, RangeBounds};
+
+	let bounds = |i: usize| match i % 3 {
+		0 => Bound::Included(i * 7),
+		1 => Bound::Excluded(i * 7),
+		_ => Bound::Unbounded,
+	};
+
+	for len in [0, 1, 8, 50, 1000] {
+		let std_map: std::collections::BTreeMap<usize, usize> =
+			(0..len).map(|i| (i * 2, i)).collect();
+		for start in 0..40 {
+			for end in start..40 {
+				let range = (bounds(start * 5), bounds(end * 5 + 1));
+				let mut btree: BTreeMap<usize, usize> = std_map.clone().into_iter().collect();
+				let removed = btree.remove_range(range);
+
+				btree.validate();
+				removed.validate();
+				assert!(removed.iter().eq(std_map.range(range)));
+				assert!(btree
+					.iter()
+					.eq(std_map.iter().filter(|(k, _)| !range.contains(*k))));
+			}
+		}
+	}
+}
+
+#[test]
+pub fn entry_handles() {
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+	let mut handles = Vec::new();
+	for (key, value) in &ITEMS {
+		if btree.insert(*key, *value).is_none() {
+			handles.push(btree.handle(*key).unwrap());
+		}
+	}
+
+	let mut tail = btree.split_off(&5000);
+	btree.append(&mut tail);
+	btree.retain(|key, _| key % 3 != 0);
+
+	for handle in &mut handles {
+		let key = *handle.key();
+		match btree.resolve(handle) {
+			Some(addr) => {
+				assert_eq!(btree.item(addr).unwrap().key(), &key);
+				assert_eq!(handle.cached_address(), addr);
+			}
+			None => assert_eq!(key % 3, 0),
+		}
+	}
+
+	assert!(btree.handle(usize::MAX).is_none());
+}
+
+#[test]
+pub fn entry_handles_replaced_key() {
+	use btree_slab::generic::map::BTreeExtMut;
+
+	let mut map: BTreeMap<usize, usize> = (0..10).map(|i| (i, i)).collect();
+	let mut handle = map.handle(4).unwrap();
+	map.remove(&5);
+	let addr = map.address_of(&4).unwrap();
+	map.replace_key_at(addr, 5);
+
+	assert_eq!(map.resolve(&mut handle), None);
+}
+
+#[test]
+pub fn entry_handles_other_map() {
+	let a: BTreeMap<usize, usize> = (0..10).map(|i| (i, i)).collect();
+	let b: BTreeMap<usize, usize> = (10..20).map(|i| (i, i)).collect();
+	let mut handle = a.handle(3).unwrap();
+
+	assert_eq!(b.resolve(&mut handle), None);
+
+	let c: BTreeMap<usize, usize> = (1..11).map(|i| (i, i)).collect();
+	let mut handle = a.handle(3).unwrap();
+	let addr = c.resolve(&mut handle).unwrap();
+	assert_eq!(c.item(addr).unwrap().key(), &3);
+}
+
+#[test]
+pub fn map_values() {
+	let mut map: BTreeMap<usize, usize> = (0..1000).map(|i| (i, i)).collect();
+	let mut seen = Vec::new();
+	map.map_values(|k, v| {
+		seen.push(*k);
+		v * 2
+	});
+	map.validate();
+	assert_eq!(seen, (0..1000).collect::<Vec<_>>());
+	assert!(map.iter().all(|(k, v)| *v == k * 2));
+
+	let strings: BTreeMap<usize, String> = map.clone().map_values_into(|_, v| v.to_string());
+	strings.validate();
+	assert_eq!(strings.len(), 1000);
+	assert_eq!(strings[&500], "1000");
+
+	let halves: Result<BTreeMap<usize, usize>, usize> =
+		map.clone().try_map_values(|_, v| Ok(v / 2));
+	assert!(halves.unwrap().into_iter().all(|(k, v)| k == v));
+
+	let mut calls = 0;
+	let err: Result<BTreeMap<usize, usize>, usize> = map.try_map_values(|k, v| {
+		calls += 1;
+		if *k == 300 {
+			Err(v)
+		} else {
+			Ok(v)
+		}
+	});
+	assert_eq!(err.unwrap_err(), 600);
+	assert_eq!(calls, 301);
+
+	let mut map: BTreeMap<usize, usize> = (0..100).map(|i| (i, i)).collect();
+	let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+		map.map_values(|k, v| if *k == 50 { panic!() } else { v })
+	}));
+	assert!(result.is_err());
+	assert!(map.is_empty());
+	map.insert(1, 1);
+	map.validate();
+}
+
+#[test]
+pub fn set_range_removal() {
+	use btree_slab::BTreeSet;
+
+	let mut set: BTreeSet<usize> = (0..1000).collect();
+	let drained: Vec<_> = set.drain_filter_range(100..200, |v| v % 2 == 0).collect();
+	assert!(drained.into_iter().eq((100..200).step_by(2)));
+	assert_eq!(set.len(), 950);
+
+	let mut drain = set.drain_filter_range(500.., |_| true);
+	assert_eq!(drain.next(), Some(500));
+	drain.keep_rest();
+	assert_eq!(set.len(), 949);
+
+	let removed = set.remove_range(300..=700);
+	assert!(removed
+		.iter()
+		.copied()
+		.eq((300..=700).filter(|v| *v != 500)));
+	assert!(set.iter().copied().eq((0..300)
+		.filter(|v| !(100..200).contains(v) || v % 2 == 1)
+		.chain(701..1000)));
+
+	let upper = set.split_off(&150);
+	assert!(set.iter().all(|v| *v < 150));
+	assert!(upper.iter().all(|v| *v >= 150));
+	assert_eq!(set.len() + upper.len(), 549);
+}
+
+#[test]
+pub fn set_replace_range() {
+	use btree_slab::BTreeSet;
+	use std::ops::Bound;
+
+	let mut set: BTreeSet<usize> = (0..1000).map(|v| v * 2).collect();
+
+	let removed = set.replace_range(100..=600, (100..=600).step_by(3));
+	assert!(removed.iter().copied().eq((100..=600).step_by(2)));
+
+	let removed = set.replace_range((Bound::Excluded(1000), Bound::Unbounded), 1001..1005);
+	assert!(removed.iter().copied().eq((1002..2000).step_by(2)));
+
+	let removed = set.replace_range(..50, std::iter::empty());
+	assert_eq!(removed.len(), 25);
+
+	let expected: Vec<_> = (50..100)
+		.step_by(2)
+		.chain((100..=600).step_by(3))
+		.chain((602..=1000).step_by(2))
+		.chain(1001..1005)
+		.collect();
+	assert!(set.iter().copied().eq(expected));
+}
+
+#[test]
+pub fn node_search() {
+	use btree_slab::{
+		generic::node::Item,
+		utils::{binary_search_min, linear_search_min},
+	};
+
+	for len in 0..10 {
+		let items: Vec<Item<usize, ()>> = (0..len).map(|i| Item::new(i * 2 + 1, ())).collect();
+		for key in 0..(len * 2 + 2) {
+			let expected = key.div_ceil(2).min(len).checked_sub(1);
+			assert_eq!(binary_search_min(&items, &key), expected);
+			assert_eq!(linear_search_min(&items, &key), expected);
 		}
 	}
+}
 
-	for (key, value) in &ITEMS {
-		btree.update(*key, |current_value| match current_value {
-			Some(current_value) => {
-				if current_value % 2 == 0 {
-					(None, ())
-				} else {
-					(Some(10000 - *value), ())
-				}
+#[cfg(feature = "rayon")]
+#[test]
+pub fn par_iter() {
+	use rayon::prelude::*;
+
+	for n in [0, 1, 8, 100, 10000] {
+		let mut map: BTreeMap<usize, usize> = (0..n).map(|i| (i, i)).collect();
+
+		let collected: Vec<_> = map.par_iter().map(|(k, v)| (*k, *v)).collect();
+		assert!(collected.into_iter().eq(map.iter().map(|(k, v)| (*k, *v))));
+		assert_eq!((&map).into_par_iter().count(), n);
+		assert_eq!(
+			map.par_iter()
+				.find_first(|(k, _)| **k >= 50)
+				.map(|(k, _)| *k),
+			(50 < n).then_some(50)
+		);
+
+		(&mut map).into_par_iter().for_each(|(k, v)| *v += k);
+		assert!(map.iter().all(|(k, v)| *v == 2 * k));
+
+		let values: Vec<_> = map.into_par_iter().map(|(_, v)| v).collect();
+		assert!(values.into_iter().eq((0..n).map(|i| 2 * i)));
+	}
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+pub fn into_par_iter_drops() {
+	use rayon::prelude::*;
+	use std::sync::Arc;
+
+	let counter = Arc::new(());
+	for n in [0, 1, 8, 100, 10000] {
+		let map: BTreeMap<usize, Arc<()>> = (0..n).map(|i| (i, counter.clone())).collect();
+		assert_eq!(
+			map.into_par_iter().filter(|(k, _)| k % 3 == 0).count(),
+			n.div_ceil(3)
+		);
+		assert_eq!(Arc::strong_count(&counter), 1);
+
+		let map: BTreeMap<usize, Arc<()>> = (0..n).map(|i| (i, counter.clone())).collect();
+		let found = map.into_par_iter().find_any(|(k, _)| *k == n / 2);
+		assert_eq!(found.as_ref().map(|(k, _)| *k), (n > 0).then_some(n / 2));
+		drop(found);
+		assert_eq!(Arc::strong_count(&counter), 1);
+	}
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+pub fn into_iter_into_par_iter() {
+	use rayon::prelude::*;
+	use std::sync::Arc;
+
+	let counter = Arc::new(());
+	for n in [1, 8, 9, 100, 1000] {
+		for front in [0, 1, 7, 8, 9, 50, n / 2] {
+			for back in [0, 1, 7, 8, 9, 50, n / 2] {
+				let make = || -> BTreeMap<usize, Arc<()>> {
+					(0..n).map(|i| (i, counter.clone())).collect()
+				};
+
+				let mut iter = make().into_iter();
+				let front = front.min(n);
+				let back = back.min(n - front);
+				iter.by_ref().take(front).for_each(drop);
+				iter.by_ref().rev().take(back).for_each(drop);
+				let keys: Vec<usize> = iter.into_par_iter().map(|(k, _)| k).collect();
+				assert!(keys.into_iter().eq(front..(n - back)));
+				assert_eq!(Arc::strong_count(&counter), 1);
+
+				let mut iter = make().into_iter();
+				iter.by_ref().take(front).for_each(drop);
+				iter.by_ref().rev().take(back).for_each(drop);
+				let found = iter.into_par_iter().find_any(|(k, _)| *k == n / 2);
+				assert_eq!(
+					found.as_ref().map(|(k, _)| *k),
+					(front <= n / 2 && n / 2 < n - back).then_some(n / 2)
+				);
+				drop(found);
+				assert_eq!(Arc::strong_count(&counter), 1);
 			}
-			None => (Some(*value), ()),
-		});
+		}
+	}
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+pub fn par_iter_mut_subtrees() {
+	use rayon::prelude::*;
+
+	let mut map: BTreeMap<usize, usize> = ITEMS.iter().cloned().collect();
+	map.par_iter_mut().for_each(|(k, v)| *v = k * 2);
+	assert!(map.iter().all(|(k, v)| *v == k * 2));
+
+	let keys: Vec<_> = map.par_iter_mut().map(|(k, _)| *k).collect();
+	assert!(keys.into_iter().eq(map.keys().copied()));
+	map.validate();
+}
+
+#[cfg(feature = "checksum")]
+#[test]
+pub fn checksum_checked_on_access() {
+	use std::{
+		cell::Cell,
+		hash::{Hash, Hasher},
+		panic::{catch_unwind, AssertUnwindSafe},
+	};
+
+	/// Value that can be modified without going through the map.
+	struct Shared(Cell<u32>);
+
+	impl Hash for Shared {
+		fn hash<H: Hasher>(&self, state: &mut H) {
+			self.0.get().hash(state)
+		}
+	}
+
+	let mut map: BTreeMap<u32, Shared> = (0..100).map(|i| (i, Shared(Cell::new(i)))).collect();
+	map.seal();
+	assert_eq!(map.get(&42).map(|v| v.0.get()), Some(42));
+	assert!(map.iter().all(|(k, v)| *k == v.0.get()));
 
+	map.get(&42).unwrap().0.set(0);
+	assert!(map.verify().is_err());
+	let error = catch_unwind(AssertUnwindSafe(|| map.get(&42).is_some())).unwrap_err();
+	assert!(error
+		.downcast_ref::<String>()
+		.unwrap()
+		.ends_with("does not match its checksum"));
+	assert!(catch_unwind(AssertUnwindSafe(|| map.iter().count())).is_err());
+
+	map.seal();
+	assert_eq!(map.get(&42).map(|v| v.0.get()), Some(0));
+
+	map.values_mut().for_each(|v| *v.0.get_mut() += 1);
+	assert!(!map.is_sealed());
+	assert_eq!(map.get(&42).map(|v| v.0.get()), Some(1));
+}
+
+#[cfg(feature = "metrics")]
+#[test]
+pub fn metrics_operations() {
+	use btree_slab::generic::map::AtomicCounters;
+	use std::sync::Arc;
+
+	let counters = Arc::new(AtomicCounters::default());
+	let mut map: BTreeMap<usize, usize> = BTreeMap::new();
+	map.set_metrics_sink(counters.clone());
+
+	map.insert(1, 1);
+	map.replace(2, 2);
+	*map.entry(3).or_insert(0) += 3;
+	map.insert_at_key(4, 4);
+	assert_eq!(counters.inserts(), 4);
+
+	assert!(map.contains_key(&1));
+	assert_eq!(map.get_key_value(&2), Some((&2, &2)));
+	assert_eq!(counters.gets(), 2);
+
+	map.remove_entry(&1);
+	map.take(&2);
+	map.pop_first();
+	map.pop_last();
+	map.pop_last();
+	assert_eq!(counters.removes(), 4);
+	assert_eq!(counters.max_depth(), 1);
+}
+
+#[test]
+pub fn partial_ord_map_incomparable() {
+	use btree_slab::{generic::partial_ord_map::IncomparableKey, PartialOrdMap};
+	use std::cmp::Ordering;
+
+	let mut map = PartialOrdMap::new();
+	for i in 0..100 {
+		assert_eq!(map.insert(i as f64 / 4.0, i), Ok(None));
+	}
+
+	assert_eq!(map.insert(f64::NAN, 100), Err(IncomparableKey));
+	assert_eq!(map.get(&f64::NAN), Err(IncomparableKey));
+	assert_eq!(map.get_mut(&f64::NAN), Err(IncomparableKey));
+	assert_eq!(map.contains_key(&f64::NAN), Err(IncomparableKey));
+	assert_eq!(map.remove(&f64::NAN), Err(IncomparableKey));
+	assert_eq!(map.get(&2.5), Ok(Some(&10)));
+	assert_eq!(map.remove(&2.5), Ok(Some(10)));
+	assert_eq!(map.len(), 99);
+
+	/// Key comparable with itself, but not with the keys of another class.
+	#[derive(PartialEq, Debug)]
+	struct Classed(u8, u32);
+
+	impl PartialOrd for Classed {
+		fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+			if self.0 == other.0 {
+				self.1.partial_cmp(&other.1)
+			} else {
+				None
+			}
+		}
+	}
+
+	let mut map = PartialOrdMap::new();
+	for i in 0..100 {
+		assert_eq!(map.insert(Classed(0, i), i), Ok(None));
+	}
+
+	assert_eq!(map.insert(Classed(1, 50), 0), Err(IncomparableKey));
+	assert_eq!(map.get(&Classed(1, 50)), Err(IncomparableKey));
+	assert_eq!(map.get(&Classed(0, 50)), Ok(Some(&50)));
+	assert_eq!(map.len(), 100);
+	map.as_map().validate();
+}
+
+#[test]
+pub fn with_capacity() {
+	let mut keys: Vec<usize> = (0..5000).collect();
+	let mut rng = SmallRng::from_seed(*SEED);
+
+	for shuffle in [false, true] {
+		if shuffle {
+			keys.shuffle(&mut rng);
+		}
+
+		let mut btree: BTreeMap<usize, usize> = BTreeMap::with_capacity(keys.len());
+		let capacity = btree.stats().slab_capacity;
+		for &key in &keys {
+			btree.insert(key, key);
+		}
+		btree.validate();
+		assert_eq!(btree.stats().slab_capacity, capacity);
+
+		let additional = keys.len() * 2;
+		btree.reserve(additional);
+		let capacity = btree.stats().slab_capacity;
+		for &key in &keys {
+			btree.insert(key + keys.len(), key);
+			btree.insert(key + keys.len() * 2, key);
+		}
 		btree.validate();
+		assert_eq!(btree.len(), keys.len() * 3);
+		assert_eq!(btree.stats().slab_capacity, capacity);
 	}
+}
 
-	for (key, value) in &ITEMS {
-		let shoud_be_present = *key % 2 == 1 || *value % 2 == 1;
+#[test]
+pub fn range_bounds() {
+	use std::ops::Bound::{self, Excluded, Included, Unbounded};
 
-		match btree.get(key) {
-			Some(current_value) => {
-				if !shoud_be_present {
-					panic!("binding {}:{} should not be present", *key, *value);
+	for len in [0usize, 1, 8, 9, 50] {
+		// Present keys are even, so odd keys are absent.
+		let mut btree: BTreeMap<usize, usize> = (0..len).map(|i| (i * 2, i)).collect();
+		let model: std::collections::BTreeMap<usize, usize> =
+			(0..len).map(|i| (i * 2, i)).collect();
+
+		let max = len * 2 + 2;
+		for a in 0..max {
+			for b in a..max {
+				let bounds: [(Bound<usize>, Bound<usize>); 5] = [
+					(Excluded(a), Included(b)),
+					(Included(a), Excluded(b)),
+					(Excluded(a), Excluded(b)),
+					(Excluded(a), Unbounded),
+					(Unbounded, Excluded(b)),
+				];
+
+				for range in bounds {
+					if range == (Excluded(a), Excluded(a)) {
+						assert!(btree.try_range(range).is_err());
+						assert!(btree.try_range_mut(range).is_err());
+						continue;
+					}
+
+					assert!(btree.try_range(range).unwrap().eq(model.range(range)));
+					assert!(btree
+						.try_range(range)
+						.unwrap()
+						.rev()
+						.eq(model.range(range).rev()));
+					assert_eq!(
+						btree.try_range_mut(range).unwrap().count(),
+						model.range(range).count()
+					);
 				}
 
-				if *key % 2 == 0 && *value % 2 == 1 {
-					assert_eq!(*current_value, 10000 - *value)
-				} else {
-					assert_eq!(*current_value, *value)
+				if a < b {
+					assert!(btree.try_range(b..a).is_err());
+					assert!(btree.try_range_mut((Included(b), Excluded(a))).is_err());
 				}
 			}
-			None => {
-				if shoud_be_present {
-					panic!("binding {}:{} should be present", *key, *value);
-				}
+		}
+	}
+}
+
+#[test]
+pub fn merge_with() {
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut keys: Vec<usize> = (0..2000).collect();
+	keys.shuffle(&mut rng);
+
+	let (left, right) = keys.split_at(1200);
+	let a: BTreeMap<usize, usize> = left.iter().map(|&k| (k, 1)).collect();
+	let b: BTreeMap<usize, usize> = right.iter().chain(&left[..300]).map(|&k| (k, 10)).collect();
+
+	let mut expected: std::collections::BTreeMap<usize, usize> =
+		a.iter().map(|(&k, &v)| (k, v)).collect();
+	for (&k, &v) in &b {
+		*expected.entry(k).or_insert(0) += v;
+	}
+
+	let merged = a.merge_with(b, |_, x, y| x + y);
+	merged.validate();
+	assert!(merged.iter().eq(expected.iter()));
+
+	let empty: BTreeMap<usize, usize> = BTreeMap::new();
+	let merged = merged.merge_with(empty, |_, _, _| unreachable!());
+	assert!(merged.iter().eq(expected.iter()));
+}
+
+#[test]
+pub fn try_insert_vacant() {
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+
+	for (key, value) in &ITEMS {
+		let present = btree.contains_key(key);
+		match btree.try_insert_vacant(*key, *value) {
+			Ok(v) => {
+				assert!(!present);
+				assert_eq!(*v, *value);
+				*v += 1;
+			}
+			Err(err) => {
+				assert!(present);
+				assert_eq!(err.entry.key(), key);
+				assert_eq!(err.value, *value);
+				assert_eq!(
+					err.to_string(),
+					format!(
+						"failed to insert {}, key {} already exists with value {}",
+						value,
+						key,
+						err.entry.get()
+					)
+				);
 			}
 		}
 	}
+
+	btree.validate();
+	let mut expected = std::collections::BTreeMap::new();
+	for (key, value) in &ITEMS {
+		expected.entry(*key).or_insert(*value + 1);
+	}
+	assert!(btree.iter().eq(expected.iter()));
 }
 
 #[test]
-pub fn drain_all() {
+pub fn compare_map() {
+	use btree_slab::{generic::compare_map::ReverseOrder, CompareMap};
+
+	let mut map: CompareMap<usize, usize, ReverseOrder> = CompareMap::new();
+	let mut model = std::collections::BTreeMap::new();
+	for (key, value) in &ITEMS {
+		assert_eq!(
+			map.insert(*key, *value),
+			model.insert(std::cmp::Reverse(*key), *value)
+		);
+	}
+
+	map.as_map().validate();
+	assert_eq!(map.len(), model.len());
+	assert!(map
+		.iter()
+		.map(|(k, v)| (*k, *v))
+		.eq(model.iter().map(|(k, v)| (k.0, *v))));
+	assert_eq!(
+		map.first_key_value().map(|(k, _)| *k),
+		model.keys().next().map(|k| k.0)
+	);
+
+	for (key, _) in ITEMS.iter().step_by(2) {
+		assert_eq!(map.remove(key), model.remove(&std::cmp::Reverse(*key)));
+	}
+
+	map.as_map().validate();
+	assert!(map
+		.iter()
+		.map(|(k, v)| (*k, *v))
+		.eq(model.iter().map(|(k, v)| (k.0, *v))));
+}
+
+#[test]
+pub fn multimap() {
+	use btree_slab::BTreeMultiMap;
+	use rand::Rng;
+
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut map: BTreeMultiMap<usize, usize> = BTreeMultiMap::new();
+	// Model: values of each key, in insertion order.
+	let mut model: std::collections::BTreeMap<usize, Vec<usize>> =
+		std::collections::BTreeMap::new();
+
+	for i in 0..5000 {
+		let key = rng.gen_range(0..200);
+		match rng.gen_range(0..10) {
+			0..=5 => {
+				map.insert(key, i);
+				model.entry(key).or_default().push(i);
+			}
+			6..=8 => {
+				let expected = match model.get_mut(&key) {
+					Some(values) if !values.is_empty() => Some(values.remove(0)),
+					_ => None,
+				};
+				assert_eq!(map.remove_one(&key), expected);
+			}
+			_ => {
+				let expected = model.remove(&key).map(|v| v.len()).unwrap_or(0);
+				assert_eq!(map.remove_all(&key), expected);
+			}
+		}
+
+		if i % 500 == 0 {
+			map.as_map().validate();
+		}
+	}
+
+	map.as_map().validate();
+	let entries = |r: std::ops::RangeInclusive<usize>| {
+		model
+			.range(r)
+			.flat_map(|(k, values)| values.iter().map(move |v| (*k, *v)))
+			.collect::<Vec<_>>()
+	};
+
+	assert!(map.iter().map(|(k, v)| (*k, *v)).eq(entries(0..=200)));
+	assert_eq!(map.len(), entries(0..=200).len());
+	for key in 0..200 {
+		let values = model.get(&key).cloned().unwrap_or_default();
+		assert!(map.get_all(&key).copied().eq(values.iter().copied()));
+		assert_eq!(map.count(&key), values.len());
+		assert_eq!(map.get(&key), values.first());
+		assert!(map
+			.range(key..=key + 10)
+			.map(|(k, v)| (*k, *v))
+			.eq(entries(key..=key + 10)));
+		assert!(map
+			.range(key..=key + 10)
+			.rev()
+			.map(|(k, v)| (*k, *v))
+			.eq(entries(key..=key + 10).into_iter().rev()));
+	}
+}
+
+#[test]
+pub fn occupied_entry_navigation() {
+	use btree_slab::generic::map::Entry;
+
+	let mut btree: BTreeMap<usize, usize> = (0..500).map(|i| (i * 2, i)).collect();
+
+	// Walk forward from the middle to the end.
+	let mut entry = match btree.entry(500) {
+		Entry::Occupied(entry) => entry,
+		Entry::Vacant(_) => panic!("missing key"),
+	};
+	let mut visited = vec![*entry.key()];
+	let last = loop {
+		*entry.get_mut() += 1000;
+		entry = match entry.next() {
+			Ok(next) => next,
+			Err(last) => break last,
+		};
+		visited.push(*entry.key());
+	};
+	assert_eq!(last.key(), &998);
+	assert!(visited.into_iter().eq((250..500).map(|i| i * 2)));
+
+	// Walk backward, removing every other entry.
+	let mut entry = last;
+	loop {
+		entry = match entry.prev() {
+			Ok(prev) => prev,
+			Err(_) => break,
+		};
+
+		if *entry.key() % 4 == 0 {
+			let key = *entry.key();
+			entry.remove();
+			entry = match btree.entry(key + 2) {
+				Entry::Occupied(entry) => entry,
+				Entry::Vacant(_) => panic!("missing key"),
+			};
+		}
+	}
+
+	btree.validate();
+	assert!(btree.iter().map(|(k, v)| (*k, *v)).eq((0..500)
+		.filter(|i| i % 2 == 1)
+		.map(|i| (i * 2, if i >= 250 { i + 1000 } else { i }))));
+}
+
+#[test]
+pub fn get_mut_borrowed() {
+	let mut map: BTreeMap<String, usize> = BTreeMap::new();
+	map.insert("a".to_string(), 1);
+	map.insert("b".to_string(), 2);
+
+	*map.get_mut("a").unwrap() += 10;
+	map["b"] += 20;
+	assert!(map.get_mut("c").is_none());
+
+	assert_eq!(map.get("a"), Some(&11));
+	assert_eq!(map.get("b"), Some(&22));
+}
+
+#[test]
+pub fn debug_impls() {
+	let std_map: std::collections::BTreeMap<usize, usize> = ITEMS.iter().cloned().collect();
+	let btree: BTreeMap<usize, usize> = ITEMS.iter().cloned().collect();
+
+	assert_eq!(format!("{:?}", btree), format!("{:?}", std_map));
+	assert_eq!(
+		format!("{:?}", btree.keys()),
+		format!("{:?}", std_map.keys())
+	);
+	assert_eq!(
+		format!("{:?}", btree.values()),
+		format!("{:?}", std_map.values())
+	);
+	assert_eq!(
+		format!("{:?}", btree.range(10..50)),
+		format!("{:?}", std_map.range(10..50))
+	);
+
+	let mut iter = btree.iter();
+	let mut std_iter = std_map.iter();
+	iter.next();
+	std_iter.next();
+	iter.next_back();
+	std_iter.next_back();
+	assert_eq!(format!("{:?}", iter), format!("{:?}", std_iter));
+
+	let std_set: std::collections::BTreeSet<usize> = std_map.keys().cloned().collect();
+	let set: btree_slab::BTreeSet<usize> = std_set.iter().cloned().collect();
+	assert_eq!(format!("{:?}", set), format!("{:?}", std_set));
+	assert_eq!(
+		format!("{:?}", set.iter()),
+		format!("{:?}", std_set.iter().collect::<Vec<_>>())
+	);
+
+	let multiset: btree_slab::BTreeMultiSet<usize> = [1, 2, 2, 3].into_iter().collect();
+	assert_eq!(format!("{:?}", multiset), "{1: 1, 2: 2, 3: 1}");
+	assert_eq!(format!("{:?}", multiset.iter()), "[1, 2, 2, 3]");
+}
+
+/// Checks the statistics of the map against a walk of the tree.
+fn check_stats(btree: &BTreeMap<usize, usize>) {
+	use btree_slab::generic::{
+		map::{Stats, M},
+		Node,
+	};
+
+	let stats = btree.stats();
+	let mut expected = Stats {
+		items: btree.len(),
+		slab_len: stats.slab_len,
+		slab_capacity: stats.slab_capacity,
+		..Stats::default()
+	};
+
+	let mut stack: Vec<(usize, usize)> = btree.root_id().map(|id| (id, 1)).into_iter().collect();
+	while let Some((id, depth)) = stack.pop() {
+		let node = btree.node(id);
+		expected.nodes += 1;
+		expected.depth = expected.depth.max(depth);
+		match node {
+			Node::Leaf(_) => {
+				expected.leaves += 1;
+				expected.leaf_items += node.item_count()
+			}
+			Node::Internal(_) => {
+				expected.internal_nodes += 1;
+				expected.internal_items += node.item_count()
+			}
+		}
+		stack.extend(node.children().map(|child| (child, depth + 1)))
+	}
+
+	assert_eq!(stats, expected);
+	assert_eq!(stats.leaf_items + stats.internal_items, btree.len());
+	assert_eq!(stats.slab_len, stats.nodes);
+	assert!(stats.slab_len <= stats.slab_capacity);
+
+	if stats.leaves > 0 {
+		assert_eq!(
+			stats.leaf_fill_factor(),
+			stats.leaf_items as f64 / (stats.leaves * M) as f64
+		);
+	}
+	if stats.internal_nodes > 0 {
+		assert_eq!(
+			stats.internal_fill_factor(),
+			stats.internal_items as f64 / (stats.internal_nodes * (M - 1)) as f64
+		);
+	}
+	assert!(stats.fill_factor() > 0.0 || btree.is_empty());
+	assert!(stats.fill_factor() <= 1.0);
+	assert_eq!(
+		stats.slab_utilization(),
+		stats.slab_len as f64 / stats.slab_capacity.max(1) as f64
+	);
+}
+
+#[test]
+pub fn stats() {
 	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+	assert_eq!(btree.stats(), Default::default());
+	assert_eq!(btree.stats().fill_factor(), 0.0);
 
 	for (key, value) in &ITEMS {
 		btree.insert(*key, *value);
+		check_stats(&btree);
 	}
 
-	let mut drained = btree.drain_filter(|_, _| true).collect::<Vec<_>>();
-	assert!(btree.is_empty());
+	let stats = btree.stats();
+	assert_eq!(stats.items, 100);
+	assert_eq!(stats.depth, btree.depth());
+	assert!(stats.depth > 1);
 
-	while let Some((key, value)) = btree.pop_first() {
-		drained.push((key, value));
+	let mut items = ITEMS;
+	let mut rng = SmallRng::from_seed(*SEED);
+	items.shuffle(&mut rng);
+	for (key, _) in &items {
+		btree.remove(key);
+		check_stats(&btree);
 	}
 
-	assert_eq!(drained.len(), ITEMS.len());
+	assert_eq!(btree.stats().nodes, 0);
+}
 
-	for (key, value) in &ITEMS {
-		assert!(drained.contains(&(*key, *value)));
+#[test]
+pub fn multiset() {
+	use btree_slab::BTreeMultiSet;
+	use rand::Rng;
+
+	let mut set: BTreeMultiSet<u32> = BTreeMultiSet::new();
+	let mut reference: std::collections::BTreeMap<u32, usize> = std::collections::BTreeMap::new();
+	let mut rng = SmallRng::from_seed(*SEED);
+
+	for _ in 0..2000 {
+		let value = rng.gen_range(0..50);
+		match rng.gen_range(0..6) {
+			0..=2 => {
+				let count = reference.entry(value).or_insert(0);
+				assert_eq!(set.insert(value), *count);
+				*count += 1
+			}
+			3 | 4 => {
+				let present = match reference.get_mut(&value) {
+					Some(1) => reference.remove(&value).is_some(),
+					Some(count) => {
+						*count -= 1;
+						true
+					}
+					None => false,
+				};
+				assert_eq!(set.remove_one(&value), present);
+			}
+			_ => {
+				assert_eq!(
+					set.remove_all(&value),
+					reference.remove(&value).unwrap_or(0)
+				);
+			}
+		}
+
+		assert_eq!(
+			set.count(&value),
+			reference.get(&value).copied().unwrap_or(0)
+		);
+		assert_eq!(set.contains(&value), reference.contains_key(&value));
+		assert_eq!(set.len(), reference.values().sum::<usize>());
+		assert_eq!(set.distinct_len(), reference.len());
+	}
+
+	let flattened: Vec<u32> = reference
+		.iter()
+		.flat_map(|(value, count)| std::iter::repeat_n(*value, *count))
+		.collect();
+	assert!(set.iter().copied().eq(flattened.iter().copied()));
+	assert!(set
+		.iter()
+		.rev()
+		.copied()
+		.eq(flattened.iter().rev().copied()));
+	assert_eq!(set.iter().len(), flattened.len());
+	assert!(set
+		.counts()
+		.map(|(value, count)| (*value, count))
+		.eq(reference.iter().map(|(value, count)| (*value, *count))));
+
+	let mut iter = set.iter();
+	let mut reference_iter = flattened.iter();
+	while let Some(value) = iter.next() {
+		assert_eq!(Some(value), reference_iter.next());
+		assert_eq!(iter.next_back(), reference_iter.next_back());
+	}
+	assert_eq!(reference_iter.next(), None);
+
+	let collected: BTreeMultiSet<u32> = flattened.iter().copied().collect();
+	assert_eq!(collected, set);
+}
+
+#[test]
+pub fn extend_unique() {
+	let mut btree: BTreeMap<usize, usize> = BTreeMap::new();
+	assert!(btree.extend_unique(ITEMS.iter().copied()).is_ok());
+	btree.validate();
+	assert!(ITEMS
+		.iter()
+		.all(|(key, value)| btree.get(key) == Some(value)));
+
+	let new_items = [(1, 1), (2, 2), (ITEMS[42].0, 0), (3, 3)];
+	let mut iter = new_items.iter().copied();
+	let error = btree.extend_unique(iter.by_ref()).unwrap_err();
+	assert_eq!((error.key, error.value), (ITEMS[42].0, 0));
+	assert_eq!(iter.next(), Some((3, 3)));
+
+	btree.validate();
+	assert_eq!(btree.len(), ITEMS.len() + 2);
+	assert_eq!(btree.get(&1), Some(&1));
+	assert_eq!(btree.get(&2), Some(&2));
+	assert_eq!(btree.get(&ITEMS[42].0), Some(&ITEMS[42].1));
+	assert_eq!(btree.get(&3), None);
+
+	let error = btree.extend_unique([(4, 4), (4, 5)]).unwrap_err();
+	assert_eq!((error.key, error.value), (4, 5));
+	assert_eq!(btree.get(&4), Some(&4));
+}
+
+#[test]
+pub fn subset_of_keys() {
+	use btree_slab::BTreeSet;
+	use rand::Rng;
+
+	let mut rng = SmallRng::from_seed(*SEED);
+	for _ in 0..500 {
+		let set_len = rng.gen_range(0..40);
+		let map_len = rng.gen_range(0..40);
+		let set: BTreeSet<u32> = (0..set_len).map(|_| rng.gen_range(0..60)).collect();
+		let map: BTreeMap<u32, ()> = (0..map_len).map(|_| (rng.gen_range(0..60), ())).collect();
+
+		assert_eq!(
+			set.is_subset_of_keys(&map),
+			set.iter().all(|value| map.contains_key(value))
+		);
+		assert_eq!(
+			set.is_superset_of_keys(&map),
+			map.keys().all(|key| set.contains(key))
+		);
 	}
+
+	let map: BTreeMap<u32, ()> = (0..100).map(|i| (i * 2, ())).collect();
+	let evens: BTreeSet<u32> = (0..50).map(|i| i * 4).collect();
+	assert!(evens.is_subset_of_keys(&map));
+	assert!(!evens.is_superset_of_keys(&map));
+
+	let all: BTreeSet<u32> = (0..200).collect();
+	assert!(!all.is_subset_of_keys(&map));
+	assert!(all.is_superset_of_keys(&map));
+
+	let empty: BTreeSet<u32> = BTreeSet::new();
+	assert!(empty.is_subset_of_keys(&map));
+	assert!(empty.is_superset_of_keys(&BTreeMap::<u32, ()>::new()));
 }
 
 const ITEMS: [(usize, usize); 100] = [
@@ -313,3 +2681,49 @@ const ITEMS: [(usize, usize); 100] = [
 	(1553, 5964),
 	(4493, 3677),
 ];
+
+#[cfg(feature = "bplus")]
+#[test]
+pub fn bplus_map() {
+	use btree_slab::BPlusTreeMap;
+	use rand::Rng;
+
+	let mut rng = SmallRng::from_seed(*SEED);
+	let mut map: BPlusTreeMap<u32, u32> = BPlusTreeMap::new();
+	let mut reference = std::collections::BTreeMap::new();
+
+	for _ in 0..5000 {
+		let key = rng.gen_range(0..500);
+		if rng.gen_bool(0.6) {
+			assert_eq!(map.insert(key, key * 2), reference.insert(key, key * 2));
+		} else {
+			assert_eq!(map.remove(&key), reference.remove(&key));
+		}
+
+		assert_eq!(map.check_invariants(), Ok(()));
+		assert_eq!(map.get(&key), reference.get(&key));
+	}
+
+	assert!(map.iter().eq(reference.iter()));
+	assert!(map.iter().rev().eq(reference.iter().rev()));
+	assert!(map.range(100..=200).eq(reference.range(100..=200)));
+	assert!(map.range(..250).rev().eq(reference.range(..250).rev()));
+
+	let mut range = map.range(10..490);
+	let mut reference_range = reference.range(10..490);
+	while let Some(entry) = range.next() {
+		assert_eq!(Some(entry), reference_range.next());
+		assert_eq!(range.next_back(), reference_range.next_back());
+	}
+	assert_eq!(reference_range.next(), None);
+
+	while let Some((key, value)) = map.pop_first() {
+		assert_eq!(reference.pop_first(), Some((key, value)));
+		assert_eq!(map.check_invariants(), Ok(()));
+		if let Some(last) = map.pop_last() {
+			assert_eq!(reference.pop_last(), Some(last));
+		}
+	}
+	assert!(map.is_empty());
+	assert_eq!(map.range(10..20).count(), 0);
+}
@@ -0,0 +1,111 @@
+//! Random cursor walks checked against a sorted vector.
+use btree_slab::{generic::map::BTreeExt, BTreeMap};
+use rand::{rngs::SmallRng, Rng, SeedableRng};
+
+const SEED: &[u8; 32] = b"testseedtestseedtestseedtestseed";
+
+#[test]
+pub fn cursor_mut_random_walk() {
+	let mut rng = SmallRng::from_seed(*SEED);
+
+	for size in [0, 1, 8, 9, 50, 200] {
+		// Keys are even so that odd keys can be inserted anywhere.
+		let mut map: BTreeMap<usize, usize> = (0..size).map(|i| (i * 2, i)).collect();
+		let mut model: Vec<(usize, usize)> = map.iter().map(|(k, v)| (*k, *v)).collect();
+
+		let mut cursor = map.cursor_front_mut();
+		let mut index = 0;
+		let mut next_key = size * 2;
+
+		for _ in 0..2000 {
+			match rng.gen_range(0..7) {
+				0 => {
+					let expected = model.get(index).copied();
+					assert_eq!(cursor.next().map(|(k, v)| (*k, *v)), expected);
+					if expected.is_some() {
+						index += 1
+					}
+				}
+				1 => {
+					let expected = index.checked_sub(1).map(|i| model[i]);
+					assert_eq!(cursor.prev().map(|(k, v)| (*k, *v)), expected);
+					if expected.is_some() {
+						index -= 1
+					}
+				}
+				2 => {
+					assert_eq!(
+						cursor.remove_next(),
+						(index < model.len()).then(|| model.remove(index))
+					);
+				}
+				3 => {
+					let expected = if index > 0 {
+						index -= 1;
+						Some(model.remove(index))
+					} else {
+						None
+					};
+					assert_eq!(cursor.remove_prev(), expected);
+				}
+				4 | 5 => {
+					// Pick a key between the neighbors, if any.
+					let low = index.checked_sub(1).map(|i| model[i].0 + 1).unwrap_or(0);
+					let high = model.get(index).map(|(k, _)| *k).unwrap_or(usize::MAX);
+					if low < high {
+						let key = rng.gen_range(low..high.min(low + 1000));
+						next_key += 1;
+						model.insert(index, (key, next_key));
+						if rng.gen() {
+							cursor.insert_after(key, next_key).unwrap();
+						} else {
+							cursor.insert_before(key, next_key).unwrap();
+							index += 1
+						}
+					}
+				}
+				_ => {
+					if let Some((_, value)) = cursor.peek_next() {
+						*value += 1;
+						model[index].1 += 1
+					}
+				}
+			}
+
+			let cursor = cursor.as_cursor();
+			assert_eq!(
+				cursor.peek_next().map(|(k, v)| (*k, *v)),
+				model.get(index).copied()
+			);
+			assert_eq!(
+				cursor.peek_prev().map(|(k, v)| (*k, *v)),
+				index.checked_sub(1).map(|i| model[i])
+			);
+		}
+
+		map.validate();
+		assert!(map.into_iter().eq(model));
+	}
+}
+
+#[test]
+pub fn cursor_walk() {
+	let map: BTreeMap<usize, usize> = (0..100).map(|i| (i, i)).collect();
+
+	let mut cursor = map.cursor_front();
+	for i in 0..100 {
+		assert_eq!(cursor.next(), Some((&i, &i)));
+	}
+	assert_eq!(cursor.next(), None);
+	assert_eq!(cursor.address(), map.cursor_back().address());
+
+	for i in (0..100).rev() {
+		assert_eq!(cursor.prev(), Some((&i, &i)));
+	}
+	assert_eq!(cursor.prev(), None);
+
+	let empty: BTreeMap<usize, usize> = BTreeMap::new();
+	let mut cursor = empty.cursor_front();
+	assert_eq!(cursor.next(), None);
+	assert_eq!(cursor.prev(), None);
+}
@@ -0,0 +1,469 @@
+//! Navigation contract of `BTreeExt` on degenerate trees.
+use btree_slab::{
+	generic::{
+		map::{BTreeExt, M},
+		node::{Address, Offset},
+		Node,
+	},
+	BTreeMap,
+};
+
+fn addr(id: usize, offset: usize) -> Address {
+	Address::new(id, offset.into())
+}
+
+fn before(id: usize) -> Address {
+	Address::new(id, Offset::before())
+}
+
+/// Checks every navigation function on a tree made of a single leaf.
+fn check_single_leaf(map: &BTreeMap<usize, usize>) {
+	let id = map.root_id().unwrap();
+	assert!(matches!(map.node(id), Node::Leaf(_)));
+
+	let n = map.len();
+	assert!(n > 0);
+
+	assert_eq!(map.first_item_address(), Some(addr(id, 0)));
+	assert_eq!(map.first_back_address(), addr(id, 0));
+	assert_eq!(map.last_item_address(), Some(addr(id, n - 1)));
+	assert_eq!(map.last_valid_address(), addr(id, n));
+
+	// Offset `-1` stands just before the first item.
+	assert_eq!(map.normalize(before(id)), Some(addr(id, 0)));
+	assert_eq!(map.leaf_address(before(id)), before(id));
+	assert_eq!(map.previous_item_address(before(id)), None);
+	assert_eq!(map.previous_front_address(before(id)), None);
+	assert_eq!(map.next_item_address(before(id)), Some(addr(id, 0)));
+	assert_eq!(map.next_back_address(before(id)), Some(addr(id, 0)));
+	assert_eq!(map.next_item_or_back_address(before(id)), Some(addr(id, 0)));
+
+	for offset in 0..=n {
+		let a = addr(id, offset);
+		assert_eq!(map.leaf_address(a), a);
+
+		if offset < n {
+			assert_eq!(map.normalize(a), Some(a));
+			assert_eq!(map.item(a).map(|item| *item.key()), Some(offset));
+		} else {
+			assert_eq!(map.normalize(a), None);
+			assert!(map.item(a).is_none());
+		}
+
+		let previous = if offset > 0 {
+			Some(addr(id, offset - 1))
+		} else {
+			None
+		};
+		assert_eq!(map.previous_item_address(a), previous);
+
+		let previous_front = if offset > 0 {
+			addr(id, offset - 1)
+		} else {
+			before(id)
+		};
+		assert_eq!(map.previous_front_address(a), Some(previous_front));
+
+		let next = if offset + 1 < n {
+			Some(addr(id, offset + 1))
+		} else {
+			None
+		};
+		assert_eq!(map.next_item_address(a), next);
+
+		let next_back = if offset < n {
+			Some(addr(id, offset + 1))
+		} else {
+			None
+		};
+		assert_eq!(map.next_back_address(a), next_back);
+		assert_eq!(
+			map.next_item_or_back_address(a),
+			Some(addr(id, (offset + 1).min(n)))
+		);
+	}
+
+	for key in 0..n {
+		assert_eq!(map.address_of(&key), Ok(addr(id, key)));
+	}
+	assert_eq!(map.address_of(&n), Err(addr(id, n)));
+}
+
+/// Checks that walking the item addresses visits every key, in both directions.
+fn check_walk(map: &BTreeMap<usize, usize>) {
+	let mut keys = Vec::new();
+	let mut cursor = map.first_item_address();
+	while let Some(a) = cursor {
+		keys.push(*map.item(a).unwrap().key());
+		cursor = map.next_item_address(a);
+	}
+	assert!(keys.iter().eq(map.keys()));
+
+	keys.clear();
+	let mut cursor = map.last_item_address();
+	while let Some(a) = cursor {
+		keys.push(*map.item(a).unwrap().key());
+		cursor = map.previous_item_address(a);
+	}
+	assert!(keys.iter().eq(map.keys().rev()));
+
+	if let Some(last) = map.last_item_address() {
+		assert_eq!(
+			map.previous_item_address(map.last_valid_address()),
+			Some(last)
+		);
+	}
+}
+
+#[test]
+pub fn empty_tree() {
+	let nowhere = Address::nowhere();
+	let map: BTreeMap<usize, usize> = BTreeMap::new();
+
+	assert_eq!(map.root_id(), None);
+	assert_eq!(map.first_item_address(), None);
+	assert_eq!(map.last_item_address(), None);
+	assert_eq!(map.first_back_address(), nowhere);
+	assert_eq!(map.last_valid_address(), nowhere);
+	assert_eq!(map.address_of(&0), Err(nowhere));
+
+	assert_eq!(map.normalize(nowhere), None);
+	assert_eq!(map.leaf_address(nowhere), nowhere);
+	assert_eq!(map.previous_item_address(nowhere), None);
+	assert_eq!(map.previous_front_address(nowhere), None);
+	assert_eq!(map.next_item_address(nowhere), None);
+	assert_eq!(map.next_back_address(nowhere), None);
+	assert_eq!(map.next_item_or_back_address(nowhere), None);
+
+	check_walk(&map);
+}
+
+#[test]
+pub fn emptied_tree() {
+	let mut map = BTreeMap::new();
+	for i in 0..20 {
+		map.insert(i, i);
+	}
+
+	for i in 0..20 {
+		map.remove(&i);
+	}
+
+	assert_eq!(map.root_id(), None);
+	assert_eq!(map.first_item_address(), None);
+	assert_eq!(map.last_valid_address(), Address::nowhere());
+}
+
+#[test]
+pub fn single_item() {
+	let mut map = BTreeMap::new();
+	map.insert(0, 0);
+	check_single_leaf(&map);
+	check_walk(&map);
+}
+
+#[test]
+pub fn full_leaf() {
+	let mut map = BTreeMap::new();
+	for i in 0..btree_slab::generic::map::M {
+		map.insert(i, i);
+		check_single_leaf(&map);
+		check_walk(&map);
+	}
+}
+
+#[test]
+pub fn root_after_merge() {
+	let m = btree_slab::generic::map::M;
+	let mut map = BTreeMap::new();
+	for i in 0..=m {
+		map.insert(i, i);
+	}
+
+	// The root leaf was split.
+	let root_id = map.root_id().unwrap();
+	assert!(matches!(map.node(root_id), Node::Internal(_)));
+	check_walk(&map);
+
+	// Remove the greatest keys until the children are merged back into a single leaf.
+	let mut last = m;
+	while matches!(map.node(map.root_id().unwrap()), Node::Internal(_)) {
+		map.remove(&last);
+		map.validate();
+		check_walk(&map);
+		last -= 1;
+	}
+
+	check_single_leaf(&map);
+
+	// Down to a single item.
+	while map.len() > 1 {
+		map.remove(&last);
+		check_single_leaf(&map);
+		last -= 1;
+	}
+
+	map.remove(&0);
+	assert_eq!(map.root_id(), None);
+}
+
+#[test]
+pub fn node_ids() {
+	let mut map = BTreeMap::new();
+	let check = |map: &BTreeMap<usize, usize>| {
+		let mut reachable: Vec<_> = map.reachable_node_ids().collect();
+		let occupied: Vec<_> = map.occupied_node_ids().collect();
+		reachable.sort_unstable();
+		assert_eq!(reachable, occupied);
+		assert!(reachable.iter().all(|id| map.is_node_reachable(*id)));
+	};
+
+	check(&map);
+	for i in 0..1000 {
+		map.insert((i * 7) % 1000, i);
+		if i % 100 == 0 {
+			check(&map);
+		}
+	}
+
+	for i in 0..1000 {
+		map.remove(&((i * 13) % 1000));
+		if i % 100 == 0 {
+			check(&map);
+		}
+	}
+
+	check(&map);
+	assert!(!map.is_node_reachable(0));
+}
+
+#[test]
+pub fn range_between() {
+	let mut map: BTreeMap<usize, usize> = (0..200).map(|i| (i * 2, i)).collect();
+
+	for (start, end) in [(0, 400), (10, 11), (37, 181), (100, 100), (399, 400)] {
+		let start_addr = map
+			.range(start..)
+			.next()
+			.map_or(map.last_valid_address(), |(k, _)| {
+				map.address_of(k).unwrap()
+			});
+		let end_addr = map
+			.range(end..)
+			.next()
+			.map_or(map.last_valid_address(), |(k, _)| {
+				map.address_of(k).unwrap()
+			});
+
+		assert!(map
+			.range_between(start_addr, end_addr)
+			.unwrap()
+			.eq(map.range(start..end)));
+		assert!(map
+			.range_between(start_addr, end_addr)
+			.unwrap()
+			.rev()
+			.eq(map.range(start..end).rev()));
+
+		let expected: Vec<_> = map.range(start..end).rev().map(|(k, v)| (*k, *v)).collect();
+		let visited: Vec<_> = map
+			.range_between_mut(start_addr, end_addr)
+			.unwrap()
+			.rev()
+			.map(|(k, v)| (*k, *v))
+			.collect();
+		assert_eq!(visited, expected);
+
+		if start < end - 1 {
+			assert!(map.range_between(end_addr, start_addr).is_err())
+		}
+	}
+
+	assert_eq!(map.range_mut(..).count(), 200);
+	assert_eq!(map.range_mut(21..).rev().count(), 189);
+
+	let invalid = addr(usize::MAX - 1, 0);
+	assert!(map
+		.range_between(invalid, map.last_valid_address())
+		.is_err());
+	let root = map.root_id().unwrap();
+	assert!(map
+		.range_between(addr(root, M), map.last_valid_address())
+		.is_err());
+}
+
+#[test]
+pub fn replace_key_at() {
+	use btree_slab::generic::map::BTreeExtMut;
+
+	let mut map: BTreeMap<usize, usize> = (0..200).map(|i| (i * 2, i)).collect();
+	for i in 0..200 {
+		let addr = map.address_of(&(i * 2)).unwrap();
+		assert_eq!(map.replace_key_at(addr, i * 2 + 1), i * 2);
+	}
+
+	map.validate();
+	assert!(map
+		.iter()
+		.map(|(k, v)| (*k, *v))
+		.eq((0..200).map(|i| (i * 2 + 1, i))));
+}
+
+#[test]
+pub fn replace_key_at_outdates_slots() {
+	use btree_slab::generic::map::BTreeExtMut;
+
+	let mut map: BTreeMap<usize, usize> = [(4, 4), (6, 6)].into_iter().collect();
+	let slot = map.vacant_slot(5).unwrap();
+	let addr = map.address_of(&4).unwrap();
+	map.replace_key_at(addr, 5);
+
+	assert_eq!(slot.insert(&mut map, 5), Err((5, 5)));
+	assert!(map.keys().eq(&[5, 6]));
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "new key is not lower than the next key")]
+pub fn replace_key_at_out_of_order() {
+	use btree_slab::generic::map::BTreeExtMut;
+
+	let mut map: BTreeMap<usize, usize> = (0..100).map(|i| (i * 2, i)).collect();
+	let addr = map.address_of(&50).unwrap();
+	map.replace_key_at(addr, 52);
+}
+
+#[test]
+pub fn check_invariants() {
+	use btree_slab::generic::map::{BTreeExtMut, Invariant, InvariantError};
+
+	let mut map: BTreeMap<usize, usize> = (0..1000).map(|i| (i, i)).collect();
+	assert_eq!(map.check_invariants(), Ok(()));
+
+	map.set_len(999);
+	assert_eq!(
+		map.check_invariants(),
+		Err(InvariantError {
+			node: None,
+			invariant: Invariant::Len
+		})
+	);
+	map.set_len(1000);
+
+	// Reparent the first leaf to itself.
+	let leaf = map.first_item_address().unwrap().id;
+	let parent = map.node(leaf).parent();
+	map.node_mut(leaf).set_parent(Some(leaf));
+	assert_eq!(
+		map.check_invariants(),
+		Err(InvariantError {
+			node: Some(leaf),
+			invariant: Invariant::Parent
+		})
+	);
+	map.node_mut(leaf).set_parent(parent);
+
+	// Break the order of the keys of the last leaf.
+	let addr = map.last_item_address().unwrap();
+	*map.item_mut(addr).unwrap().key_mut() = 0;
+	let err = map.check_invariants().unwrap_err();
+	assert_eq!(err.node, Some(addr.id));
+	assert_eq!(err.invariant, Invariant::Sorted);
+	assert_eq!(
+		err.to_string(),
+		format!("node {}: node items are not sorted", addr.id)
+	);
+}
+
+#[test]
+fn addresses() {
+	let empty: BTreeMap<usize, usize> = BTreeMap::new();
+	assert_eq!(empty.addresses().count(), 0);
+	assert_eq!(empty.addresses_in(0..10).count(), 0);
+
+	for len in [1, M, M + 1, 1000] {
+		let map: BTreeMap<usize, usize> = (0..len).map(|i| (i * 2, i)).collect();
+
+		// Same sequence as the `next_item_address` loop.
+		let mut expected = Vec::new();
+		let mut addr = map.first_item_address();
+		while let Some(a) = addr {
+			expected.push(a);
+			addr = map.next_item_address(a);
+		}
+		assert_eq!(map.addresses().collect::<Vec<_>>(), expected);
+		assert!(map.addresses().rev().eq(expected.iter().rev().copied()));
+
+		for (start, end) in [(0, 0), (1, 7), (3, len + 3), (len, len * 3)] {
+			let keys: Vec<usize> = map
+				.addresses_in(start..end)
+				.map(|addr| *map.item(addr).unwrap().key())
+				.collect();
+			let model: Vec<usize> = map.range(start..end).map(|(k, _)| *k).collect();
+			assert_eq!(keys, model);
+		}
+	}
+}
+
+#[test]
+fn nodes_walk() {
+	let empty: BTreeMap<usize, usize> = BTreeMap::new();
+	assert_eq!(empty.nodes().count(), 0);
+
+	for len in [1, M, M + 1, 1000] {
+		let map: BTreeMap<usize, usize> = (0..len).map(|i| (i, i)).collect();
+		let nodes: Vec<_> = map.nodes().collect();
+
+		// Pre-order: the root comes first, and every parent before its children.
+		assert_eq!(nodes[0].id(), map.root_id().unwrap());
+		assert_eq!(nodes[0].depth(), 0);
+		assert_eq!(nodes[0].parent(), None);
+		for (i, node) in nodes.iter().enumerate().skip(1) {
+			let parent = nodes[..i]
+				.iter()
+				.find(|p| Some(p.id()) == node.parent())
+				.unwrap();
+			assert_eq!(node.depth(), parent.depth() + 1);
+			assert!(parent.child_ids().any(|id| id == node.id()));
+		}
+
+		let mut ids: Vec<_> = nodes.iter().map(|n| n.id()).collect();
+		let mut reachable: Vec<_> = map.reachable_node_ids().collect();
+		ids.sort();
+		reachable.sort();
+		assert_eq!(ids, reachable);
+
+		// Leaves are visited left to right, and so are their items.
+		let leaf_keys: Vec<usize> = nodes
+			.iter()
+			.filter(|n| n.is_leaf())
+			.flat_map(|n| n.items().map(|item| *item.key()))
+			.collect();
+		assert!(leaf_keys.windows(2).all(|w| w[0] < w[1]));
+
+		let item_count: usize = nodes.iter().map(|n| n.item_count()).sum();
+		assert_eq!(item_count, len);
+	}
+}
+
+#[cfg(feature = "validate-addresses")]
+#[test]
+#[should_panic(expected = "which is not allocated")]
+fn stale_address_item() {
+	let mut map: BTreeMap<usize, usize> = (0..100).map(|i| (i, i)).collect();
+	let stale = map.last_item_address().unwrap();
+	map.clear();
+	map.insert(0, 0);
+	map.item(stale);
+}
+
+#[cfg(feature = "validate-addresses")]
+#[test]
+#[should_panic(expected = "out of the bounds")]
+fn out_of_bounds_remove_at() {
+	use btree_slab::generic::map::BTreeExtMut;
+
+	let mut map: BTreeMap<usize, usize> = (0..4).map(|i| (i, i)).collect();
+	let back = map.last_valid_address();
+	map.remove_at(back);
+}
@@ -1,5 +1,5 @@
 use btree_slab::BTreeMap;
-use std::{cell::Cell, rc::Rc};
+use std::{cell::Cell, ops::Bound, rc::Rc};
 
 #[test]
 pub fn iter() {
@@ -188,3 +188,382 @@ pub fn into_iter_both_ends2() {
 
 	assert_eq!(counter.get(), 100);
 }
+
+#[test]
+pub fn fold_in_range() {
+	use std::ops::{Bound, RangeBounds};
+
+	let mut map = BTreeMap::new();
+	for i in 0..200 {
+		map.insert(i * 2, i);
+	}
+
+	let bounds = |b: i32, kind: u8| match kind {
+		0 => Bound::Included(b),
+		1 => Bound::Excluded(b),
+		_ => Bound::Unbounded,
+	};
+
+	for start in (-2..402).step_by(3) {
+		for end in (start..403).step_by(17) {
+			for start_kind in 0..3 {
+				for end_kind in 0..3 {
+					let range = (bounds(start, start_kind), bounds(end, end_kind));
+					if start == end && start_kind == 1 && end_kind == 1 {
+						continue;
+					}
+
+					let expected: Vec<_> =
+						map.keys().cloned().filter(|k| range.contains(k)).collect();
+					let folded = map.fold_in_range(range, Vec::new(), |mut acc, k, _| {
+						acc.push(*k);
+						acc
+					});
+					assert_eq!(folded, expected)
+				}
+			}
+		}
+	}
+}
+
+#[test]
+pub fn min_max_key_in() {
+	use std::ops::{Bound, RangeBounds};
+
+	let mut map = BTreeMap::new();
+	assert_eq!(map.first_key(), None);
+	assert_eq!(map.last_key(), None);
+	assert_eq!(map.min_key_in(..), None);
+	assert_eq!(map.max_key_in(..), None);
+
+	for i in 0..200 {
+		map.insert(i * 2, i);
+	}
+
+	assert_eq!(map.first_key(), Some(&0));
+	assert_eq!(map.last_key(), Some(&398));
+
+	let bounds = |b: i32, kind: u8| match kind {
+		0 => Bound::Included(b),
+		1 => Bound::Excluded(b),
+		_ => Bound::Unbounded,
+	};
+
+	for start in (-2..402).step_by(3) {
+		for end in (start..403).step_by(17) {
+			for start_kind in 0..3 {
+				for end_kind in 0..3 {
+					let range = (bounds(start, start_kind), bounds(end, end_kind));
+					if start == end && start_kind == 1 && end_kind == 1 {
+						continue;
+					}
+
+					let expected: Vec<_> =
+						map.keys().cloned().filter(|k| range.contains(k)).collect();
+					assert_eq!(map.min_key_in(range), expected.first());
+					assert_eq!(map.max_key_in(range), expected.last());
+				}
+			}
+		}
+	}
+}
+
+#[test]
+pub fn range_all_bounds() {
+	use std::ops::{Bound, RangeBounds};
+
+	let mut map = BTreeMap::new();
+	for i in 0..200 {
+		map.insert(i * 2, i);
+	}
+
+	let bounds = |b: i32, kind: u8| match kind {
+		0 => Bound::Included(b),
+		1 => Bound::Excluded(b),
+		_ => Bound::Unbounded,
+	};
+
+	for start in (-2..402).step_by(3) {
+		for end in (start..403).step_by(17) {
+			for start_kind in 0..3 {
+				for end_kind in 0..3 {
+					let range = (bounds(start, start_kind), bounds(end, end_kind));
+					if start == end && start_kind == 1 && end_kind == 1 {
+						continue;
+					}
+
+					let expected: Vec<_> =
+						map.keys().cloned().filter(|k| range.contains(k)).collect();
+					assert_eq!(
+						map.range(range).map(|(k, _)| *k).collect::<Vec<_>>(),
+						expected
+					);
+					assert_eq!(
+						map.range(range).rev().map(|(k, _)| *k).collect::<Vec<_>>(),
+						expected.iter().rev().cloned().collect::<Vec<_>>()
+					);
+				}
+			}
+		}
+	}
+}
+
+#[test]
+pub fn strictly_above_below() {
+	let mut map = BTreeMap::new();
+	for i in 0..200 {
+		map.insert(i * 2, i);
+	}
+
+	for key in -2i32..402 {
+		let above = (0..200).map(|i| i * 2).find(|k| *k > key);
+		let below = (0..200).map(|i| i * 2).rev().find(|k| *k < key);
+		assert_eq!(map.strictly_above(&key).map(|(k, _)| *k), above);
+		assert_eq!(map.strictly_below(&key).map(|(k, _)| *k), below);
+	}
+}
+
+#[test]
+pub fn cloned_keys() {
+	let map: BTreeMap<String, usize> = (0..100).map(|i| (format!("{:03}", i), i)).collect();
+
+	let iter = map.iter_cloned_keys();
+	assert_eq!(iter.size_hint(), (100, Some(100)));
+	assert!(iter.eq(map.iter().map(|(k, v)| (k.clone(), v))));
+
+	let bounds = (Bound::Included("010"), Bound::Excluded("020"));
+	let range = map.range_cloned_keys::<str, _>(bounds);
+	let expected = map.range::<str, _>(bounds).map(|(k, v)| (k.clone(), v));
+	assert!(range.rev().eq(expected.rev()));
+}
+
+#[test]
+pub fn fold() {
+	/// Consumes `skip` items from the front and `skip_back` items from the back.
+	fn skipped<'a, I>(mut iter: I, skip: usize, skip_back: usize) -> I
+	where
+		I: DoubleEndedIterator<Item = (&'a usize, &'a usize)>,
+	{
+		(&mut iter).take(skip).for_each(drop);
+		(&mut iter).rev().take(skip_back).for_each(drop);
+		iter
+	}
+
+	fn check<'a, I>(make: impl Fn() -> I)
+	where
+		I: Iterator<Item = (&'a usize, &'a usize)>,
+	{
+		let mut expected = Vec::new();
+		// A `for` loop goes through `next`.
+		for (k, v) in make() {
+			expected.push((*k, *v))
+		}
+
+		let folded = make().fold(Vec::new(), |mut items, (k, v)| {
+			items.push((*k, *v));
+			items
+		});
+		assert_eq!(folded, expected)
+	}
+
+	for n in [0, 1, 8, 9, 50, 300] {
+		let map: BTreeMap<usize, usize> = (0..n).map(|i| (i * 2, i)).collect();
+
+		for skip in [0, 1, 7, 20] {
+			for skip_back in [0, 1, 9] {
+				check(|| skipped(map.iter(), skip, skip_back));
+
+				for start in [0, 3, 16, 101] {
+					for end in [start, start + 1, start + 15, n * 2] {
+						if end >= start {
+							check(|| skipped(map.range(start..end), skip, skip_back));
+						}
+					}
+				}
+			}
+		}
+
+		assert_eq!(
+			map.keys().sum::<usize>(),
+			(0..n).map(|i| i * 2).sum::<usize>()
+		);
+		assert_eq!(map.values().sum::<usize>(), (0..n).sum::<usize>());
+	}
+}
+
+#[test]
+pub fn range_len() {
+	let mut map: BTreeMap<usize, usize> = (0..500).map(|i| (i * 2, i)).collect();
+
+	for (start, end) in [
+		(0, 1000),
+		(0, 0),
+		(3, 4),
+		(13, 977),
+		(500, 501),
+		(998, 1000),
+	] {
+		let expected = (start..end).filter(|i| i % 2 == 0).count();
+
+		let mut range = map.range(start..end);
+		for i in 0..expected {
+			assert_eq!(range.len(), expected - i);
+			if i % 2 == 0 {
+				range.next();
+			} else {
+				range.next_back();
+			}
+		}
+		assert_eq!(range.len(), 0);
+
+		let mut range = map.range_mut(start..end);
+		assert_eq!(range.size_hint(), (expected, Some(expected)));
+		range.next_back();
+		assert_eq!(range.len(), expected.saturating_sub(1));
+	}
+}
+
+#[test]
+pub fn iter_mut_peek() {
+	let mut map: BTreeMap<usize, usize> = (0..100).map(|i| (i, i)).collect();
+
+	let mut iter = map.iter_mut();
+	for i in 0..50 {
+		assert_eq!(iter.peek(), Some((&i, &i)));
+		assert_eq!(iter.peek_back(), Some((&(99 - i), &(99 - i))));
+		*iter.peek_back_mut().unwrap().1 += 1000;
+		let (_, v) = iter.next().unwrap();
+		*v += 1000;
+		iter.next_back();
+	}
+	assert_eq!(iter.peek(), None);
+	assert_eq!(iter.peek_back(), None);
+	assert!(map.values().all(|v| *v >= 1000));
+
+	let mut range = map.range_mut(10..20);
+	assert_eq!(range.peek(), Some((&10, &1010)));
+	assert_eq!(range.peek_back(), Some((&19, &1019)));
+	for i in 10..20 {
+		let (k, v) = range.peek_mut().unwrap();
+		assert_eq!(*k, i);
+		*v = 0;
+		range.next();
+	}
+	assert_eq!(range.peek(), None);
+	assert_eq!(range.peek_back_mut(), None);
+	assert!(map.range(10..20).all(|(_, v)| *v == 0));
+}
+
+#[test]
+pub fn range_entries_mut() {
+	use btree_slab::generic::map::BTreeExt;
+
+	for len in [0, 1, 10, 100, 1000] {
+		let mut map: BTreeMap<usize, usize> = (0..len).map(|i| (i, i)).collect();
+
+		let mut entries =
+			map.range_entries_mut((Bound::Excluded(len / 4), Bound::Included(len / 2)));
+		let mut visited = Vec::new();
+		while let Some(mut entry) = entries.next_entry() {
+			let key = *entry.key();
+			visited.push(key);
+			match key % 3 {
+				0 => assert_eq!(entry.remove(), key),
+				1 => assert_eq!(entry.replace_value(key * 10), key),
+				_ => (),
+			}
+		}
+		assert!(entries.is_empty());
+		assert_eq!(entries.peek(), None);
+
+		let range = (len / 4 + 1)..=(len / 2);
+		assert!(visited.into_iter().eq(range.clone().filter(|k| *k < len)));
+
+		map.validate();
+		let expected = (0..len).filter_map(|k| {
+			if range.contains(&k) {
+				match k % 3 {
+					0 => None,
+					1 => Some((k, k * 10)),
+					_ => Some((k, k)),
+				}
+			} else {
+				Some((k, k))
+			}
+		});
+		assert!(map.into_iter().eq(expected));
+	}
+}
+
+#[test]
+pub fn into_entries() {
+	use btree_slab::generic::map::BTreeExt;
+
+	for len in [0, 1, 10, 100, 1000] {
+		let mut map: BTreeMap<usize, usize> = (0..len).map(|i| (i, i)).collect();
+		for i in (0..len).step_by(3) {
+			map.remove(&i);
+		}
+
+		let expected: Vec<_> = map
+			.addresses()
+			.map(|addr| {
+				let item = map.item(addr).unwrap();
+				(addr, *item.key(), *item.value())
+			})
+			.collect();
+
+		assert!(map.clone().into_entries().eq(expected.iter().cloned()));
+		assert!(map
+			.clone()
+			.into_entries()
+			.rev()
+			.eq(expected.iter().rev().cloned()));
+
+		let half = expected.len() / 2;
+		let mut entries = map.into_entries();
+		let mut front = Vec::new();
+		let mut back = Vec::new();
+		while entries.len() > 0 {
+			if front.len() < half {
+				front.push(entries.next().unwrap())
+			} else {
+				back.push(entries.next_back().unwrap())
+			}
+		}
+		back.reverse();
+		front.extend(back);
+		assert_eq!(front, expected);
+	}
+}
+
+#[test]
+pub fn iter_mut_lent_references() {
+	let mut map: BTreeMap<usize, usize> = (0..100).map(|i| (i, i)).collect();
+
+	// Keep every lent reference alive while the iterators move on, from both ends.
+	let mut iter = map.iter_mut();
+	let mut values = Vec::new();
+	while let Some((_, front)) = iter.next() {
+		values.push(front);
+		if let Some((_, back)) = iter.next_back() {
+			values.push(back)
+		}
+	}
+	for value in values {
+		*value *= 2
+	}
+
+	let mut range = map.range_mut(10..90);
+	let front = range.next().unwrap().1;
+	let back = range.next_back().unwrap().1;
+	*range.peek_mut().unwrap().1 += 1;
+	assert_eq!(range.len(), 78);
+	assert!(format!("{:?}", range).starts_with("[(11, 23), (12, 24)"));
+	std::mem::swap(front, back);
+
+	let mut expected: Vec<_> = (0..100).map(|i| i * 2).collect();
+	expected.swap(10, 89);
+	expected[11] += 1;
+	assert!(map.into_values().eq(expected));
+}
@@ -0,0 +1,182 @@
+//! Exhaustive small-tree tests against a reference model.
+//!
+//! Every sequence of up to `DEPTH` operations over a small key universe
+//! is applied to a set of initial trees of various shapes,
+//! and the map is checked against [`std::collections::BTreeMap`]
+//! after each operation.
+use btree_slab::{generic::map::BTreeExt, BTreeMap};
+use std::collections::BTreeMap as Model;
+
+/// Maximum length of the operation sequences.
+const DEPTH: usize = 3;
+
+/// Sizes of the initial trees.
+///
+/// Chosen around the node capacity so that operations trigger splits,
+/// merges and rotations.
+const SIZES: [usize; 10] = [0, 1, 7, 8, 9, 15, 16, 17, 24, 50];
+
+/// Number of keys in the universe of each initial tree.
+const KEYS: usize = 5;
+
+#[derive(Clone, Copy, Debug)]
+enum Op {
+	Insert(usize),
+	Remove(usize),
+	EntryRemove(usize),
+	EntryInsert(usize),
+	PopFirst,
+	PopLast,
+	RetainEven,
+	Clear,
+}
+
+impl Op {
+	/// Lists every operation over the given keys.
+	fn all(keys: &[usize]) -> Vec<Op> {
+		let mut ops = vec![Op::PopFirst, Op::PopLast, Op::RetainEven, Op::Clear];
+		for &key in keys {
+			ops.push(Op::Insert(key));
+			ops.push(Op::Remove(key));
+			ops.push(Op::EntryRemove(key));
+			ops.push(Op::EntryInsert(key));
+		}
+
+		ops
+	}
+}
+
+/// Map paired with its reference model.
+#[derive(Clone)]
+struct Shadow {
+	map: BTreeMap<usize, usize>,
+	model: Model<usize, usize>,
+}
+
+impl Shadow {
+	fn new(size: usize) -> Self {
+		let mut shadow = Shadow {
+			map: BTreeMap::new(),
+			model: Model::new(),
+		};
+
+		// Present keys are even, so odd keys fall in between.
+		for i in 0..size {
+			shadow.map.insert(i * 2, i);
+			shadow.model.insert(i * 2, i);
+		}
+
+		shadow.check(&[]);
+		shadow
+	}
+
+	fn apply(&mut self, op: Op, history: &[Op]) {
+		use btree_slab::generic::map::Entry;
+		use std::collections::btree_map::Entry as ModelEntry;
+
+		match op {
+			Op::Insert(key) => {
+				assert_eq!(
+					self.map.insert(key, key + 1),
+					self.model.insert(key, key + 1),
+					"{:?}",
+					history
+				)
+			}
+			Op::Remove(key) => {
+				assert_eq!(
+					self.map.remove(&key),
+					self.model.remove(&key),
+					"{:?}",
+					history
+				)
+			}
+			Op::EntryRemove(key) => {
+				let removed = match self.map.entry(key) {
+					Entry::Occupied(entry) => Some(entry.remove_entry()),
+					Entry::Vacant(_) => None,
+				};
+
+				let expected = match self.model.entry(key) {
+					ModelEntry::Occupied(entry) => Some(entry.remove_entry()),
+					ModelEntry::Vacant(_) => None,
+				};
+
+				assert_eq!(removed, expected, "{:?}", history)
+			}
+			Op::EntryInsert(key) => {
+				*self.map.entry(key).or_insert(0) += 1;
+				*self.model.entry(key).or_insert(0) += 1;
+			}
+			Op::PopFirst => {
+				assert_eq!(
+					self.map.pop_first(),
+					self.model.pop_first(),
+					"{:?}",
+					history
+				)
+			}
+			Op::PopLast => {
+				assert_eq!(self.map.pop_last(), self.model.pop_last(), "{:?}", history)
+			}
+			Op::RetainEven => {
+				self.map.retain(|_, v| *v % 2 == 0);
+				self.model.retain(|_, v| *v % 2 == 0);
+			}
+			Op::Clear => {
+				self.map.clear();
+				self.model.clear();
+			}
+		}
+
+		self.check(history)
+	}
+
+	fn check(&self, history: &[Op]) {
+		self.map.validate();
+		assert_eq!(self.map.len(), self.model.len(), "{:?}", history);
+		assert!(self.map.iter().eq(self.model.iter()), "{:?}", history);
+		assert!(
+			self.map.iter().rev().eq(self.model.iter().rev()),
+			"{:?}",
+			history
+		);
+	}
+}
+
+fn explore(shadow: &Shadow, ops: &[Op], history: &mut Vec<Op>) {
+	if history.len() < DEPTH {
+		for &op in ops {
+			let mut next = shadow.clone();
+			history.push(op);
+			next.apply(op, history);
+			explore(&next, ops, history);
+			history.pop();
+		}
+	}
+}
+
+#[test]
+pub fn exhaustive_small_trees() {
+	for size in SIZES {
+		// Spread the key universe over the tree, before, in and after it.
+		let max = size * 2 + 1;
+		let keys: Vec<usize> = (0..KEYS).map(|i| i * max / (KEYS - 1)).collect();
+
+		let shadow = Shadow::new(size);
+		explore(&shadow, &Op::all(&keys), &mut Vec::new())
+	}
+}
+
+#[cfg(feature = "testing")]
+#[test]
+pub fn random_sequences() {
+	use btree_slab::testing::{check_ops, random_ops};
+
+	// Small universes keep the map dense, larger ones make it grow deeper.
+	for keys in [8, 64, 1000] {
+		for seed in 0..20 {
+			check_ops(random_ops(seed, 2000, keys))
+		}
+	}
+}